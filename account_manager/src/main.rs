@@ -1,9 +1,12 @@
 use bls::Keypair;
 use clap::{App, Arg, SubCommand};
-use slog::{crit, debug, info, o, Drain};
+use slog::{crit, debug, info, o, warn, Drain};
 use std::fs;
-use std::path::PathBuf;
+use std::path::{Path, PathBuf};
 use types::test_utils::generate_deterministic_keypair;
+use validator_client::slashing_protection::{
+    Interchange, InterchangeValidator, NotSafe, SlashingProtection,
+};
 use validator_client::Config as ValidatorClientConfig;
 
 pub const DEFAULT_DATA_DIR: &str = ".lighthouse-validator";
@@ -59,6 +62,36 @@ fn main() {
                         .default_value("1"),
                 ),
         )
+        .subcommand(
+            SubCommand::with_name("export_slashing_protection")
+                .about("Exports the signing history of all validators to a JSON interchange file")
+                .version("0.0.1")
+                .author("Sigma Prime <contact@sigmaprime.io>")
+                .arg(
+                    Arg::with_name("file")
+                        .long("file")
+                        .short("f")
+                        .value_name("FILE")
+                        .help("The path of the interchange file to write.")
+                        .takes_value(true)
+                        .required(true),
+                ),
+        )
+        .subcommand(
+            SubCommand::with_name("import_slashing_protection")
+                .about("Merges the signing history in a JSON interchange file into all matching validators")
+                .version("0.0.1")
+                .author("Sigma Prime <contact@sigmaprime.io>")
+                .arg(
+                    Arg::with_name("file")
+                        .long("file")
+                        .short("f")
+                        .value_name("FILE")
+                        .help("The path of the interchange file to read.")
+                        .takes_value(true)
+                        .required(true),
+                ),
+        )
         .get_matches();
 
     let data_dir = match matches
@@ -127,6 +160,18 @@ fn main() {
                 }
             }
         }
+        ("export_slashing_protection", Some(m)) => {
+            let path = PathBuf::from(m.value_of("file").expect("File is required"));
+            if let Err(e) = export_slashing_protection(&path, &client_config, &log) {
+                crit!(log, "Failed to export slashing protection"; "error" => format!("{:?}", e));
+            }
+        }
+        ("import_slashing_protection", Some(m)) => {
+            let path = PathBuf::from(m.value_of("file").expect("File is required"));
+            if let Err(e) = import_slashing_protection(&path, &client_config, &log) {
+                crit!(log, "Failed to import slashing protection"; "error" => format!("{:?}", e));
+            }
+        }
         _ => panic!(
             "The account manager must be run with a subcommand. See help for more information."
         ),
@@ -170,3 +215,247 @@ fn save_key(keypair: &Keypair, config: &ValidatorClientConfig, log: &slog::Logge
         key_path.to_string_lossy()
     );
 }
+
+/// Returns the keys in the data directory, or an error if there are none.
+///
+/// An unreadable or mistyped data directory must not be mistaken for one without validators, as
+/// an empty export could later be imported in place of the real signing history.
+fn fetch_keys(config: &ValidatorClientConfig, log: &slog::Logger) -> Result<Vec<Keypair>, NotSafe> {
+    config.fetch_keys(log).ok_or_else(|| {
+        NotSafe::IoError(format!("No validator keys found in {:?}", config.data_dir))
+    })
+}
+
+/// Writes the signing history of every validator in the data directory to the interchange file
+/// at `path`.
+fn export_slashing_protection(
+    path: &Path,
+    config: &ValidatorClientConfig,
+    log: &slog::Logger,
+) -> Result<(), NotSafe> {
+    let keypairs = fetch_keys(config, log)?;
+
+    let validators = keypairs
+        .iter()
+        .map(|keypair| {
+            let protection = SlashingProtection::open(&config.validator_dir(keypair))?;
+            Ok(InterchangeValidator::new(
+                keypair.pk.clone(),
+                protection.history(),
+            ))
+        })
+        .collect::<Result<Vec<_>, NotSafe>>()?;
+
+    Interchange::new(validators).write(path)?;
+
+    info!(
+        log,
+        "Exported slashing protection";
+        "validators" => keypairs.len(),
+        "file" => format!("{:?}", path)
+    );
+
+    Ok(())
+}
+
+/// Merges the signing history in the interchange file at `path` into each validator in the data
+/// directory with a matching public key.
+///
+/// Records for validators without a key in the data directory are ignored. Fails if the history of
+/// a validator is open elsewhere, such as in a running validator client.
+fn import_slashing_protection(
+    path: &Path,
+    config: &ValidatorClientConfig,
+    log: &slog::Logger,
+) -> Result<(), NotSafe> {
+    let interchange = Interchange::read(path)?;
+    let keypairs = fetch_keys(config, log)?;
+
+    let mut imported = 0;
+
+    for validator in &interchange.validators {
+        match keypairs
+            .iter()
+            .find(|keypair| keypair.pk == validator.pubkey)
+        {
+            Some(keypair) => {
+                SlashingProtection::open(&config.validator_dir(keypair))?
+                    .import(&validator.history())?;
+                imported += 1;
+            }
+            None => warn!(
+                log,
+                "No key for validator in interchange file";
+                "pubkey" => format!("{:?}", validator.pubkey)
+            ),
+        }
+    }
+
+    info!(
+        log,
+        "Imported slashing protection";
+        "validators" => imported,
+        "file" => format!("{:?}", path)
+    );
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::tempdir;
+    use types::{Epoch, Hash256, Slot};
+    use validator_client::slashing_protection::{LowerBound, SignedBlock, SigningHistory};
+
+    fn config(data_dir: &Path) -> ValidatorClientConfig {
+        let mut config = ValidatorClientConfig::default();
+        config.data_dir = data_dir.to_path_buf();
+        config
+    }
+
+    #[test]
+    fn export_then_import_refuses_slashable_messages() {
+        let log = slog::Logger::root(slog::Discard, o!());
+        let keypairs: Vec<Keypair> = (0..2).map(generate_deterministic_keypair).collect();
+
+        // Sign some messages with the validators on the first machine.
+        let source_dir = tempdir().unwrap();
+        let source = config(source_dir.path());
+        for (i, keypair) in keypairs.iter().enumerate() {
+            source.save_key(keypair).unwrap();
+
+            let protection = SlashingProtection::open(&source.validator_dir(keypair)).unwrap();
+            protection
+                .record_block(Slot::new(8), Hash256::from(i as u64))
+                .unwrap();
+            protection
+                .record_attestation(Epoch::new(2), Epoch::new(3), Hash256::from(i as u64))
+                .unwrap();
+        }
+
+        let interchange_dir = tempdir().unwrap();
+        let interchange_path = interchange_dir.path().join("interchange.json");
+        export_slashing_protection(&interchange_path, &source, &log).unwrap();
+
+        // Move the keys to a second machine with no signing history.
+        let dest_dir = tempdir().unwrap();
+        let dest = config(dest_dir.path());
+        for keypair in &keypairs {
+            dest.save_key(keypair).unwrap();
+        }
+        import_slashing_protection(&interchange_path, &dest, &log).unwrap();
+
+        for (i, keypair) in keypairs.iter().enumerate() {
+            let protection = SlashingProtection::open(&dest.validator_dir(keypair)).unwrap();
+
+            assert_eq!(
+                protection.check_block(Slot::new(8), Hash256::from(100)),
+                Err(NotSafe::DoubleBlockProposal { slot: Slot::new(8) })
+            );
+            assert_eq!(
+                protection.check_attestation(Epoch::new(2), Epoch::new(3), Hash256::from(100)),
+                Err(NotSafe::DoubleVote {
+                    target_epoch: Epoch::new(3)
+                })
+            );
+            assert_eq!(
+                protection.check_attestation(Epoch::new(1), Epoch::new(4), Hash256::from(100)),
+                Err(NotSafe::SurroundingVote {
+                    source_epoch: Epoch::new(2),
+                    target_epoch: Epoch::new(3)
+                })
+            );
+
+            // The exported messages themselves may still be re-signed.
+            assert_eq!(
+                protection.check_block(Slot::new(8), Hash256::from(i as u64)),
+                Ok(())
+            );
+        }
+    }
+
+    #[test]
+    fn import_ignores_unknown_validators() {
+        let log = slog::Logger::root(slog::Discard, o!());
+        let known = generate_deterministic_keypair(0);
+        let unknown = generate_deterministic_keypair(1);
+
+        let interchange_dir = tempdir().unwrap();
+        let interchange_path = interchange_dir.path().join("interchange.json");
+        let history = SigningHistory::new(
+            &[SignedBlock {
+                slot: Slot::new(1),
+                signing_root: Hash256::from(1),
+            }],
+            &[],
+            LowerBound::default(),
+        );
+        Interchange::new(vec![
+            InterchangeValidator::new(known.pk.clone(), history.clone()),
+            InterchangeValidator::new(unknown.pk.clone(), history),
+        ])
+        .write(&interchange_path)
+        .unwrap();
+
+        let dest_dir = tempdir().unwrap();
+        let dest = config(dest_dir.path());
+        dest.save_key(&known).unwrap();
+        import_slashing_protection(&interchange_path, &dest, &log).unwrap();
+
+        let protection = SlashingProtection::open(&dest.validator_dir(&known)).unwrap();
+        assert!(protection
+            .check_block(Slot::new(1), Hash256::from(2))
+            .is_err());
+        assert!(!dest.validator_dir(&unknown).exists());
+    }
+
+    #[test]
+    fn export_requires_keys() {
+        let log = slog::Logger::root(slog::Discard, o!());
+
+        let interchange_dir = tempdir().unwrap();
+        let interchange_path = interchange_dir.path().join("interchange.json");
+
+        let empty_dir = tempdir().unwrap();
+        let missing_dir = empty_dir.path().join("missing");
+        for data_dir in &[empty_dir.path(), missing_dir.as_path()] {
+            assert!(
+                export_slashing_protection(&interchange_path, &config(data_dir), &log).is_err()
+            );
+            assert!(!interchange_path.exists());
+        }
+    }
+
+    #[test]
+    fn import_refuses_open_history() {
+        let log = slog::Logger::root(slog::Discard, o!());
+        let keypair = generate_deterministic_keypair(0);
+
+        let interchange_dir = tempdir().unwrap();
+        let interchange_path = interchange_dir.path().join("interchange.json");
+        Interchange::new(vec![InterchangeValidator::new(
+            keypair.pk.clone(),
+            SigningHistory::default(),
+        )])
+        .write(&interchange_path)
+        .unwrap();
+
+        let dest_dir = tempdir().unwrap();
+        let dest = config(dest_dir.path());
+        dest.save_key(&keypair).unwrap();
+
+        // The history is held open, as it would be by a running validator client.
+        let protection = SlashingProtection::open(&dest.validator_dir(&keypair)).unwrap();
+        match import_slashing_protection(&interchange_path, &dest, &log) {
+            Err(NotSafe::Locked(_)) => {}
+            result => panic!("import should refuse a locked history: {:?}", result),
+        }
+
+        drop(protection);
+        assert_eq!(
+            import_slashing_protection(&interchange_path, &dest, &log),
+            Ok(())
+        );
+    }
+}
@@ -1,25 +1,30 @@
 use crate::checkpoint::CheckPoint;
 use crate::errors::{BeaconChainError as Error, BlockProductionError};
+use crate::events::{BeaconChainEvent, EventBus};
 use crate::fork_choice::{Error as ForkChoiceError, ForkChoice};
 use crate::metrics::Metrics;
 use crate::persisted_beacon_chain::{PersistedBeaconChain, BEACON_CHAIN_DB_KEY};
-use lmd_ghost::LmdGhost;
+use crate::persisted_fork_choice::{PersistedForkChoice, FORK_CHOICE_DB_KEY};
+use crate::schema;
+use lmd_ghost::{LmdGhost, TreeDump};
 use log::trace;
 use operation_pool::DepositInsertStatus;
 use operation_pool::{OperationPool, PersistedOperationPool};
 use parking_lot::{RwLock, RwLockReadGuard};
 use slot_clock::SlotClock;
 use state_processing::per_block_processing::errors::{
-    AttestationValidationError, AttesterSlashingValidationError, DepositValidationError,
-    ExitValidationError, ProposerSlashingValidationError, TransferValidationError,
+    AttestationValidationError, AttesterSlashingValidationError, DepositInvalid,
+    DepositValidationError, ExitValidationError, ProposerSlashingValidationError,
+    TransferValidationError,
 };
+use state_processing::per_block_processing::verify_deposit_merkle_proof;
 use state_processing::{
     per_block_processing, per_block_processing_without_verifying_block_signature,
     per_slot_processing, BlockProcessingError,
 };
 use std::sync::Arc;
 use store::iter::{BlockIterator, BlockRootsIterator, StateRootsIterator};
-use store::{Error as DBError, Store};
+use store::{canonical_index, Error as DBError, Store, WriteBatch};
 use tree_hash::TreeHash;
 use types::*;
 
@@ -83,6 +88,8 @@ pub struct BeaconChain<T: BeaconChainTypes> {
     pub fork_choice: ForkChoice<T>,
     /// Stores metrics about this `BeaconChain`.
     pub metrics: Metrics,
+    /// Publishes changes to the chain (e.g., imported blocks and new heads) to subscribers.
+    pub events: EventBus,
 }
 
 impl<T: BeaconChainTypes> BeaconChain<T> {
@@ -96,15 +103,26 @@ impl<T: BeaconChainTypes> BeaconChain<T> {
     ) -> Result<Self, Error> {
         genesis_state.build_all_caches(&spec)?;
 
+        let mut batch = WriteBatch::new();
+
         let state_root = genesis_state.canonical_root();
-        store.put(&state_root, &genesis_state)?;
+        batch.put(&state_root, &genesis_state);
 
         let genesis_block_root = genesis_block.block_header().canonical_root();
-        store.put(&genesis_block_root, &genesis_block)?;
+        batch.put(&genesis_block_root, &genesis_block);
 
         // Also store the genesis block under the `ZERO_HASH` key.
-        let genesis_block_root = genesis_block.block_header().canonical_root();
-        store.put(&spec.zero_hash, &genesis_block)?;
+        batch.put(&spec.zero_hash, &genesis_block);
+
+        schema::put_current_schema_version(&mut batch);
+        canonical_index::put_canonical_roots(
+            &mut batch,
+            genesis_block.slot,
+            genesis_block_root,
+            state_root,
+        );
+
+        store.write_batch(&batch)?;
 
         let canonical_head = RwLock::new(CheckPoint::new(
             genesis_block.clone(),
@@ -122,6 +140,7 @@ impl<T: BeaconChainTypes> BeaconChain<T> {
             genesis_block_root,
             fork_choice: ForkChoice::new(store.clone(), &genesis_block, genesis_block_root),
             metrics: Metrics::new()?,
+            events: EventBus::default(),
             store,
         })
     }
@@ -131,6 +150,13 @@ impl<T: BeaconChainTypes> BeaconChain<T> {
         store: Arc<T::Store>,
         spec: ChainSpec,
     ) -> Result<Option<BeaconChain<T>>, Error> {
+        // Upgrade the database to the current schema (or refuse to open it) before reading any
+        // items from it.
+        match schema::get_schema_version(store.as_ref())? {
+            Some(version) => schema::migrate_schema::<T>(&store, version)?,
+            None => return Ok(None),
+        }
+
         let key = Hash256::from_slice(&BEACON_CHAIN_DB_KEY.as_bytes());
         let p: PersistedBeaconChain<T> = match store.get(&key) {
             Err(e) => return Err(e.into()),
@@ -149,32 +175,59 @@ impl<T: BeaconChainTypes> BeaconChain<T> {
 
         let op_pool = p.op_pool.into_operation_pool(&p.state, &spec);
 
+        // Restore the votes seen prior to shutdown, if they were persisted.
+        let fork_choice_key = Hash256::from_slice(&FORK_CHOICE_DB_KEY.as_bytes());
+        let fork_choice = match store.get::<PersistedForkChoice>(&fork_choice_key)? {
+            Some(persisted) => ForkChoice::from_persisted(persisted, store.clone())?,
+            None => ForkChoice::new(store.clone(), last_finalized_block, last_finalized_root),
+        };
+
         Ok(Some(BeaconChain {
             spec,
             slot_clock,
-            fork_choice: ForkChoice::new(store.clone(), last_finalized_block, last_finalized_root),
+            fork_choice,
             op_pool,
             canonical_head: RwLock::new(p.canonical_head),
             state: RwLock::new(p.state),
             genesis_block_root: p.genesis_block_root,
             metrics: Metrics::new()?,
+            events: EventBus::default(),
             store,
         }))
     }
 
     /// Attempt to save this instance to `self.store`.
+    ///
+    /// The canonical head, operation pool, present-slot state and fork choice are written in a
+    /// single atomic batch.
     pub fn persist(&self) -> Result<(), Error> {
+        let mut batch = WriteBatch::new();
+        self.persist_to_batch(&mut batch, &self.head(), &self.state.read());
+        self.store.write_batch(&batch)?;
+
+        Ok(())
+    }
+
+    /// Add the writes which save this instance to `batch`, with the given `canonical_head` and
+    /// present-slot `state`.
+    fn persist_to_batch(
+        &self,
+        batch: &mut WriteBatch,
+        canonical_head: &CheckPoint<T::EthSpec>,
+        state: &BeaconState<T::EthSpec>,
+    ) {
         let p: PersistedBeaconChain<T> = PersistedBeaconChain {
-            canonical_head: self.canonical_head.read().clone(),
+            canonical_head: canonical_head.clone(),
             op_pool: PersistedOperationPool::from_operation_pool(&self.op_pool),
             genesis_block_root: self.genesis_block_root,
-            state: self.state.read().clone(),
+            state: state.clone(),
         };
 
         let key = Hash256::from_slice(&BEACON_CHAIN_DB_KEY.as_bytes());
-        self.store.put(&key, &p)?;
+        batch.put(&key, &p);
 
-        Ok(())
+        let fork_choice_key = Hash256::from_slice(&FORK_CHOICE_DB_KEY.as_bytes());
+        batch.put(&fork_choice_key, &self.fork_choice.as_persisted());
     }
 
     /// Returns the beacon block body for each beacon block root in `roots`.
@@ -243,6 +296,44 @@ impl<T: BeaconChainTypes> BeaconChain<T> {
         Ok(self.store.get(block_root)?)
     }
 
+    /// Returns the root of the block at `slot` in the canonical chain, in constant time.
+    ///
+    /// If `slot` was skipped, returns the root of the most recent prior block. Returns `None` if
+    /// `slot` is later than the canonical head.
+    ///
+    /// ## Errors
+    ///
+    /// May return a database error.
+    pub fn block_root_at_slot(&self, slot: Slot) -> Result<Option<Hash256>, Error> {
+        Ok(self.store.get_canonical_block_root(slot)?)
+    }
+
+    /// Returns the root of the state at `slot` in the canonical chain, in constant time.
+    ///
+    /// Returns `None` if `slot` is later than the canonical head.
+    ///
+    /// ## Errors
+    ///
+    /// May return a database error.
+    pub fn state_root_at_slot(&self, slot: Slot) -> Result<Option<Hash256>, Error> {
+        Ok(self.store.get_canonical_state_root(slot)?)
+    }
+
+    /// Returns the block at `slot` in the canonical chain.
+    ///
+    /// If `slot` was skipped, returns the most recent prior block. Returns `None` if `slot` is
+    /// later than the canonical head.
+    ///
+    /// ## Errors
+    ///
+    /// May return a database error.
+    pub fn block_at_slot(&self, slot: Slot) -> Result<Option<BeaconBlock>, Error> {
+        match self.block_root_at_slot(slot)? {
+            Some(block_root) => self.get_block(&block_root),
+            None => Ok(None),
+        }
+    }
+
     /// Returns a read-lock guarded `BeaconState` which is the `canonical_head` that has been
     /// updated to match the current slot clock.
     pub fn current_state(&self) -> RwLockReadGuard<BeaconState<T::EthSpec>> {
@@ -480,14 +571,16 @@ impl<T: BeaconChainTypes> BeaconChain<T> {
         self.metrics.attestation_processing_requests.inc();
         let timer = self.metrics.attestation_processing_times.start_timer();
 
-        let result = self
-            .op_pool
-            .insert_attestation(attestation, &*self.state.read(), &self.spec);
+        let result =
+            self.op_pool
+                .insert_attestation(attestation.clone(), &*self.state.read(), &self.spec);
 
         timer.observe_duration();
 
         if result.is_ok() {
             self.metrics.attestation_processing_successes.inc();
+            self.events
+                .publish(BeaconChainEvent::AttestationImported { attestation });
         }
 
         // TODO: process attestation. Please consider:
@@ -505,10 +598,27 @@ impl<T: BeaconChainTypes> BeaconChain<T> {
     }
 
     /// Accept some deposit and queue it for inclusion in an appropriate block.
+    ///
+    /// The deposit must not yet be included in the current state, and its Merkle proof must
+    /// verify against the state's Eth1 deposit root. A deposit which passes these checks replaces
+    /// any different deposit already pooled at the same index.
     pub fn process_deposit(
         &self,
         deposit: Deposit,
     ) -> Result<DepositInsertStatus, DepositValidationError> {
+        {
+            let state = self.state.read();
+
+            if deposit.index < state.deposit_index {
+                return Err(DepositValidationError::Invalid(DepositInvalid::BadIndex {
+                    state: state.deposit_index,
+                    deposit: deposit.index,
+                }));
+            }
+
+            verify_deposit_merkle_proof(&state, &deposit, &self.spec)?;
+        }
+
         self.op_pool.insert_deposit(deposit)
     }
 
@@ -628,23 +738,40 @@ impl<T: BeaconChainTypes> BeaconChain<T> {
             return Ok(BlockProcessingOutcome::StateRootMismatch);
         }
 
-        // Store the block and state.
-        self.store.put(&block_root, &block)?;
-        self.store.put(&state_root, &state)?;
+        // Store the block and state in a single batch, so that a block is never stored without
+        // its state. This happens before the block is given to fork choice, which reads blocks and
+        // states from the store.
+        let mut batch = WriteBatch::new();
+        batch.put(&block_root, &block);
+        batch.put(&state_root, &state);
+        self.store.write_batch(&batch)?;
 
         // Register the new block with the fork choice service.
         self.fork_choice.process_block(&state, &block, block_root)?;
 
+        let attestation_count = block.body.attestations.len();
+
+        // Store the fork choice in the same batch as any resulting change of head, so the votes
+        // from the attestations in this block are persisted even if the head does not change.
+        let mut batch = WriteBatch::new();
+        batch.put(
+            &Hash256::from_slice(&FORK_CHOICE_DB_KEY.as_bytes()),
+            &self.fork_choice.as_persisted(),
+        );
+
         // Execute the fork choice algorithm, enthroning a new head if discovered.
         //
         // Note: in the future we may choose to run fork-choice less often, potentially based upon
         // some heuristic around number of attestations seen for the block.
-        self.fork_choice()?;
+        self.fork_choice_with_batch(
+            batch,
+            Some(CheckPoint::new(block, block_root, state, state_root)),
+        )?;
 
         self.metrics.block_processing_successes.inc();
         self.metrics
             .operations_per_block_attestation
-            .observe(block.body.attestations.len() as f64);
+            .observe(attestation_count as f64);
         timer.observe_duration();
 
         Ok(BlockProcessingOutcome::Processed { block_root })
@@ -741,6 +868,19 @@ impl<T: BeaconChainTypes> BeaconChain<T> {
 
     /// Execute the fork choice algorithm and enthrone the result as the canonical head.
     pub fn fork_choice(&self) -> Result<(), Error> {
+        self.fork_choice_with_batch(WriteBatch::new(), None)
+    }
+
+    /// Execute the fork choice algorithm and enthrone the result as the canonical head, writing
+    /// `batch` to the store atomically with any change of head.
+    ///
+    /// If `imported` is supplied, it is the block and state which were just stored. It is announced
+    /// with a `BlockImported` event once `batch` is written, before any change of head.
+    fn fork_choice_with_batch(
+        &self,
+        batch: WriteBatch,
+        imported: Option<CheckPoint<T::EthSpec>>,
+    ) -> Result<(), Error> {
         self.metrics.fork_choice_requests.inc();
 
         // Start fork choice metrics timer.
@@ -752,43 +892,118 @@ impl<T: BeaconChainTypes> BeaconChain<T> {
         // End fork choice metrics timer.
         timer.observe_duration();
 
+        let imported_block = imported.as_ref().map(|checkpoint| {
+            (
+                checkpoint.beacon_block_root,
+                checkpoint.beacon_block.clone(),
+            )
+        });
+        let publish_imported = || {
+            if let Some((block_root, block)) = imported_block.clone() {
+                self.events
+                    .publish(BeaconChainEvent::BlockImported { block_root, block });
+            }
+        };
+
         // If a new head was chosen.
         if beacon_block_root != self.head().beacon_block_root {
             self.metrics.fork_choice_changed_head.inc();
 
-            let beacon_block: BeaconBlock = self
-                .store
-                .get(&beacon_block_root)?
-                .ok_or_else(|| Error::MissingBeaconBlock(beacon_block_root))?;
-
-            let beacon_state_root = beacon_block.state_root;
-            let beacon_state: BeaconState<T::EthSpec> = self
-                .store
-                .get(&beacon_state_root)?
-                .ok_or_else(|| Error::MissingBeaconState(beacon_state_root))?;
+            let (beacon_block, beacon_state, beacon_state_root) = match imported {
+                Some(checkpoint) if checkpoint.beacon_block_root == beacon_block_root => (
+                    checkpoint.beacon_block,
+                    checkpoint.beacon_state,
+                    checkpoint.beacon_state_root,
+                ),
+                _ => {
+                    let beacon_block: BeaconBlock = self
+                        .store
+                        .get(&beacon_block_root)?
+                        .ok_or_else(|| Error::MissingBeaconBlock(beacon_block_root))?;
+
+                    let beacon_state_root = beacon_block.state_root;
+                    let beacon_state: BeaconState<T::EthSpec> = self
+                        .store
+                        .get(&beacon_state_root)?
+                        .ok_or_else(|| Error::MissingBeaconState(beacon_state_root))?;
+
+                    (beacon_block, beacon_state, beacon_state_root)
+                }
+            };
 
             // If we switched to a new chain (instead of building atop the present chain).
             if self.head().beacon_block_root != beacon_block.previous_block_root {
                 self.metrics.fork_choice_reorg_count.inc();
             };
 
-            let old_finalized_epoch = self.head().beacon_state.finalized_epoch;
+            let (previous_head_root, previous_head_slot, old_justified, old_finalized) = {
+                let head = self.head();
+                let state = &head.beacon_state;
+                (
+                    head.beacon_block_root,
+                    head.beacon_block.slot,
+                    (state.current_justified_epoch, state.current_justified_root),
+                    (state.finalized_epoch, state.finalized_root),
+                )
+            };
+            let old_finalized_epoch = old_finalized.0;
             let new_finalized_epoch = beacon_state.finalized_epoch;
             let finalized_root = beacon_state.finalized_root;
+            let new_justified = (
+                beacon_state.current_justified_epoch,
+                beacon_state.current_justified_root,
+            );
+            let head_slot = beacon_block.slot;
 
             // Never revert back past a finalized epoch.
             if new_finalized_epoch < old_finalized_epoch {
+                // The head is unchanged, but the fork choice is still stored.
+                self.store.write_batch(&batch)?;
+                publish_imported();
+
                 Err(Error::RevertedFinalizedEpoch {
                     previous_epoch: old_finalized_epoch,
                     new_epoch: new_finalized_epoch,
                 })
             } else {
-                self.update_canonical_head(CheckPoint {
-                    beacon_block: beacon_block,
-                    beacon_block_root,
-                    beacon_state,
-                    beacon_state_root,
-                })?;
+                let common_slot = self.update_canonical_head(
+                    CheckPoint {
+                        beacon_block: beacon_block,
+                        beacon_block_root,
+                        beacon_state,
+                        beacon_state_root,
+                    },
+                    batch,
+                )?;
+
+                // Published before the change of head, so subscribers see the block first.
+                publish_imported();
+
+                self.events.publish(BeaconChainEvent::HeadChanged {
+                    previous_head_root,
+                    previous_head_slot,
+                    head_root: beacon_block_root,
+                    head_slot,
+                    reorg_depth: previous_head_slot
+                        .as_u64()
+                        .saturating_sub(common_slot.as_u64()),
+                });
+
+                if new_justified != old_justified {
+                    self.events
+                        .publish(BeaconChainEvent::JustifiedCheckpointChanged {
+                            epoch: new_justified.0,
+                            root: new_justified.1,
+                        });
+                }
+
+                if (new_finalized_epoch, finalized_root) != old_finalized {
+                    self.events
+                        .publish(BeaconChainEvent::FinalizedCheckpointChanged {
+                            epoch: new_finalized_epoch,
+                            root: finalized_root,
+                        });
+                }
 
                 if new_finalized_epoch != old_finalized_epoch {
                     self.after_finalization(old_finalized_epoch, finalized_root)?;
@@ -797,19 +1012,28 @@ impl<T: BeaconChainTypes> BeaconChain<T> {
                 Ok(())
             }
         } else {
+            self.store.write_batch(&batch)?;
+            publish_imported();
+
             Ok(())
         }
     }
 
-    /// Update the canonical head to `new_head`.
-    fn update_canonical_head(&self, new_head: CheckPoint<T::EthSpec>) -> Result<(), Error> {
-        // Update the checkpoint that stores the head of the chain at the time it received the
-        // block.
-        *self.canonical_head.write() = new_head;
+    /// Update the canonical head to `new_head`, writing `batch` to the store atomically with the
+    /// new head.
+    ///
+    /// Returns the latest slot at which the previous and new canonical chains agree.
+    fn update_canonical_head(
+        &self,
+        new_head: CheckPoint<T::EthSpec>,
+        mut batch: WriteBatch,
+    ) -> Result<Slot, Error> {
+        let old_head_slot = self.canonical_head.read().beacon_block.slot;
+        let common_slot = self.update_canonical_index(&mut batch, old_head_slot, &new_head)?;
 
-        // Update the always-at-the-present-slot state we keep around for performance gains.
-        *self.state.write() = {
-            let mut state = self.canonical_head.read().beacon_state.clone();
+        // Build the always-at-the-present-slot state we keep around for performance gains.
+        let state = {
+            let mut state = new_head.beacon_state.clone();
 
             let present_slot = match self.slot_clock.present_slot() {
                 Ok(Some(slot)) => slot,
@@ -826,10 +1050,72 @@ impl<T: BeaconChainTypes> BeaconChain<T> {
             state
         };
 
-        // Save `self` to `self.store`.
-        self.persist()?;
+        // Save the new head to `self.store`, atomically with the canonical index and `batch`.
+        //
+        // The store is written before the in-memory head is updated, so that any reader of the
+        // head may find its block and state in the store.
+        self.persist_to_batch(&mut batch, &new_head, &state);
+        self.store.write_batch(&batch)?;
 
-        Ok(())
+        // Update the checkpoint that stores the head of the chain at the time it received the
+        // block.
+        *self.canonical_head.write() = new_head;
+        *self.state.write() = state;
+
+        Ok(common_slot)
+    }
+
+    /// Add writes to `batch` which update the canonical index from a chain with its head at
+    /// `old_head_slot` to the chain with its head at `new_head`.
+    ///
+    /// Walks back from `new_head` until the index agrees with the new chain, so the cost is
+    /// proportional to the distance from the previous head to the common ancestor.
+    ///
+    /// Returns the slot at which the index first agrees with the new chain (i.e., the latest slot
+    /// shared by both chains), or the lowest slot visited if it never agrees.
+    fn update_canonical_index(
+        &self,
+        batch: &mut WriteBatch,
+        old_head_slot: Slot,
+        new_head: &CheckPoint<T::EthSpec>,
+    ) -> Result<Slot, Error> {
+        let head_slot = new_head.beacon_state.slot;
+
+        // Remove any slots beyond the new head, which belonged to a longer chain.
+        for slot in head_slot.as_u64() + 1..=old_head_slot.as_u64() {
+            canonical_index::delete_canonical_roots(batch, Slot::new(slot));
+        }
+
+        let state = &new_head.beacon_state;
+        let ancestors = BlockRootsIterator::new(self.store.clone(), state, head_slot)
+            .zip(StateRootsIterator::new(
+                self.store.clone(),
+                state,
+                head_slot,
+            ))
+            .map(|((block_root, slot), (state_root, _))| (block_root, state_root, slot));
+        let roots = std::iter::once((
+            new_head.beacon_block_root,
+            new_head.beacon_state_root,
+            head_slot,
+        ))
+        .chain(ancestors);
+
+        let mut common_slot = head_slot;
+
+        for (block_root, state_root, slot) in roots {
+            common_slot = slot;
+
+            if self.store.get_canonical_block_root(slot)? == Some(block_root)
+                && self.store.get_canonical_state_root(slot)? == Some(state_root)
+            {
+                break;
+            }
+
+            canonical_index::put_canonical_roots(batch, slot, block_root, state_root);
+        }
+
+        Ok(common_slot)
     }
 
     /// Called after `self` has had a new block finalized.
@@ -856,6 +1142,16 @@ impl<T: BeaconChainTypes> BeaconChain<T> {
             self.fork_choice
                 .process_finalization(&finalized_block, finalized_block_root)?;
 
+            let finalized_state_root = finalized_block.state_root;
+            let finalized_state = self
+                .store
+                .get::<BeaconState<T::EthSpec>>(&finalized_state_root)?
+                .ok_or_else(|| Error::MissingBeaconState(finalized_state_root))?;
+
+            // Allow the store to migrate blocks and states prior to finalization into long-term
+            // storage.
+            T::Store::freeze_to_state(self.store.clone(), finalized_state_root, &finalized_state)?;
+
             Ok(())
         }
     }
@@ -912,6 +1208,13 @@ impl<T: BeaconChainTypes> BeaconChain<T> {
 
         Ok(dump)
     }
+
+    /// Returns a snapshot of the fork choice tree, including the latest votes and weights.
+    ///
+    /// Intended for debugging forks, see `TreeDump::to_json` and `TreeDump::to_dot`.
+    pub fn fork_choice_dump(&self) -> Result<TreeDump, Error> {
+        Ok(self.fork_choice.dump()?)
+    }
 }
 
 impl From<DBError> for Error {
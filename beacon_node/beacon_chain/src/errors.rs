@@ -1,5 +1,6 @@
 use crate::fork_choice::Error as ForkChoiceError;
 use crate::metrics::Error as MetricsError;
+use crate::schema::Error as SchemaError;
 use state_processing::BlockProcessingError;
 use state_processing::SlotProcessingError;
 use types::*;
@@ -31,9 +32,11 @@ pub enum BeaconChainError {
     MissingBeaconState(Hash256),
     SlotProcessingError(SlotProcessingError),
     MetricsError(String),
+    SchemaError(SchemaError),
 }
 
 easy_from_to!(SlotProcessingError, BeaconChainError);
+easy_from_to!(SchemaError, BeaconChainError);
 
 impl From<MetricsError> for BeaconChainError {
     fn from(e: MetricsError) -> BeaconChainError {
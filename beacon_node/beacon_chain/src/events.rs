@@ -0,0 +1,225 @@
+//! A publish/subscribe bus for changes to the `BeaconChain`.
+//!
+//! Each subscriber receives events on its own bounded channel. A subscriber that falls behind
+//! misses events (rather than blocking the beacon chain), except for reorgs and changes of
+//! justified or finalized checkpoint, which are queued until there is space in its channel. A
+//! subscriber that drops its receiver is removed when the next event is published.
+use crossbeam_channel::{bounded, Receiver, Sender, TrySendError};
+use parking_lot::Mutex;
+use serde_derive::Serialize;
+use std::collections::VecDeque;
+use types::{Attestation, BeaconBlock, Epoch, Hash256, Slot};
+
+/// The number of events that may be queued for a subscriber before events are dropped.
+pub const EVENT_CHANNEL_CAPACITY: usize = 1_024;
+
+/// A change to the `BeaconChain`.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+#[serde(tag = "event", rename_all = "snake_case")]
+pub enum BeaconChainEvent {
+    /// A valid block was imported, although it is not necessarily the head.
+    BlockImported {
+        block_root: Hash256,
+        block: BeaconBlock,
+    },
+    /// A valid attestation was added to the operation pool.
+    AttestationImported { attestation: Attestation },
+    /// Fork choice chose a new head.
+    ///
+    /// `reorg_depth` is the number of slots between the previous head and the latest slot at
+    /// which the previous and new chains agree. It is zero if the new head descends from the
+    /// previous head.
+    HeadChanged {
+        previous_head_root: Hash256,
+        previous_head_slot: Slot,
+        head_root: Hash256,
+        head_slot: Slot,
+        reorg_depth: u64,
+    },
+    /// The head state has a different current justified checkpoint to the previous head.
+    JustifiedCheckpointChanged { epoch: Epoch, root: Hash256 },
+    /// The head state has a different finalized checkpoint to the previous head.
+    FinalizedCheckpointChanged { epoch: Epoch, root: Hash256 },
+}
+
+impl BeaconChainEvent {
+    /// Returns `true` if the event is a head change which reverted blocks from the previous chain.
+    pub fn is_reorg(&self) -> bool {
+        match self {
+            BeaconChainEvent::HeadChanged { reorg_depth, .. } => *reorg_depth > 0,
+            _ => false,
+        }
+    }
+
+    /// Returns `true` if the event must never be dropped, even for a subscriber that has fallen
+    /// behind.
+    pub fn is_critical(&self) -> bool {
+        match self {
+            BeaconChainEvent::JustifiedCheckpointChanged { .. }
+            | BeaconChainEvent::FinalizedCheckpointChanged { .. } => true,
+            _ => self.is_reorg(),
+        }
+    }
+}
+
+/// The sending half of a subscription.
+struct Subscriber {
+    sender: Sender<BeaconChainEvent>,
+    /// Critical events which did not fit in the channel, oldest first.
+    pending: VecDeque<BeaconChainEvent>,
+}
+
+impl Subscriber {
+    /// Send `event`, after any pending events. Returns `false` if the receiver has been dropped.
+    fn send(&mut self, event: &BeaconChainEvent) -> bool {
+        while let Some(pending) = self.pending.pop_front() {
+            match self.sender.try_send(pending) {
+                Ok(()) => {}
+                Err(TrySendError::Full(pending)) => {
+                    self.pending.push_front(pending);
+                    break;
+                }
+                Err(TrySendError::Disconnected(_)) => return false,
+            }
+        }
+
+        // Events may only be sent directly if none are pending, otherwise they would be received
+        // out of order.
+        if self.pending.is_empty() {
+            match self.sender.try_send(event.clone()) {
+                Ok(()) => {}
+                Err(TrySendError::Full(event)) => {
+                    if event.is_critical() {
+                        self.pending.push_back(event);
+                    }
+                }
+                Err(TrySendError::Disconnected(_)) => return false,
+            }
+        } else if event.is_critical() {
+            self.pending.push_back(event.clone());
+        }
+
+        true
+    }
+}
+
+/// Distributes each `BeaconChainEvent` to all current subscribers.
+#[derive(Default)]
+pub struct EventBus {
+    subscribers: Mutex<Vec<Subscriber>>,
+}
+
+impl EventBus {
+    /// Returns a receiver for all events published after this call.
+    pub fn subscribe(&self) -> Receiver<BeaconChainEvent> {
+        let (sender, receiver) = bounded(EVENT_CHANNEL_CAPACITY);
+        self.subscribers.lock().push(Subscriber {
+            sender,
+            pending: VecDeque::new(),
+        });
+        receiver
+    }
+
+    /// Returns the number of subscribers, including any which have been dropped since the last
+    /// event was published.
+    pub fn subscriber_count(&self) -> usize {
+        self.subscribers.lock().len()
+    }
+
+    /// Send `event` to all subscribers.
+    ///
+    /// For any subscriber whose channel is full, the event is dropped unless it is critical, in
+    /// which case it is sent during a later call to `publish`.
+    pub fn publish(&self, event: BeaconChainEvent) {
+        let mut subscribers = self.subscribers.lock();
+
+        let retained = subscribers
+            .drain(..)
+            .filter_map(|mut subscriber| {
+                if subscriber.send(&event) {
+                    Some(subscriber)
+                } else {
+                    None
+                }
+            })
+            .collect();
+
+        *subscribers = retained;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn head_changed(head_slot: u64, reorg_depth: u64) -> BeaconChainEvent {
+        BeaconChainEvent::HeadChanged {
+            previous_head_root: Hash256::zero(),
+            previous_head_slot: Slot::new(head_slot - 1),
+            head_root: Hash256::from(head_slot),
+            head_slot: Slot::new(head_slot),
+            reorg_depth,
+        }
+    }
+
+    fn finalized(epoch: u64) -> BeaconChainEvent {
+        BeaconChainEvent::FinalizedCheckpointChanged {
+            epoch: Epoch::new(epoch),
+            root: Hash256::from(epoch),
+        }
+    }
+
+    #[test]
+    fn critical_events_are_not_dropped() {
+        let bus = EventBus::default();
+        let events = bus.subscribe();
+
+        for slot in 1..=EVENT_CHANNEL_CAPACITY as u64 {
+            bus.publish(head_changed(slot, 0));
+        }
+
+        // The channel is full.
+        let overflow = EVENT_CHANNEL_CAPACITY as u64 + 1;
+        bus.publish(head_changed(overflow, 0));
+        bus.publish(finalized(1));
+        bus.publish(head_changed(overflow + 1, 2));
+        bus.publish(head_changed(overflow + 2, 0));
+
+        let received: Vec<_> = events.try_iter().collect();
+        assert_eq!(received.len(), EVENT_CHANNEL_CAPACITY);
+        assert_eq!(
+            received.last(),
+            Some(&head_changed(EVENT_CHANNEL_CAPACITY as u64, 0))
+        );
+
+        // Queued critical events are sent before the next event, whilst the non-critical events
+        // published after them were dropped.
+        bus.publish(head_changed(overflow + 3, 0));
+        assert_eq!(
+            events.try_iter().collect::<Vec<_>>(),
+            vec![
+                finalized(1),
+                head_changed(overflow + 1, 2),
+                head_changed(overflow + 3, 0)
+            ]
+        );
+
+        bus.publish(head_changed(overflow + 4, 0));
+        assert_eq!(
+            events.try_iter().collect::<Vec<_>>(),
+            vec![head_changed(overflow + 4, 0)]
+        );
+    }
+
+    #[test]
+    fn dropped_subscribers_are_removed() {
+        let bus = EventBus::default();
+        let kept = bus.subscribe();
+        drop(bus.subscribe());
+
+        assert_eq!(bus.subscriber_count(), 2);
+        bus.publish(finalized(1));
+        assert_eq!(bus.subscriber_count(), 1);
+        assert_eq!(kept.try_recv(), Ok(finalized(1)));
+    }
+}
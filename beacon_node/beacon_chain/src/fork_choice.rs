@@ -1,5 +1,6 @@
+use crate::persisted_fork_choice::PersistedForkChoice;
 use crate::{BeaconChain, BeaconChainTypes};
-use lmd_ghost::LmdGhost;
+use lmd_ghost::{LmdGhost, TreeDump};
 use state_processing::common::get_attesting_indices_unsorted;
 use std::sync::Arc;
 use store::{Error as StoreError, Store};
@@ -41,6 +42,22 @@ impl<T: BeaconChainTypes> ForkChoice<T> {
         }
     }
 
+    /// Returns the state of `self` in a form which may be written to the store.
+    pub fn as_persisted(&self) -> PersistedForkChoice {
+        PersistedForkChoice {
+            backend_bytes: self.backend.as_bytes(),
+            genesis_block_root: self.genesis_block_root,
+        }
+    }
+
+    /// Restore a fork chooser from the output of `as_persisted`.
+    pub fn from_persisted(persisted: PersistedForkChoice, store: Arc<T::Store>) -> Result<Self> {
+        Ok(Self {
+            backend: T::LmdGhost::from_bytes(&persisted.backend_bytes, store)?,
+            genesis_block_root: persisted.genesis_block_root,
+        })
+    }
+
     pub fn find_head(&self, chain: &BeaconChain<T>) -> Result<Hash256> {
         let start_slot = |epoch: Epoch| epoch.start_slot(T::EthSpec::slots_per_epoch());
 
@@ -175,6 +192,11 @@ impl<T: BeaconChainTypes> ForkChoice<T> {
             .update_finalized_root(finalized_block, finalized_block_root)
             .map_err(Into::into)
     }
+
+    /// Returns a snapshot of the blocks, votes and weights known to the backend, for debugging.
+    pub fn dump(&self) -> Result<TreeDump> {
+        self.backend.dump().map_err(Into::into)
+    }
 }
 
 impl From<BeaconStateError> for Error {
@@ -1,20 +1,27 @@
 mod beacon_chain;
 mod checkpoint;
 mod errors;
+mod events;
 mod fork_choice;
 mod metrics;
 mod persisted_beacon_chain;
+mod persisted_fork_choice;
+pub mod schema;
 pub mod test_utils;
 
 pub use self::beacon_chain::{BeaconChain, BeaconChainTypes, BlockProcessingOutcome};
 pub use self::checkpoint::CheckPoint;
 pub use self::errors::{BeaconChainError, BlockProductionError};
+pub use self::events::{BeaconChainEvent, EventBus, EVENT_CHANNEL_CAPACITY};
+pub use self::persisted_beacon_chain::{PersistedBeaconChain, BEACON_CHAIN_DB_KEY};
+pub use self::persisted_fork_choice::{PersistedForkChoice, FORK_CHOICE_DB_KEY};
 pub use lmd_ghost;
 pub use parking_lot;
 pub use slot_clock;
 pub use state_processing::per_block_processing::errors::{
-    AttestationValidationError, AttesterSlashingValidationError, DepositValidationError,
-    ExitValidationError, ProposerSlashingValidationError, TransferValidationError,
+    AttestationInvalid, AttestationValidationError, AttesterSlashingValidationError,
+    DepositValidationError, ExitValidationError, ProposerSlashingValidationError,
+    TransferValidationError,
 };
 pub use store;
 pub use types;
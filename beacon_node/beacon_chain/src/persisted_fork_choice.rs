@@ -0,0 +1,32 @@
+use ssz::{Decode, Encode};
+use ssz_derive::{Decode, Encode};
+use store::{DBColumn, Error as StoreError, StoreItem};
+use types::Hash256;
+
+/// 32-byte key for accessing the `PersistedForkChoice`.
+pub const FORK_CHOICE_DB_KEY: &str = "PERSISTEDFORKCHOICEPERSISTEDFORK";
+
+/// The state of the `ForkChoice`, written alongside the `PersistedBeaconChain`.
+///
+/// Databases written before fork choice was persisted do not contain this item, in which case the
+/// fork choice is rebuilt without any votes.
+#[derive(Encode, Decode)]
+pub struct PersistedForkChoice {
+    /// The output of `LmdGhost::as_bytes`.
+    pub backend_bytes: Vec<u8>,
+    pub genesis_block_root: Hash256,
+}
+
+impl StoreItem for PersistedForkChoice {
+    fn db_column() -> DBColumn {
+        DBColumn::BeaconChain
+    }
+
+    fn as_store_bytes(&self) -> Vec<u8> {
+        self.as_ssz_bytes()
+    }
+
+    fn from_store_bytes(bytes: &mut [u8]) -> Result<Self, StoreError> {
+        Self::from_ssz_bytes(bytes).map_err(Into::into)
+    }
+}
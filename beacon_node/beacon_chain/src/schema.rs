@@ -0,0 +1,260 @@
+//! Versioning of the on-disk database layout.
+//!
+//! A `SchemaVersion` is stored in `DBColumn::BeaconChain` when a database is created. Any change to
+//! the encoding of a `StoreItem`, or to the layout of a `Store`, must increment
+//! `CURRENT_SCHEMA_VERSION` and add a `Migration` from the previous version to `migration`.
+//!
+//! When an existing database is opened, each migration from the stored version to the current
+//! version is applied in turn. Databases created by a newer version of Lighthouse, or that have no
+//! migration path, are refused.
+use crate::persisted_beacon_chain::{PersistedBeaconChain, BEACON_CHAIN_DB_KEY};
+use crate::BeaconChainTypes;
+use ssz::{Decode, Encode};
+use std::sync::Arc;
+use store::canonical_index;
+use store::iter::{BlockRootsIterator, StateRootsIterator};
+use store::{DBColumn, Error as StoreError, Store, StoreItem, WriteBatch};
+use types::Hash256;
+
+/// 32-byte key for accessing the `SchemaVersion`.
+pub const SCHEMA_VERSION_DB_KEY: &str = "SCHEMAVERSIONSCHEMAVERSIONSCHEMA";
+
+/// The version of the database layout produced by this version of Lighthouse.
+pub const CURRENT_SCHEMA_VERSION: SchemaVersion = SchemaVersion(2);
+
+/// A step that upgrades a database from some version to the next.
+///
+/// Any writes must be added to the given `WriteBatch`, which is applied atomically along with the
+/// new `SchemaVersion`.
+type Migration<T> =
+    fn(&Arc<<T as BeaconChainTypes>::Store>, &mut WriteBatch) -> Result<(), StoreError>;
+
+/// The version of the layout of a database.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub struct SchemaVersion(pub u64);
+
+impl StoreItem for SchemaVersion {
+    fn db_column() -> DBColumn {
+        DBColumn::BeaconChain
+    }
+
+    fn as_store_bytes(&self) -> Vec<u8> {
+        self.0.as_ssz_bytes()
+    }
+
+    fn from_store_bytes(bytes: &mut [u8]) -> Result<Self, StoreError> {
+        Ok(SchemaVersion(u64::from_ssz_bytes(bytes)?))
+    }
+}
+
+#[derive(Debug, PartialEq)]
+pub enum Error {
+    /// The database was written by a newer version of Lighthouse.
+    UnsupportedSchemaVersion {
+        stored: SchemaVersion,
+        current: SchemaVersion,
+    },
+    /// There is no migration from the stored version.
+    NoMigrationPath {
+        from: SchemaVersion,
+    },
+    StoreError(StoreError),
+}
+
+impl From<StoreError> for Error {
+    fn from(e: StoreError) -> Error {
+        Error::StoreError(e)
+    }
+}
+
+/// Returns the key of the `SchemaVersion`.
+fn schema_version_key() -> Hash256 {
+    Hash256::from_slice(SCHEMA_VERSION_DB_KEY.as_bytes())
+}
+
+/// Returns the migration from `version` to the next version, if any.
+fn migration<T: BeaconChainTypes>(version: SchemaVersion) -> Option<Migration<T>> {
+    match version {
+        SchemaVersion(0) => Some(add_schema_version::<T> as Migration<T>),
+        SchemaVersion(1) => Some(add_canonical_index::<T> as Migration<T>),
+        _ => None,
+    }
+}
+
+/// Migrate from v0 to v1.
+///
+/// Databases created before schema versioning have a layout identical to v1, except for the
+/// absence of a version record (which is added by `migrate_schema`).
+fn add_schema_version<T: BeaconChainTypes>(
+    _store: &Arc<T::Store>,
+    _batch: &mut WriteBatch,
+) -> Result<(), StoreError> {
+    Ok(())
+}
+
+/// Migrate from v1 to v2.
+///
+/// v2 adds the canonical index (see `store::canonical_index`), which is backfilled from the
+/// persisted canonical head back to genesis.
+fn add_canonical_index<T: BeaconChainTypes>(
+    store: &Arc<T::Store>,
+    batch: &mut WriteBatch,
+) -> Result<(), StoreError> {
+    let key = Hash256::from_slice(BEACON_CHAIN_DB_KEY.as_bytes());
+    let head = match store.get::<PersistedBeaconChain<T>>(&key)? {
+        Some(persisted_chain) => persisted_chain.canonical_head,
+        None => return Ok(()),
+    };
+
+    let state = &head.beacon_state;
+    let head_slot = state.slot;
+
+    canonical_index::put_canonical_roots(
+        batch,
+        head_slot,
+        head.beacon_block_root,
+        head.beacon_state_root,
+    );
+
+    let ancestors = BlockRootsIterator::new(store.clone(), state, head_slot)
+        .zip(StateRootsIterator::new(store.clone(), state, head_slot));
+
+    for ((block_root, slot), (state_root, _)) in ancestors {
+        canonical_index::put_canonical_roots(batch, slot, block_root, state_root);
+    }
+
+    Ok(())
+}
+
+/// Add the current schema version to `batch`, for use when creating a new database.
+pub fn put_current_schema_version(batch: &mut WriteBatch) {
+    batch.put(&schema_version_key(), &CURRENT_SCHEMA_VERSION);
+}
+
+/// Read the schema version of `store`.
+///
+/// Returns `None` if the database is empty. A non-empty database without a version record predates
+/// schema versioning and is treated as `SchemaVersion(0)`.
+pub fn get_schema_version<S: Store>(store: &S) -> Result<Option<SchemaVersion>, Error> {
+    if let Some(version) = store.get(&schema_version_key())? {
+        return Ok(Some(version));
+    }
+
+    let persisted_chain_key = Hash256::from_slice(BEACON_CHAIN_DB_KEY.as_bytes());
+    let chain_column: &str = DBColumn::BeaconChain.into();
+
+    if store.key_exists(chain_column, persisted_chain_key.as_bytes())? {
+        Ok(Some(SchemaVersion(0)))
+    } else {
+        Ok(None)
+    }
+}
+
+/// Upgrade `store` from `from` to `CURRENT_SCHEMA_VERSION`, one version at a time.
+///
+/// Each migration is applied atomically along with its resulting version, so an interrupted
+/// upgrade may be resumed.
+pub fn migrate_schema<T: BeaconChainTypes>(
+    store: &Arc<T::Store>,
+    from: SchemaVersion,
+) -> Result<(), Error> {
+    if from > CURRENT_SCHEMA_VERSION {
+        return Err(Error::UnsupportedSchemaVersion {
+            stored: from,
+            current: CURRENT_SCHEMA_VERSION,
+        });
+    }
+
+    let mut version = from;
+
+    while version < CURRENT_SCHEMA_VERSION {
+        let migrate = migration::<T>(version).ok_or_else(|| Error::NoMigrationPath { from })?;
+        let next = SchemaVersion(version.0 + 1);
+
+        let mut batch = WriteBatch::new();
+        migrate(store, &mut batch)?;
+        batch.put(&schema_version_key(), &next);
+        store.write_batch(&batch)?;
+
+        version = next;
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_utils::CommonTypes;
+    use lmd_ghost::ThreadSafeReducedTree;
+    use store::MemoryStore;
+    use types::MinimalEthSpec;
+
+    type TestTypes =
+        CommonTypes<ThreadSafeReducedTree<MemoryStore, MinimalEthSpec>, MinimalEthSpec>;
+
+    #[test]
+    fn empty_database_has_no_version() {
+        let store = MemoryStore::open();
+
+        assert_eq!(get_schema_version(&store), Ok(None));
+    }
+
+    #[test]
+    fn new_database_is_current() {
+        let store = Arc::new(MemoryStore::open());
+
+        let mut batch = WriteBatch::new();
+        put_current_schema_version(&mut batch);
+        store.write_batch(&batch).unwrap();
+
+        assert_eq!(
+            get_schema_version(store.as_ref()),
+            Ok(Some(CURRENT_SCHEMA_VERSION))
+        );
+        assert_eq!(
+            migrate_schema::<TestTypes>(&store, CURRENT_SCHEMA_VERSION),
+            Ok(())
+        );
+    }
+
+    #[test]
+    fn unversioned_database_is_detected() {
+        let store = MemoryStore::open();
+        let chain_column: &str = DBColumn::BeaconChain.into();
+        store
+            .put_bytes(chain_column, BEACON_CHAIN_DB_KEY.as_bytes(), &[])
+            .unwrap();
+
+        assert_eq!(get_schema_version(&store), Ok(Some(SchemaVersion(0))));
+    }
+
+    #[test]
+    fn database_without_chain_is_migrated() {
+        let store = Arc::new(MemoryStore::open());
+        store.put(&schema_version_key(), &SchemaVersion(1)).unwrap();
+
+        migrate_schema::<TestTypes>(&store, SchemaVersion(1)).unwrap();
+
+        assert_eq!(
+            get_schema_version(store.as_ref()),
+            Ok(Some(CURRENT_SCHEMA_VERSION))
+        );
+    }
+
+    #[test]
+    fn newer_database_is_refused() {
+        let store = Arc::new(MemoryStore::open());
+        let newer = SchemaVersion(CURRENT_SCHEMA_VERSION.0 + 1);
+        store.put(&schema_version_key(), &newer).unwrap();
+
+        assert_eq!(
+            migrate_schema::<TestTypes>(&store, newer),
+            Err(Error::UnsupportedSchemaVersion {
+                stored: newer,
+                current: CURRENT_SCHEMA_VERSION,
+            })
+        );
+        assert_eq!(get_schema_version(store.as_ref()), Ok(Some(newer)));
+    }
+}
@@ -69,7 +69,7 @@ where
     L: LmdGhost<MemoryStore, E>,
     E: EthSpec,
 {
-    pub chain: BeaconChain<CommonTypes<L, E>>,
+    pub chain: Arc<BeaconChain<CommonTypes<L, E>>>,
     pub keypairs: Vec<Keypair>,
     pub spec: ChainSpec,
 }
@@ -109,7 +109,7 @@ where
         .expect("Terminate if beacon chain generation fails");
 
         Self {
-            chain,
+            chain: Arc::new(chain),
             keypairs,
             spec,
         }
@@ -1,14 +1,21 @@
 #![cfg(not(debug_assertions))]
 
+use beacon_chain::schema::{self, SchemaVersion, CURRENT_SCHEMA_VERSION, SCHEMA_VERSION_DB_KEY};
 use beacon_chain::test_utils::{
     AttestationStrategy, BeaconChainHarness, BlockStrategy, CommonTypes, PersistedBeaconChain,
     BEACON_CHAIN_DB_KEY,
 };
-use lmd_ghost::ThreadSafeReducedTree;
+use beacon_chain::{
+    BeaconChain, BeaconChainError, BeaconChainEvent, PersistedForkChoice, FORK_CHOICE_DB_KEY,
+};
+use lmd_ghost::{LmdGhost, ThreadSafeReducedTree};
 use rand::Rng;
-use store::{MemoryStore, Store};
+use std::sync::Arc;
+use store::iter::{BlockRootsIterator, StateRootsIterator};
+use store::{canonical_index, HotColdDB, MemoryStore, Store, WriteBatch};
+use tempfile::tempdir;
 use types::test_utils::{SeedableRng, TestRandom, XorShiftRng};
-use types::{Deposit, EthSpec, Hash256, MinimalEthSpec, Slot};
+use types::{BeaconBlock, Deposit, EthSpec, Hash256, MinimalEthSpec, Slot};
 
 // Should ideally be divisible by 3.
 pub const VALIDATOR_COUNT: usize = 24;
@@ -83,6 +90,182 @@ fn fork() {
     );
 }
 
+/// Checks that the canonical index of `harness` agrees with the state of its head.
+fn assert_canonical_index_matches_head(
+    harness: &BeaconChainHarness<TestForkChoice, MinimalEthSpec>,
+) {
+    let chain = &harness.chain;
+    let head = chain.head();
+    let head_slot = head.beacon_block.slot;
+
+    assert_eq!(
+        chain.block_root_at_slot(head_slot),
+        Ok(Some(head.beacon_block_root))
+    );
+    assert_eq!(
+        chain.state_root_at_slot(head_slot),
+        Ok(Some(head.beacon_state_root))
+    );
+
+    for (block_root, slot) in
+        BlockRootsIterator::new(chain.store.clone(), &head.beacon_state, head_slot)
+    {
+        assert_eq!(chain.block_root_at_slot(slot), Ok(Some(block_root)));
+    }
+
+    for (state_root, slot) in
+        StateRootsIterator::new(chain.store.clone(), &head.beacon_state, head_slot)
+    {
+        assert_eq!(chain.state_root_at_slot(slot), Ok(Some(state_root)));
+    }
+
+    assert_eq!(chain.block_root_at_slot(head_slot + 1), Ok(None));
+    assert_eq!(chain.state_root_at_slot(head_slot + 1), Ok(None));
+}
+
+#[test]
+fn canonical_index_follows_reorg() {
+    let harness = get_harness(VALIDATOR_COUNT);
+
+    let two_thirds = (VALIDATOR_COUNT / 3) * 2;
+    let delay = MinimalEthSpec::default_spec().min_attestation_inclusion_delay as usize;
+
+    let honest_validators: Vec<usize> = (0..two_thirds).collect();
+    let faulty_validators: Vec<usize> = (two_thirds..VALIDATOR_COUNT).collect();
+
+    let initial_blocks = delay + 1;
+
+    harness.extend_chain(
+        initial_blocks,
+        BlockStrategy::OnCanonicalHead,
+        AttestationStrategy::AllValidators,
+    );
+    assert_canonical_index_matches_head(&harness);
+
+    harness.advance_slot();
+
+    // Build a long chain supported by a minority, which is canonical until a competing chain
+    // appears.
+    let faulty_head = harness.extend_chain(
+        delay + 3,
+        BlockStrategy::OnCanonicalHead,
+        AttestationStrategy::SomeValidators(faulty_validators),
+    );
+    assert_eq!(harness.chain.head().beacon_block_root, faulty_head);
+    assert_canonical_index_matches_head(&harness);
+
+    // Build a shorter chain (with a skipped slot) supported by the majority, which should become
+    // canonical.
+    let honest_head = harness.extend_chain(
+        delay + 1,
+        BlockStrategy::ForkCanonicalChainAt {
+            previous_slot: Slot::from(initial_blocks),
+            first_slot: Slot::from(initial_blocks + 2),
+        },
+        AttestationStrategy::SomeValidators(honest_validators),
+    );
+    assert_eq!(harness.chain.head().beacon_block_root, honest_head);
+    assert_canonical_index_matches_head(&harness);
+}
+
+#[test]
+fn events_report_head_changes_and_reorgs() {
+    let harness = get_harness(VALIDATOR_COUNT);
+    let events = harness.chain.events.subscribe();
+
+    let two_thirds = (VALIDATOR_COUNT / 3) * 2;
+    let delay = MinimalEthSpec::default_spec().min_attestation_inclusion_delay as usize;
+
+    let honest_validators: Vec<usize> = (0..two_thirds).collect();
+    let faulty_validators: Vec<usize> = (two_thirds..VALIDATOR_COUNT).collect();
+
+    let initial_blocks = delay + 1;
+    let faulty_blocks = delay + 3;
+    let honest_blocks = delay + 1;
+
+    harness.extend_chain(
+        initial_blocks,
+        BlockStrategy::OnCanonicalHead,
+        AttestationStrategy::AllValidators,
+    );
+    harness.advance_slot();
+
+    let faulty_head = harness.extend_chain(
+        faulty_blocks,
+        BlockStrategy::OnCanonicalHead,
+        AttestationStrategy::SomeValidators(faulty_validators),
+    );
+
+    let before_fork: Vec<BeaconChainEvent> = events.try_iter().collect();
+
+    assert!(
+        before_fork.iter().all(|event| !event.is_reorg()),
+        "extending the head should not reorg"
+    );
+    assert_eq!(
+        before_fork
+            .iter()
+            .filter(|event| match event {
+                BeaconChainEvent::HeadChanged { .. } => true,
+                _ => false,
+            })
+            .count(),
+        initial_blocks + faulty_blocks,
+        "every block should change the head"
+    );
+    assert!(
+        before_fork.iter().any(|event| match event {
+            BeaconChainEvent::AttestationImported { .. } => true,
+            _ => false,
+        }),
+        "attestations should be reported"
+    );
+
+    let honest_head = harness.extend_chain(
+        honest_blocks,
+        BlockStrategy::ForkCanonicalChainAt {
+            previous_slot: Slot::from(initial_blocks),
+            first_slot: Slot::from(initial_blocks + 2),
+        },
+        AttestationStrategy::SomeValidators(honest_validators),
+    );
+    assert_eq!(harness.chain.head().beacon_block_root, honest_head);
+
+    let after_fork: Vec<BeaconChainEvent> = events.try_iter().collect();
+
+    assert_eq!(
+        after_fork
+            .iter()
+            .filter(|event| match event {
+                BeaconChainEvent::BlockImported { .. } => true,
+                _ => false,
+            })
+            .count(),
+        honest_blocks,
+        "every block should be reported"
+    );
+
+    let reorgs: Vec<&BeaconChainEvent> = after_fork.iter().filter(|e| e.is_reorg()).collect();
+    assert_eq!(reorgs.len(), 1, "the chain should reorg exactly once");
+
+    match reorgs[0] {
+        BeaconChainEvent::HeadChanged {
+            previous_head_root,
+            previous_head_slot,
+            reorg_depth,
+            ..
+        } => {
+            assert_eq!(*previous_head_root, faulty_head);
+            // The chains agree up to the last of the initial blocks.
+            assert_eq!(
+                *reorg_depth,
+                previous_head_slot.as_u64() - initial_blocks as u64
+            );
+        }
+        _ => unreachable!(),
+    }
+}
+
 #[test]
 fn finalizes_with_full_participation() {
     let num_blocks_produced = MinimalEthSpec::slots_per_epoch() * 5;
@@ -250,7 +433,8 @@ fn roundtrip_operation_pool() {
     for _ in 0..rng.gen_range(1, VALIDATOR_COUNT) {
         harness
             .chain
-            .process_deposit(Deposit::random_for_test(rng))
+            .op_pool
+            .insert_deposit(Deposit::random_for_test(rng))
             .unwrap();
     }
 
@@ -265,3 +449,231 @@ fn roundtrip_operation_pool() {
 
     assert_eq!(harness.chain.op_pool, restored_op_pool);
 }
+
+#[test]
+fn schema_version_is_checked_on_load() {
+    let harness = get_harness(VALIDATOR_COUNT);
+    harness.chain.persist().unwrap();
+
+    let store = harness.chain.store.clone();
+    assert_eq!(
+        schema::get_schema_version(store.as_ref()),
+        Ok(Some(CURRENT_SCHEMA_VERSION))
+    );
+
+    let load = || {
+        BeaconChain::<CommonTypes<TestForkChoice, MinimalEthSpec>>::from_store(
+            store.clone(),
+            harness.spec.clone(),
+        )
+    };
+
+    assert!(load().unwrap().is_some());
+
+    let key = Hash256::from_slice(SCHEMA_VERSION_DB_KEY.as_bytes());
+    let newer = SchemaVersion(CURRENT_SCHEMA_VERSION.0 + 1);
+    store.put(&key, &newer).unwrap();
+
+    match load() {
+        Err(BeaconChainError::SchemaError(schema::Error::UnsupportedSchemaVersion {
+            stored,
+            current,
+        })) => {
+            assert_eq!(stored, newer);
+            assert_eq!(current, CURRENT_SCHEMA_VERSION);
+        }
+        _ => panic!("a database with a newer schema should be refused"),
+    }
+}
+
+#[test]
+fn canonical_index_is_backfilled_by_migration() {
+    let num_blocks_produced = MinimalEthSpec::slots_per_epoch() * 2;
+
+    let harness = get_harness(VALIDATOR_COUNT);
+
+    harness.extend_chain(
+        num_blocks_produced as usize,
+        BlockStrategy::OnCanonicalHead,
+        AttestationStrategy::AllValidators,
+    );
+    harness.chain.persist().unwrap();
+
+    // Reduce the database to a v1 database, which has no canonical index.
+    let store = harness.chain.store.clone();
+    let head_slot = harness.chain.head().beacon_block.slot;
+    let mut batch = WriteBatch::new();
+    for slot in 0..=head_slot.as_u64() {
+        canonical_index::delete_canonical_roots(&mut batch, Slot::new(slot));
+    }
+    batch.put(
+        &Hash256::from_slice(SCHEMA_VERSION_DB_KEY.as_bytes()),
+        &SchemaVersion(1),
+    );
+    store.write_batch(&batch).unwrap();
+
+    assert_eq!(harness.chain.block_root_at_slot(head_slot), Ok(None));
+
+    BeaconChain::<CommonTypes<TestForkChoice, MinimalEthSpec>>::from_store(
+        store.clone(),
+        harness.spec.clone(),
+    )
+    .unwrap()
+    .expect("should load persisted chain");
+
+    assert_eq!(
+        schema::get_schema_version(store.as_ref()),
+        Ok(Some(CURRENT_SCHEMA_VERSION))
+    );
+    assert_canonical_index_matches_head(&harness);
+}
+
+#[test]
+fn fork_choice_is_restored_from_store() {
+    let num_blocks_produced = MinimalEthSpec::slots_per_epoch() * 3;
+
+    let harness = get_harness(VALIDATOR_COUNT);
+
+    harness.extend_chain(
+        num_blocks_produced as usize,
+        BlockStrategy::OnCanonicalHead,
+        AttestationStrategy::AllValidators,
+    );
+    harness.chain.persist().unwrap();
+
+    let restored = BeaconChain::<CommonTypes<TestForkChoice, MinimalEthSpec>>::from_store(
+        harness.chain.store.clone(),
+        harness.spec.clone(),
+    )
+    .unwrap()
+    .expect("should load persisted chain");
+
+    assert_eq!(
+        restored.fork_choice.as_persisted().backend_bytes,
+        harness.chain.fork_choice.as_persisted().backend_bytes,
+        "the votes should be restored"
+    );
+    assert_eq!(
+        restored.fork_choice.find_head(&restored),
+        Ok(harness.chain.head().beacon_block_root),
+        "the restored fork choice should find the same head"
+    );
+}
+
+#[test]
+fn fork_choice_is_persisted_without_head_change() {
+    let harness = get_harness(VALIDATOR_COUNT);
+
+    harness.extend_chain(
+        MinimalEthSpec::slots_per_epoch() as usize,
+        BlockStrategy::OnCanonicalHead,
+        AttestationStrategy::AllValidators,
+    );
+    let head = harness.chain.head().beacon_block_root;
+
+    // A fork block which no validator attests to, so the head does not change.
+    let fork_root = harness.extend_chain(
+        1,
+        BlockStrategy::ForkCanonicalChainAt {
+            previous_slot: Slot::new(2),
+            first_slot: Slot::new(4),
+        },
+        AttestationStrategy::SomeValidators(vec![]),
+    );
+    assert_eq!(harness.chain.head().beacon_block_root, head);
+
+    let key = Hash256::from_slice(&FORK_CHOICE_DB_KEY.as_bytes());
+    let persisted: PersistedForkChoice = harness
+        .chain
+        .store
+        .get(&key)
+        .unwrap()
+        .expect("should persist fork choice");
+
+    let restored =
+        TestForkChoice::from_bytes(&persisted.backend_bytes, harness.chain.store.clone())
+            .expect("should decode fork choice");
+
+    assert!(
+        restored
+            .dump()
+            .unwrap()
+            .nodes
+            .iter()
+            .any(|node| node.block_root == fork_root),
+        "the persisted fork choice should include the latest block"
+    );
+}
+
+#[test]
+fn hot_cold_db_replays_frozen_blocks() {
+    let num_blocks_produced = MinimalEthSpec::slots_per_epoch() * 3 + 1;
+
+    let harness = get_harness(VALIDATOR_COUNT);
+
+    harness.extend_chain(
+        num_blocks_produced as usize,
+        BlockStrategy::OnCanonicalHead,
+        AttestationStrategy::AllValidators,
+    );
+
+    let head = harness.chain.head();
+    let head_slot = head.beacon_state.slot;
+
+    let hot_dir = tempdir().unwrap();
+    let cold_dir = tempdir().unwrap();
+    let store = Arc::new(
+        HotColdDB::open(
+            hot_dir.path(),
+            cold_dir.path(),
+            4,
+            MinimalEthSpec::default_spec(),
+        )
+        .unwrap(),
+    );
+
+    // Copy the canonical chain of the harness into the hot database.
+    let block_roots: Vec<(Hash256, Slot)> =
+        BlockRootsIterator::new(harness.chain.store.clone(), &head.beacon_state, head_slot)
+            .collect();
+    let state_roots: Vec<(Hash256, Slot)> =
+        StateRootsIterator::new(harness.chain.store.clone(), &head.beacon_state, head_slot)
+            .collect();
+
+    for (block_root, _) in &block_roots {
+        let block: BeaconBlock = harness.chain.store.get(block_root).unwrap().unwrap();
+        store.put(block_root, &block).unwrap();
+    }
+    for (state_root, _) in &state_roots {
+        let state = harness
+            .chain
+            .store
+            .get_state::<MinimalEthSpec>(state_root)
+            .unwrap()
+            .unwrap();
+        store.put(state_root, &state).unwrap();
+    }
+    store
+        .put(&head.beacon_block_root, &head.beacon_block)
+        .unwrap();
+    store
+        .put(&head.beacon_state_root, &head.beacon_state)
+        .unwrap();
+
+    HotColdDB::freeze_to_state(store.clone(), head.beacon_state_root, &head.beacon_state).unwrap();
+    assert_eq!(store.split_slot(), head_slot);
+
+    // Every frozen state is either a restore point or replayed from one using real blocks.
+    for (state_root, slot) in &state_roots {
+        let state = store
+            .get_state::<MinimalEthSpec>(state_root)
+            .unwrap()
+            .expect("frozen state should be reconstructed");
+        assert_eq!(state.slot, *slot);
+        assert_eq!(state.canonical_root(), *state_root);
+    }
+    for (block_root, slot) in &block_roots {
+        let block: BeaconBlock = store.get(block_root).unwrap().unwrap();
+        assert_eq!(block.slot, *slot);
+    }
+}
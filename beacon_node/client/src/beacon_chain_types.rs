@@ -25,19 +25,37 @@ pub trait InitialiseBeaconChain<T: BeaconChainTypes> {
     }
 }
 
-#[derive(Clone)]
-pub struct ClientType<S: Store, E: EthSpec> {
+/// The `BeaconChainTypes` used by the client.
+///
+/// The fork choice backend `F` may be any `LmdGhost` implementation, defaulting to the reduced
+/// tree.
+pub struct ClientType<S: Store, E: EthSpec, F = ThreadSafeReducedTree<S, E>> {
     _phantom_t: PhantomData<S>,
     _phantom_u: PhantomData<E>,
+    _phantom_f: PhantomData<F>,
 }
 
-impl<S: Store, E: EthSpec + Clone> BeaconChainTypes for ClientType<S, E> {
+// Implemented manually, since deriving would require the fork choice backend to be `Clone`.
+impl<S: Store + Clone, E: EthSpec + Clone, F> Clone for ClientType<S, E, F> {
+    fn clone(&self) -> Self {
+        Self {
+            _phantom_t: PhantomData,
+            _phantom_u: PhantomData,
+            _phantom_f: PhantomData,
+        }
+    }
+}
+
+impl<S: Store, E: EthSpec + Clone, F: LmdGhost<S, E>> BeaconChainTypes for ClientType<S, E, F> {
     type Store = S;
     type SlotClock = SystemTimeSlotClock;
-    type LmdGhost = ThreadSafeReducedTree<S, E>;
+    type LmdGhost = F;
     type EthSpec = E;
 }
-impl<T: Store, E: EthSpec, X: BeaconChainTypes> InitialiseBeaconChain<X> for ClientType<T, E> {}
+impl<T: Store, E: EthSpec, F, X: BeaconChainTypes> InitialiseBeaconChain<X>
+    for ClientType<T, E, F>
+{
+}
 
 /// Loads a `BeaconChain` from `store`, if it exists. Otherwise, create a new chain from genesis.
 fn maybe_load_from_store_for_testnet<T, U: Store, V: EthSpec>(
@@ -11,6 +11,18 @@ pub struct Config {
     pub data_dir: PathBuf,
     pub db_type: String,
     db_name: String,
+    #[serde(default = "default_hot_db_name")]
+    hot_db_name: String,
+    #[serde(default = "default_freezer_db_name")]
+    freezer_db_name: String,
+    #[serde(default = "default_rocks_db_name")]
+    rocks_db_name: String,
+    /// The number of slots between each full state stored in the freezer database.
+    #[serde(default = "default_slots_per_restore_point")]
+    pub slots_per_restore_point: u64,
+    /// If set, the fork choice tree is written to this directory (as JSON and DOT) every slot.
+    #[serde(default)]
+    pub fork_choice_dump_dir: Option<PathBuf>,
     pub network: network::NetworkConfig,
     pub rpc: rpc::RPCConfig,
     pub http: HttpServerConfig,
@@ -22,6 +34,11 @@ impl Default for Config {
             data_dir: PathBuf::from(".lighthouse"),
             db_type: "disk".to_string(),
             db_name: "chain_db".to_string(),
+            hot_db_name: default_hot_db_name(),
+            freezer_db_name: default_freezer_db_name(),
+            rocks_db_name: default_rocks_db_name(),
+            slots_per_restore_point: default_slots_per_restore_point(),
+            fork_choice_dump_dir: None,
             // Note: there are no default bootnodes specified.
             // Once bootnodes are established, add them here.
             network: NetworkConfig::new(),
@@ -31,13 +48,52 @@ impl Default for Config {
     }
 }
 
+fn default_hot_db_name() -> String {
+    "hot_db".to_string()
+}
+
+fn default_freezer_db_name() -> String {
+    "freezer_db".to_string()
+}
+
+fn default_rocks_db_name() -> String {
+    "rocks_db".to_string()
+}
+
+fn default_slots_per_restore_point() -> u64 {
+    store::DEFAULT_SLOTS_PER_RESTORE_POINT
+}
+
 impl Config {
     /// Returns the path to which the client may initialize an on-disk database.
+    ///
+    /// Each `db_type` has its own path, so that switching `db_type` never opens a database written
+    /// by another backend.
     pub fn db_path(&self) -> Option<PathBuf> {
         self.data_dir()
             .and_then(|path| Some(path.join(&self.db_name)))
     }
 
+    /// Returns the path to which the client may initialize the on-disk database of unfinalized
+    /// blocks and states, when using a `HotColdDB`.
+    pub fn hot_db_path(&self) -> Option<PathBuf> {
+        self.data_dir()
+            .and_then(|path| Some(path.join(&self.hot_db_name)))
+    }
+
+    /// Returns the path to which the client may initialize the on-disk freezer database, which
+    /// stores finalized blocks and states.
+    pub fn freezer_db_path(&self) -> Option<PathBuf> {
+        self.data_dir()
+            .and_then(|path| Some(path.join(&self.freezer_db_name)))
+    }
+
+    /// Returns the path to which the client may initialize an on-disk RocksDB database.
+    pub fn rocks_db_path(&self) -> Option<PathBuf> {
+        self.data_dir()
+            .and_then(|path| Some(path.join(&self.rocks_db_name)))
+    }
+
     /// Returns the core path for the client.
     pub fn data_dir(&self) -> Option<PathBuf> {
         let path = dirs::home_dir()?.join(&self.data_dir);
@@ -58,6 +114,16 @@ impl Config {
             self.db_type = dir.to_string();
         }
 
+        if let Some(slots) = args.value_of("slots-per-restore-point") {
+            self.slots_per_restore_point = slots
+                .parse()
+                .map_err(|_| format!("Invalid slots per restore point: {}", slots))?;
+        }
+
+        if let Some(dir) = args.value_of("dump-fork-choice") {
+            self.fork_choice_dump_dir = Some(PathBuf::from(dir));
+        }
+
         self.network.apply_cli_args(args)?;
         self.rpc.apply_cli_args(args)?;
         self.http.apply_cli_args(args)?;
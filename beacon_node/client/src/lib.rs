@@ -10,9 +10,11 @@ use exit_future::Signal;
 use futures::{future::Future, Stream};
 use network::Service as NetworkService;
 use prometheus::Registry;
-use slog::{error, info, o};
+use slog::{error, info, o, warn};
 use slot_clock::SlotClock;
+use std::fs;
 use std::marker::PhantomData;
+use std::path::Path;
 use std::sync::Arc;
 use std::time::{Duration, Instant};
 use tokio::runtime::TaskExecutor;
@@ -134,6 +136,7 @@ where
                 &client_config.http,
                 executor,
                 network_send,
+                network.peer_manager(),
                 beacon_chain.clone(),
                 client_config.db_path().expect("unable to read datadir"),
                 metrics_registry,
@@ -154,6 +157,7 @@ where
             };
 
             let chain = beacon_chain.clone();
+            let fork_choice_dump_dir = client_config.fork_choice_dump_dir.clone();
             let log = log.new(o!("Service" => "SlotTimer"));
             executor.spawn(
                 exit.until(
@@ -161,6 +165,10 @@ where
                         .for_each(move |_| {
                             do_state_catchup(&chain, &log);
 
+                            if let Some(dir) = &fork_choice_dump_dir {
+                                dump_fork_choice(&chain, dir, &log);
+                            }
+
                             Ok(())
                         })
                         .map_err(|_| ()),
@@ -184,8 +192,14 @@ where
 
 impl<T: BeaconChainTypes> Drop for Client<T> {
     fn drop(&mut self) {
-        // Save the beacon chain to it's store before dropping.
-        let _result = self.beacon_chain.persist();
+        // Save the beacon chain (including the fork choice) to it's store before dropping.
+        if let Err(e) = self.beacon_chain.persist() {
+            error!(
+                self.log,
+                "BeaconChainPersistFailed";
+                "error" => format!("{:?}", e),
+            );
+        }
     }
 }
 
@@ -216,3 +230,41 @@ fn do_state_catchup<T: BeaconChainTypes>(chain: &Arc<BeaconChain<T>>, log: &slog
         };
     }
 }
+
+/// Writes the fork choice tree to `fork_choice.json` and `fork_choice.dot` in `dir`, replacing
+/// the files written at the previous slot.
+fn dump_fork_choice<T: BeaconChainTypes>(
+    chain: &Arc<BeaconChain<T>>,
+    dir: &Path,
+    log: &slog::Logger,
+) {
+    let result = chain
+        .fork_choice_dump()
+        .map_err(|e| format!("{:?}", e))
+        .and_then(|dump| {
+            fs::create_dir_all(dir).map_err(|e| format!("{:?}", e))?;
+
+            write_replacing(&dir.join("fork_choice.json"), dump.to_json()?.as_bytes())?;
+            write_replacing(&dir.join("fork_choice.dot"), dump.to_dot().as_bytes())
+        });
+
+    if let Err(e) = result {
+        warn!(
+            log,
+            "ForkChoiceDumpFailed";
+            "dir" => format!("{:?}", dir),
+            "slot" => chain.present_slot(),
+            "error" => e,
+        );
+    }
+}
+
+/// Writes `bytes` to a temporary file which is then renamed to `path`, so that readers of `path`
+/// never see a partially written file.
+fn write_replacing(path: &Path, bytes: &[u8]) -> Result<(), String> {
+    let mut tmp_path = path.as_os_str().to_owned();
+    tmp_path.push(".tmp");
+
+    fs::write(&tmp_path, bytes).map_err(|e| format!("{:?}", e))?;
+    fs::rename(&tmp_path, path).map_err(|e| format!("{:?}", e))
+}
@@ -1,8 +1,8 @@
 use crate::Client;
-use beacon_chain::BeaconChainTypes;
+use beacon_chain::{BeaconChainEvent, BeaconChainTypes};
 use exit_future::Exit;
 use futures::{Future, Stream};
-use slog::{debug, o};
+use slog::{debug, info, o, warn};
 use std::sync::{Arc, Mutex};
 use std::time::{Duration, Instant};
 use tokio::runtime::TaskExecutor;
@@ -18,7 +18,8 @@ pub fn run<T: BeaconChainTypes + Send + Sync + 'static>(
     // notification heartbeat
     let interval = Interval::new(Instant::now(), Duration::from_secs(5));
 
-    let _log = client.log.new(o!("Service" => "Notifier"));
+    let log = client.log.new(o!("Service" => "Notifier"));
+    let events = client.beacon_chain.events.subscribe();
 
     // TODO: Debugging only
     let counter = Arc::new(Mutex::new(0));
@@ -36,6 +37,10 @@ pub fn run<T: BeaconChainTypes + Send + Sync + 'static>(
             network.send_message();
         }
 
+        for event in events.try_iter() {
+            log_event(&log, &event);
+        }
+
         Ok(())
     };
 
@@ -47,3 +52,37 @@ pub fn run<T: BeaconChainTypes + Send + Sync + 'static>(
 
     executor.spawn(exit.until(heartbeat_interval).map(|_| ()));
 }
+
+/// Reports chain reorgs and checkpoint changes, which are otherwise only visible in metrics.
+fn log_event(log: &slog::Logger, event: &BeaconChainEvent) {
+    match event {
+        BeaconChainEvent::HeadChanged {
+            previous_head_root,
+            previous_head_slot,
+            head_root,
+            head_slot,
+            reorg_depth,
+        } if event.is_reorg() => warn!(
+            log,
+            "Chain reorg";
+            "previous_head_root" => format!("{}", previous_head_root),
+            "previous_head_slot" => previous_head_slot,
+            "head_root" => format!("{}", head_root),
+            "head_slot" => head_slot,
+            "reorg_depth" => reorg_depth,
+        ),
+        BeaconChainEvent::JustifiedCheckpointChanged { epoch, root } => info!(
+            log,
+            "New justified checkpoint";
+            "epoch" => epoch,
+            "root" => format!("{}", root),
+        ),
+        BeaconChainEvent::FinalizedCheckpointChanged { epoch, root } => info!(
+            log,
+            "New finalized checkpoint";
+            "epoch" => epoch,
+            "root" => format!("{}", root),
+        ),
+        _ => (),
+    }
+}
@@ -18,7 +18,9 @@ use slog::{o, trace, warn};
 use ssz::{ssz_encode, Decode, DecodeError, Encode};
 use std::num::NonZeroU32;
 use std::time::Duration;
-use types::{Attestation, BeaconBlock};
+use types::{
+    Attestation, AttesterSlashing, BeaconBlock, ProposerSlashing, Transfer, VoluntaryExit,
+};
 
 /// Builds the network behaviour that manages the core protocols of eth2.
 /// This core behaviour is managed by `Behaviour` which adds peer management to all core
@@ -109,6 +111,9 @@ impl<TSubstream: AsyncRead + AsyncWrite> NetworkBehaviourEventProcess<RPCMessage
             RPCMessage::PeerDialed(peer_id) => {
                 self.events.push(BehaviourEvent::PeerDialed(peer_id))
             }
+            RPCMessage::PeerDisconnected(peer_id) => {
+                self.events.push(BehaviourEvent::PeerDisconnected(peer_id))
+            }
             RPCMessage::RPC(peer_id, rpc_event) => {
                 self.events.push(BehaviourEvent::RPC(peer_id, rpc_event))
             }
@@ -174,6 +179,7 @@ impl<TSubstream: AsyncRead + AsyncWrite> Behaviour<TSubstream> {
 pub enum BehaviourEvent {
     RPC(PeerId, RPCEvent),
     PeerDialed(PeerId),
+    PeerDisconnected(PeerId),
     GossipMessage {
         source: PeerId,
         topics: Vec<TopicHash>,
@@ -188,6 +194,14 @@ pub enum PubsubMessage {
     Block(BeaconBlock),
     /// Gossipsub message providing notification of a new attestation.
     Attestation(Attestation),
+    /// Gossipsub message providing notification of a new voluntary exit.
+    VoluntaryExit(VoluntaryExit),
+    /// Gossipsub message providing notification of a new proposer slashing.
+    ProposerSlashing(ProposerSlashing),
+    /// Gossipsub message providing notification of a new attester slashing.
+    AttesterSlashing(AttesterSlashing),
+    /// Gossipsub message providing notification of a new transfer.
+    Transfer(Transfer),
 }
 
 //TODO: Correctly encode/decode enums. Prefixing with integer for now.
@@ -214,6 +228,22 @@ impl Encode for PubsubMessage {
                 // Encode the gossip as a Vec<u8>;
                 encoder.append(&attestation_gossip.as_ssz_bytes());
             }
+            PubsubMessage::VoluntaryExit(exit_gossip) => {
+                encoder.append(&2_u32);
+                encoder.append(&exit_gossip.as_ssz_bytes());
+            }
+            PubsubMessage::ProposerSlashing(slashing_gossip) => {
+                encoder.append(&3_u32);
+                encoder.append(&slashing_gossip.as_ssz_bytes());
+            }
+            PubsubMessage::AttesterSlashing(slashing_gossip) => {
+                encoder.append(&4_u32);
+                encoder.append(&slashing_gossip.as_ssz_bytes());
+            }
+            PubsubMessage::Transfer(transfer_gossip) => {
+                encoder.append(&5_u32);
+                encoder.append(&transfer_gossip.as_ssz_bytes());
+            }
         }
 
         encoder.finalize();
@@ -241,6 +271,16 @@ impl Decode for PubsubMessage {
             1 => Ok(PubsubMessage::Attestation(Attestation::from_ssz_bytes(
                 &body,
             )?)),
+            2 => Ok(PubsubMessage::VoluntaryExit(VoluntaryExit::from_ssz_bytes(
+                &body,
+            )?)),
+            3 => Ok(PubsubMessage::ProposerSlashing(
+                ProposerSlashing::from_ssz_bytes(&body)?,
+            )),
+            4 => Ok(PubsubMessage::AttesterSlashing(
+                AttesterSlashing::from_ssz_bytes(&body)?,
+            )),
+            5 => Ok(PubsubMessage::Transfer(Transfer::from_ssz_bytes(&body)?)),
             _ => Err(DecodeError::BytesInvalid(
                 "Invalid PubsubMessage id".to_string(),
             )),
@@ -263,4 +303,20 @@ mod test {
 
         assert_eq!(original, decoded);
     }
+
+    #[test]
+    fn ssz_encoding_operations() {
+        let exit = VoluntaryExit {
+            epoch: Epoch::new(3),
+            validator_index: 42,
+            signature: Signature::empty_signature(),
+        };
+        let original = PubsubMessage::VoluntaryExit(exit);
+
+        let encoded = ssz_encode(&original);
+
+        let decoded = PubsubMessage::from_ssz_bytes(&encoded).unwrap();
+
+        assert_eq!(original, decoded);
+    }
 }
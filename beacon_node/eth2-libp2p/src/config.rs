@@ -9,6 +9,10 @@ use std::time::Duration;
 pub const BEACON_PUBSUB_TOPIC: &str = "beacon_block";
 pub const BEACON_ATTESTATION_TOPIC: &str = "beacon_attestation";
 pub const SHARD_TOPIC_PREFIX: &str = "shard";
+pub const VOLUNTARY_EXIT_TOPIC: &str = "voluntary_exit";
+pub const PROPOSER_SLASHING_TOPIC: &str = "proposer_slashing";
+pub const ATTESTER_SLASHING_TOPIC: &str = "attester_slashing";
+pub const TRANSFER_TOPIC: &str = "transfer";
 
 #[derive(Clone, Debug, Serialize, Deserialize)]
 #[serde(default)]
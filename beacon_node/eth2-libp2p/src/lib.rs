@@ -6,12 +6,15 @@ pub mod behaviour;
 mod config;
 mod discovery;
 pub mod error;
+mod peer_manager;
 pub mod rpc;
 mod service;
 
 pub use behaviour::PubsubMessage;
 pub use config::{
-    Config as NetworkConfig, BEACON_ATTESTATION_TOPIC, BEACON_PUBSUB_TOPIC, SHARD_TOPIC_PREFIX,
+    Config as NetworkConfig, ATTESTER_SLASHING_TOPIC, BEACON_ATTESTATION_TOPIC,
+    BEACON_PUBSUB_TOPIC, PROPOSER_SLASHING_TOPIC, SHARD_TOPIC_PREFIX, TRANSFER_TOPIC,
+    VOLUNTARY_EXIT_TOPIC,
 };
 pub use libp2p::floodsub::{Topic, TopicBuilder, TopicHash};
 pub use libp2p::multiaddr;
@@ -20,6 +23,7 @@ pub use libp2p::{
     gossipsub::{GossipsubConfig, GossipsubConfigBuilder},
     PeerId,
 };
+pub use peer_manager::{PeerAction, PeerManager, PeerScore};
 pub use rpc::RPCEvent;
 pub use service::Libp2pEvent;
 pub use service::Service;
@@ -0,0 +1,301 @@
+use libp2p::PeerId;
+use parking_lot::RwLock;
+use std::collections::HashMap;
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+
+/// The score of a peer which has not yet been reported.
+const DEFAULT_SCORE: i64 = 0;
+
+/// Scores are capped at this value, so that good behaviour cannot excuse unlimited misbehaviour.
+const MAX_SCORE: i64 = 100;
+
+/// A peer whose score falls to, or below, this value is banned.
+const BAN_THRESHOLD: i64 = -100;
+
+/// The cooling-off period during which a banned peer may not reconnect.
+const BAN_DURATION: Duration = Duration::from_secs(30 * 60);
+
+/// A peer which disconnects with a negative score is remembered for this long, so that it cannot
+/// reset its score by reconnecting.
+const PENALTY_DURATION: Duration = Duration::from_secs(30 * 60);
+
+/// Behaviour of a peer which changes its score.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum PeerAction {
+    /// The peer sent a valid block which we did not already know.
+    ValidBlock,
+    /// The peer sent a block which failed validation, or blocks which do not form a chain.
+    InvalidBlock,
+    /// The peer sent an attestation which is invalid on any fork.
+    InvalidAttestation,
+    /// The peer did not respond to an RPC request in time.
+    RPCTimeout,
+    /// The peer's `Hello` shows that it is on a different network or finalized chain.
+    WrongChain,
+}
+
+impl PeerAction {
+    /// The change to a peer's score caused by the action.
+    fn score_change(self) -> i64 {
+        match self {
+            PeerAction::ValidBlock => 1,
+            PeerAction::InvalidBlock => -50,
+            PeerAction::InvalidAttestation => -10,
+            PeerAction::RPCTimeout => -10,
+            PeerAction::WrongChain => BAN_THRESHOLD,
+        }
+    }
+}
+
+/// The reputation of a single peer.
+#[derive(Debug, Clone, PartialEq)]
+pub struct PeerScore {
+    pub score: i64,
+    /// The time at which the peer's ban expires, if it is banned.
+    pub banned_until: Option<Instant>,
+    /// The time at which the peer is forgotten, if it disconnected with a negative score and has
+    /// not been reported since.
+    pub forget_at: Option<Instant>,
+}
+
+impl Default for PeerScore {
+    fn default() -> Self {
+        Self {
+            score: DEFAULT_SCORE,
+            banned_until: None,
+            forget_at: None,
+        }
+    }
+}
+
+impl PeerScore {
+    pub fn is_banned(&self) -> bool {
+        self.banned_until.is_some()
+    }
+}
+
+/// Tracks the score of each peer which has been reported, banning those whose score falls below
+/// `BAN_THRESHOLD` for `BAN_DURATION`.
+///
+/// A peer is forgotten when it disconnects with a non-negative score. A penalised peer is
+/// forgotten `PENALTY_DURATION` after it disconnects, unless it is reported again in the meantime,
+/// and a banned peer once its ban expires.
+///
+/// Cloning yields a handle to the same scores, so they may be read outside of the network service.
+#[derive(Clone, Default)]
+pub struct PeerManager {
+    peers: Arc<RwLock<HashMap<PeerId, PeerScore>>>,
+}
+
+impl PeerManager {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Adjusts the score of `peer_id` according to `action`.
+    ///
+    /// Returns `true` if the peer was not already banned and should now be banned.
+    pub fn report_peer(&self, peer_id: &PeerId, action: PeerAction) -> bool {
+        let mut peers = self.peers.write();
+        let peer = peers.entry(peer_id.clone()).or_default();
+        // The peer has reconnected, so its score is kept until it disconnects again.
+        peer.forget_at = None;
+
+        if peer.is_banned() {
+            return false;
+        }
+
+        peer.score = std::cmp::min(peer.score + action.score_change(), MAX_SCORE);
+
+        if peer.score <= BAN_THRESHOLD {
+            peer.banned_until = Some(Instant::now() + BAN_DURATION);
+            true
+        } else {
+            false
+        }
+    }
+
+    /// Returns `true` if `peer_id` is banned.
+    pub fn is_banned(&self, peer_id: &PeerId) -> bool {
+        self.peers
+            .read()
+            .get(peer_id)
+            .map_or(false, PeerScore::is_banned)
+    }
+
+    /// Forgets `peer_id` if its score is not negative. Otherwise, it is forgotten by
+    /// `Self::forget_expired_penalties` once `PENALTY_DURATION` has passed, or by
+    /// `Self::unban_expired` if it is banned.
+    pub fn peer_disconnected(&self, peer_id: &PeerId) {
+        let mut peers = self.peers.write();
+
+        let peer = match peers.get_mut(peer_id) {
+            Some(peer) => peer,
+            None => return,
+        };
+
+        if peer.score < DEFAULT_SCORE {
+            if !peer.is_banned() {
+                peer.forget_at = Some(Instant::now() + PENALTY_DURATION);
+            }
+        } else {
+            peers.remove(peer_id);
+        }
+    }
+
+    /// Forgets each disconnected peer whose penalty has expired, so that it begins again with the
+    /// default score if it reconnects.
+    pub fn forget_expired_penalties(&self) {
+        let now = Instant::now();
+
+        self.peers
+            .write()
+            .retain(|_, peer| peer.forget_at.map_or(true, |forget_at| forget_at > now));
+    }
+
+    /// Lifts the ban of each peer whose cooling-off period has passed, returning those peers.
+    ///
+    /// Banned peers are disconnected, so unbanned peers are forgotten and begin again with the
+    /// default score if they reconnect.
+    pub fn unban_expired(&self) -> Vec<PeerId> {
+        let now = Instant::now();
+        let mut peers = self.peers.write();
+
+        let expired: Vec<PeerId> = peers
+            .iter()
+            .filter(|(_, peer)| peer.banned_until.map_or(false, |until| until <= now))
+            .map(|(peer_id, _)| peer_id.clone())
+            .collect();
+
+        for peer_id in &expired {
+            peers.remove(peer_id);
+        }
+
+        expired
+    }
+
+    /// Returns the number of peers which are currently banned.
+    pub fn banned_count(&self) -> usize {
+        self.peers
+            .read()
+            .values()
+            .filter(|peer| peer.is_banned())
+            .count()
+    }
+
+    /// Returns the score of each peer which has been reported.
+    pub fn scores(&self) -> HashMap<PeerId, PeerScore> {
+        self.peers.read().clone()
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn bans_below_threshold() {
+        let manager = PeerManager::new();
+        let peer_id = PeerId::random();
+
+        assert!(!manager.report_peer(&peer_id, PeerAction::InvalidBlock));
+        assert!(!manager.is_banned(&peer_id));
+
+        assert!(manager.report_peer(&peer_id, PeerAction::InvalidBlock));
+        assert!(manager.is_banned(&peer_id));
+
+        // A banned peer is only reported as newly banned once.
+        assert!(!manager.report_peer(&peer_id, PeerAction::InvalidBlock));
+        assert_eq!(manager.scores()[&peer_id].score, BAN_THRESHOLD);
+    }
+
+    #[test]
+    fn wrong_chain_is_banned_immediately() {
+        let manager = PeerManager::new();
+        let peer_id = PeerId::random();
+
+        assert!(manager.report_peer(&peer_id, PeerAction::WrongChain));
+        assert!(manager.is_banned(&peer_id));
+        assert!(manager.unban_expired().is_empty());
+    }
+
+    #[test]
+    fn score_is_capped() {
+        let manager = PeerManager::new();
+        let peer_id = PeerId::random();
+
+        for _ in 0..MAX_SCORE * 2 {
+            manager.report_peer(&peer_id, PeerAction::ValidBlock);
+        }
+
+        assert_eq!(manager.scores()[&peer_id].score, MAX_SCORE);
+    }
+
+    #[test]
+    fn expired_bans_are_lifted() {
+        let manager = PeerManager::new();
+        let peer_id = PeerId::random();
+
+        manager.report_peer(&peer_id, PeerAction::WrongChain);
+        manager
+            .peers
+            .write()
+            .get_mut(&peer_id)
+            .unwrap()
+            .banned_until = Some(Instant::now());
+
+        assert_eq!(manager.unban_expired(), vec![peer_id.clone()]);
+        assert!(!manager.is_banned(&peer_id));
+        assert!(manager.scores().is_empty());
+    }
+
+    #[test]
+    fn disconnected_peers_are_forgotten_unless_penalised() {
+        let manager = PeerManager::new();
+        let good_peer = PeerId::random();
+        let penalised_peer = PeerId::random();
+        let bad_peer = PeerId::random();
+
+        manager.report_peer(&good_peer, PeerAction::ValidBlock);
+        manager.report_peer(&penalised_peer, PeerAction::RPCTimeout);
+        manager.report_peer(&bad_peer, PeerAction::WrongChain);
+
+        manager.peer_disconnected(&good_peer);
+        manager.peer_disconnected(&penalised_peer);
+        manager.peer_disconnected(&bad_peer);
+        manager.forget_expired_penalties();
+
+        let scores = manager.scores();
+        assert!(!scores.contains_key(&good_peer));
+        assert_eq!(scores[&penalised_peer].score, -10);
+        assert!(scores[&bad_peer].is_banned());
+        assert_eq!(manager.banned_count(), 1);
+    }
+
+    #[test]
+    fn reconnecting_does_not_reset_penalty() {
+        let manager = PeerManager::new();
+        let peer_id = PeerId::random();
+
+        manager.report_peer(&peer_id, PeerAction::InvalidBlock);
+        manager.peer_disconnected(&peer_id);
+
+        // The peer reconnects and misbehaves again, which bans it.
+        assert!(manager.report_peer(&peer_id, PeerAction::InvalidBlock));
+        assert!(manager.is_banned(&peer_id));
+    }
+
+    #[test]
+    fn expired_penalties_are_forgotten() {
+        let manager = PeerManager::new();
+        let peer_id = PeerId::random();
+
+        manager.report_peer(&peer_id, PeerAction::InvalidBlock);
+        manager.peer_disconnected(&peer_id);
+        manager.peers.write().get_mut(&peer_id).unwrap().forget_at = Some(Instant::now());
+
+        manager.forget_expired_penalties();
+        assert!(manager.scores().is_empty());
+    }
+}
@@ -0,0 +1,129 @@
+//! Encodings for the payload of an RPC stream.
+
+use super::protocol::DecodeError;
+use std::io::{Read, Write};
+
+/// The maximum size of an uncompressed RPC payload.
+pub const MAX_PAYLOAD_SIZE: usize = 4_194_304; // 4M
+
+/// The encoding of an RPC payload, negotiated per stream via the protocol ID.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Encoding {
+    /// The raw SSZ bytes.
+    SSZ,
+    /// The SSZ bytes compressed with the snappy framing format, prefixed by the uncompressed
+    /// length as an unsigned varint.
+    SSZSnappy,
+}
+
+impl Encoding {
+    /// The encodings supported by this node, most preferred first.
+    pub fn all() -> Vec<Encoding> {
+        vec![Encoding::SSZSnappy, Encoding::SSZ]
+    }
+
+    /// The name of the encoding within a protocol ID.
+    pub fn name(self) -> &'static str {
+        match self {
+            Encoding::SSZ => "ssz",
+            Encoding::SSZSnappy => "ssz_snappy",
+        }
+    }
+
+    /// Parses the name of an encoding within a protocol ID.
+    pub fn from_name(name: &str) -> Option<Self> {
+        match name {
+            "ssz" => Some(Encoding::SSZ),
+            "ssz_snappy" => Some(Encoding::SSZSnappy),
+            _ => None,
+        }
+    }
+
+    /// Encodes the SSZ `bytes` of a message.
+    pub fn encode(self, bytes: Vec<u8>) -> Vec<u8> {
+        match self {
+            Encoding::SSZ => bytes,
+            Encoding::SSZSnappy => {
+                let mut length_buf = unsigned_varint::encode::usize_buffer();
+                let mut packet =
+                    unsigned_varint::encode::usize(bytes.len(), &mut length_buf).to_vec();
+
+                let mut writer = snap::write::FrameEncoder::new(packet);
+                writer
+                    .write_all(&bytes)
+                    .expect("writing to a vec cannot fail");
+                packet = writer.into_inner().expect("flushing to a vec cannot fail");
+
+                packet
+            }
+        }
+    }
+
+    /// Decodes a `packet` into the SSZ bytes of a message.
+    ///
+    /// For compressed encodings, the declared length is checked against `MAX_PAYLOAD_SIZE` before
+    /// any decompression occurs and decompression halts once the declared length is exceeded.
+    pub fn decode(self, packet: Vec<u8>) -> Result<Vec<u8>, DecodeError> {
+        match self {
+            Encoding::SSZ => Ok(packet),
+            Encoding::SSZSnappy => {
+                let (length, compressed) = unsigned_varint::decode::usize(&packet)
+                    .map_err(|_| DecodeError::InvalidLengthPrefix)?;
+
+                if length > MAX_PAYLOAD_SIZE {
+                    return Err(DecodeError::LimitExceeded);
+                }
+
+                let mut bytes = Vec::with_capacity(length);
+                snap::read::FrameDecoder::new(compressed)
+                    .take(length as u64 + 1)
+                    .read_to_end(&mut bytes)
+                    .map_err(DecodeError::DecompressionError)?;
+
+                if bytes.len() != length {
+                    return Err(DecodeError::InvalidLengthPrefix);
+                }
+
+                Ok(bytes)
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn ssz_snappy_round_trip() {
+        let bytes = vec![42; 1_000];
+
+        let packet = Encoding::SSZSnappy.encode(bytes.clone());
+        assert!(packet.len() < bytes.len());
+
+        assert_eq!(Encoding::SSZSnappy.decode(packet).unwrap(), bytes);
+    }
+
+    #[test]
+    fn ssz_snappy_rejects_oversized_length() {
+        let mut length_buf = unsigned_varint::encode::usize_buffer();
+        let packet = unsigned_varint::encode::usize(MAX_PAYLOAD_SIZE + 1, &mut length_buf).to_vec();
+
+        match Encoding::SSZSnappy.decode(packet) {
+            Err(DecodeError::LimitExceeded) => {}
+            result => panic!("oversized payload accepted: {:?}", result),
+        }
+    }
+
+    #[test]
+    fn ssz_snappy_rejects_incorrect_length() {
+        let mut packet = Encoding::SSZSnappy.encode(vec![42; 100]);
+        // Declare a length of 99 bytes rather than 100.
+        packet[0] = 99;
+
+        match Encoding::SSZSnappy.decode(packet) {
+            Err(DecodeError::InvalidLengthPrefix) => {}
+            result => panic!("incorrect length accepted: {:?}", result),
+        }
+    }
+}
@@ -2,9 +2,16 @@
 
 use ssz::{impl_decode_via_from, impl_encode_via_from};
 use ssz_derive::{Decode, Encode};
-use types::{BeaconBlockBody, BeaconBlockHeader, Epoch, Hash256, Slot};
+use types::{BeaconBlock, BeaconBlockBody, BeaconBlockHeader, Epoch, Hash256, Slot};
 
-#[derive(Debug)]
+/// The maximum number of block roots which may be requested or returned in a single message.
+pub const MAX_REQUEST_BLOCK_ROOTS: u64 = 32_768;
+
+/// The maximum number of blocks, headers or bodies which may be requested or returned in a single
+/// message.
+pub const MAX_REQUEST_BLOCKS: u64 = 1_024;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
 /// Available Serenity Libp2p RPC methods
 pub enum RPCMethod {
     /// Initialise handshake between connecting peers.
@@ -19,10 +26,50 @@ pub enum RPCMethod {
     BeaconBlockBodies,
     /// Requests values for a merkle proof for the current blocks state root.
     BeaconChainState, // Note: experimental, not complete.
+    /// Requests a range of beacon blocks by slot.
+    BeaconBlocksByRange,
+    /// Requests a number of beacon blocks by root.
+    BeaconBlocksByRoot,
     /// Unknown method received.
     Unknown,
 }
 
+impl RPCMethod {
+    /// All known methods, each of which is served on its own protocol.
+    pub fn all() -> Vec<RPCMethod> {
+        vec![
+            RPCMethod::Hello,
+            RPCMethod::Goodbye,
+            RPCMethod::BeaconBlockRoots,
+            RPCMethod::BeaconBlockHeaders,
+            RPCMethod::BeaconBlockBodies,
+            RPCMethod::BeaconChainState,
+            RPCMethod::BeaconBlocksByRange,
+            RPCMethod::BeaconBlocksByRoot,
+        ]
+    }
+
+    /// The name of the method within a protocol ID.
+    pub fn name(self) -> &'static str {
+        match self {
+            RPCMethod::Hello => "hello",
+            RPCMethod::Goodbye => "goodbye",
+            RPCMethod::BeaconBlockRoots => "beacon_block_roots",
+            RPCMethod::BeaconBlockHeaders => "beacon_block_headers",
+            RPCMethod::BeaconBlockBodies => "beacon_block_bodies",
+            RPCMethod::BeaconChainState => "beacon_chain_state",
+            RPCMethod::BeaconBlocksByRange => "beacon_blocks_by_range",
+            RPCMethod::BeaconBlocksByRoot => "beacon_blocks_by_root",
+            RPCMethod::Unknown => "unknown",
+        }
+    }
+
+    /// Parses the name of a method within a protocol ID.
+    pub fn from_name(name: &str) -> Option<Self> {
+        Self::all().into_iter().find(|method| method.name() == name)
+    }
+}
+
 impl From<u16> for RPCMethod {
     fn from(method_id: u16) -> Self {
         match method_id {
@@ -32,6 +79,8 @@ impl From<u16> for RPCMethod {
             11 => RPCMethod::BeaconBlockHeaders,
             12 => RPCMethod::BeaconBlockBodies,
             13 => RPCMethod::BeaconChainState,
+            14 => RPCMethod::BeaconBlocksByRange,
+            15 => RPCMethod::BeaconBlocksByRoot,
 
             _ => RPCMethod::Unknown,
         }
@@ -47,6 +96,8 @@ impl Into<u16> for RPCMethod {
             RPCMethod::BeaconBlockHeaders => 11,
             RPCMethod::BeaconBlockBodies => 12,
             RPCMethod::BeaconChainState => 13,
+            RPCMethod::BeaconBlocksByRange => 14,
+            RPCMethod::BeaconBlocksByRoot => 15,
             _ => 0,
         }
     }
@@ -60,6 +111,8 @@ pub enum RPCRequest {
     BeaconBlockHeaders(BeaconBlockHeadersRequest),
     BeaconBlockBodies(BeaconBlockBodiesRequest),
     BeaconChainState(BeaconChainStateRequest),
+    BeaconBlocksByRange(BeaconBlocksByRangeRequest),
+    BeaconBlocksByRoot(BeaconBlocksByRootRequest),
 }
 
 impl RPCRequest {
@@ -71,9 +124,31 @@ impl RPCRequest {
             RPCRequest::BeaconBlockHeaders(_) => RPCMethod::BeaconBlockHeaders,
             RPCRequest::BeaconBlockBodies(_) => RPCMethod::BeaconBlockBodies,
             RPCRequest::BeaconChainState(_) => RPCMethod::BeaconChainState,
+            RPCRequest::BeaconBlocksByRange(_) => RPCMethod::BeaconBlocksByRange,
+            RPCRequest::BeaconBlocksByRoot(_) => RPCMethod::BeaconBlocksByRoot,
         };
         method.into()
     }
+
+    /// Returns `false` if the request asks for more items than a peer is obliged to serve.
+    pub fn is_within_limits(&self) -> bool {
+        match self {
+            RPCRequest::BeaconBlockRoots(req) => req.count <= MAX_REQUEST_BLOCK_ROOTS,
+            RPCRequest::BeaconBlockHeaders(req) => req.max_headers <= MAX_REQUEST_BLOCKS,
+            RPCRequest::BeaconBlockBodies(req) => {
+                req.block_roots.len() as u64 <= MAX_REQUEST_BLOCKS
+            }
+            RPCRequest::BeaconBlocksByRange(req) => {
+                req.step > 0
+                    && req
+                        .count
+                        .checked_mul(req.step)
+                        .map_or(false, |span| span <= MAX_REQUEST_BLOCKS)
+            }
+            RPCRequest::BeaconBlocksByRoot(req) => req.roots.len() as u64 <= MAX_REQUEST_BLOCKS,
+            RPCRequest::Hello(_) | RPCRequest::Goodbye(_) | RPCRequest::BeaconChainState(_) => true,
+        }
+    }
 }
 
 #[derive(Debug, Clone)]
@@ -83,6 +158,8 @@ pub enum RPCResponse {
     BeaconBlockHeaders(BeaconBlockHeadersResponse),
     BeaconBlockBodies(BeaconBlockBodiesResponse),
     BeaconChainState(BeaconChainStateResponse),
+    BeaconBlocksByRange(BeaconBlocksResponse),
+    BeaconBlocksByRoot(BeaconBlocksResponse),
 }
 
 impl RPCResponse {
@@ -93,9 +170,26 @@ impl RPCResponse {
             RPCResponse::BeaconBlockHeaders(_) => RPCMethod::BeaconBlockHeaders,
             RPCResponse::BeaconBlockBodies(_) => RPCMethod::BeaconBlockBodies,
             RPCResponse::BeaconChainState(_) => RPCMethod::BeaconChainState,
+            RPCResponse::BeaconBlocksByRange(_) => RPCMethod::BeaconBlocksByRange,
+            RPCResponse::BeaconBlocksByRoot(_) => RPCMethod::BeaconBlocksByRoot,
         };
         method.into()
     }
+
+    /// Returns `false` if the response contains more items than could have been requested.
+    pub fn is_within_limits(&self) -> bool {
+        match self {
+            RPCResponse::BeaconBlockRoots(res) => res.roots.len() as u64 <= MAX_REQUEST_BLOCK_ROOTS,
+            RPCResponse::BeaconBlockHeaders(res) => res.headers.len() as u64 <= MAX_REQUEST_BLOCKS,
+            RPCResponse::BeaconBlockBodies(res) => {
+                res.block_bodies.len() as u64 <= MAX_REQUEST_BLOCKS
+            }
+            RPCResponse::BeaconBlocksByRange(res) | RPCResponse::BeaconBlocksByRoot(res) => {
+                res.blocks.len() as u64 <= MAX_REQUEST_BLOCKS
+            }
+            RPCResponse::Hello(_) | RPCResponse::BeaconChainState(_) => true,
+        }
+    }
 }
 
 /* Request/Response data structures for RPC methods */
@@ -159,7 +253,9 @@ pub struct BeaconBlockRootsRequest {
     /// The starting slot of the requested blocks.
     pub start_slot: Slot,
     /// The number of blocks from the start slot.
-    pub count: u64, // this must be less than 32768. //TODO: Enforce this in the lower layers
+    ///
+    /// Must not exceed `MAX_REQUEST_BLOCK_ROOTS`.
+    pub count: u64,
 }
 
 /// Response containing a number of beacon block roots from a peer.
@@ -199,6 +295,8 @@ pub struct BeaconBlockHeadersRequest {
     /// The starting slot of the requested headers.
     pub start_slot: Slot,
     /// The maximum number of headers than can be returned.
+    ///
+    /// Must not exceed `MAX_REQUEST_BLOCKS`.
     pub max_headers: u64,
     /// The maximum number of slots to skip between blocks.
     pub skip_slots: u64,
@@ -215,6 +313,8 @@ pub struct BeaconBlockHeadersResponse {
 #[derive(Encode, Decode, Clone, Debug, PartialEq)]
 pub struct BeaconBlockBodiesRequest {
     /// The list of beacon block bodies being requested.
+    ///
+    /// Must not contain more than `MAX_REQUEST_BLOCKS` roots.
     pub block_roots: Vec<Hash256>,
 }
 
@@ -225,6 +325,39 @@ pub struct BeaconBlockBodiesResponse {
     pub block_bodies: Vec<BeaconBlockBody>,
 }
 
+/// Request the beacon blocks in a range of slots from a peer.
+///
+/// The blocks at slots `start_slot`, `start_slot + step`, ..., `start_slot + (count - 1) * step`
+/// are returned, in ascending order of slot. Skipped slots are omitted.
+#[derive(Encode, Decode, Clone, Debug, PartialEq)]
+pub struct BeaconBlocksByRangeRequest {
+    /// The first slot of the range.
+    pub start_slot: Slot,
+    /// The number of slots in the range.
+    ///
+    /// The range, `count * step` slots, must not exceed `MAX_REQUEST_BLOCKS`.
+    pub count: u64,
+    /// The distance between each slot in the range. Must be at least `1`.
+    pub step: u64,
+}
+
+/// Request a number of beacon blocks by their roots from a peer.
+#[derive(Encode, Decode, Clone, Debug, PartialEq)]
+pub struct BeaconBlocksByRootRequest {
+    /// The roots of the requested blocks.
+    ///
+    /// Must not contain more than `MAX_REQUEST_BLOCKS` roots.
+    pub roots: Vec<Hash256>,
+}
+
+/// Response containing the requested beacon blocks. Blocks which the peer does not know are
+/// omitted.
+#[derive(Encode, Decode, Clone, Debug, PartialEq)]
+pub struct BeaconBlocksResponse {
+    /// The list of requested beacon blocks.
+    pub blocks: Vec<BeaconBlock>,
+}
+
 /// Request values for tree hashes which yield a blocks `state_root`.
 #[derive(Encode, Decode, Clone, Debug, PartialEq)]
 pub struct BeaconChainStateRequest {
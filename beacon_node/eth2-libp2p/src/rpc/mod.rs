@@ -1,10 +1,13 @@
 /// RPC Protocol over libp2p.
 ///
-/// This is purpose built for Ethereum 2.0 serenity and the protocol listens on
-/// `/eth/serenity/rpc/1.0.0`
+/// This is purpose built for Ethereum 2.0 serenity. Each method is served on its own protocol,
+/// `/eth/serenity/rpc/<method>/<version>/<encoding>`, where the encoding is either `ssz` or
+/// `ssz_snappy`.
+mod codec;
 pub mod methods;
 mod protocol;
 
+pub use codec::Encoding;
 use futures::prelude::*;
 use libp2p::core::protocols_handler::{OneShotHandler, ProtocolsHandler};
 use libp2p::core::swarm::{
@@ -12,7 +15,7 @@ use libp2p::core::swarm::{
 };
 use libp2p::{Multiaddr, PeerId};
 pub use methods::{HelloMessage, RPCMethod, RPCRequest, RPCResponse};
-pub use protocol::{RPCEvent, RPCProtocol, RequestId};
+pub use protocol::{ProtocolId, RPCEvent, RPCProtocol, RequestId};
 use slog::o;
 use std::marker::PhantomData;
 use tokio::io::{AsyncRead, AsyncWrite};
@@ -72,7 +75,12 @@ where
         }
     }
 
-    fn inject_disconnected(&mut self, _: &PeerId, _: ConnectedPoint) {}
+    fn inject_disconnected(&mut self, peer_id: &PeerId, _: ConnectedPoint) {
+        // report this upwards so that the peer is no longer used for syncing
+        self.events.push(NetworkBehaviourAction::GenerateEvent(
+            RPCMessage::PeerDisconnected(peer_id.clone()),
+        ));
+    }
 
     fn inject_node_event(
         &mut self,
@@ -112,6 +120,7 @@ where
 pub enum RPCMessage {
     RPC(PeerId, RPCEvent),
     PeerDialed(PeerId),
+    PeerDisconnected(PeerId),
 }
 
 /// Transmission between the `OneShotHandler` and the `RPCEvent`.
@@ -1,26 +1,111 @@
+use super::codec::Encoding;
 use super::methods::*;
 use libp2p::core::{upgrade, InboundUpgrade, OutboundUpgrade, UpgradeInfo};
 use ssz::{impl_decode_via_from, impl_encode_via_from, ssz_encode, Decode, Encode};
 use ssz_derive::{Decode, Encode};
 use std::hash::{Hash, Hasher};
 use std::io;
-use std::iter;
 use tokio::io::{AsyncRead, AsyncWrite};
 
-/// The maximum bytes that can be sent across the RPC.
+/// The maximum bytes that can be sent across the RPC, prior to decompression.
 const MAX_READ_SIZE: usize = 4_194_304; // 4M
 
+/// The prefix of every RPC protocol ID.
+const PROTOCOL_PREFIX: &str = "/eth/serenity/rpc";
+
+/// The version of each RPC method's protocol.
+const PROTOCOL_VERSION: &str = "1";
+
+/// The single protocol ID which carried every method, SSZ encoded, before per-method protocols.
+/// It is still supported, least preferred, so that peers which only speak it can be reached.
+const LEGACY_PROTOCOL_ID: &[u8] = b"/eth/serenity/rpc/1.0.0";
+
+/// The protocol ID of a stream, as negotiated by multistream-select.
+pub type RawProtocolId = Vec<u8>;
+
+/// Identifies the method, version and payload encoding of an RPC stream, in the form
+/// `/eth/serenity/rpc/<method>/<version>/<encoding>`.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ProtocolId {
+    pub method: RPCMethod,
+    pub version: String,
+    pub encoding: Encoding,
+}
+
+impl ProtocolId {
+    pub fn new(method: RPCMethod, encoding: Encoding) -> Self {
+        Self {
+            method,
+            version: PROTOCOL_VERSION.to_string(),
+            encoding,
+        }
+    }
+
+    /// The IDs of each supported encoding of `method`, most preferred first.
+    pub fn all_for_method(method: RPCMethod) -> Vec<RawProtocolId> {
+        Encoding::all()
+            .into_iter()
+            .map(|encoding| ProtocolId::new(method, encoding).into())
+            .collect()
+    }
+
+    /// Returns `true` if `bytes` is the legacy protocol ID, which is shared by all methods.
+    pub fn is_legacy(bytes: &[u8]) -> bool {
+        bytes == LEGACY_PROTOCOL_ID
+    }
+
+    /// Parses a protocol ID which was negotiated for a stream.
+    pub fn from_bytes(bytes: &[u8]) -> Result<Self, DecodeError> {
+        let protocol = std::str::from_utf8(bytes).map_err(|_| DecodeError::InvalidProtocol)?;
+
+        if !protocol.starts_with(PROTOCOL_PREFIX) {
+            return Err(DecodeError::InvalidProtocol);
+        }
+
+        // The remainder begins with a `/`, so the first part is empty.
+        let mut parts = protocol[PROTOCOL_PREFIX.len()..].split('/').skip(1);
+
+        match (parts.next(), parts.next(), parts.next(), parts.next()) {
+            (Some(method), Some(version), Some(encoding), None) if version == PROTOCOL_VERSION => {
+                Ok(Self {
+                    method: RPCMethod::from_name(method).ok_or(DecodeError::InvalidProtocol)?,
+                    version: version.to_string(),
+                    encoding: Encoding::from_name(encoding).ok_or(DecodeError::InvalidProtocol)?,
+                })
+            }
+            _ => Err(DecodeError::InvalidProtocol),
+        }
+    }
+}
+
+impl Into<RawProtocolId> for ProtocolId {
+    fn into(self) -> RawProtocolId {
+        format!(
+            "{}/{}/{}/{}",
+            PROTOCOL_PREFIX,
+            self.method.name(),
+            self.version,
+            self.encoding.name()
+        )
+        .into_bytes()
+    }
+}
+
 /// Implementation of the `ConnectionUpgrade` for the rpc protocol.
 #[derive(Debug, Clone)]
 pub struct RPCProtocol;
 
 impl UpgradeInfo for RPCProtocol {
-    type Info = &'static [u8];
-    type InfoIter = iter::Once<Self::Info>;
+    type Info = RawProtocolId;
+    type InfoIter = Vec<Self::Info>;
 
     #[inline]
     fn protocol_info(&self) -> Self::InfoIter {
-        iter::once(b"/eth/serenity/rpc/1.0.0")
+        RPCMethod::all()
+            .into_iter()
+            .flat_map(ProtocolId::all_for_method)
+            .chain(std::iter::once(LEGACY_PROTOCOL_ID.to_vec()))
+            .collect()
     }
 }
 
@@ -90,17 +175,30 @@ pub enum RPCEvent {
     },
 }
 
+impl RPCEvent {
+    /// The method of the request or response.
+    fn method(&self) -> RPCMethod {
+        match self {
+            RPCEvent::Request { method_id, .. } | RPCEvent::Response { method_id, .. } => {
+                RPCMethod::from(*method_id)
+            }
+        }
+    }
+}
+
 impl UpgradeInfo for RPCEvent {
-    type Info = &'static [u8];
-    type InfoIter = iter::Once<Self::Info>;
+    type Info = RawProtocolId;
+    type InfoIter = Vec<Self::Info>;
 
     #[inline]
     fn protocol_info(&self) -> Self::InfoIter {
-        iter::once(b"/eth/serenity/rpc/1.0.0")
+        let mut protocols = ProtocolId::all_for_method(self.method());
+        protocols.push(LEGACY_PROTOCOL_ID.to_vec());
+        protocols
     }
 }
 
-type FnDecodeRPCEvent = fn(Vec<u8>, ()) -> Result<RPCEvent, DecodeError>;
+type FnDecodeRPCEvent = fn(Vec<u8>, RawProtocolId) -> Result<RPCEvent, DecodeError>;
 
 impl<TSocket> InboundUpgrade<TSocket> for RPCProtocol
 where
@@ -108,11 +206,34 @@ where
 {
     type Output = RPCEvent;
     type Error = DecodeError;
-    type Future = upgrade::ReadOneThen<upgrade::Negotiated<TSocket>, (), FnDecodeRPCEvent>;
+    type Future =
+        upgrade::ReadOneThen<upgrade::Negotiated<TSocket>, RawProtocolId, FnDecodeRPCEvent>;
+
+    fn upgrade_inbound(
+        self,
+        socket: upgrade::Negotiated<TSocket>,
+        protocol: Self::Info,
+    ) -> Self::Future {
+        upgrade::read_one_then(socket, MAX_READ_SIZE, protocol, decode_stream)
+    }
+}
+
+/// Decodes the message read from a stream which was negotiated for `protocol`.
+fn decode_stream(packet: Vec<u8>, protocol: RawProtocolId) -> Result<RPCEvent, DecodeError> {
+    // Legacy streams carry any method, uncompressed.
+    if ProtocolId::is_legacy(&protocol) {
+        return decode(packet);
+    }
 
-    fn upgrade_inbound(self, socket: upgrade::Negotiated<TSocket>, _: Self::Info) -> Self::Future {
-        upgrade::read_one_then(socket, MAX_READ_SIZE, (), |packet, ()| Ok(decode(packet)?))
+    let protocol = ProtocolId::from_bytes(&protocol)?;
+    let event = decode(protocol.encoding.decode(packet)?)?;
+
+    // The message must be of the method that the stream was negotiated for.
+    if event.method() != protocol.method {
+        return Err(DecodeError::InvalidProtocol);
     }
+
+    Ok(event)
 }
 
 /// A helper structed used to obtain SSZ serialization for RPC messages.
@@ -148,9 +269,19 @@ fn decode(packet: Vec<u8>) -> Result<RPCEvent, DecodeError> {
             RPCMethod::BeaconChainState => {
                 RPCRequest::BeaconChainState(BeaconChainStateRequest::from_ssz_bytes(&msg.bytes)?)
             }
+            RPCMethod::BeaconBlocksByRange => RPCRequest::BeaconBlocksByRange(
+                BeaconBlocksByRangeRequest::from_ssz_bytes(&msg.bytes)?,
+            ),
+            RPCMethod::BeaconBlocksByRoot => RPCRequest::BeaconBlocksByRoot(
+                BeaconBlocksByRootRequest::from_ssz_bytes(&msg.bytes)?,
+            ),
             RPCMethod::Unknown => return Err(DecodeError::UnknownRPCMethod),
         };
 
+        if !body.is_within_limits() {
+            return Err(DecodeError::LimitExceeded);
+        }
+
         Ok(RPCEvent::Request {
             id: RequestId::from(msg.id),
             method_id: msg.other,
@@ -173,11 +304,21 @@ fn decode(packet: Vec<u8>) -> Result<RPCEvent, DecodeError> {
             RPCMethod::BeaconChainState => {
                 RPCResponse::BeaconChainState(BeaconChainStateResponse::from_ssz_bytes(&msg.bytes)?)
             }
+            RPCMethod::BeaconBlocksByRange => {
+                RPCResponse::BeaconBlocksByRange(BeaconBlocksResponse::from_ssz_bytes(&msg.bytes)?)
+            }
+            RPCMethod::BeaconBlocksByRoot => {
+                RPCResponse::BeaconBlocksByRoot(BeaconBlocksResponse::from_ssz_bytes(&msg.bytes)?)
+            }
             // We should never receive a goodbye response; it is invalid.
             RPCMethod::Goodbye => return Err(DecodeError::UnknownRPCMethod),
             RPCMethod::Unknown => return Err(DecodeError::UnknownRPCMethod),
         };
 
+        if !result.is_within_limits() {
+            return Err(DecodeError::LimitExceeded);
+        }
+
         Ok(RPCEvent::Response {
             id: RequestId::from(msg.id),
             method_id: msg.other,
@@ -195,9 +336,19 @@ where
     type Future = upgrade::WriteOne<upgrade::Negotiated<TSocket>>;
 
     #[inline]
-    fn upgrade_outbound(self, socket: upgrade::Negotiated<TSocket>, _: Self::Info) -> Self::Future {
-        let bytes = ssz_encode(&self);
-        upgrade::write_one(socket, bytes)
+    fn upgrade_outbound(
+        self,
+        socket: upgrade::Negotiated<TSocket>,
+        protocol: Self::Info,
+    ) -> Self::Future {
+        // The legacy protocol is uncompressed. Only protocols from `protocol_info` can be
+        // negotiated, so also fall back to the uncompressed encoding if the ID is somehow
+        // unparseable.
+        let encoding = ProtocolId::from_bytes(&protocol)
+            .map(|protocol| protocol.encoding)
+            .unwrap_or(Encoding::SSZ);
+
+        upgrade::write_one(socket, encoding.encode(ssz_encode(&self)))
     }
 }
 
@@ -223,6 +374,8 @@ impl Encode for RPCEvent {
                     RPCRequest::BeaconBlockHeaders(body) => body.as_ssz_bytes(),
                     RPCRequest::BeaconBlockBodies(body) => body.as_ssz_bytes(),
                     RPCRequest::BeaconChainState(body) => body.as_ssz_bytes(),
+                    RPCRequest::BeaconBlocksByRange(body) => body.as_ssz_bytes(),
+                    RPCRequest::BeaconBlocksByRoot(body) => body.as_ssz_bytes(),
                 },
             },
             RPCEvent::Response {
@@ -239,6 +392,8 @@ impl Encode for RPCEvent {
                     RPCResponse::BeaconBlockHeaders(response) => response.as_ssz_bytes(),
                     RPCResponse::BeaconBlockBodies(response) => response.as_ssz_bytes(),
                     RPCResponse::BeaconChainState(response) => response.as_ssz_bytes(),
+                    RPCResponse::BeaconBlocksByRange(response) => response.as_ssz_bytes(),
+                    RPCResponse::BeaconBlocksByRoot(response) => response.as_ssz_bytes(),
                 },
             },
         };
@@ -252,6 +407,15 @@ pub enum DecodeError {
     ReadError(upgrade::ReadOneError),
     SSZDecodeError(ssz::DecodeError),
     UnknownRPCMethod,
+    /// The message requests or contains more items than permitted by the protocol, or its
+    /// declared length exceeds the maximum payload size.
+    LimitExceeded,
+    /// The protocol ID is unknown or does not match the message.
+    InvalidProtocol,
+    /// The length prefix of a compressed payload is malformed or does not match its contents.
+    InvalidLengthPrefix,
+    /// A compressed payload could not be decompressed.
+    DecompressionError(io::Error),
 }
 
 impl From<upgrade::ReadOneError> for DecodeError {
@@ -267,3 +431,136 @@ impl From<ssz::DecodeError> for DecodeError {
         DecodeError::SSZDecodeError(err)
     }
 }
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use types::Slot;
+
+    fn blocks_by_range(count: u64, step: u64) -> RPCEvent {
+        let body = RPCRequest::BeaconBlocksByRange(BeaconBlocksByRangeRequest {
+            start_slot: Slot::new(64),
+            count,
+            step,
+        });
+
+        RPCEvent::Request {
+            id: RequestId::from(1),
+            method_id: body.method_id(),
+            body,
+        }
+    }
+
+    #[test]
+    fn ssz_encoding_blocks_by_range() {
+        let encoded = ssz_encode(&blocks_by_range(MAX_REQUEST_BLOCKS, 1));
+
+        match decode(encoded).unwrap() {
+            RPCEvent::Request {
+                body: RPCRequest::BeaconBlocksByRange(request),
+                ..
+            } => {
+                assert_eq!(request.count, MAX_REQUEST_BLOCKS);
+                assert_eq!(request.step, 1);
+            }
+            event => panic!("unexpected event: {:?}", event),
+        }
+    }
+
+    #[test]
+    fn stepped_range_within_limits() {
+        let encoded = ssz_encode(&blocks_by_range(MAX_REQUEST_BLOCKS / 4, 4));
+
+        match decode(encoded).unwrap() {
+            RPCEvent::Request {
+                body: RPCRequest::BeaconBlocksByRange(request),
+                ..
+            } => {
+                assert_eq!(request.count, MAX_REQUEST_BLOCKS / 4);
+                assert_eq!(request.step, 4);
+            }
+            event => panic!("unexpected event: {:?}", event),
+        }
+    }
+
+    #[test]
+    fn request_limits_enforced() {
+        for event in vec![
+            blocks_by_range(MAX_REQUEST_BLOCKS + 1, 1),
+            blocks_by_range(MAX_REQUEST_BLOCKS / 2 + 1, 2),
+            blocks_by_range(u64::max_value() / 2 + 1, 2),
+            blocks_by_range(1, 0),
+        ] {
+            match decode(ssz_encode(&event)) {
+                Err(DecodeError::LimitExceeded) => {}
+                result => panic!("limit not enforced: {:?}", result),
+            }
+        }
+    }
+
+    #[test]
+    fn protocol_id_round_trip() {
+        for method in RPCMethod::all() {
+            for raw in ProtocolId::all_for_method(method) {
+                let protocol = ProtocolId::from_bytes(&raw).unwrap();
+
+                assert_eq!(protocol.method, method);
+                assert_eq!(Into::<RawProtocolId>::into(protocol), raw);
+            }
+        }
+
+        let protocol =
+            ProtocolId::from_bytes(b"/eth/serenity/rpc/beacon_blocks_by_range/1/ssz_snappy")
+                .unwrap();
+        assert_eq!(protocol.method, RPCMethod::BeaconBlocksByRange);
+        assert_eq!(protocol.encoding, Encoding::SSZSnappy);
+    }
+
+    #[test]
+    fn invalid_protocol_ids_rejected() {
+        for raw in vec![
+            &b"/eth/serenity/rpc/hello/2/ssz"[..],
+            b"/eth/serenity/rpc/hello/1/json",
+            b"/eth/serenity/rpc/unknown/1/ssz",
+            b"/eth/serenity/rpc/hello/1/ssz/extra",
+        ] {
+            assert!(ProtocolId::from_bytes(raw).is_err());
+        }
+    }
+
+    #[test]
+    fn legacy_protocol_negotiates() {
+        assert!(RPCProtocol
+            .protocol_info()
+            .iter()
+            .any(|raw| ProtocolId::is_legacy(raw)));
+
+        // The legacy protocol is offered for every outbound message, after the per-method IDs.
+        let event = blocks_by_range(1, 1);
+        let protocols = event.protocol_info();
+        assert_eq!(
+            protocols.last().map(|raw| ProtocolId::is_legacy(raw)),
+            Some(true)
+        );
+
+        // A stream negotiated with the legacy ID accepts any method, SSZ encoded.
+        match decode_stream(ssz_encode(&event), b"/eth/serenity/rpc/1.0.0".to_vec()).unwrap() {
+            RPCEvent::Request {
+                body: RPCRequest::BeaconBlocksByRange(request),
+                ..
+            } => assert_eq!(request.count, 1),
+            event => panic!("unexpected event: {:?}", event),
+        }
+    }
+
+    #[test]
+    fn per_method_protocol_must_match_message() {
+        let event = blocks_by_range(1, 1);
+        let hello = ProtocolId::new(RPCMethod::Hello, Encoding::SSZ);
+
+        match decode_stream(ssz_encode(&event), hello.into()) {
+            Err(DecodeError::InvalidProtocol) => {}
+            result => panic!("mismatched method accepted: {:?}", result),
+        }
+    }
+}
@@ -1,10 +1,14 @@
 use crate::behaviour::{Behaviour, BehaviourEvent, PubsubMessage};
 use crate::error;
 use crate::multiaddr::Protocol;
+use crate::peer_manager::{PeerAction, PeerManager};
 use crate::rpc::RPCEvent;
 use crate::NetworkConfig;
 use crate::{TopicBuilder, TopicHash};
-use crate::{BEACON_ATTESTATION_TOPIC, BEACON_PUBSUB_TOPIC};
+use crate::{
+    ATTESTER_SLASHING_TOPIC, BEACON_ATTESTATION_TOPIC, BEACON_PUBSUB_TOPIC,
+    PROPOSER_SLASHING_TOPIC, TRANSFER_TOPIC, VOLUNTARY_EXIT_TOPIC,
+};
 use futures::prelude::*;
 use futures::Stream;
 use libp2p::core::{
@@ -20,13 +24,18 @@ use slog::{debug, info, trace, warn};
 use std::fs::File;
 use std::io::prelude::*;
 use std::io::{Error, ErrorKind};
-use std::time::Duration;
+use std::time::{Duration, Instant};
+use tokio::timer::Interval;
 
 type Libp2pStream = Boxed<(PeerId, StreamMuxerBox), Error>;
 type Libp2pBehaviour = Behaviour<Substream<StreamMuxerBox>>;
 
 const NETWORK_KEY_FILENAME: &str = "key";
 
+/// How often banned peers are checked for an expired ban, and disconnected peers for an expired
+/// penalty.
+const UNBAN_INTERVAL: Duration = Duration::from_secs(30);
+
 /// The configuration and state of the libp2p components for the beacon node.
 pub struct Service {
     /// The libp2p Swarm handler.
@@ -34,6 +43,10 @@ pub struct Service {
     pub swarm: Swarm<Libp2pStream, Libp2pBehaviour>,
     /// This node's PeerId.
     _local_peer_id: PeerId,
+    /// The scores of peers, used to ban those which misbehave.
+    peer_manager: PeerManager,
+    /// Fires every `UNBAN_INTERVAL` to lift expired bans and penalties.
+    unban_interval: Interval,
     /// The libp2p logger handle.
     pub log: slog::Logger,
 }
@@ -81,6 +94,10 @@ impl Service {
         //attestations
         topics.push(BEACON_ATTESTATION_TOPIC.to_string());
         topics.push(BEACON_PUBSUB_TOPIC.to_string());
+        topics.push(VOLUNTARY_EXIT_TOPIC.to_string());
+        topics.push(PROPOSER_SLASHING_TOPIC.to_string());
+        topics.push(ATTESTER_SLASHING_TOPIC.to_string());
+        topics.push(TRANSFER_TOPIC.to_string());
         topics.append(&mut config.topics.clone());
 
         let mut subscribed_topics = vec![];
@@ -97,10 +114,49 @@ impl Service {
 
         Ok(Service {
             _local_peer_id: local_peer_id,
+            peer_manager: PeerManager::new(),
+            unban_interval: Interval::new(Instant::now() + UNBAN_INTERVAL, UNBAN_INTERVAL),
             swarm,
             log,
         })
     }
+
+    /// Returns a handle to the scores of peers.
+    pub fn peer_manager(&self) -> PeerManager {
+        self.peer_manager.clone()
+    }
+
+    /// Adjusts the score of `peer_id`, banning it if the score falls too low.
+    ///
+    /// A banned peer is disconnected and any connection to or from it is refused until the ban is
+    /// lifted.
+    pub fn report_peer(&mut self, peer_id: PeerId, action: PeerAction) {
+        debug!(
+            self.log, "PeerReported";
+            "peer" => format!("{:?}", peer_id),
+            "action" => format!("{:?}", action),
+        );
+
+        if self.peer_manager.report_peer(&peer_id, action) {
+            warn!(
+                self.log, "PeerBanned";
+                "peer" => format!("{:?}", peer_id),
+                "action" => format!("{:?}", action),
+            );
+            Swarm::ban_peer_id(&mut self.swarm, peer_id);
+        }
+    }
+
+    /// Lifts the ban of each peer whose cooling-off period has passed, and forgets disconnected
+    /// peers whose penalty has expired.
+    fn unban_expired_peers(&mut self) {
+        for peer_id in self.peer_manager.unban_expired() {
+            info!(self.log, "PeerUnbanned"; "peer" => format!("{:?}", peer_id));
+            Swarm::unban_peer_id(&mut self.swarm, peer_id);
+        }
+
+        self.peer_manager.forget_expired_penalties();
+    }
 }
 
 impl Stream for Service {
@@ -108,6 +164,17 @@ impl Stream for Service {
     type Error = crate::error::Error;
 
     fn poll(&mut self) -> Poll<Option<Self::Item>, Self::Error> {
+        loop {
+            match self.unban_interval.poll() {
+                Ok(Async::Ready(_)) => self.unban_expired_peers(),
+                Ok(Async::NotReady) => break,
+                Err(e) => {
+                    warn!(self.log, "Unban timer failed: {:?}", e);
+                    break;
+                }
+            }
+        }
+
         loop {
             // TODO: Currently only gossipsub events passed here.
             // Build a type for more generic events
@@ -133,6 +200,10 @@ impl Stream for Service {
                     BehaviourEvent::PeerDialed(peer_id) => {
                         return Ok(Async::Ready(Some(Libp2pEvent::PeerDialed(peer_id))));
                     }
+                    BehaviourEvent::PeerDisconnected(peer_id) => {
+                        self.peer_manager.peer_disconnected(&peer_id);
+                        return Ok(Async::Ready(Some(Libp2pEvent::PeerDisconnected(peer_id))));
+                    }
                 },
                 Ok(Async::Ready(None)) => unreachable!("Swarm stream shouldn't end"),
                 Ok(Async::NotReady) => break,
@@ -182,6 +253,8 @@ pub enum Libp2pEvent {
     RPC(PeerId, RPCEvent),
     /// Initiated the connection to a new peer.
     PeerDialed(PeerId),
+    /// A peer has disconnected.
+    PeerDisconnected(PeerId),
     /// Received pubsub message.
     PubsubMessage {
         source: PeerId,
@@ -1,11 +1,16 @@
-use crate::{key::BeaconChainKey, map_persistent_err_to_500};
+use crate::error::{ApiError, ApiResult};
+use crate::helpers::{get_beacon_chain, handler, UrlQuery};
+use crate::key::{BeaconChainKey, EventStreamsKey, NetworkChannelKey, PeerManagerKey};
+use crate::{beacon, events, map_persistent_err_to_500, operations, peers, validator};
 use beacon_chain::{BeaconChain, BeaconChainTypes};
+use eth2_libp2p::PeerManager;
 use iron::prelude::*;
 use iron::{
     headers::{CacheControl, CacheDirective, ContentType},
     status::Status,
     AfterMiddleware, Handler, IronResult, Request, Response,
 };
+use network::NetworkMessage;
 use persistent::Read;
 use router::Router;
 use serde_json::json;
@@ -14,15 +19,150 @@ use std::sync::Arc;
 /// Yields a handler for the HTTP API.
 pub fn build_handler<T: BeaconChainTypes + 'static>(
     beacon_chain: Arc<BeaconChain<T>>,
+    network_chan: crossbeam_channel::Sender<NetworkMessage>,
+    peer_manager: PeerManager,
 ) -> impl Handler {
     let mut router = Router::new();
 
     router.get("/node/fork", handle_fork::<T>, "fork");
+    router.get("/node/info", handler(handle_node_info::<T>), "info");
+    router.get(
+        "/node/fork_choice",
+        handler(handle_fork_choice::<T>),
+        "fork_choice",
+    );
+    router.get("/events", handler(events::handle_events::<T>), "events");
+
+    router.get("/network/peers", handler(peers::get_peers), "peers");
+
+    router.get("/beacon/head", handler(beacon::get_head::<T>), "head");
+    router.get("/beacon/block", handler(beacon::get_block::<T>), "block");
+    router.get(
+        "/beacon/state_root",
+        handler(beacon::get_state_root::<T>),
+        "state_root",
+    );
+    router.get("/beacon/state", handler(beacon::get_state::<T>), "state");
+    router.get(
+        "/beacon/validator",
+        handler(beacon::get_validator::<T>),
+        "validator",
+    );
+    router.get(
+        "/beacon/committees",
+        handler(beacon::get_committees::<T>),
+        "committees",
+    );
+    router.get(
+        "/beacon/proposer",
+        handler(beacon::get_proposer::<T>),
+        "proposer",
+    );
+    router.get(
+        "/beacon/finality",
+        handler(beacon::get_finality::<T>),
+        "finality",
+    );
+
+    router.post(
+        "/validator/duties",
+        handler(validator::post_duties::<T>),
+        "validator_duties",
+    );
+    router.get(
+        "/validator/block",
+        handler(validator::get_new_block::<T>),
+        "validator_get_block",
+    );
+    router.post(
+        "/validator/block",
+        handler(validator::post_block::<T>),
+        "validator_post_block",
+    );
+    router.get(
+        "/validator/attestation_data",
+        handler(validator::get_attestation_data::<T>),
+        "validator_attestation_data",
+    );
+    router.post(
+        "/validator/attestation",
+        handler(validator::post_attestation::<T>),
+        "validator_attestation",
+    );
+
+    router.post(
+        "/operations/voluntary_exit",
+        handler(operations::post_voluntary_exit::<T>),
+        "operations_voluntary_exit",
+    );
+    router.post(
+        "/operations/proposer_slashing",
+        handler(operations::post_proposer_slashing::<T>),
+        "operations_proposer_slashing",
+    );
+    router.post(
+        "/operations/attester_slashing",
+        handler(operations::post_attester_slashing::<T>),
+        "operations_attester_slashing",
+    );
+    router.post(
+        "/operations/transfer",
+        handler(operations::post_transfer::<T>),
+        "operations_transfer",
+    );
+    router.post(
+        "/operations/deposit",
+        handler(operations::post_deposit::<T>),
+        "operations_deposit",
+    );
+    router.get(
+        "/operations/counts",
+        handler(operations::get_counts::<T>),
+        "operations_counts",
+    );
+    router.get(
+        "/operations/attestations",
+        handler(operations::get_attestations::<T>),
+        "operations_attestations",
+    );
+    router.get(
+        "/operations/deposits",
+        handler(operations::get_deposits::<T>),
+        "operations_deposits",
+    );
+    router.get(
+        "/operations/proposer_slashings",
+        handler(operations::get_proposer_slashings::<T>),
+        "operations_proposer_slashings",
+    );
+    router.get(
+        "/operations/attester_slashings",
+        handler(operations::get_attester_slashings::<T>),
+        "operations_attester_slashings",
+    );
+    router.get(
+        "/operations/voluntary_exits",
+        handler(operations::get_voluntary_exits::<T>),
+        "operations_voluntary_exits",
+    );
+    router.get(
+        "/operations/transfers",
+        handler(operations::get_transfers::<T>),
+        "operations_transfers",
+    );
 
     let mut chain = Chain::new(router);
 
     // Insert `BeaconChain` so it may be accessed in a request.
     chain.link(Read::<BeaconChainKey<T>>::both(beacon_chain.clone()));
+    // Insert the network channel so operations may be published in a request.
+    chain.link(Read::<NetworkChannelKey>::both(network_chan));
+    // Insert the peer manager so peer scores may be read in a request.
+    chain.link(Read::<PeerManagerKey>::both(peer_manager));
+    // Insert the count of open event streams, so they may be limited.
+    chain.link(Read::<EventStreamsKey>::both(
+        events::EventStreams::default(),
+    ));
     // Set the content-type headers.
     chain.link_after(SetJsonContentType);
     // Set the cache headers.
@@ -69,3 +209,47 @@ fn handle_fork<T: BeaconChainTypes + 'static>(req: &mut Request) -> IronResult<R
 
     Ok(Response::with((Status::Ok, response.to_string())))
 }
+
+/// Provides the same information as the gRPC `BeaconNodeService::info` endpoint.
+fn handle_node_info<T: BeaconChainTypes + 'static>(req: &mut Request) -> ApiResult {
+    let beacon_chain = get_beacon_chain::<T>(req)?;
+    let head = beacon_chain.head();
+
+    Ok(Response::with((
+        Status::Ok,
+        json!({
+            "version": version::version(),
+            "fork": head.beacon_state.fork,
+            "chain_id": beacon_chain.spec.chain_id,
+            "genesis_time": head.beacon_state.genesis_time,
+            "genesis_slot": beacon_chain.spec.genesis_slot,
+            "finalized_epoch": head.beacon_state.finalized_epoch,
+        })
+        .to_string(),
+    )))
+}
+
+/// Dumps the fork choice tree as JSON, or as a Graphviz DOT digraph if `?format=dot` is given.
+fn handle_fork_choice<T: BeaconChainTypes + 'static>(req: &mut Request) -> ApiResult {
+    let beacon_chain = get_beacon_chain::<T>(req)?;
+
+    let query = UrlQuery::from_request(req);
+    let format = query.get("format").unwrap_or("json");
+
+    let dump = beacon_chain
+        .fork_choice_dump()
+        .map_err(|e| ApiError::ServerError(format!("Unable to dump fork choice: {:?}", e)))?;
+
+    match format {
+        "json" => Ok(Response::with((
+            Status::Ok,
+            dump.to_json().map_err(ApiError::ServerError)?,
+        ))),
+        "dot" => {
+            let mut resp = Response::with((Status::Ok, dump.to_dot()));
+            resp.headers.set(ContentType::plaintext());
+            Ok(resp)
+        }
+        other => Err(ApiError::BadRequest(format!("Unknown format: {}", other))),
+    }
+}
@@ -0,0 +1,406 @@
+//! Handlers for `/beacon/*` endpoints, which query the state of the `BeaconChain`.
+use crate::error::{ApiError, ApiResult};
+use crate::helpers::{
+    accepts_ssz, get_beacon_chain, json_response, parse_hex, parse_query_param, parse_root,
+    query_param, ssz_response,
+};
+use beacon_chain::{BeaconChain, BeaconChainTypes};
+use iron::Request;
+use serde_derive::Serialize;
+use types::{
+    BeaconBlock, BeaconState, Epoch, EthSpec, Hash256, PublicKey, RelativeEpoch, Slot, Validator,
+};
+
+#[derive(Serialize)]
+struct HeadResponse {
+    slot: Slot,
+    block_root: Hash256,
+    state_root: Hash256,
+    parent_root: Hash256,
+    current_justified_epoch: Epoch,
+    current_justified_root: Hash256,
+    finalized_epoch: Epoch,
+    finalized_root: Hash256,
+}
+
+/// `GET /beacon/head`
+pub fn get_head<T: BeaconChainTypes + 'static>(req: &mut Request) -> ApiResult {
+    let beacon_chain = get_beacon_chain::<T>(req)?;
+    let head = beacon_chain.head();
+
+    json_response(&HeadResponse {
+        slot: head.beacon_block.slot,
+        block_root: head.beacon_block_root,
+        state_root: head.beacon_state_root,
+        parent_root: head.beacon_block.previous_block_root,
+        current_justified_epoch: head.beacon_state.current_justified_epoch,
+        current_justified_root: head.beacon_state.current_justified_root,
+        finalized_epoch: head.beacon_state.finalized_epoch,
+        finalized_root: head.beacon_state.finalized_root,
+    })
+}
+
+#[derive(Serialize)]
+struct BlockResponse {
+    root: Hash256,
+    beacon_block: BeaconBlock,
+}
+
+/// `GET /beacon/block?root=0x..` or `GET /beacon/block?slot=N`
+///
+/// A slot is looked up in the canonical chain. If the slot was skipped, there is no block. The
+/// block alone is returned as SSZ if the request accepts `application/octet-stream`.
+pub fn get_block<T: BeaconChainTypes + 'static>(req: &mut Request) -> ApiResult {
+    let beacon_chain = get_beacon_chain::<T>(req)?;
+
+    let (root, beacon_block) = match (query_param(req, "root"), slot_param(req)?) {
+        (Some(root), None) => {
+            let root = parse_root(&root)?;
+            let block = beacon_chain
+                .get_block(&root)?
+                .ok_or_else(|| ApiError::NotFound(format!("Unknown block: {:?}", root)))?;
+
+            (root, block)
+        }
+        (None, Some(slot)) => {
+            let root = beacon_chain
+                .block_root_at_slot(slot)?
+                .ok_or_else(|| ApiError::NotFound(format!("Slot {} is after the head", slot)))?;
+            let block = beacon_chain.get_block(&root)?.ok_or_else(|| {
+                ApiError::ServerError(format!("Canonical block is missing: {:?}", root))
+            })?;
+
+            if block.slot != slot {
+                return Err(ApiError::NotFound(format!("Slot {} was skipped", slot)));
+            }
+
+            (root, block)
+        }
+        _ => {
+            return Err(ApiError::BadRequest(
+                "Exactly one of root or slot must be given".to_string(),
+            ))
+        }
+    };
+
+    if accepts_ssz(req) {
+        ssz_response(&beacon_block)
+    } else {
+        json_response(&BlockResponse { root, beacon_block })
+    }
+}
+
+#[derive(Serialize)]
+struct StateRootResponse {
+    slot: Slot,
+    root: Hash256,
+}
+
+/// `GET /beacon/state_root?slot=N`
+///
+/// Only slots up to the canonical head are known.
+pub fn get_state_root<T: BeaconChainTypes + 'static>(req: &mut Request) -> ApiResult {
+    let beacon_chain = get_beacon_chain::<T>(req)?;
+
+    let slot =
+        slot_param(req)?.ok_or_else(|| ApiError::BadRequest("A slot must be given".to_string()))?;
+    let root = beacon_chain
+        .state_root_at_slot(slot)?
+        .ok_or_else(|| ApiError::NotFound(format!("Slot {} is after the head", slot)))?;
+
+    json_response(&StateRootResponse { slot, root })
+}
+
+#[derive(Serialize)]
+struct StateResponse<E: EthSpec> {
+    root: Hash256,
+    beacon_state: BeaconState<E>,
+}
+
+/// `GET /beacon/state`, `GET /beacon/state?root=0x..` or `GET /beacon/state?slot=N`
+///
+/// Returns the head state if neither a root or slot are given. The state alone is returned as
+/// SSZ if the request accepts `application/octet-stream`.
+pub fn get_state<T: BeaconChainTypes + 'static>(req: &mut Request) -> ApiResult {
+    let beacon_chain = get_beacon_chain::<T>(req)?;
+
+    let root = match (query_param(req, "root"), slot_param(req)?) {
+        (None, None) => beacon_chain.head().beacon_state_root,
+        (Some(root), None) => parse_root(&root)?,
+        (None, Some(slot)) => beacon_chain
+            .state_root_at_slot(slot)?
+            .ok_or_else(|| ApiError::NotFound(format!("Slot {} is after the head", slot)))?,
+        _ => {
+            return Err(ApiError::BadRequest(
+                "Only one of root or slot may be given".to_string(),
+            ))
+        }
+    };
+
+    let beacon_state: BeaconState<T::EthSpec> = beacon_chain
+        .store
+        .get(&root)?
+        .ok_or_else(|| ApiError::NotFound(format!("Unknown state: {:?}", root)))?;
+
+    if accepts_ssz(req) {
+        ssz_response(&beacon_state)
+    } else {
+        json_response(&StateResponse { root, beacon_state })
+    }
+}
+
+/// The status of a validator at some epoch.
+#[derive(Serialize, Debug, PartialEq)]
+#[serde(rename_all = "snake_case")]
+pub enum ValidatorStatus {
+    /// Not yet activated.
+    Pending,
+    Active,
+    /// Active, but will be ejected after being slashed.
+    ActiveSlashed,
+    /// Exited, but not yet withdrawable.
+    Exited,
+    /// Exited after being slashed, but not yet withdrawable.
+    ExitedSlashed,
+    Withdrawable,
+}
+
+impl ValidatorStatus {
+    pub fn at_epoch(validator: &Validator, epoch: Epoch) -> Self {
+        if epoch < validator.activation_epoch {
+            ValidatorStatus::Pending
+        } else if epoch < validator.exit_epoch {
+            if validator.slashed {
+                ValidatorStatus::ActiveSlashed
+            } else {
+                ValidatorStatus::Active
+            }
+        } else if epoch < validator.withdrawable_epoch {
+            if validator.slashed {
+                ValidatorStatus::ExitedSlashed
+            } else {
+                ValidatorStatus::Exited
+            }
+        } else {
+            ValidatorStatus::Withdrawable
+        }
+    }
+}
+
+#[derive(Serialize)]
+struct ValidatorResponse {
+    index: usize,
+    status: ValidatorStatus,
+    balance: u64,
+    validator: Validator,
+}
+
+/// `GET /beacon/validator?index=N` or `GET /beacon/validator?pubkey=0x..`
+///
+/// Information is read from the state at the present slot. The `Validator` alone is returned as
+/// SSZ if the request accepts `application/octet-stream`.
+pub fn get_validator<T: BeaconChainTypes + 'static>(req: &mut Request) -> ApiResult {
+    let beacon_chain = get_beacon_chain::<T>(req)?;
+    let state = beacon_chain.current_state();
+
+    let index = match (
+        parse_query_param::<usize>(req, "index")?,
+        query_param(req, "pubkey"),
+    ) {
+        (Some(index), None) => index,
+        (None, Some(pubkey)) => {
+            let pubkey = PublicKey::from_bytes(&parse_hex(&pubkey)?)
+                .map_err(|e| ApiError::BadRequest(format!("Invalid pubkey: {:?}", e)))?;
+
+            state
+                .get_validator_index(&pubkey)?
+                .ok_or_else(|| ApiError::NotFound("Unknown pubkey".to_string()))?
+        }
+        _ => {
+            return Err(ApiError::BadRequest(
+                "Exactly one of index or pubkey must be given".to_string(),
+            ))
+        }
+    };
+
+    let validator = state
+        .validator_registry
+        .get(index)
+        .ok_or_else(|| ApiError::NotFound(format!("Unknown validator index: {}", index)))?;
+
+    if accepts_ssz(req) {
+        ssz_response(validator)
+    } else {
+        json_response(&ValidatorResponse {
+            index,
+            status: ValidatorStatus::at_epoch(validator, state.current_epoch()),
+            balance: state.balances.get(index).cloned().unwrap_or(0),
+            validator: validator.clone(),
+        })
+    }
+}
+
+#[derive(Serialize)]
+struct CommitteeResponse {
+    slot: Slot,
+    shard: u64,
+    committee: Vec<usize>,
+}
+
+/// `GET /beacon/committees?epoch=N`
+///
+/// Returns the crosslink committees of every slot in the epoch (the current epoch, if none is
+/// given). Epochs up to one after the present epoch are known.
+pub fn get_committees<T: BeaconChainTypes + 'static>(req: &mut Request) -> ApiResult {
+    let beacon_chain = get_beacon_chain::<T>(req)?;
+    let epoch = match parse_query_param::<u64>(req, "epoch")? {
+        Some(epoch) => Epoch::new(epoch),
+        None => beacon_chain.current_state().current_epoch(),
+    };
+
+    let committees = with_state_at_epoch(&beacon_chain, epoch, |state, _| {
+        let mut committees = vec![];
+
+        for slot in epoch.slot_iter(T::EthSpec::slots_per_epoch()) {
+            for committee in state.get_crosslink_committees_at_slot(slot)? {
+                committees.push(CommitteeResponse {
+                    slot: committee.slot,
+                    shard: committee.shard,
+                    committee: committee.committee.to_vec(),
+                });
+            }
+        }
+
+        Ok(committees)
+    })?;
+
+    json_response(&committees)
+}
+
+#[derive(Serialize)]
+struct ProposerResponse {
+    slot: Slot,
+    proposer_index: usize,
+    pubkey: PublicKey,
+}
+
+/// `GET /beacon/proposer?slot=N`
+///
+/// Returns the proposer of the slot (the present slot, if none is given). Slots up to the end of
+/// the epoch after the present epoch are known.
+pub fn get_proposer<T: BeaconChainTypes + 'static>(req: &mut Request) -> ApiResult {
+    let beacon_chain = get_beacon_chain::<T>(req)?;
+    let slot = match slot_param(req)? {
+        Some(slot) => slot,
+        None => beacon_chain.present_slot(),
+    };
+    let epoch = slot.epoch(T::EthSpec::slots_per_epoch());
+
+    let (proposer_index, pubkey) =
+        with_state_at_epoch(&beacon_chain, epoch, |state, relative_epoch| {
+            let index =
+                state.get_beacon_proposer_index(slot, relative_epoch, &beacon_chain.spec)?;
+            Ok((index, state.validator_registry[index].pubkey.clone()))
+        })?;
+
+    json_response(&ProposerResponse {
+        slot,
+        proposer_index,
+        pubkey,
+    })
+}
+
+#[derive(Serialize)]
+struct Checkpoint {
+    epoch: Epoch,
+    root: Hash256,
+}
+
+#[derive(Serialize)]
+struct FinalityResponse {
+    previous_justified: Checkpoint,
+    current_justified: Checkpoint,
+    finalized: Checkpoint,
+}
+
+/// `GET /beacon/finality`
+///
+/// Returns the justified and finalized checkpoints of the head state.
+pub fn get_finality<T: BeaconChainTypes + 'static>(req: &mut Request) -> ApiResult {
+    let beacon_chain = get_beacon_chain::<T>(req)?;
+    let head = beacon_chain.head();
+    let state = &head.beacon_state;
+
+    json_response(&FinalityResponse {
+        previous_justified: Checkpoint {
+            epoch: state.previous_justified_epoch,
+            root: state.previous_justified_root,
+        },
+        current_justified: Checkpoint {
+            epoch: state.current_justified_epoch,
+            root: state.current_justified_root,
+        },
+        finalized: Checkpoint {
+            epoch: state.finalized_epoch,
+            root: state.finalized_root,
+        },
+    })
+}
+
+fn slot_param(req: &Request) -> Result<Option<Slot>, ApiError> {
+    Ok(parse_query_param::<u64>(req, "slot")?.map(Slot::new))
+}
+
+/// Calls `f` with a canonical state which has a committee cache for `epoch`, and the epoch
+/// relative to that state.
+///
+/// The present state is used for the previous, current and next epochs. For earlier epochs, the
+/// state of the last block at or before the end of the epoch is loaded from the store.
+fn with_state_at_epoch<T, F, R>(
+    beacon_chain: &BeaconChain<T>,
+    epoch: Epoch,
+    f: F,
+) -> Result<R, ApiError>
+where
+    T: BeaconChainTypes,
+    F: FnOnce(&BeaconState<T::EthSpec>, RelativeEpoch) -> Result<R, ApiError>,
+{
+    {
+        let state = beacon_chain.current_state();
+
+        if let Ok(relative_epoch) = RelativeEpoch::from_epoch(state.current_epoch(), epoch) {
+            return f(&state, relative_epoch);
+        }
+
+        if epoch > state.current_epoch() {
+            return Err(ApiError::BadRequest(format!(
+                "Epoch {} is too far in the future",
+                epoch
+            )));
+        }
+    }
+
+    let last_slot = epoch.end_slot(T::EthSpec::slots_per_epoch());
+    let block = beacon_chain
+        .block_at_slot(last_slot)?
+        .ok_or_else(|| ApiError::NotFound(format!("No block at or before slot {}", last_slot)))?;
+
+    let mut state: BeaconState<T::EthSpec> =
+        beacon_chain.store.get(&block.state_root)?.ok_or_else(|| {
+            ApiError::ServerError(format!(
+                "Canonical state is missing: {:?}",
+                block.state_root
+            ))
+        })?;
+
+    // The last block may be from a prior epoch, if the following slots were skipped.
+    let relative_epoch = RelativeEpoch::from_epoch(state.current_epoch(), epoch).map_err(|_| {
+        ApiError::NotFound(format!(
+            "No canonical state is available for epoch {}",
+            epoch
+        ))
+    })?;
+    state.build_committee_cache(relative_epoch, &beacon_chain.spec)?;
+
+    f(&state, relative_epoch)
+}
@@ -0,0 +1,120 @@
+use beacon_chain::{
+    AttesterSlashingValidationError, BeaconChainError, DepositValidationError, ExitValidationError,
+    ProposerSlashingValidationError, TransferValidationError,
+};
+use iron::{status::Status, Response};
+use serde_json::json;
+use store::Error as StoreError;
+use types::BeaconStateError;
+
+pub type ApiResult = Result<Response, ApiError>;
+
+/// An error which is returned to the client as a JSON object with an `error` message.
+#[derive(Debug, PartialEq)]
+pub enum ApiError {
+    /// The request was malformed (e.g., an invalid query parameter).
+    BadRequest(String),
+    /// The requested item does not exist.
+    NotFound(String),
+    /// The request body is larger than the server will read.
+    PayloadTooLarge(String),
+    /// The request was valid but could not be served.
+    ServerError(String),
+    /// The server is too busy to serve the request, which may be retried later.
+    ServiceUnavailable(String),
+}
+
+impl ApiError {
+    pub fn status(&self) -> Status {
+        match self {
+            ApiError::BadRequest(_) => Status::BadRequest,
+            ApiError::NotFound(_) => Status::NotFound,
+            ApiError::PayloadTooLarge(_) => Status::PayloadTooLarge,
+            ApiError::ServerError(_) => Status::InternalServerError,
+            ApiError::ServiceUnavailable(_) => Status::ServiceUnavailable,
+        }
+    }
+
+    pub fn message(&self) -> &str {
+        match self {
+            ApiError::BadRequest(message)
+            | ApiError::NotFound(message)
+            | ApiError::PayloadTooLarge(message)
+            | ApiError::ServerError(message)
+            | ApiError::ServiceUnavailable(message) => message,
+        }
+    }
+
+    pub fn into_response(self) -> Response {
+        let body = json!({ "error": self.message() });
+        Response::with((self.status(), body.to_string()))
+    }
+}
+
+impl From<BeaconChainError> for ApiError {
+    fn from(e: BeaconChainError) -> ApiError {
+        ApiError::ServerError(format!("Beacon chain error: {:?}", e))
+    }
+}
+
+impl From<StoreError> for ApiError {
+    fn from(e: StoreError) -> ApiError {
+        ApiError::ServerError(format!("Database error: {:?}", e))
+    }
+}
+
+impl From<BeaconStateError> for ApiError {
+    fn from(e: BeaconStateError) -> ApiError {
+        ApiError::ServerError(format!("Beacon state error: {:?}", e))
+    }
+}
+
+// Operations rejected by the operation pool are reported with the specific `Invalid` reason, so
+// the client can tell why (e.g., `InvalidVoluntaryExit: AlreadyExited(3)`).
+
+impl From<ExitValidationError> for ApiError {
+    fn from(e: ExitValidationError) -> ApiError {
+        let ExitValidationError::Invalid(e) = e;
+        ApiError::BadRequest(format!("InvalidVoluntaryExit: {:?}", e))
+    }
+}
+
+impl From<ProposerSlashingValidationError> for ApiError {
+    fn from(e: ProposerSlashingValidationError) -> ApiError {
+        let ProposerSlashingValidationError::Invalid(e) = e;
+        ApiError::BadRequest(format!("InvalidProposerSlashing: {:?}", e))
+    }
+}
+
+impl From<AttesterSlashingValidationError> for ApiError {
+    fn from(e: AttesterSlashingValidationError) -> ApiError {
+        match e {
+            AttesterSlashingValidationError::Invalid(e) => {
+                ApiError::BadRequest(format!("InvalidAttesterSlashing: {:?}", e))
+            }
+            AttesterSlashingValidationError::BeaconStateError(e) => e.into(),
+        }
+    }
+}
+
+impl From<TransferValidationError> for ApiError {
+    fn from(e: TransferValidationError) -> ApiError {
+        match e {
+            TransferValidationError::Invalid(e) => {
+                ApiError::BadRequest(format!("InvalidTransfer: {:?}", e))
+            }
+            TransferValidationError::BeaconStateError(e) => e.into(),
+        }
+    }
+}
+
+impl From<DepositValidationError> for ApiError {
+    fn from(e: DepositValidationError) -> ApiError {
+        match e {
+            DepositValidationError::Invalid(e) => {
+                ApiError::BadRequest(format!("InvalidDeposit: {:?}", e))
+            }
+            DepositValidationError::BeaconStateError(e) => e.into(),
+        }
+    }
+}
@@ -0,0 +1,264 @@
+use crate::error::{ApiError, ApiResult};
+use crate::helpers::{get_beacon_chain, UrlQuery};
+use crate::key::EventStreamsKey;
+use beacon_chain::{BeaconChainEvent, BeaconChainTypes};
+use crossbeam_channel::{Receiver, RecvTimeoutError};
+use iron::headers::ContentType;
+use iron::mime::Mime;
+use iron::response::WriteBody;
+use iron::{status::Status, Request, Response};
+use persistent::Read;
+use std::io::{self, Write};
+use std::str::FromStr;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Arc;
+use std::time::Duration;
+
+/// If no event is sent for this long, a comment is sent to keep the connection alive (and to
+/// discover whether the client has disconnected).
+const KEEP_ALIVE_INTERVAL: Duration = Duration::from_secs(15);
+
+/// The maximum number of event streams which may be open at once.
+///
+/// Each stream occupies one of the server's worker threads until the client disconnects, so
+/// streams must not be allowed to starve the rest of the API.
+pub const MAX_EVENT_STREAMS: usize = 4;
+
+/// A category of `BeaconChainEvent` which a client may subscribe to.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Topic {
+    Head,
+    Block,
+    Attestation,
+    FinalizedCheckpoint,
+    ChainReorg,
+}
+
+impl Topic {
+    pub fn all() -> Vec<Topic> {
+        vec![
+            Topic::Head,
+            Topic::Block,
+            Topic::Attestation,
+            Topic::FinalizedCheckpoint,
+            Topic::ChainReorg,
+        ]
+    }
+
+    pub fn as_str(self) -> &'static str {
+        match self {
+            Topic::Head => "head",
+            Topic::Block => "block",
+            Topic::Attestation => "attestation",
+            Topic::FinalizedCheckpoint => "finalized_checkpoint",
+            Topic::ChainReorg => "chain_reorg",
+        }
+    }
+
+    /// Returns `true` if `event` belongs to `self`.
+    ///
+    /// A reorg belongs to both `Head` and `ChainReorg`.
+    pub fn matches(self, event: &BeaconChainEvent) -> bool {
+        match (self, event) {
+            (Topic::Head, BeaconChainEvent::HeadChanged { .. }) => true,
+            (Topic::Block, BeaconChainEvent::BlockImported { .. }) => true,
+            (Topic::Attestation, BeaconChainEvent::AttestationImported { .. }) => true,
+            (Topic::FinalizedCheckpoint, BeaconChainEvent::FinalizedCheckpointChanged { .. }) => {
+                true
+            }
+            (Topic::ChainReorg, event) => event.is_reorg(),
+            _ => false,
+        }
+    }
+}
+
+impl FromStr for Topic {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Topic::all()
+            .into_iter()
+            .find(|topic| topic.as_str() == s)
+            .ok_or_else(|| format!("Unknown topic: {}", s))
+    }
+}
+
+/// The number of open event streams, shared between all requests.
+#[derive(Clone, Default)]
+pub struct EventStreams(Arc<AtomicUsize>);
+
+impl EventStreams {
+    /// Reserves a stream, returning `None` if `MAX_EVENT_STREAMS` are already open.
+    ///
+    /// The stream is released when the returned guard is dropped.
+    pub fn try_open(&self) -> Option<EventStreamGuard> {
+        let mut count = self.0.load(Ordering::SeqCst);
+
+        loop {
+            if count >= MAX_EVENT_STREAMS {
+                return None;
+            }
+
+            match self
+                .0
+                .compare_exchange(count, count + 1, Ordering::SeqCst, Ordering::SeqCst)
+            {
+                Ok(_) => return Some(EventStreamGuard(self.0.clone())),
+                Err(previous) => count = previous,
+            }
+        }
+    }
+
+    /// Returns the number of open streams.
+    pub fn count(&self) -> usize {
+        self.0.load(Ordering::SeqCst)
+    }
+}
+
+/// Releases a stream reserved by `EventStreams::try_open` when dropped.
+pub struct EventStreamGuard(Arc<AtomicUsize>);
+
+impl Drop for EventStreamGuard {
+    fn drop(&mut self) {
+        self.0.fetch_sub(1, Ordering::SeqCst);
+    }
+}
+
+/// Returns the topics given by `?topics=a,b,...` (or repeated `topics` parameters), or all topics
+/// if there is no `topics` parameter.
+pub fn parse_topics(query: &UrlQuery) -> Result<Vec<Topic>, ApiError> {
+    if query.get("topics").is_none() {
+        return Ok(Topic::all());
+    }
+
+    let topics = query
+        .all_of("topics")
+        .into_iter()
+        .map(Topic::from_str)
+        .collect::<Result<Vec<_>, _>>()
+        .map_err(ApiError::BadRequest)?;
+
+    if topics.is_empty() {
+        Err(ApiError::BadRequest("No topics given".to_string()))
+    } else {
+        Ok(topics)
+    }
+}
+
+/// Streams `BeaconChainEvent`s to the client as server-sent events, until the client disconnects.
+///
+/// Each event is sent once for every subscribed topic it matches, as:
+///
+/// ```text
+/// event: <topic>
+/// data: <event as JSON>
+/// ```
+struct EventStream {
+    events: Receiver<BeaconChainEvent>,
+    topics: Vec<Topic>,
+    /// Held until the client disconnects.
+    _guard: EventStreamGuard,
+}
+
+impl WriteBody for EventStream {
+    fn write_body(&mut self, res: &mut dyn Write) -> io::Result<()> {
+        loop {
+            match self.events.recv_timeout(KEEP_ALIVE_INTERVAL) {
+                Ok(event) => {
+                    for topic in self.topics.iter().filter(|topic| topic.matches(&event)) {
+                        let data = serde_json::to_string(&event)
+                            .map_err(|e| io::Error::new(io::ErrorKind::Other, e))?;
+                        write!(res, "event: {}\ndata: {}\n\n", topic.as_str(), data)?;
+                    }
+                }
+                Err(RecvTimeoutError::Timeout) => res.write_all(b":\n\n")?,
+                // The beacon chain has been dropped, so there will be no more events.
+                Err(RecvTimeoutError::Disconnected) => return Ok(()),
+            }
+
+            res.flush()?;
+        }
+    }
+}
+
+/// Subscribes to the `BeaconChain` event bus and streams events matching `?topics=a,b,...` (or all
+/// topics, if none are given) as server-sent events.
+///
+/// Note: the stream occupies one of the server's worker threads until the client disconnects, so
+/// at most `MAX_EVENT_STREAMS` may be open at once.
+pub fn handle_events<T: BeaconChainTypes + 'static>(req: &mut Request) -> ApiResult {
+    let beacon_chain = get_beacon_chain::<T>(req)?;
+    let topics = parse_topics(&UrlQuery::from_request(req))?;
+
+    let streams = req
+        .get::<Read<EventStreamsKey>>()
+        .map_err(|e| ApiError::ServerError(format!("Unable to get event streams: {:?}", e)))?;
+    let guard = streams.try_open().ok_or_else(|| {
+        ApiError::ServiceUnavailable(format!(
+            "No more than {} event streams may be open at once",
+            MAX_EVENT_STREAMS
+        ))
+    })?;
+
+    let stream = EventStream {
+        events: beacon_chain.events.subscribe(),
+        topics,
+        _guard: guard,
+    };
+
+    let mut resp = Response::with((Status::Ok, Box::new(stream) as Box<dyn WriteBody>));
+    resp.headers.set(ContentType(
+        Mime::from_str("text/event-stream").expect("should parse static mime type"),
+    ));
+
+    Ok(resp)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn topics(query: &str) -> Result<Vec<Topic>, ApiError> {
+        parse_topics(&UrlQuery::from_query_str(query))
+    }
+
+    #[test]
+    fn all_topics_by_default() {
+        assert_eq!(topics(""), Ok(Topic::all()));
+        assert_eq!(topics("other=head"), Ok(Topic::all()));
+    }
+
+    #[test]
+    fn comma_separated_topics() {
+        let expected = Ok(vec![Topic::Head, Topic::ChainReorg]);
+
+        assert_eq!(topics("topics=head,chain_reorg"), expected);
+        assert_eq!(topics("topics=head%2Cchain_reorg"), expected);
+        assert_eq!(topics("topics=head%2cchain_reorg"), expected);
+        assert_eq!(topics("topics=head&topics=chain_reorg"), expected);
+        assert_eq!(topics("topics=head,,chain_reorg,"), expected);
+    }
+
+    #[test]
+    fn invalid_topics() {
+        assert!(topics("topics=").is_err());
+        assert!(topics("topics=,").is_err());
+        assert!(topics("topics=head,unknown").is_err());
+        assert!(topics("topics=Head").is_err());
+    }
+
+    #[test]
+    fn event_streams_are_limited() {
+        let streams = EventStreams::default();
+
+        let guards: Vec<_> = (0..MAX_EVENT_STREAMS)
+            .map(|_| streams.try_open().expect("should open stream"))
+            .collect();
+        assert_eq!(streams.count(), MAX_EVENT_STREAMS);
+        assert!(streams.try_open().is_none());
+
+        drop(guards);
+        assert_eq!(streams.count(), 0);
+        assert!(streams.try_open().is_some());
+    }
+}
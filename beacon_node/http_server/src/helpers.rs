@@ -0,0 +1,352 @@
+use crate::error::{ApiError, ApiResult};
+use crate::key::{BeaconChainKey, NetworkChannelKey};
+use beacon_chain::{BeaconChain, BeaconChainTypes};
+use eth2_libp2p::{PubsubMessage, TopicBuilder};
+use iron::headers::{Accept, ContentType};
+use iron::mime::{Mime, SubLevel, TopLevel};
+use iron::{status::Status, Handler, IronResult, Request, Response};
+use network::NetworkMessage;
+use persistent::Read;
+use serde::{de::DeserializeOwned, Serialize};
+use ssz::{Decode, Encode};
+use std::io;
+use std::str::FromStr;
+use std::sync::Arc;
+use types::Hash256;
+use url::form_urlencoded;
+
+/// The media type of SSZ-encoded request and response bodies.
+pub const SSZ_CONTENT_TYPE: &str = "application/octet-stream";
+
+/// The maximum number of bytes read from a request body. Larger bodies are rejected with `413`.
+///
+/// This comfortably exceeds the largest operation which may be submitted, an attester slashing.
+pub const MAX_BODY_SIZE: u64 = 1_048_576;
+
+/// Converts a function returning an `ApiResult` into an iron `Handler`, rendering any `ApiError`
+/// as a JSON response with the appropriate status.
+pub fn handler<F>(f: F) -> impl Handler
+where
+    F: Fn(&mut Request) -> ApiResult + Send + Sync + 'static,
+{
+    move |req: &mut Request| -> IronResult<Response> {
+        Ok(f(req).unwrap_or_else(ApiError::into_response))
+    }
+}
+
+/// Returns the `BeaconChain` inserted into every request by the API middleware.
+pub fn get_beacon_chain<T: BeaconChainTypes + 'static>(
+    req: &mut Request,
+) -> Result<Arc<BeaconChain<T>>, ApiError> {
+    req.get::<Read<BeaconChainKey<T>>>()
+        .map(|beacon_chain| (*beacon_chain).clone())
+        .map_err(|e| ApiError::ServerError(format!("Unable to get beacon chain: {:?}", e)))
+}
+
+/// Publishes `message` on the gossipsub `topic` via the network service.
+pub fn publish_to_network(
+    req: &mut Request,
+    topic: &str,
+    message: PubsubMessage,
+) -> Result<(), ApiError> {
+    let network_chan = req
+        .get::<Read<NetworkChannelKey>>()
+        .map_err(|e| ApiError::ServerError(format!("Unable to get network channel: {:?}", e)))?;
+
+    network_chan
+        .send(NetworkMessage::Publish {
+            topics: vec![TopicBuilder::new(topic).build()],
+            message: Box::new(message),
+        })
+        .map_err(|e| ApiError::ServerError(format!("Unable to publish to network: {:?}", e)))
+}
+
+/// The percent-decoded `name=value` pairs in the query string of a request.
+pub struct UrlQuery(Vec<(String, String)>);
+
+impl UrlQuery {
+    /// Parses the query string of `req`.
+    pub fn from_request(req: &Request) -> Self {
+        Self::from_query_str(req.url.query().unwrap_or(""))
+    }
+
+    /// Parses a query string, without the leading `?`.
+    pub fn from_query_str(query: &str) -> Self {
+        UrlQuery(
+            form_urlencoded::parse(query.as_bytes())
+                .into_owned()
+                .collect(),
+        )
+    }
+
+    /// Returns the value of the last pair named `name`, if any.
+    pub fn get(&self, name: &str) -> Option<&str> {
+        self.0
+            .iter()
+            .rev()
+            .find(|(key, _)| key == name)
+            .map(|(_, value)| value.as_str())
+    }
+
+    /// Returns the comma-separated values of every pair named `name`, omitting empty values.
+    ///
+    /// E.g., both `?a=x,y` and `?a=x&a=y` return `["x", "y"]`.
+    pub fn all_of(&self, name: &str) -> Vec<&str> {
+        self.0
+            .iter()
+            .filter(|(key, _)| key == name)
+            .flat_map(|(_, value)| value.split(','))
+            .filter(|value| !value.is_empty())
+            .collect()
+    }
+}
+
+/// Returns the value of the last `name=value` pair in the query string of `req`, if any.
+pub fn query_param(req: &Request, name: &str) -> Option<String> {
+    UrlQuery::from_request(req).get(name).map(String::from)
+}
+
+/// Parses the query parameter `name` with `FromStr`, if it is present.
+pub fn parse_query_param<V: FromStr>(req: &Request, name: &str) -> Result<Option<V>, ApiError> {
+    query_param(req, name)
+        .map(|value| {
+            value
+                .parse()
+                .map_err(|_| ApiError::BadRequest(format!("Invalid {}: {}", name, value)))
+        })
+        .transpose()
+}
+
+/// Parses a `0x`-prefixed (or unprefixed) hex string into bytes.
+pub fn parse_hex(value: &str) -> Result<Vec<u8>, ApiError> {
+    let value = if value.starts_with("0x") {
+        &value[2..]
+    } else {
+        value
+    };
+
+    hex::decode(value).map_err(|e| ApiError::BadRequest(format!("Invalid hex: {:?}", e)))
+}
+
+/// Parses a hex string into a 32-byte root.
+pub fn parse_root(value: &str) -> Result<Hash256, ApiError> {
+    let bytes = parse_hex(value)?;
+
+    if bytes.len() == 32 {
+        Ok(Hash256::from_slice(&bytes))
+    } else {
+        Err(ApiError::BadRequest(format!(
+            "Root must be 32 bytes, not {}",
+            bytes.len()
+        )))
+    }
+}
+
+/// Reads the body of `req` as JSON.
+pub fn read_json_body<V: DeserializeOwned>(req: &mut Request) -> Result<V, ApiError> {
+    decode_json(&read_body_bytes(req)?)
+}
+
+/// Reads the body of `req` as SSZ if its `Content-Type` is SSZ, otherwise as JSON.
+pub fn read_body<V: DeserializeOwned + Decode>(req: &mut Request) -> Result<V, ApiError> {
+    let is_ssz = is_ssz_content_type(req.headers.get::<ContentType>());
+    decode_body(is_ssz, &read_body_bytes(req)?)
+}
+
+/// Reads at most `MAX_BODY_SIZE` bytes of the body of `req`.
+fn read_body_bytes(req: &mut Request) -> Result<Vec<u8>, ApiError> {
+    let mut body = vec![];
+    io::Read::read_to_end(
+        &mut io::Read::take(&mut req.body, MAX_BODY_SIZE + 1),
+        &mut body,
+    )
+    .map_err(|e| ApiError::BadRequest(format!("Unable to read body: {:?}", e)))?;
+
+    if body.len() as u64 > MAX_BODY_SIZE {
+        return Err(ApiError::PayloadTooLarge(format!(
+            "Body exceeds {} bytes",
+            MAX_BODY_SIZE
+        )));
+    }
+
+    Ok(body)
+}
+
+/// Decodes `body` as SSZ if `is_ssz`, otherwise as JSON.
+pub fn decode_body<V: DeserializeOwned + Decode>(is_ssz: bool, body: &[u8]) -> Result<V, ApiError> {
+    if is_ssz {
+        V::from_ssz_bytes(body)
+            .map_err(|e| ApiError::BadRequest(format!("Invalid SSZ body: {:?}", e)))
+    } else {
+        decode_json(body)
+    }
+}
+
+fn decode_json<V: DeserializeOwned>(body: &[u8]) -> Result<V, ApiError> {
+    serde_json::from_slice(body)
+        .map_err(|e| ApiError::BadRequest(format!("Invalid JSON body: {:?}", e)))
+}
+
+/// Returns `true` if the `Accept` header of `req` asks for SSZ.
+pub fn accepts_ssz(req: &Request) -> bool {
+    prefers_ssz(req.headers.get::<Accept>())
+}
+
+/// Returns `true` if `content_type` is the SSZ media type, with any parameters.
+fn is_ssz_content_type(content_type: Option<&ContentType>) -> bool {
+    match content_type {
+        Some(ContentType(mime)) => is_ssz_mime(mime),
+        None => false,
+    }
+}
+
+/// Returns `true` if `accept` lists SSZ as acceptable, and JSON is not preferred to it.
+///
+/// A missing or unparseable `Accept` header selects JSON.
+fn prefers_ssz(accept: Option<&Accept>) -> bool {
+    let accept = match accept {
+        Some(accept) => accept,
+        None => return false,
+    };
+
+    let quality_of = |is_media_type: fn(&Mime) -> bool| {
+        accept
+            .iter()
+            .filter(|item| is_media_type(&item.item))
+            .map(|item| item.quality.0)
+            .max()
+    };
+
+    match (quality_of(is_ssz_mime), quality_of(is_json_mime)) {
+        (Some(ssz), json) => ssz > 0 && json.map_or(true, |json| ssz >= json),
+        (None, _) => false,
+    }
+}
+
+fn is_ssz_mime(mime: &Mime) -> bool {
+    match mime {
+        Mime(TopLevel::Application, SubLevel::OctetStream, _) => true,
+        _ => false,
+    }
+}
+
+fn is_json_mime(mime: &Mime) -> bool {
+    match mime {
+        Mime(TopLevel::Application, SubLevel::Json, _) => true,
+        _ => false,
+    }
+}
+
+/// Returns `item` as SSZ if the request accepts it, otherwise as JSON.
+pub fn negotiated_response<V: Serialize + Encode>(req: &Request, item: &V) -> ApiResult {
+    if accepts_ssz(req) {
+        ssz_response(item)
+    } else {
+        json_response(item)
+    }
+}
+
+/// Returns `item` as a JSON response.
+pub fn json_response<V: Serialize>(item: &V) -> ApiResult {
+    let body = serde_json::to_string(item)
+        .map_err(|e| ApiError::ServerError(format!("Unable to encode JSON: {:?}", e)))?;
+
+    Ok(Response::with((Status::Ok, body)))
+}
+
+/// Returns `item` as an SSZ response.
+pub fn ssz_response<V: Encode>(item: &V) -> ApiResult {
+    let mut resp = Response::with((Status::Ok, item.as_ssz_bytes()));
+    resp.headers.set(ContentType(
+        Mime::from_str(SSZ_CONTENT_TYPE).expect("should parse static mime type"),
+    ));
+
+    Ok(resp)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use types::{Epoch, Fork};
+
+    fn fork() -> Fork {
+        Fork {
+            previous_version: [1, 2, 3, 4],
+            current_version: [5, 6, 7, 8],
+            epoch: Epoch::new(9),
+        }
+    }
+
+    #[test]
+    fn decode_json_body() {
+        let body = serde_json::to_vec(&fork()).unwrap();
+
+        assert_eq!(decode_body::<Fork>(false, &body), Ok(fork()));
+        assert!(decode_body::<Fork>(true, &body).is_err());
+    }
+
+    #[test]
+    fn url_query_is_percent_decoded() {
+        let query = UrlQuery::from_query_str("format=dot&root=0x%2Aab&format=json");
+
+        assert_eq!(query.get("format"), Some("json"));
+        assert_eq!(query.get("root"), Some("0x*ab"));
+        assert_eq!(query.get("slot"), None);
+    }
+
+    #[test]
+    fn url_query_splits_values() {
+        let query = UrlQuery::from_query_str("topics=a%2Cb&topics=c,,d&other=e");
+
+        assert_eq!(query.all_of("topics"), vec!["a", "b", "c", "d"]);
+        assert!(query.all_of("missing").is_empty());
+    }
+
+    fn accept(value: &str) -> Accept {
+        iron::headers::Header::parse_header(&[value.as_bytes().to_vec()]).unwrap()
+    }
+
+    fn content_type(value: &str) -> ContentType {
+        iron::headers::Header::parse_header(&[value.as_bytes().to_vec()]).unwrap()
+    }
+
+    #[test]
+    fn ssz_content_type_is_parsed() {
+        assert!(is_ssz_content_type(Some(&content_type(
+            "application/octet-stream"
+        ))));
+        assert!(is_ssz_content_type(Some(&content_type(
+            "application/octet-stream; charset=binary"
+        ))));
+        assert!(!is_ssz_content_type(Some(&content_type(
+            "application/octet-stream-extra"
+        ))));
+        assert!(!is_ssz_content_type(Some(&content_type(
+            "application/json"
+        ))));
+        assert!(!is_ssz_content_type(None));
+    }
+
+    #[test]
+    fn ssz_is_accepted_by_quality() {
+        assert!(prefers_ssz(Some(&accept("application/octet-stream"))));
+        assert!(prefers_ssz(Some(&accept(
+            "application/json;q=0.5, application/octet-stream"
+        ))));
+        assert!(!prefers_ssz(Some(&accept("application/octet-stream;q=0"))));
+        assert!(!prefers_ssz(Some(&accept(
+            "application/json, application/octet-stream;q=0.5"
+        ))));
+        assert!(!prefers_ssz(Some(&accept("*/*"))));
+        assert!(!prefers_ssz(None));
+    }
+
+    #[test]
+    fn decode_ssz_body() {
+        let body = fork().as_ssz_bytes();
+
+        assert_eq!(decode_body::<Fork>(true, &body), Ok(fork()));
+        assert!(decode_body::<Fork>(false, &body).is_err());
+        assert!(decode_body::<Fork>(true, &body[1..]).is_err());
+    }
+}
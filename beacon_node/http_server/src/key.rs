@@ -1,6 +1,9 @@
+use crate::events::EventStreams;
 use crate::metrics::LocalMetrics;
 use beacon_chain::{BeaconChain, BeaconChainTypes};
+use eth2_libp2p::PeerManager;
 use iron::typemap::Key;
+use network::NetworkMessage;
 use prometheus::Registry;
 use std::marker::PhantomData;
 use std::path::PathBuf;
@@ -14,6 +17,18 @@ impl<T: BeaconChainTypes + 'static> Key for BeaconChainKey<T> {
     type Value = Arc<BeaconChain<T>>;
 }
 
+pub struct NetworkChannelKey;
+
+impl Key for NetworkChannelKey {
+    type Value = crossbeam_channel::Sender<NetworkMessage>;
+}
+
+pub struct PeerManagerKey;
+
+impl Key for PeerManagerKey {
+    type Value = PeerManager;
+}
+
 pub struct MetricsRegistryKey;
 
 impl Key for MetricsRegistryKey {
@@ -31,3 +46,9 @@ pub struct DBPathKey;
 impl Key for DBPathKey {
     type Value = PathBuf;
 }
+
+pub struct EventStreamsKey;
+
+impl Key for EventStreamsKey {
+    type Value = EventStreams;
+}
@@ -1,9 +1,17 @@
 mod api;
+mod beacon;
+mod error;
+mod events;
+mod helpers;
 mod key;
 mod metrics;
+mod operations;
+mod peers;
+mod validator;
 
 use beacon_chain::{BeaconChain, BeaconChainTypes};
 use clap::ArgMatches;
+use eth2_libp2p::PeerManager;
 use futures::Future;
 use iron::prelude::*;
 use network::NetworkMessage;
@@ -15,6 +23,9 @@ use std::path::PathBuf;
 use std::sync::Arc;
 use tokio::runtime::TaskExecutor;
 
+pub use events::MAX_EVENT_STREAMS;
+pub use helpers::MAX_BODY_SIZE;
+
 #[derive(PartialEq, Clone, Debug, Serialize, Deserialize)]
 pub struct HttpServerConfig {
     pub enabled: bool,
@@ -53,6 +64,8 @@ impl HttpServerConfig {
 /// Build the `iron` HTTP server, defining the core routes.
 pub fn create_iron_http_server<T: BeaconChainTypes + 'static>(
     beacon_chain: Arc<BeaconChain<T>>,
+    network_chan: crossbeam_channel::Sender<NetworkMessage>,
+    peer_manager: PeerManager,
     db_path: PathBuf,
     metrics_registry: Registry,
 ) -> Iron<Router> {
@@ -61,12 +74,21 @@ pub fn create_iron_http_server<T: BeaconChainTypes + 'static>(
     // A `GET` request to `/metrics` is handled by the `metrics` module.
     router.get(
         "/metrics",
-        metrics::build_handler(beacon_chain.clone(), db_path, metrics_registry),
+        metrics::build_handler(
+            beacon_chain.clone(),
+            peer_manager.clone(),
+            db_path,
+            metrics_registry,
+        ),
         "metrics",
     );
 
     // Any request to all other endpoints is handled by the `api` module.
-    router.any("/*", api::build_handler(beacon_chain.clone()), "api");
+    router.any(
+        "/*",
+        api::build_handler(beacon_chain.clone(), network_chan, peer_manager),
+        "api",
+    );
 
     Iron::new(router)
 }
@@ -75,7 +97,8 @@ pub fn create_iron_http_server<T: BeaconChainTypes + 'static>(
 pub fn start_service<T: BeaconChainTypes + 'static>(
     config: &HttpServerConfig,
     executor: &TaskExecutor,
-    _network_chan: crossbeam_channel::Sender<NetworkMessage>,
+    network_chan: crossbeam_channel::Sender<NetworkMessage>,
+    peer_manager: PeerManager,
     beacon_chain: Arc<BeaconChain<T>>,
     db_path: PathBuf,
     metrics_registry: Registry,
@@ -89,7 +112,13 @@ pub fn start_service<T: BeaconChainTypes + 'static>(
     let (shutdown_trigger, wait_for_shutdown) = exit_future::signal();
 
     // Create an `iron` http, without starting it yet.
-    let iron = create_iron_http_server(beacon_chain, db_path, metrics_registry);
+    let iron = create_iron_http_server(
+        beacon_chain,
+        network_chan,
+        peer_manager,
+        db_path,
+        metrics_registry,
+    );
 
     // Create a HTTP server future.
     //
@@ -1,8 +1,9 @@
 use crate::{
-    key::{BeaconChainKey, DBPathKey, LocalMetricsKey, MetricsRegistryKey},
+    key::{BeaconChainKey, DBPathKey, LocalMetricsKey, MetricsRegistryKey, PeerManagerKey},
     map_persistent_err_to_500,
 };
 use beacon_chain::{BeaconChain, BeaconChainTypes};
+use eth2_libp2p::PeerManager;
 use iron::prelude::*;
 use iron::{status::Status, Handler, IronResult, Request, Response};
 use persistent::Read;
@@ -17,6 +18,7 @@ mod local_metrics;
 /// Yields a handler for the metrics endpoint.
 pub fn build_handler<T: BeaconChainTypes + 'static>(
     beacon_chain: Arc<BeaconChain<T>>,
+    peer_manager: PeerManager,
     db_path: PathBuf,
     metrics_registry: Registry,
 ) -> impl Handler {
@@ -26,6 +28,7 @@ pub fn build_handler<T: BeaconChainTypes + 'static>(
     local_metrics.register(&metrics_registry).unwrap();
 
     chain.link(Read::<BeaconChainKey<T>>::both(beacon_chain));
+    chain.link(Read::<PeerManagerKey>::both(peer_manager));
     chain.link(Read::<MetricsRegistryKey>::both(metrics_registry));
     chain.link(Read::<LocalMetricsKey>::both(local_metrics));
     chain.link(Read::<DBPathKey>::both(db_path));
@@ -41,6 +44,10 @@ fn handle_metrics<T: BeaconChainTypes + 'static>(req: &mut Request) -> IronResul
         .get::<Read<BeaconChainKey<T>>>()
         .map_err(map_persistent_err_to_500)?;
 
+    let peer_manager = req
+        .get::<Read<PeerManagerKey>>()
+        .map_err(map_persistent_err_to_500)?;
+
     let r = req
         .get::<Read<MetricsRegistryKey>>()
         .map_err(map_persistent_err_to_500)?;
@@ -54,7 +61,7 @@ fn handle_metrics<T: BeaconChainTypes + 'static>(req: &mut Request) -> IronResul
         .map_err(map_persistent_err_to_500)?;
 
     // Update metrics that are calculated on each scrape.
-    local_metrics.update(&beacon_chain, &db_path);
+    local_metrics.update(&beacon_chain, &peer_manager, &db_path);
 
     let mut buffer = vec![];
     let encoder = TextEncoder::new();
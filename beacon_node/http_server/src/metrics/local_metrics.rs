@@ -1,4 +1,5 @@
 use beacon_chain::{BeaconChain, BeaconChainTypes};
+use eth2_libp2p::PeerManager;
 use prometheus::{IntGauge, Opts, Registry};
 use slot_clock::SlotClock;
 use std::fs::File;
@@ -18,6 +19,7 @@ pub struct LocalMetrics {
     finalized_epoch: IntGauge,
     validator_balances_sum: IntGauge,
     database_size: IntGauge,
+    banned_peer_count: IntGauge,
 }
 
 impl LocalMetrics {
@@ -56,6 +58,10 @@ impl LocalMetrics {
                 let opts = Opts::new("database_size", "size_of_on_disk_db_in_mb");
                 IntGauge::with_opts(opts)?
             },
+            banned_peer_count: {
+                let opts = Opts::new("banned_peer_count", "number_of_currently_banned_peers");
+                IntGauge::with_opts(opts)?
+            },
         })
     }
 
@@ -69,12 +75,18 @@ impl LocalMetrics {
         registry.register(Box::new(self.justified_epoch.clone()))?;
         registry.register(Box::new(self.validator_balances_sum.clone()))?;
         registry.register(Box::new(self.database_size.clone()))?;
+        registry.register(Box::new(self.banned_peer_count.clone()))?;
 
         Ok(())
     }
 
     /// Update the metrics in `self` to the latest values.
-    pub fn update<T: BeaconChainTypes>(&self, beacon_chain: &BeaconChain<T>, db_path: &PathBuf) {
+    pub fn update<T: BeaconChainTypes>(
+        &self,
+        beacon_chain: &BeaconChain<T>,
+        peer_manager: &PeerManager,
+        db_path: &PathBuf,
+    ) {
         let state = &beacon_chain.head().beacon_state;
 
         let present_slot = beacon_chain
@@ -102,5 +114,8 @@ impl LocalMetrics {
             .and_then(|m| Ok(m.len()))
             .unwrap_or(0);
         self.database_size.set(db_size as i64);
+
+        self.banned_peer_count
+            .set(peer_manager.banned_count() as i64);
     }
 }
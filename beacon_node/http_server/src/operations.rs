@@ -0,0 +1,222 @@
+//! Handlers for `/operations/*` endpoints, which submit operations to the `OperationPool` for
+//! inclusion in a future block, and list the operations it holds.
+//!
+//! Operations are accepted as SSZ if the request has an `application/octet-stream`
+//! `Content-Type` header, and as JSON otherwise. An operation which fails validation is rejected
+//! with a `400 Bad Request` that names the reason (e.g., `InvalidVoluntaryExit: AlreadyExited(3)`).
+//!
+//! Lists of operations are returned as SSZ if the request has an `application/octet-stream`
+//! `Accept` header, and as JSON otherwise.
+use crate::error::ApiResult;
+use crate::helpers::{
+    get_beacon_chain, json_response, negotiated_response, publish_to_network, read_body,
+};
+use beacon_chain::BeaconChainTypes;
+use eth2_libp2p::{
+    PubsubMessage, ATTESTER_SLASHING_TOPIC, PROPOSER_SLASHING_TOPIC, TRANSFER_TOPIC,
+    VOLUNTARY_EXIT_TOPIC,
+};
+use iron::Request;
+use operation_pool::DepositInsertStatus;
+use serde_derive::Serialize;
+use serde_json::json;
+use types::{Attestation, AttesterSlashing, Deposit, ProposerSlashing, Transfer, VoluntaryExit};
+
+/// `POST /operations/voluntary_exit` with a signed `VoluntaryExit` as the body.
+///
+/// A valid exit is added to the operation pool and published to the network.
+pub fn post_voluntary_exit<T: BeaconChainTypes + 'static>(req: &mut Request) -> ApiResult {
+    let beacon_chain = get_beacon_chain::<T>(req)?;
+    let exit: VoluntaryExit = read_body(req)?;
+
+    beacon_chain.process_voluntary_exit(exit.clone())?;
+
+    publish_to_network(
+        req,
+        VOLUNTARY_EXIT_TOPIC,
+        PubsubMessage::VoluntaryExit(exit),
+    )?;
+
+    json_response(&json!({}))
+}
+
+/// `POST /operations/proposer_slashing` with a `ProposerSlashing` as the body.
+///
+/// A valid slashing is added to the operation pool and published to the network.
+pub fn post_proposer_slashing<T: BeaconChainTypes + 'static>(req: &mut Request) -> ApiResult {
+    let beacon_chain = get_beacon_chain::<T>(req)?;
+    let slashing: ProposerSlashing = read_body(req)?;
+
+    beacon_chain.process_proposer_slashing(slashing.clone())?;
+
+    publish_to_network(
+        req,
+        PROPOSER_SLASHING_TOPIC,
+        PubsubMessage::ProposerSlashing(slashing),
+    )?;
+
+    json_response(&json!({}))
+}
+
+/// `POST /operations/attester_slashing` with an `AttesterSlashing` as the body.
+///
+/// A valid slashing is added to the operation pool and published to the network.
+pub fn post_attester_slashing<T: BeaconChainTypes + 'static>(req: &mut Request) -> ApiResult {
+    let beacon_chain = get_beacon_chain::<T>(req)?;
+    let slashing: AttesterSlashing = read_body(req)?;
+
+    beacon_chain.process_attester_slashing(slashing.clone())?;
+
+    publish_to_network(
+        req,
+        ATTESTER_SLASHING_TOPIC,
+        PubsubMessage::AttesterSlashing(slashing),
+    )?;
+
+    json_response(&json!({}))
+}
+
+/// `POST /operations/transfer` with a signed `Transfer` as the body.
+///
+/// A valid transfer is added to the operation pool and published to the network.
+pub fn post_transfer<T: BeaconChainTypes + 'static>(req: &mut Request) -> ApiResult {
+    let beacon_chain = get_beacon_chain::<T>(req)?;
+    let transfer: Transfer = read_body(req)?;
+
+    beacon_chain.process_transfer(transfer.clone())?;
+
+    publish_to_network(req, TRANSFER_TOPIC, PubsubMessage::Transfer(transfer))?;
+
+    json_response(&json!({}))
+}
+
+/// `POST /operations/deposit` with a `Deposit` as the body.
+///
+/// Returns `{"status": S}`, where `S` is `fresh`, `duplicate` or `replaced` depending on whether
+/// the pool already held a deposit with the same index. The deposit is rejected if its index has
+/// already been processed or if its Merkle proof does not verify against the head state's Eth1
+/// deposit root; a verified deposit replaces any different deposit pooled at its index.
+///
+/// Unlike the other operations, deposits are not published to the network: every node reads them
+/// from the deposit contract on the Eth1 chain, so there is no gossip topic for them.
+pub fn post_deposit<T: BeaconChainTypes + 'static>(req: &mut Request) -> ApiResult {
+    let beacon_chain = get_beacon_chain::<T>(req)?;
+    let deposit: Deposit = read_body(req)?;
+
+    let status = match beacon_chain.process_deposit(deposit)? {
+        DepositInsertStatus::Fresh => "fresh",
+        DepositInsertStatus::Duplicate => "duplicate",
+        DepositInsertStatus::Replaced(_) => "replaced",
+    };
+
+    json_response(&json!({ "status": status }))
+}
+
+#[derive(Serialize)]
+struct OperationPoolCounts {
+    attestations: usize,
+    deposits: usize,
+    proposer_slashings: usize,
+    attester_slashings: usize,
+    voluntary_exits: usize,
+    transfers: usize,
+}
+
+/// `GET /operations/counts`
+///
+/// Returns the number of each type of operation in the pool.
+pub fn get_counts<T: BeaconChainTypes + 'static>(req: &mut Request) -> ApiResult {
+    let beacon_chain = get_beacon_chain::<T>(req)?;
+    let op_pool = &beacon_chain.op_pool;
+
+    json_response(&OperationPoolCounts {
+        attestations: op_pool.num_attestations(),
+        deposits: op_pool.num_deposits(),
+        proposer_slashings: op_pool.num_proposer_slashings(),
+        attester_slashings: op_pool.num_attester_slashings(),
+        voluntary_exits: op_pool.num_voluntary_exits(),
+        transfers: op_pool.num_transfers(),
+    })
+}
+
+#[derive(Serialize)]
+struct PendingAttestation {
+    /// The number of validators which contributed to the aggregate.
+    aggregation_bits: usize,
+    attestation: Attestation,
+}
+
+/// Attestations which share an `AttestationId` (i.e., the same data and fork).
+#[derive(Serialize)]
+struct AttestationGroup {
+    id: String,
+    attestations: Vec<PendingAttestation>,
+}
+
+/// `GET /operations/attestations`
+///
+/// Returns the attestations in the pool, grouped by `AttestationId` and ordered by id.
+pub fn get_attestations<T: BeaconChainTypes + 'static>(req: &mut Request) -> ApiResult {
+    let beacon_chain = get_beacon_chain::<T>(req)?;
+
+    let groups: Vec<_> = beacon_chain
+        .op_pool
+        .get_all_attestations()
+        .into_iter()
+        .map(|(id, attestations)| AttestationGroup {
+            id: format!("0x{}", hex::encode(id.as_bytes())),
+            attestations: attestations
+                .into_iter()
+                .map(|attestation| PendingAttestation {
+                    aggregation_bits: attestation.aggregation_bitfield.num_set_bits(),
+                    attestation,
+                })
+                .collect(),
+        })
+        .collect();
+
+    json_response(&groups)
+}
+
+/// `GET /operations/deposits`
+///
+/// Returns the deposits in the pool, ordered by deposit index.
+pub fn get_deposits<T: BeaconChainTypes + 'static>(req: &mut Request) -> ApiResult {
+    let deposits = get_beacon_chain::<T>(req)?.op_pool.get_all_deposits();
+
+    negotiated_response(req, &deposits)
+}
+
+/// `GET /operations/proposer_slashings`
+pub fn get_proposer_slashings<T: BeaconChainTypes + 'static>(req: &mut Request) -> ApiResult {
+    let slashings = get_beacon_chain::<T>(req)?
+        .op_pool
+        .get_all_proposer_slashings();
+
+    negotiated_response(req, &slashings)
+}
+
+/// `GET /operations/attester_slashings`
+pub fn get_attester_slashings<T: BeaconChainTypes + 'static>(req: &mut Request) -> ApiResult {
+    let attester_slashings = get_beacon_chain::<T>(req)?
+        .op_pool
+        .get_all_attester_slashings();
+
+    negotiated_response(req, &attester_slashings)
+}
+
+/// `GET /operations/voluntary_exits`
+pub fn get_voluntary_exits<T: BeaconChainTypes + 'static>(req: &mut Request) -> ApiResult {
+    let exits = get_beacon_chain::<T>(req)?
+        .op_pool
+        .get_all_voluntary_exits();
+
+    negotiated_response(req, &exits)
+}
+
+/// `GET /operations/transfers`
+pub fn get_transfers<T: BeaconChainTypes + 'static>(req: &mut Request) -> ApiResult {
+    let transfers = get_beacon_chain::<T>(req)?.op_pool.get_all_transfers();
+
+    negotiated_response(req, &transfers)
+}
@@ -0,0 +1,52 @@
+//! Handlers for `/network/*` endpoints, which describe the peers known to the network service.
+use crate::error::{ApiError, ApiResult};
+use crate::helpers::json_response;
+use crate::key::PeerManagerKey;
+use iron::prelude::*;
+use persistent::Read;
+use serde_derive::Serialize;
+use std::time::Instant;
+
+#[derive(Serialize)]
+struct PeerInfo {
+    /// The base58 encoding of the peer's ID.
+    peer_id: String,
+    score: i64,
+    banned: bool,
+    /// The number of seconds until the peer's ban is lifted, if it is banned.
+    ban_remaining_secs: Option<u64>,
+}
+
+/// `GET /network/peers`
+///
+/// Returns the score of each peer which has been reported by sync or gossip, lowest score first.
+pub fn get_peers(req: &mut Request) -> ApiResult {
+    let peer_manager = req
+        .get::<Read<PeerManagerKey>>()
+        .map_err(|e| ApiError::ServerError(format!("Unable to get peer manager: {:?}", e)))?;
+
+    let now = Instant::now();
+    let mut peers: Vec<PeerInfo> = peer_manager
+        .scores()
+        .into_iter()
+        .map(|(peer_id, peer)| PeerInfo {
+            peer_id: peer_id.to_base58(),
+            score: peer.score,
+            banned: peer.is_banned(),
+            ban_remaining_secs: peer.banned_until.map(|until| {
+                if until > now {
+                    (until - now).as_secs()
+                } else {
+                    0
+                }
+            }),
+        })
+        .collect();
+    peers.sort_by(|a, b| {
+        a.score
+            .cmp(&b.score)
+            .then_with(|| a.peer_id.cmp(&b.peer_id))
+    });
+
+    json_response(&peers)
+}
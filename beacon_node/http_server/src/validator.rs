@@ -0,0 +1,194 @@
+//! Handlers for `/validator/*` endpoints, which allow a validator client to perform its duties
+//! over HTTP, as an alternative to the gRPC `ValidatorService`, `BeaconBlockService` and
+//! `AttestationService`.
+//!
+//! Blocks, attestations and attestation data are sent and received as SSZ if the request has an
+//! `application/octet-stream` `Accept` or `Content-Type` header, and as JSON otherwise.
+use crate::error::{ApiError, ApiResult};
+use crate::helpers::{
+    get_beacon_chain, json_response, negotiated_response, parse_hex, parse_query_param,
+    publish_to_network, read_body, read_json_body,
+};
+use beacon_chain::{BeaconChainTypes, BlockProcessingOutcome};
+use eth2_libp2p::{PubsubMessage, BEACON_PUBSUB_TOPIC, SHARD_TOPIC_PREFIX};
+use iron::Request;
+use serde_derive::{Deserialize, Serialize};
+use serde_json::json;
+use ssz::Decode;
+use types::{
+    Attestation, AttestationDuty, BeaconBlock, Epoch, EthSpec, Hash256, PublicKey, RelativeEpoch,
+    Signature, Slot,
+};
+
+#[derive(Deserialize)]
+struct DutiesRequest {
+    epoch: Epoch,
+    pubkeys: Vec<PublicKey>,
+}
+
+#[derive(Serialize)]
+struct ValidatorDuty {
+    pubkey: PublicKey,
+    /// `None` if the validator is not in the registry.
+    validator_index: Option<usize>,
+    /// The slot at which the validator must propose, if any.
+    block_production_slot: Option<Slot>,
+    /// `None` if the validator is not active in the epoch.
+    attestation_duty: Option<AttestationDuty>,
+}
+
+/// `POST /validator/duties` with a body of `{"epoch": N, "pubkeys": ["0x..", ..]}`
+///
+/// Returns the duties of each validator in the epoch, in the order they were requested. The
+/// public keys are sent in the body, rather than the query, to avoid hitting URL length limits
+/// when a client manages many validators.
+pub fn post_duties<T: BeaconChainTypes + 'static>(req: &mut Request) -> ApiResult {
+    let beacon_chain = get_beacon_chain::<T>(req)?;
+    let request: DutiesRequest = read_json_body(req)?;
+    let state = beacon_chain.current_state();
+
+    let relative_epoch = RelativeEpoch::from_epoch(state.current_epoch(), request.epoch)
+        .map_err(|e| ApiError::BadRequest(format!("Invalid epoch: {:?}", e)))?;
+
+    let proposers = request
+        .epoch
+        .slot_iter(T::EthSpec::slots_per_epoch())
+        .map(|slot| {
+            state
+                .get_beacon_proposer_index(slot, relative_epoch, &beacon_chain.spec)
+                .map(|index| (slot, index))
+        })
+        .collect::<Result<Vec<_>, _>>()?;
+
+    let duties = request
+        .pubkeys
+        .into_iter()
+        .map(|pubkey| {
+            let validator_index = state.get_validator_index(&pubkey)?;
+
+            let (block_production_slot, attestation_duty) = match validator_index {
+                Some(index) => (
+                    proposers
+                        .iter()
+                        .find(|(_, proposer)| *proposer == index)
+                        .map(|(slot, _)| *slot),
+                    state.get_attestation_duties(index, relative_epoch)?,
+                ),
+                None => (None, None),
+            };
+
+            Ok(ValidatorDuty {
+                pubkey,
+                validator_index,
+                block_production_slot,
+                attestation_duty,
+            })
+        })
+        .collect::<Result<Vec<_>, ApiError>>()?;
+
+    json_response(&duties)
+}
+
+/// `GET /validator/block?slot=N&randao_reveal=0x..`
+///
+/// Produces an unsigned block at the present slot, which must be `slot`.
+pub fn get_new_block<T: BeaconChainTypes + 'static>(req: &mut Request) -> ApiResult {
+    let beacon_chain = get_beacon_chain::<T>(req)?;
+
+    let slot = parse_query_param::<u64>(req, "slot")?
+        .map(Slot::new)
+        .ok_or_else(|| ApiError::BadRequest("A slot must be given".to_string()))?;
+    let randao_reveal = parse_query_param::<String>(req, "randao_reveal")?
+        .ok_or_else(|| ApiError::BadRequest("A randao_reveal must be given".to_string()))?;
+    let randao_reveal = Signature::from_ssz_bytes(&parse_hex(&randao_reveal)?)
+        .map_err(|_| ApiError::BadRequest("Invalid randao reveal signature".to_string()))?;
+
+    let present_slot = beacon_chain.present_slot();
+    if slot != present_slot {
+        return Err(ApiError::BadRequest(format!(
+            "Can only produce a block at the present slot {}, not {}",
+            present_slot, slot
+        )));
+    }
+
+    let (block, _state) = beacon_chain
+        .produce_block(randao_reveal)
+        .map_err(|e| ApiError::ServerError(format!("Could not produce a block: {:?}", e)))?;
+
+    negotiated_response(req, &block)
+}
+
+#[derive(Serialize)]
+struct PublishBlockResponse {
+    block_root: Hash256,
+}
+
+/// `POST /validator/block` with a signed `BeaconBlock` as the body.
+///
+/// A valid block is imported and published to the network. An invalid block is rejected with a
+/// `400 Bad Request`.
+pub fn post_block<T: BeaconChainTypes + 'static>(req: &mut Request) -> ApiResult {
+    let beacon_chain = get_beacon_chain::<T>(req)?;
+    let block: BeaconBlock = read_body(req)?;
+
+    let outcome = beacon_chain
+        .process_block(block.clone())
+        .map_err(|e| ApiError::ServerError(format!("Unable to process block: {:?}", e)))?;
+
+    match outcome {
+        BlockProcessingOutcome::Processed { block_root } => {
+            publish_to_network(req, BEACON_PUBSUB_TOPIC, PubsubMessage::Block(block))?;
+
+            json_response(&PublishBlockResponse { block_root })
+        }
+        outcome => Err(ApiError::BadRequest(format!("InvalidBlock: {:?}", outcome))),
+    }
+}
+
+/// `GET /validator/attestation_data?slot=N&shard=S`
+///
+/// Produces `AttestationData` for the shard at the present slot, which must be `slot`.
+pub fn get_attestation_data<T: BeaconChainTypes + 'static>(req: &mut Request) -> ApiResult {
+    let beacon_chain = get_beacon_chain::<T>(req)?;
+
+    let slot = parse_query_param::<u64>(req, "slot")?
+        .map(Slot::new)
+        .ok_or_else(|| ApiError::BadRequest("A slot must be given".to_string()))?;
+    let shard = parse_query_param::<u64>(req, "shard")?
+        .ok_or_else(|| ApiError::BadRequest("A shard must be given".to_string()))?;
+
+    let state_slot = beacon_chain.present_slot();
+    if slot != state_slot {
+        return Err(ApiError::BadRequest(format!(
+            "Can only produce attestation data at the present slot {}, not {}",
+            state_slot, slot
+        )));
+    }
+
+    let attestation_data = beacon_chain.produce_attestation_data(shard).map_err(|e| {
+        ApiError::ServerError(format!("Could not produce attestation data: {:?}", e))
+    })?;
+
+    negotiated_response(req, &attestation_data)
+}
+
+/// `POST /validator/attestation` with a signed `Attestation` as the body.
+///
+/// A valid attestation is added to the operation pool and published to the network. An invalid
+/// attestation is rejected with a `400 Bad Request`.
+pub fn post_attestation<T: BeaconChainTypes + 'static>(req: &mut Request) -> ApiResult {
+    let beacon_chain = get_beacon_chain::<T>(req)?;
+    let attestation: Attestation = read_body(req)?;
+
+    beacon_chain
+        .process_attestation(attestation.clone())
+        .map_err(|e| ApiError::BadRequest(format!("InvalidAttestation: {:?}", e)))?;
+
+    publish_to_network(
+        req,
+        SHARD_TOPIC_PREFIX,
+        PubsubMessage::Attestation(attestation),
+    )?;
+
+    json_response(&json!({}))
+}
@@ -0,0 +1,434 @@
+#![cfg(not(debug_assertions))]
+
+use beacon_chain::test_utils::{AttestationStrategy, BeaconChainHarness, BlockStrategy};
+use eth2_libp2p::{PeerAction, PeerId, PeerManager};
+use http_server::{create_iron_http_server, MAX_EVENT_STREAMS};
+use iron::{response::WriteBody, status::Status, Headers, Response};
+use iron_test::{request, response};
+use lmd_ghost::ThreadSafeReducedTree;
+use prometheus::Registry;
+use router::Router;
+use serde_json::Value;
+use ssz::Decode;
+use std::collections::HashSet;
+use std::io::{self, Write};
+use std::path::PathBuf;
+use store::MemoryStore;
+use types::{BeaconBlock, BeaconState, EthSpec, Hash256, MinimalEthSpec, Slot, Validator};
+
+type TestEthSpec = MinimalEthSpec;
+type TestHarness = BeaconChainHarness<ThreadSafeReducedTree<MemoryStore, TestEthSpec>, TestEthSpec>;
+
+const VALIDATOR_COUNT: usize = 24;
+
+/// A `BeaconChainHarness` with the HTTP API handler attached to its chain.
+struct ApiTester {
+    harness: TestHarness,
+    peer_manager: PeerManager,
+    router: Router,
+}
+
+impl ApiTester {
+    /// Builds a chain of `num_blocks` blocks with full participation, skipping `skipped_slot`.
+    fn new(num_blocks: usize, skipped_slot: Slot) -> Self {
+        let harness = TestHarness::new(VALIDATOR_COUNT);
+
+        // Move past the zero slot.
+        harness.advance_slot();
+
+        let before_skip = (skipped_slot.as_u64() - 1) as usize;
+
+        harness.extend_chain(
+            before_skip,
+            BlockStrategy::OnCanonicalHead,
+            AttestationStrategy::AllValidators,
+        );
+        harness.advance_slot();
+        harness.extend_chain(
+            num_blocks - before_skip,
+            BlockStrategy::OnCanonicalHead,
+            AttestationStrategy::AllValidators,
+        );
+
+        let (network_chan, _) = crossbeam_channel::unbounded();
+        let peer_manager = PeerManager::new();
+        let router = create_iron_http_server(
+            harness.chain.clone(),
+            network_chan,
+            peer_manager.clone(),
+            PathBuf::from("/tmp"),
+            Registry::new(),
+        )
+        .handler;
+
+        Self {
+            harness,
+            peer_manager,
+            router,
+        }
+    }
+
+    fn get_with_headers(&self, path: &str, headers: Headers) -> (Status, Vec<u8>) {
+        let url = format!("http://localhost:5052{}", path);
+        let resp = request::get(&url, headers, &self.router).expect("request should not error");
+        let status = resp.status.expect("response should have a status");
+
+        (status, response::extract_body_to_bytes(resp))
+    }
+
+    fn get_ssz(&self, path: &str) -> (Status, Vec<u8>) {
+        let mut headers = Headers::new();
+        headers.set_raw("Accept", vec![b"application/octet-stream".to_vec()]);
+        self.get_with_headers(path, headers)
+    }
+
+    fn get(&self, path: &str) -> (Status, Value) {
+        let (status, body) = self.get_with_headers(path, Headers::new());
+        let json = serde_json::from_slice(&body).expect("response should be JSON");
+
+        (status, json)
+    }
+
+    /// Returns the response without reading the body, for streaming endpoints.
+    fn get_response(&self, path: &str) -> Response {
+        let url = format!("http://localhost:5052{}", path);
+        request::get(&url, Headers::new(), &self.router).expect("request should not error")
+    }
+
+    fn get_ok(&self, path: &str) -> Value {
+        let (status, json) = self.get(path);
+        assert_eq!(status, Status::Ok, "{} failed with {}", path, json);
+        json
+    }
+}
+
+fn root_string(root: Hash256) -> String {
+    format!("{:?}", root)
+}
+
+/// Collects a server-sent event stream, hanging up once a `head` event has been written.
+#[derive(Default)]
+struct EventCollector(Vec<u8>);
+
+impl EventCollector {
+    fn as_str(&self) -> &str {
+        std::str::from_utf8(&self.0).expect("stream should be UTF-8")
+    }
+}
+
+impl Write for EventCollector {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        self.0.extend_from_slice(buf);
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        if self.as_str().contains("event: head\n") {
+            Err(io::Error::new(io::ErrorKind::BrokenPipe, "client hung up"))
+        } else {
+            Ok(())
+        }
+    }
+}
+
+#[test]
+fn head_and_finality() {
+    let num_blocks = TestEthSpec::slots_per_epoch() as usize * 5;
+    let tester = ApiTester::new(num_blocks, Slot::new(3));
+    let head = tester.harness.chain.head();
+    let state = &head.beacon_state;
+
+    let json = tester.get_ok("/beacon/head");
+    assert_eq!(json["slot"], head.beacon_block.slot.as_u64());
+    assert_eq!(json["block_root"], root_string(head.beacon_block_root));
+    assert_eq!(json["state_root"], root_string(head.beacon_state_root));
+    assert_eq!(
+        json["parent_root"],
+        root_string(head.beacon_block.previous_block_root)
+    );
+
+    let json = tester.get_ok("/beacon/finality");
+    assert!(state.finalized_epoch > 0, "chain should have finalized");
+    assert_eq!(json["finalized"]["epoch"], state.finalized_epoch.as_u64());
+    assert_eq!(json["finalized"]["root"], root_string(state.finalized_root));
+    assert_eq!(
+        json["current_justified"]["epoch"],
+        state.current_justified_epoch.as_u64()
+    );
+    assert_eq!(
+        json["previous_justified"]["root"],
+        root_string(state.previous_justified_root)
+    );
+}
+
+#[test]
+fn blocks_and_state_roots() {
+    let skipped_slot = Slot::new(3);
+    let tester = ApiTester::new(8, skipped_slot);
+    let chain = &tester.harness.chain;
+    let head_slot = chain.head().beacon_block.slot;
+
+    for slot in 1..=head_slot.as_u64() {
+        let slot = Slot::new(slot);
+        let block_path = format!("/beacon/block?slot={}", slot);
+
+        if slot == skipped_slot {
+            let (status, _) = tester.get(&block_path);
+            assert_eq!(
+                status,
+                Status::NotFound,
+                "skipped slot should have no block"
+            );
+        } else {
+            let block_root = chain.block_root_at_slot(slot).unwrap().unwrap();
+            let json = tester.get_ok(&block_path);
+
+            assert_eq!(json["root"], root_string(block_root));
+            assert_eq!(json["beacon_block"]["slot"], slot.as_u64());
+
+            let by_root = tester.get_ok(&format!("/beacon/block?root={:?}", block_root));
+            assert_eq!(by_root, json, "block by root should match block by slot");
+
+            let (status, body) = tester.get_ssz(&block_path);
+            assert_eq!(status, Status::Ok);
+            assert_eq!(
+                BeaconBlock::from_ssz_bytes(&body).expect("should decode SSZ"),
+                chain.get_block(&block_root).unwrap().unwrap()
+            );
+        }
+
+        let state_root = chain.state_root_at_slot(slot).unwrap().unwrap();
+        let json = tester.get_ok(&format!("/beacon/state_root?slot={}", slot));
+        assert_eq!(json["root"], root_string(state_root));
+    }
+
+    let (status, _) = tester.get(&format!("/beacon/block?slot={}", head_slot + 1));
+    assert_eq!(status, Status::NotFound, "no block after the head");
+
+    let (status, _) = tester.get(&format!("/beacon/block?root={:?}", Hash256::from(42)));
+    assert_eq!(status, Status::NotFound, "unknown root");
+
+    let (status, _) = tester.get("/beacon/block?slot=1&root=0x00");
+    assert_eq!(status, Status::BadRequest, "root and slot are exclusive");
+
+    let (status, _) = tester.get("/beacon/block?slot=one");
+    assert_eq!(status, Status::BadRequest, "slot must be an integer");
+}
+
+#[test]
+fn state_as_json_and_ssz() {
+    let tester = ApiTester::new(8, Slot::new(3));
+    let head = tester.harness.chain.head();
+
+    let json = tester.get_ok("/beacon/state");
+    assert_eq!(json["root"], root_string(head.beacon_state_root));
+    assert_eq!(
+        json["beacon_state"]["slot"],
+        head.beacon_state.slot.as_u64()
+    );
+
+    let json = tester.get_ok(&format!("/beacon/state?slot={}", head.beacon_block.slot));
+    assert_eq!(json["root"], root_string(head.beacon_state_root));
+
+    let (status, body) = tester.get_ssz("/beacon/state");
+    assert_eq!(status, Status::Ok);
+
+    let state = BeaconState::<TestEthSpec>::from_ssz_bytes(&body).expect("should decode SSZ");
+    assert_eq!(state.canonical_root(), head.beacon_state_root);
+}
+
+#[test]
+fn validators() {
+    let tester = ApiTester::new(8, Slot::new(3));
+    let state = tester.harness.chain.current_state().clone();
+
+    for index in 0..VALIDATOR_COUNT {
+        let json = tester.get_ok(&format!("/beacon/validator?index={}", index));
+
+        assert_eq!(json["index"], index);
+        assert_eq!(json["status"], "active");
+        assert_eq!(json["balance"], state.balances[index]);
+        assert_eq!(
+            json["validator"]["effective_balance"],
+            state.validator_registry[index].effective_balance
+        );
+
+        let pubkey = json["validator"]["pubkey"]
+            .as_str()
+            .expect("pubkey should be a string");
+        let by_pubkey = tester.get_ok(&format!("/beacon/validator?pubkey={}", pubkey));
+        assert_eq!(by_pubkey, json, "validator by pubkey should match by index");
+
+        let (status, body) = tester.get_ssz(&format!("/beacon/validator?index={}", index));
+        assert_eq!(status, Status::Ok);
+        assert_eq!(
+            Validator::from_ssz_bytes(&body).expect("should decode SSZ"),
+            state.validator_registry[index]
+        );
+    }
+
+    let (status, _) = tester.get(&format!("/beacon/validator?index={}", VALIDATOR_COUNT));
+    assert_eq!(status, Status::NotFound, "unknown index");
+
+    let (status, _) = tester.get("/beacon/validator");
+    assert_eq!(status, Status::BadRequest, "index or pubkey is required");
+}
+
+#[test]
+fn committees_and_proposers() {
+    let slots_per_epoch = TestEthSpec::slots_per_epoch();
+    let tester = ApiTester::new(slots_per_epoch as usize * 3, Slot::new(3));
+    let chain = &tester.harness.chain;
+    let current_epoch = chain.current_state().current_epoch();
+
+    // Every active validator is in exactly one committee per epoch, including in epochs which
+    // must be loaded from the store.
+    for epoch in 0..=current_epoch.as_u64() + 1 {
+        let json = tester.get_ok(&format!("/beacon/committees?epoch={}", epoch));
+        let committees = json.as_array().expect("should be a list of committees");
+
+        let mut members = HashSet::new();
+        for committee in committees {
+            let slot = committee["slot"].as_u64().unwrap();
+            assert_eq!(slot / slots_per_epoch, epoch, "committee in wrong epoch");
+
+            for index in committee["committee"].as_array().unwrap() {
+                assert!(
+                    members.insert(index.as_u64().unwrap()),
+                    "validator in two committees"
+                );
+            }
+        }
+        assert_eq!(members.len(), VALIDATOR_COUNT, "epoch {}", epoch);
+    }
+
+    let (status, _) = tester.get(&format!("/beacon/committees?epoch={}", current_epoch + 2));
+    assert_eq!(status, Status::BadRequest, "epoch too far in the future");
+
+    let present_slot = chain.present_slot();
+    let json = tester.get_ok("/beacon/proposer");
+    assert_eq!(json["slot"], present_slot.as_u64());
+    assert_eq!(
+        json["proposer_index"],
+        chain.block_proposer(present_slot).unwrap()
+    );
+
+    // Proposers of past slots are read from states loaded from the store.
+    for slot in 1..present_slot.as_u64() {
+        let json = tester.get_ok(&format!("/beacon/proposer?slot={}", slot));
+        let index = json["proposer_index"].as_u64().unwrap() as usize;
+        assert!(index < VALIDATOR_COUNT);
+        assert_eq!(
+            json["pubkey"],
+            serde_json::to_value(&chain.current_state().validator_registry[index].pubkey).unwrap()
+        );
+    }
+}
+
+#[test]
+fn peer_scores() {
+    let tester = ApiTester::new(8, Slot::new(3));
+    let good_peer = PeerId::random();
+    let bad_peer = PeerId::random();
+
+    tester
+        .peer_manager
+        .report_peer(&good_peer, PeerAction::ValidBlock);
+    tester
+        .peer_manager
+        .report_peer(&bad_peer, PeerAction::WrongChain);
+
+    let json = tester.get_ok("/network/peers");
+    let peers = json.as_array().unwrap();
+    assert_eq!(peers.len(), 2);
+
+    // Peers are listed lowest score first.
+    assert_eq!(peers[0]["peer_id"], bad_peer.to_base58());
+    assert_eq!(peers[0]["banned"], true);
+    assert!(peers[0]["ban_remaining_secs"].as_u64().unwrap() > 0);
+
+    assert_eq!(peers[1]["peer_id"], good_peer.to_base58());
+    assert_eq!(peers[1]["score"], 1);
+    assert_eq!(peers[1]["banned"], false);
+    assert!(peers[1]["ban_remaining_secs"].is_null());
+
+    let (status, body) = tester.get_with_headers("/metrics", Headers::new());
+    assert_eq!(status, Status::Ok);
+    let metrics = String::from_utf8(body).unwrap();
+    // Metrics are aggregated, rather than labelled by peer.
+    assert!(metrics.contains("banned_peer_count 1"));
+    assert!(!metrics.contains(&good_peer.to_base58()));
+}
+
+#[test]
+fn fork_choice_dump() {
+    let tester = ApiTester::new(8, Slot::new(3));
+    let head = tester.harness.chain.head().beacon_block_root;
+
+    let json = tester.get_ok("/node/fork_choice");
+    assert_eq!(json["head"], root_string(head));
+    assert!(json["nodes"]
+        .as_array()
+        .unwrap()
+        .iter()
+        .any(|node| node["block_root"] == root_string(head)));
+
+    // The query string is percent-decoded.
+    let (status, body) = tester.get_with_headers("/node/fork_choice?format=%64ot", Headers::new());
+    assert_eq!(status, Status::Ok);
+    assert!(String::from_utf8(body).unwrap().starts_with("digraph"));
+
+    let (status, _) = tester.get("/node/fork_choice?format=yaml");
+    assert_eq!(status, Status::BadRequest);
+}
+
+#[test]
+fn events_are_streamed() {
+    let tester = ApiTester::new(8, Slot::new(3));
+
+    let resp = tester.get_response("/events?topics=head%2Cblock");
+    assert_eq!(resp.status, Some(Status::Ok));
+
+    tester.harness.extend_chain(
+        1,
+        BlockStrategy::OnCanonicalHead,
+        AttestationStrategy::AllValidators,
+    );
+    let head = tester.harness.chain.head().beacon_block_root;
+
+    let mut collector = EventCollector::default();
+    resp.body
+        .expect("response should have a body")
+        .write_body(&mut collector)
+        .expect_err("stream should end when the client hangs up");
+
+    let stream = collector.as_str();
+    assert!(stream.contains("event: block\n"), "{}", stream);
+    assert!(stream.contains("event: head\n"), "{}", stream);
+    assert!(stream.contains(&root_string(head)), "{}", stream);
+    assert!(
+        !stream.contains("event: finalized_checkpoint"),
+        "{}",
+        stream
+    );
+}
+
+#[test]
+fn event_streams_are_limited() {
+    let tester = ApiTester::new(8, Slot::new(3));
+
+    let (status, _) = tester.get("/events?topics=unknown");
+    assert_eq!(status, Status::BadRequest);
+
+    let open: Vec<_> = (0..MAX_EVENT_STREAMS)
+        .map(|_| tester.get_response("/events"))
+        .collect();
+    assert!(open.iter().all(|resp| resp.status == Some(Status::Ok)));
+
+    let (status, _) = tester.get("/events");
+    assert_eq!(status, Status::ServiceUnavailable);
+
+    // Closing a stream frees its slot.
+    drop(open);
+    assert_eq!(tester.get_response("/events").status, Some(Status::Ok));
+}
@@ -0,0 +1,258 @@
+#![cfg(not(debug_assertions))]
+
+use beacon_chain::test_utils::{AttestationStrategy, BeaconChainHarness, BlockStrategy};
+use crossbeam_channel::Receiver;
+use eth2_libp2p::PeerManager;
+use http_server::{create_iron_http_server, MAX_BODY_SIZE};
+use iron::{status::Status, Headers};
+use iron_test::{request, response};
+use lmd_ghost::ThreadSafeReducedTree;
+use network::NetworkMessage;
+use prometheus::Registry;
+use router::Router;
+use serde_json::Value;
+use std::path::PathBuf;
+use store::MemoryStore;
+use types::test_utils::{
+    TestingDepositBuilder, TestingProposerSlashingBuilder, TestingVoluntaryExitBuilder,
+};
+use types::{Attestation, MinimalEthSpec, ProposerSlashing};
+
+type TestEthSpec = MinimalEthSpec;
+type TestHarness = BeaconChainHarness<ThreadSafeReducedTree<MemoryStore, TestEthSpec>, TestEthSpec>;
+
+const VALIDATOR_COUNT: usize = 24;
+
+/// A `BeaconChainHarness` with the HTTP API handler attached to its chain, and the receiving end of
+/// the channel the API publishes to.
+struct ApiTester {
+    harness: TestHarness,
+    router: Router,
+    network_recv: Receiver<NetworkMessage>,
+}
+
+impl ApiTester {
+    fn new() -> Self {
+        let harness = TestHarness::new(VALIDATOR_COUNT);
+
+        // Move past the zero slot.
+        harness.advance_slot();
+
+        harness.extend_chain(
+            4,
+            BlockStrategy::OnCanonicalHead,
+            AttestationStrategy::AllValidators,
+        );
+
+        let (network_chan, network_recv) = crossbeam_channel::unbounded();
+        let router = create_iron_http_server(
+            harness.chain.clone(),
+            network_chan,
+            PeerManager::new(),
+            PathBuf::from("/tmp"),
+            Registry::new(),
+        )
+        .handler;
+
+        Self {
+            harness,
+            router,
+            network_recv,
+        }
+    }
+
+    fn url(path: &str) -> String {
+        format!("http://localhost:5052{}", path)
+    }
+
+    fn parse(resp: iron::Response) -> (Status, Value) {
+        let status = resp.status.expect("response should have a status");
+        let body = response::extract_body_to_string(resp);
+        let json = serde_json::from_str(&body).expect("response should be JSON");
+
+        (status, json)
+    }
+
+    fn get_ok(&self, path: &str) -> Value {
+        let (status, json) = Self::parse(
+            request::get(&Self::url(path), Headers::new(), &self.router)
+                .expect("request should not error"),
+        );
+        assert_eq!(status, Status::Ok, "{} failed with {}", path, json);
+        json
+    }
+
+    fn post<T: serde::Serialize>(&self, path: &str, body: &T) -> (Status, Value) {
+        let body = serde_json::to_string(body).unwrap();
+        Self::parse(
+            request::post(&Self::url(path), Headers::new(), &body, &self.router)
+                .expect("request should not error"),
+        )
+    }
+
+    /// Asserts that the request was rejected for the given reason, and nothing was published.
+    fn assert_rejected(&self, (status, json): (Status, Value), reason: &str) {
+        assert_eq!(status, Status::BadRequest, "{}", json);
+
+        let message = json["error"]
+            .as_str()
+            .expect("should have an error message");
+        assert!(
+            message.contains(reason),
+            "{} should contain {}",
+            message,
+            reason
+        );
+        assert!(self.network_recv.try_recv().is_err(), "should not publish");
+    }
+
+    /// Asserts that exactly one message was published to the network.
+    fn assert_published(&self) {
+        match self.network_recv.try_recv() {
+            Ok(NetworkMessage::Publish { .. }) => {}
+            _ => panic!("should have published to the network"),
+        }
+        assert!(self.network_recv.try_recv().is_err(), "published too much");
+    }
+
+    fn proposer_slashing(&self, proposer_index: u64) -> ProposerSlashing {
+        let chain = &self.harness.chain;
+        let fork = chain.current_state().fork.clone();
+
+        TestingProposerSlashingBuilder::double_vote::<TestEthSpec, _>(
+            proposer_index,
+            |index, message, epoch, domain| {
+                let domain = chain.spec.get_domain(epoch, domain, &fork);
+                let sk = &self.harness.keypairs[index as usize].sk;
+                types::Signature::new(message, domain, sk)
+            },
+        )
+    }
+}
+
+#[test]
+fn voluntary_exit() {
+    let tester = ApiTester::new();
+    let chain = &tester.harness.chain;
+    let state = chain.current_state().clone();
+
+    let exit = |validator_index: u64| {
+        let mut builder = TestingVoluntaryExitBuilder::new(state.current_epoch(), validator_index);
+        builder.sign(&tester.harness.keypairs[0].sk, &state.fork, &chain.spec);
+        builder.build()
+    };
+
+    tester.assert_rejected(
+        tester.post("/operations/voluntary_exit", &exit(VALIDATOR_COUNT as u64)),
+        "InvalidVoluntaryExit: ValidatorUnknown",
+    );
+
+    // Validators may not exit until they have served the persistent committee period.
+    tester.assert_rejected(
+        tester.post("/operations/voluntary_exit", &exit(0)),
+        "InvalidVoluntaryExit: TooYoungToLeave",
+    );
+}
+
+#[test]
+fn proposer_slashing() {
+    let tester = ApiTester::new();
+
+    let mut identical = tester.proposer_slashing(1);
+    identical.header_2 = identical.header_1.clone();
+    tester.assert_rejected(
+        tester.post("/operations/proposer_slashing", &identical),
+        "InvalidProposerSlashing: ProposalsIdentical",
+    );
+
+    let slashing = tester.proposer_slashing(1);
+    let (status, json) = tester.post("/operations/proposer_slashing", &slashing);
+    assert_eq!(status, Status::Ok, "{}", json);
+    tester.assert_published();
+
+    let json = tester.get_ok("/operations/proposer_slashings");
+    assert_eq!(json, serde_json::to_value(vec![slashing]).unwrap());
+    assert_eq!(tester.get_ok("/operations/counts")["proposer_slashings"], 1);
+}
+
+#[test]
+fn oversized_body_is_rejected() {
+    let tester = ApiTester::new();
+    let body = " ".repeat(MAX_BODY_SIZE as usize + 1);
+
+    let (status, json) = ApiTester::parse(
+        request::post(
+            &ApiTester::url("/operations/voluntary_exit"),
+            Headers::new(),
+            &body,
+            &tester.router,
+        )
+        .expect("request should not error"),
+    );
+    assert_eq!(status, Status::PayloadTooLarge, "{}", json);
+    assert!(
+        tester.network_recv.try_recv().is_err(),
+        "should not publish"
+    );
+}
+
+#[test]
+fn deposit() {
+    let tester = ApiTester::new();
+    let chain = &tester.harness.chain;
+    let state = chain.current_state().clone();
+
+    let keypair = &tester.harness.keypairs[0];
+    let mut builder =
+        TestingDepositBuilder::new(keypair.pk.clone(), chain.spec.max_effective_balance);
+    builder.set_index(state.deposit_index);
+    builder.sign(keypair, state.current_epoch(), &state.fork, &chain.spec);
+    let deposit = builder.build();
+
+    // The harness never votes in Eth1 data, so no proof verifies against its deposit root.
+    let (status, json) = tester.post("/operations/deposit", &deposit);
+    assert_eq!(status, Status::BadRequest);
+    assert!(
+        json["error"].as_str().unwrap().contains("BadMerkleProof"),
+        "{}",
+        json
+    );
+    assert_eq!(tester.get_ok("/operations/counts")["deposits"], 0);
+
+    assert!(
+        tester.network_recv.try_recv().is_err(),
+        "deposits should not be published"
+    );
+}
+
+#[test]
+fn attestations() {
+    let tester = ApiTester::new();
+    let op_pool = &tester.harness.chain.op_pool;
+    assert!(op_pool.num_attestations() > 0);
+
+    let groups = tester.get_ok("/operations/attestations");
+    let groups = groups.as_array().expect("should be a list of groups");
+
+    let mut total = 0;
+    for group in groups {
+        let attestations = group["attestations"].as_array().unwrap();
+        assert!(!attestations.is_empty(), "groups should not be empty");
+
+        for pending in attestations {
+            let attestation: Attestation =
+                serde_json::from_value(pending["attestation"].clone()).unwrap();
+            assert_eq!(
+                pending["aggregation_bits"],
+                attestation.aggregation_bitfield.num_set_bits()
+            );
+            assert!(pending["aggregation_bits"].as_u64().unwrap() > 0);
+        }
+        total += attestations.len();
+    }
+
+    let counts = tester.get_ok("/operations/counts");
+    assert_eq!(counts["attestations"], total);
+    assert_eq!(counts["attestations"], op_pool.num_attestations());
+    assert_eq!(counts["transfers"], 0);
+}
@@ -0,0 +1,319 @@
+#![cfg(not(debug_assertions))]
+
+use beacon_chain::test_utils::{AttestationStrategy, BeaconChainHarness, BlockStrategy};
+use crossbeam_channel::Receiver;
+use eth2_libp2p::PeerManager;
+use http_server::create_iron_http_server;
+use iron::{status::Status, Headers};
+use iron_test::{request, response};
+use lmd_ghost::ThreadSafeReducedTree;
+use network::NetworkMessage;
+use prometheus::Registry;
+use router::Router;
+use serde_json::{json, Value};
+use ssz::Decode;
+use std::path::PathBuf;
+use store::MemoryStore;
+use tree_hash::{SignedRoot, TreeHash};
+use types::{
+    AggregateSignature, Attestation, AttestationData, AttestationDataAndCustodyBit, BeaconBlock,
+    Bitfield, Domain, EthSpec, Keypair, MinimalEthSpec, RelativeEpoch, Signature,
+};
+
+type TestEthSpec = MinimalEthSpec;
+type TestHarness = BeaconChainHarness<ThreadSafeReducedTree<MemoryStore, TestEthSpec>, TestEthSpec>;
+
+const VALIDATOR_COUNT: usize = 24;
+
+/// A `BeaconChainHarness` with the HTTP API handler attached to its chain, and the receiving end of
+/// the channel the API publishes to.
+struct ApiTester {
+    harness: TestHarness,
+    router: Router,
+    network_recv: Receiver<NetworkMessage>,
+}
+
+impl ApiTester {
+    fn new(num_blocks: usize) -> Self {
+        let harness = TestHarness::new(VALIDATOR_COUNT);
+
+        // Move past the zero slot.
+        harness.advance_slot();
+
+        harness.extend_chain(
+            num_blocks,
+            BlockStrategy::OnCanonicalHead,
+            AttestationStrategy::AllValidators,
+        );
+
+        let (network_chan, network_recv) = crossbeam_channel::unbounded();
+        let router = create_iron_http_server(
+            harness.chain.clone(),
+            network_chan,
+            PeerManager::new(),
+            PathBuf::from("/tmp"),
+            Registry::new(),
+        )
+        .handler;
+
+        Self {
+            harness,
+            router,
+            network_recv,
+        }
+    }
+
+    fn url(path: &str) -> String {
+        format!("http://localhost:5052{}", path)
+    }
+
+    fn parse(resp: iron::Response) -> (Status, Value) {
+        let status = resp.status.expect("response should have a status");
+        let body = response::extract_body_to_string(resp);
+        let json = serde_json::from_str(&body).expect("response should be JSON");
+
+        (status, json)
+    }
+
+    fn get(&self, path: &str) -> (Status, Value) {
+        Self::parse(
+            request::get(&Self::url(path), Headers::new(), &self.router)
+                .expect("request should not error"),
+        )
+    }
+
+    fn post(&self, path: &str, body: &Value) -> (Status, Value) {
+        Self::parse(
+            request::post(
+                &Self::url(path),
+                Headers::new(),
+                &body.to_string(),
+                &self.router,
+            )
+            .expect("request should not error"),
+        )
+    }
+
+    /// Asserts that exactly one message was published to the network.
+    fn assert_published(&self) {
+        match self.network_recv.try_recv() {
+            Ok(NetworkMessage::Publish { .. }) => {}
+            _ => panic!("should have published to the network"),
+        }
+        assert!(self.network_recv.try_recv().is_err(), "published too much");
+    }
+}
+
+#[test]
+fn duties() {
+    let tester = ApiTester::new(4);
+    let chain = &tester.harness.chain;
+    let state = chain.current_state().clone();
+    let epoch = state.current_epoch();
+
+    let unknown = Keypair::random().pk;
+    let mut pubkeys: Vec<_> = tester
+        .harness
+        .keypairs
+        .iter()
+        .map(|keypair| keypair.pk.clone())
+        .collect();
+    pubkeys.push(unknown);
+
+    let (status, json) = tester.post(
+        "/validator/duties",
+        &json!({ "epoch": epoch, "pubkeys": pubkeys }),
+    );
+    assert_eq!(status, Status::Ok, "{}", json);
+
+    let duties = json.as_array().expect("should be a list of duties");
+    assert_eq!(duties.len(), VALIDATOR_COUNT + 1);
+
+    for (index, duty) in duties.iter().take(VALIDATOR_COUNT).enumerate() {
+        assert_eq!(duty["validator_index"], index);
+
+        let expected = state
+            .get_attestation_duties(index, RelativeEpoch::Current)
+            .unwrap();
+        assert_eq!(
+            duty["attestation_duty"],
+            serde_json::to_value(&expected).unwrap()
+        );
+
+        if let Some(slot) = duty["block_production_slot"].as_u64() {
+            assert_eq!(chain.block_proposer(slot.into()).unwrap(), index);
+        }
+    }
+
+    let unknown_duty = &duties[VALIDATOR_COUNT];
+    assert_eq!(unknown_duty["validator_index"], Value::Null);
+    assert_eq!(unknown_duty["attestation_duty"], Value::Null);
+
+    let (status, _) = tester.post(
+        "/validator/duties",
+        &json!({ "epoch": epoch + 2, "pubkeys": [] }),
+    );
+    assert_eq!(status, Status::BadRequest, "epoch is not known");
+
+    let (status, _) = tester.post("/validator/duties", &json!({ "epoch": epoch }));
+    assert_eq!(status, Status::BadRequest, "pubkeys are required");
+}
+
+#[test]
+fn produce_and_publish_block() {
+    let tester = ApiTester::new(4);
+    let chain = &tester.harness.chain;
+    let spec = &tester.harness.spec;
+
+    tester.harness.advance_slot();
+
+    let slot = chain.present_slot();
+    let epoch = slot.epoch(TestEthSpec::slots_per_epoch());
+    let fork = chain.current_state().fork.clone();
+    let proposer = chain.block_proposer(slot).unwrap();
+    let sk = &tester.harness.keypairs[proposer].sk;
+
+    let randao_reveal = {
+        let domain = spec.get_domain(epoch, Domain::Randao, &fork);
+        Signature::new(&epoch.tree_hash_root(), domain, sk)
+    };
+    let randao_reveal = serde_json::to_value(&randao_reveal).unwrap();
+    let randao_reveal = randao_reveal.as_str().unwrap();
+
+    let (status, _) = tester.get(&format!(
+        "/validator/block?slot={}&randao_reveal={}",
+        slot + 1,
+        randao_reveal
+    ));
+    assert_eq!(
+        status,
+        Status::BadRequest,
+        "can only produce at the present slot"
+    );
+
+    let (status, json) = tester.get(&format!(
+        "/validator/block?slot={}&randao_reveal={}",
+        slot, randao_reveal
+    ));
+    assert_eq!(status, Status::Ok, "{}", json);
+
+    let mut block: BeaconBlock = serde_json::from_value(json).unwrap();
+    assert_eq!(block.slot, slot);
+
+    let mut headers = Headers::new();
+    headers.set_raw("Accept", vec![b"application/octet-stream".to_vec()]);
+    let resp = request::get(
+        &ApiTester::url(&format!(
+            "/validator/block?slot={}&randao_reveal={}",
+            slot, randao_reveal
+        )),
+        headers,
+        &tester.router,
+    )
+    .unwrap();
+    assert_eq!(
+        BeaconBlock::from_ssz_bytes(&response::extract_body_to_bytes(resp)),
+        Ok(block.clone()),
+        "block should be the same as SSZ"
+    );
+
+    block.signature = {
+        let domain = spec.get_domain(epoch, Domain::BeaconProposer, &fork);
+        Signature::new(&block.signed_root(), domain, sk)
+    };
+    let block = serde_json::to_value(&block).unwrap();
+
+    let (status, json) = tester.post("/validator/block", &block);
+    assert_eq!(status, Status::Ok, "{}", json);
+    assert_eq!(
+        json["block_root"],
+        format!("{:?}", chain.head().beacon_block_root),
+        "block should be the new head"
+    );
+    tester.assert_published();
+
+    let (status, _) = tester.post("/validator/block", &block);
+    assert_eq!(status, Status::BadRequest, "block is already known");
+    assert!(tester.network_recv.try_recv().is_err());
+}
+
+#[test]
+fn produce_and_publish_attestation() {
+    let tester = ApiTester::new(4);
+    let chain = &tester.harness.chain;
+    let spec = &tester.harness.spec;
+
+    // Attest at a slot which has no attestations from the harness.
+    tester.harness.advance_slot();
+
+    let state = chain.current_state().clone();
+    let committee = state.get_crosslink_committees_at_slot(state.slot).unwrap()[0].clone();
+
+    let (status, _) = tester.get(&format!(
+        "/validator/attestation_data?slot={}&shard={}",
+        state.slot - 1,
+        committee.shard
+    ));
+    assert_eq!(
+        status,
+        Status::BadRequest,
+        "can only attest at the present slot"
+    );
+
+    let (status, json) = tester.get(&format!(
+        "/validator/attestation_data?slot={}&shard={}",
+        state.slot, committee.shard
+    ));
+    assert_eq!(status, Status::Ok, "{}", json);
+
+    let data: AttestationData = serde_json::from_value(json).unwrap();
+    assert_eq!(data.beacon_block_root, chain.head().beacon_block_root);
+
+    let attestation = |signer: usize| {
+        let mut aggregation_bitfield = Bitfield::new();
+        aggregation_bitfield.set(0, true);
+        aggregation_bitfield.set(committee.committee.len(), false);
+
+        let mut custody_bitfield = Bitfield::new();
+        custody_bitfield.set(committee.committee.len(), false);
+
+        let message = AttestationDataAndCustodyBit {
+            data: data.clone(),
+            custody_bit: false,
+        }
+        .tree_hash_root();
+        let domain = spec.get_domain(data.target_epoch, Domain::Attestation, &state.fork);
+
+        let mut signature = AggregateSignature::new();
+        signature.add(&Signature::new(
+            &message,
+            domain,
+            &tester.harness.keypairs[signer].sk,
+        ));
+
+        Attestation {
+            aggregation_bitfield,
+            data: data.clone(),
+            custody_bitfield,
+            signature,
+        }
+    };
+
+    // Signed by a validator outside the committee.
+    let wrong_signer = (0..VALIDATOR_COUNT)
+        .find(|index| !committee.committee.contains(index))
+        .unwrap();
+    let (status, _) = tester.post(
+        "/validator/attestation",
+        &serde_json::to_value(&attestation(wrong_signer)).unwrap(),
+    );
+    assert_eq!(status, Status::BadRequest, "signature is invalid");
+    assert!(tester.network_recv.try_recv().is_err());
+
+    let (status, json) = tester.post(
+        "/validator/attestation",
+        &serde_json::to_value(&attestation(committee.committee[0])).unwrap(),
+    );
+    assert_eq!(status, Status::Ok, "{}", json);
+    tester.assert_published();
+}
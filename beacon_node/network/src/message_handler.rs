@@ -6,18 +6,21 @@ use crossbeam_channel::{unbounded as channel, Sender};
 use eth2_libp2p::{
     behaviour::PubsubMessage,
     rpc::{methods::GoodbyeReason, RPCRequest, RPCResponse, RequestId},
-    PeerId, RPCEvent,
+    PeerAction, PeerId, RPCEvent,
 };
-use futures::future;
+use futures::{future, Future, Stream};
 use slog::{debug, warn};
 use std::collections::HashMap;
 use std::sync::Arc;
-use std::time::Instant;
+use std::time::{Duration, Instant};
+use tokio::timer::Interval;
 
 /// Timeout for RPC requests.
 // const REQUEST_TIMEOUT: Duration = Duration::from_secs(30);
 /// Timeout before banning a peer for non-identification.
 // const HELLO_TIMEOUT: Duration = Duration::from_secs(30);
+/// The interval at which sync checks its outstanding requests for timeouts.
+const TICK_INTERVAL: Duration = Duration::from_secs(5);
 
 /// Handles messages received from the network and client and organises syncing.
 pub struct MessageHandler<T: BeaconChainTypes> {
@@ -42,6 +45,8 @@ pub enum HandlerMessage {
     RPC(PeerId, RPCEvent),
     /// A gossip message has been received.
     PubsubMessage(PeerId, Box<PubsubMessage>),
+    /// Sent every `TICK_INTERVAL`, so that timeouts fire even if no messages arrive.
+    Tick,
 }
 
 impl<T: BeaconChainTypes + 'static> MessageHandler<T> {
@@ -67,6 +72,16 @@ impl<T: BeaconChainTypes + 'static> MessageHandler<T> {
             log: log.clone(),
         };
 
+        // drive sync timeouts
+        let tick_send = handler_send.clone();
+        let tick_log = log.clone();
+        executor.spawn(
+            Interval::new(Instant::now() + TICK_INTERVAL, TICK_INTERVAL)
+                .map_err(move |e| debug!(tick_log, "Timer error {}", e))
+                .for_each(move |_| tick_send.send(HandlerMessage::Tick).map_err(|_| ()))
+                .map(|_| ()),
+        );
+
         // spawn handler task
         // TODO: Handle manual termination of thread
         executor.spawn(future::poll_fn(move || -> Result<_, _> {
@@ -87,6 +102,10 @@ impl<T: BeaconChainTypes + 'static> MessageHandler<T> {
             HandlerMessage::PeerDialed(peer_id) => {
                 self.sync.on_connect(peer_id, &mut self.network_context);
             }
+            // a peer has disconnected
+            HandlerMessage::PeerDisconnected(peer_id) => {
+                self.sync.on_disconnect(peer_id, &mut self.network_context);
+            }
             // we have received an RPC message request/response
             HandlerMessage::RPC(peer_id, rpc_event) => {
                 self.handle_rpc_message(peer_id, rpc_event);
@@ -95,8 +114,7 @@ impl<T: BeaconChainTypes + 'static> MessageHandler<T> {
             HandlerMessage::PubsubMessage(peer_id, gossip) => {
                 self.handle_gossip(peer_id, *gossip);
             }
-            //TODO: Handle all messages
-            _ => {}
+            HandlerMessage::Tick => self.sync.on_tick(&mut self.network_context),
         }
     }
 
@@ -121,7 +139,10 @@ impl<T: BeaconChainTypes + 'static> MessageHandler<T> {
                 hello_message,
                 &mut self.network_context,
             ),
-            RPCRequest::Goodbye(goodbye_reason) => self.sync.on_goodbye(peer_id, goodbye_reason),
+            RPCRequest::Goodbye(goodbye_reason) => {
+                self.sync
+                    .on_goodbye(peer_id, goodbye_reason, &mut self.network_context)
+            }
             RPCRequest::BeaconBlockRoots(request) => self.sync.on_beacon_block_roots_request(
                 peer_id,
                 request_id,
@@ -145,6 +166,20 @@ impl<T: BeaconChainTypes + 'static> MessageHandler<T> {
                 // useful for light-client support in later phases.
                 warn!(self.log, "BeaconChainState RPC call is not supported.");
             }
+            RPCRequest::BeaconBlocksByRange(request) => {
+                self.sync.on_beacon_blocks_by_range_request(
+                    peer_id,
+                    request_id,
+                    request,
+                    &mut self.network_context,
+                )
+            }
+            RPCRequest::BeaconBlocksByRoot(request) => self.sync.on_beacon_blocks_by_root_request(
+                peer_id,
+                request_id,
+                request,
+                &mut self.network_context,
+            ),
         }
     }
 
@@ -201,6 +236,22 @@ impl<T: BeaconChainTypes + 'static> MessageHandler<T> {
                 // beacon state RPC request.
                 warn!(self.log, "BeaconChainState RPC call is not supported.");
             }
+            RPCResponse::BeaconBlocksByRange(response) => {
+                self.sync.on_beacon_blocks_by_range_response(
+                    peer_id,
+                    id,
+                    response,
+                    &mut self.network_context,
+                );
+            }
+            RPCResponse::BeaconBlocksByRoot(response) => {
+                self.sync.on_beacon_blocks_by_root_response(
+                    peer_id,
+                    id,
+                    response,
+                    &mut self.network_context,
+                );
+            }
         };
     }
 
@@ -216,6 +267,22 @@ impl<T: BeaconChainTypes + 'static> MessageHandler<T> {
                 self.sync
                     .on_attestation_gossip(peer_id, message, &mut self.network_context)
             }
+            PubsubMessage::VoluntaryExit(message) => {
+                self.sync
+                    .on_voluntary_exit_gossip(peer_id, message, &mut self.network_context)
+            }
+            PubsubMessage::ProposerSlashing(message) => {
+                self.sync
+                    .on_proposer_slashing_gossip(peer_id, message, &mut self.network_context)
+            }
+            PubsubMessage::AttesterSlashing(message) => {
+                self.sync
+                    .on_attester_slashing_gossip(peer_id, message, &mut self.network_context)
+            }
+            PubsubMessage::Transfer(message) => {
+                self.sync
+                    .on_transfer_gossip(peer_id, message, &mut self.network_context)
+            }
         }
     }
 }
@@ -242,11 +309,19 @@ impl NetworkContext {
     }
 
     pub fn disconnect(&mut self, peer_id: PeerId, reason: GoodbyeReason) {
-        self.send_rpc_request(peer_id, RPCRequest::Goodbye(reason))
+        self.send_rpc_request(peer_id, RPCRequest::Goodbye(reason));
         // TODO: disconnect peers.
     }
 
-    pub fn send_rpc_request(&mut self, peer_id: PeerId, rpc_request: RPCRequest) {
+    /// Adjusts the score of `peer_id` in response to its behaviour.
+    pub fn report_peer(&mut self, peer_id: PeerId, action: PeerAction) {
+        self.network_send
+            .send(NetworkMessage::ReportPeer { peer_id, action })
+            .unwrap_or_else(|_| warn!(self.log, "Could not report peer to the network service"));
+    }
+
+    /// Sends `rpc_request` to `peer_id`, returning the id with which the response will arrive.
+    pub fn send_rpc_request(&mut self, peer_id: PeerId, rpc_request: RPCRequest) -> RequestId {
         let id = self.generate_request_id(&peer_id);
 
         self.outstanding_outgoing_request_ids
@@ -260,6 +335,8 @@ impl NetworkContext {
                 body: rpc_request,
             },
         );
+
+        id
     }
 
     pub fn send_rpc_response(
@@ -5,7 +5,7 @@ use beacon_chain::{BeaconChain, BeaconChainTypes};
 use crossbeam_channel::{unbounded as channel, Sender, TryRecvError};
 use eth2_libp2p::Service as LibP2PService;
 use eth2_libp2p::Topic;
-use eth2_libp2p::{Libp2pEvent, PeerId};
+use eth2_libp2p::{Libp2pEvent, PeerAction, PeerId, PeerManager};
 use eth2_libp2p::{PubsubMessage, RPCEvent};
 use futures::prelude::*;
 use futures::sync::oneshot;
@@ -20,6 +20,8 @@ pub struct Service<T: BeaconChainTypes> {
     //libp2p_service: Arc<Mutex<LibP2PService>>,
     _libp2p_exit: oneshot::Sender<()>,
     network_send: crossbeam_channel::Sender<NetworkMessage>,
+    /// The scores of connected and banned peers.
+    peer_manager: PeerManager,
     _phantom: PhantomData<T>, //message_handler: MessageHandler,
                               //message_handler_send: Sender<HandlerMessage>
 }
@@ -45,6 +47,7 @@ impl<T: BeaconChainTypes + 'static> Service<T> {
         // launch libp2p service
         let libp2p_log = log.new(o!("Service" => "Libp2p"));
         let libp2p_service = LibP2PService::new(config.clone(), libp2p_log)?;
+        let peer_manager = libp2p_service.peer_manager();
 
         // TODO: Spawn thread to handle libp2p messages and pass to message handler thread.
         let libp2p_exit = spawn_service(
@@ -57,12 +60,18 @@ impl<T: BeaconChainTypes + 'static> Service<T> {
         let network_service = Service {
             _libp2p_exit: libp2p_exit,
             network_send: network_send.clone(),
+            peer_manager,
             _phantom: PhantomData,
         };
 
         Ok((Arc::new(network_service), network_send))
     }
 
+    /// Returns a handle to the scores of peers.
+    pub fn peer_manager(&self) -> PeerManager {
+        self.peer_manager.clone()
+    }
+
     // TODO: Testing only
     pub fn send_message(&self) {
         self.network_send
@@ -126,6 +135,12 @@ fn network_service(
                             .send(HandlerMessage::PeerDialed(peer_id))
                             .map_err(|_| "failed to send rpc to handler")?;
                     }
+                    Libp2pEvent::PeerDisconnected(peer_id) => {
+                        debug!(log, "Peer Disconnected: {:?}", peer_id);
+                        message_handler_send
+                            .send(HandlerMessage::PeerDisconnected(peer_id))
+                            .map_err(|_| "failed to send rpc to handler")?;
+                    }
                     Libp2pEvent::PubsubMessage {
                         source, message, ..
                     } => {
@@ -163,6 +178,9 @@ fn network_service(
                     debug!(log, "Sending pubsub message on topics {:?}", topics);
                     libp2p_service.swarm.publish(topics, *message);
                 }
+                Ok(NetworkMessage::ReportPeer { peer_id, action }) => {
+                    libp2p_service.report_peer(peer_id, action);
+                }
                 Err(TryRecvError::Empty) => break,
                 Err(TryRecvError::Disconnected) => {
                     return Err(eth2_libp2p::error::Error::from(
@@ -186,6 +204,8 @@ pub enum NetworkMessage {
         topics: Vec<Topic>,
         message: Box<PubsubMessage>,
     },
+    /// Adjust the score of a peer, which may result in it being banned.
+    ReportPeer { peer_id: PeerId, action: PeerAction },
 }
 
 /// Type of outgoing messages that can be sent through the network service.
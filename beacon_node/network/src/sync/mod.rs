@@ -1,9 +1,12 @@
 mod import_queue;
+mod parent_lookup;
+mod range_sync;
 /// Syncing for lighthouse.
 ///
 /// Stores the various syncing methods for the beacon chain.
 mod simple_sync;
 
+pub use range_sync::SyncState;
 pub use simple_sync::SimpleSync;
 
 /// Currently implemented sync methods.
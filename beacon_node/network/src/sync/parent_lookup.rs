@@ -0,0 +1,704 @@
+use super::simple_sync::hello_message;
+use crate::message_handler::NetworkContext;
+use beacon_chain::{BeaconChain, BeaconChainTypes, BlockProcessingOutcome};
+use eth2_libp2p::rpc::methods::*;
+use eth2_libp2p::rpc::{RPCRequest, RequestId};
+use eth2_libp2p::{PeerAction, PeerId};
+use slog::{debug, info, o, warn};
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+use store::Store;
+use types::{BeaconBlock, Hash256};
+
+/// The maximum number of ancestors which will be requested for an orphaned block. Beyond this,
+/// the peer is asked for a `Hello` so that we may sync from it instead.
+const MAX_PARENT_DEPTH: usize = 32;
+
+/// The maximum number of orphaned blocks whose ancestors may be looked up at once.
+const MAX_PARENT_LOOKUPS: usize = 16;
+
+/// A lookup which has been awaiting a response for longer than this is abandoned.
+const PARENT_REQUEST_TIMEOUT: Duration = Duration::from_secs(30);
+
+/// A chain of blocks, descending from an unknown block, which are being downloaded one at a time
+/// from the peer which sent the orphan.
+struct ParentLookup {
+    peer_id: PeerId,
+    request_id: RequestId,
+    requested: Instant,
+    /// The orphan, followed by each of its ancestors that have been downloaded. Each block is the
+    /// child of the next.
+    blocks: Vec<BeaconBlock>,
+    /// Later orphans which descend from the lookup, with the peers that sent them. Each child's
+    /// parent is in `blocks`, is being searched for, or is an earlier child.
+    children: Vec<(PeerId, BeaconBlock)>,
+}
+
+impl ParentLookup {
+    /// The earliest block downloaded, whose parent is not yet known.
+    fn earliest(&self) -> &BeaconBlock {
+        self.blocks
+            .last()
+            .expect("a lookup always contains the orphan")
+    }
+
+    /// Returns `true` if the lookup holds the block with `block_root`, or is searching for it.
+    fn contains(&self, block_root: Hash256) -> bool {
+        self.earliest().previous_block_root == block_root
+            || self
+                .blocks
+                .iter()
+                .chain(self.children.iter().map(|(_, block)| block))
+                .any(|block| block.canonical_root() == block_root)
+    }
+}
+
+/// Finds the ancestors of blocks which arrive via gossip before their parent, by recursively
+/// requesting each missing parent from the peer that sent the block.
+///
+/// Once the earliest ancestor's parent is known to the chain, the segment is imported in order. A
+/// peer which sends an ancestor that does not link to its child, or a segment containing an
+/// invalid block, is penalised. A peer which does not have the parent is not penalised, as it may
+/// have forwarded the orphan without importing it.
+///
+/// An orphan which descends from an existing lookup is imported along with it, rather than
+/// starting a lookup of its own. Lookups which are not answered within `PARENT_REQUEST_TIMEOUT`
+/// are expired by `Self::on_tick`.
+pub struct ParentLookups<T: BeaconChainTypes> {
+    chain: Arc<BeaconChain<T>>,
+    lookups: Vec<ParentLookup>,
+    log: slog::Logger,
+}
+
+impl<T: BeaconChainTypes> ParentLookups<T> {
+    pub fn new(chain: Arc<BeaconChain<T>>, log: &slog::Logger) -> Self {
+        Self {
+            chain,
+            lookups: vec![],
+            log: log.new(o!("Service" => "ParentLookup")),
+        }
+    }
+
+    /// Begins looking up the ancestors of `block`, which was received from `peer_id` and whose
+    /// parent is unknown. If `block` descends from an existing lookup, it is added to that lookup
+    /// instead.
+    ///
+    /// Returns the block if `MAX_PARENT_LOOKUPS` are already in progress, so that the caller may
+    /// queue it for later.
+    #[must_use]
+    pub fn add_orphan(
+        &mut self,
+        peer_id: PeerId,
+        block: BeaconBlock,
+        network: &mut NetworkContext,
+    ) -> Option<BeaconBlock> {
+        let block_root = block.canonical_root();
+        if self
+            .lookups
+            .iter()
+            .any(|lookup| lookup.contains(block_root))
+        {
+            return None;
+        }
+
+        if let Some(lookup) = self
+            .lookups
+            .iter_mut()
+            .find(|lookup| lookup.contains(block.previous_block_root))
+        {
+            debug!(
+                self.log, "ParentLookupExtended";
+                "block_root" => format!("{}", block_root),
+                "parent_root" => format!("{}", block.previous_block_root),
+                "peer" => format!("{:?}", peer_id),
+            );
+            lookup.children.push((peer_id, block));
+            return None;
+        }
+
+        if self.lookups.len() >= MAX_PARENT_LOOKUPS {
+            debug!(
+                self.log, "ParentLookupDeferred";
+                "reason" => "too many lookups",
+                "block_root" => format!("{}", block_root),
+            );
+            return Some(block);
+        }
+
+        debug!(
+            self.log, "ParentLookupStarted";
+            "block_root" => format!("{}", block_root),
+            "parent_root" => format!("{}", block.previous_block_root),
+            "peer" => format!("{:?}", peer_id),
+        );
+
+        let request_id = Self::request_parent(&block, peer_id.clone(), network);
+        self.lookups.push(ParentLookup {
+            peer_id,
+            request_id,
+            requested: Instant::now(),
+            blocks: vec![block],
+            children: vec![],
+        });
+
+        None
+    }
+
+    /// Abandons lookups which have timed out.
+    pub fn on_tick(&mut self, network: &mut NetworkContext) {
+        self.expire_lookups(Instant::now(), network);
+    }
+
+    /// Abandons each lookup whose request was sent more than `PARENT_REQUEST_TIMEOUT` before
+    /// `now`, penalising its peer.
+    fn expire_lookups(&mut self, now: Instant, network: &mut NetworkContext) {
+        let (expired, lookups): (Vec<_>, Vec<_>) = std::mem::replace(&mut self.lookups, vec![])
+            .into_iter()
+            .partition(|lookup| now.duration_since(lookup.requested) > PARENT_REQUEST_TIMEOUT);
+        self.lookups = lookups;
+
+        for lookup in expired {
+            debug!(
+                self.log, "ParentLookupTimedOut";
+                "parent_root" => format!("{}", lookup.earliest().previous_block_root),
+                "peer" => format!("{:?}", lookup.peer_id),
+            );
+            network.report_peer(lookup.peer_id, PeerAction::RPCTimeout);
+        }
+    }
+
+    /// Returns `true` if the response to `request_id` from `peer_id` belongs to a lookup.
+    pub fn is_lookup_request(&self, peer_id: &PeerId, request_id: RequestId) -> bool {
+        self.position(peer_id, request_id).is_some()
+    }
+
+    /// Handles the parent of the earliest block in a lookup, importing the chain of blocks if it
+    /// now links to a known block, or requesting the next parent otherwise.
+    pub fn on_blocks_response(
+        &mut self,
+        peer_id: PeerId,
+        request_id: RequestId,
+        blocks: Vec<BeaconBlock>,
+        network: &mut NetworkContext,
+    ) {
+        let index = match self.position(&peer_id, request_id) {
+            Some(index) => index,
+            None => return,
+        };
+        let parent_root = self.lookups[index].earliest().previous_block_root;
+
+        if blocks.is_empty() {
+            let lookup = self.lookups.remove(index);
+            debug!(
+                self.log, "ParentLookupAbandoned";
+                "reason" => "peer does not have the parent",
+                "parent_root" => format!("{}", parent_root),
+                "peer" => format!("{:?}", lookup.peer_id),
+            );
+            return;
+        }
+
+        let parent = match blocks
+            .into_iter()
+            .find(|block| block.canonical_root() == parent_root)
+        {
+            Some(parent) => parent,
+            None => return self.fail(index, "parent block not provided", network),
+        };
+
+        let mut lookup = self.lookups.remove(index);
+        lookup.blocks.push(parent);
+
+        let parent_root = lookup.earliest().previous_block_root;
+        let parent_is_known = self
+            .chain
+            .store
+            .exists::<BeaconBlock>(&parent_root)
+            .unwrap_or(false);
+
+        if parent_is_known {
+            self.import(lookup, network);
+        } else if lookup.blocks.len() > MAX_PARENT_DEPTH {
+            // We are likely far behind this peer, sync from it instead.
+            info!(
+                self.log, "ParentLookupTooDeep";
+                "depth" => lookup.blocks.len(),
+                "peer" => format!("{:?}", lookup.peer_id),
+            );
+            network.send_rpc_request(
+                lookup.peer_id,
+                RPCRequest::Hello(hello_message(&self.chain)),
+            );
+        } else {
+            lookup.request_id =
+                Self::request_parent(lookup.earliest(), lookup.peer_id.clone(), network);
+            lookup.requested = Instant::now();
+            self.lookups.push(lookup);
+        }
+    }
+
+    /// Imports the blocks of a lookup whose earliest block has a known parent, earliest first,
+    /// followed by its children.
+    fn import(&mut self, lookup: ParentLookup, network: &mut NetworkContext) {
+        let ParentLookup {
+            peer_id,
+            blocks,
+            children,
+            ..
+        } = lookup;
+        let count = blocks.len() + children.len();
+
+        let segment = blocks
+            .into_iter()
+            .rev()
+            .map(|block| (peer_id.clone(), block))
+            .chain(children);
+
+        for (sender, block) in segment {
+            match self.chain.process_block(block) {
+                Ok(BlockProcessingOutcome::Processed { .. })
+                | Ok(BlockProcessingOutcome::BlockIsAlreadyKnown) => {}
+                Ok(outcome @ BlockProcessingOutcome::StateRootMismatch)
+                | Ok(outcome @ BlockProcessingOutcome::PerBlockProcessingError(_)) => {
+                    warn!(
+                        self.log, "ParentLookupFailed";
+                        "reason" => "chain segment is invalid",
+                        "outcome" => format!("{:?}", outcome),
+                        "peer" => format!("{:?}", sender),
+                    );
+                    network.report_peer(sender, PeerAction::InvalidBlock);
+                    return;
+                }
+                outcome => {
+                    // The segment may be valid but unusable, e.g., it has been finalized over or
+                    // is ahead of our clock. The peer is not at fault.
+                    debug!(
+                        self.log, "ParentLookupAbandoned";
+                        "reason" => "chain segment did not import",
+                        "outcome" => format!("{:?}", outcome),
+                        "peer" => format!("{:?}", sender),
+                    );
+                    return;
+                }
+            }
+        }
+
+        info!(
+            self.log, "ParentLookupComplete";
+            "imported" => count,
+            "peer" => format!("{:?}", peer_id),
+        );
+    }
+
+    /// Abandons a lookup because its peer provided blocks which do not link to the chain.
+    fn fail(&mut self, index: usize, reason: &str, network: &mut NetworkContext) {
+        let lookup = self.lookups.remove(index);
+
+        warn!(
+            self.log, "ParentLookupFailed";
+            "reason" => reason,
+            "parent_root" => format!("{}", lookup.earliest().previous_block_root),
+            "peer" => format!("{:?}", lookup.peer_id),
+        );
+
+        network.report_peer(lookup.peer_id, PeerAction::InvalidBlock);
+    }
+
+    fn position(&self, peer_id: &PeerId, request_id: RequestId) -> Option<usize> {
+        self.lookups
+            .iter()
+            .position(|lookup| lookup.peer_id == *peer_id && lookup.request_id == request_id)
+    }
+
+    /// Requests the parent of `block`.
+    fn request_parent(
+        block: &BeaconBlock,
+        peer_id: PeerId,
+        network: &mut NetworkContext,
+    ) -> RequestId {
+        network.send_rpc_request(
+            peer_id,
+            RPCRequest::BeaconBlocksByRoot(BeaconBlocksByRootRequest {
+                roots: vec![block.previous_block_root],
+            }),
+        )
+    }
+}
+
+#[cfg(all(test, not(debug_assertions)))]
+mod tests {
+    use super::*;
+    use crate::service::{NetworkMessage, OutgoingMessage};
+    use beacon_chain::test_utils::{
+        AttestationStrategy, BeaconChainHarness, BlockStrategy, CommonTypes,
+    };
+    use crossbeam_channel::Receiver;
+    use eth2_libp2p::RPCEvent;
+    use lmd_ghost::ThreadSafeReducedTree;
+    use store::MemoryStore;
+    use types::{MinimalEthSpec, Slot};
+
+    type TestForkChoice = ThreadSafeReducedTree<MemoryStore, MinimalEthSpec>;
+    type TestHarness = BeaconChainHarness<TestForkChoice, MinimalEthSpec>;
+
+    const VALIDATOR_COUNT: usize = 16;
+
+    /// Lookups on a chain which is missing the latest blocks of the `source` chain.
+    struct Tester {
+        source: TestHarness,
+        chain: Arc<BeaconChain<CommonTypes<TestForkChoice, MinimalEthSpec>>>,
+        lookups: ParentLookups<CommonTypes<TestForkChoice, MinimalEthSpec>>,
+        network: NetworkContext,
+        network_recv: Receiver<NetworkMessage>,
+        peer_id: PeerId,
+    }
+
+    impl Tester {
+        /// Builds a `source` chain of `num_blocks`, skipping `skipped_slot`, of which only the
+        /// blocks up to `known_slot` are imported. `known_slot` must be before `skipped_slot`.
+        fn new(num_blocks: usize, skipped_slot: Slot, known_slot: Slot) -> Self {
+            let source = TestHarness::new(VALIDATOR_COUNT);
+            source.advance_slot();
+
+            source.extend_chain(
+                (skipped_slot.as_u64() - 1) as usize,
+                BlockStrategy::OnCanonicalHead,
+                AttestationStrategy::AllValidators,
+            );
+            source.extend_chain(
+                num_blocks - (skipped_slot.as_u64() - 1) as usize,
+                BlockStrategy::ForkCanonicalChainAt {
+                    previous_slot: skipped_slot - 1,
+                    first_slot: skipped_slot + 1,
+                },
+                AttestationStrategy::AllValidators,
+            );
+
+            let chain = TestHarness::new(VALIDATOR_COUNT).chain;
+            chain
+                .slot_clock
+                .set_slot(source.chain.head().beacon_block.slot.as_u64());
+
+            let log = slog::Logger::root(slog::Discard, o!());
+            let (network_send, network_recv) = crossbeam_channel::unbounded();
+
+            let tester = Self {
+                source,
+                lookups: ParentLookups::new(chain.clone(), &log),
+                chain,
+                network: NetworkContext::new(network_send, log),
+                network_recv,
+                peer_id: PeerId::random(),
+            };
+
+            for slot in 1..=known_slot.as_u64() {
+                let block = tester.block_at(Slot::new(slot)).unwrap();
+                let block_root = block.canonical_root();
+                assert_eq!(
+                    tester.chain.process_block(block),
+                    Ok(BlockProcessingOutcome::Processed { block_root })
+                );
+            }
+
+            tester
+        }
+
+        /// The block at `slot` in the `source` chain, if `slot` was not skipped.
+        fn block_at(&self, slot: Slot) -> Option<BeaconBlock> {
+            self.source
+                .chain
+                .block_at_slot(slot)
+                .unwrap()
+                .filter(|block| block.slot == slot)
+        }
+
+        fn is_known(&self, block: &BeaconBlock) -> bool {
+            self.chain
+                .get_block(&block.canonical_root())
+                .unwrap()
+                .is_some()
+        }
+
+        fn add_orphan(&mut self, slot: u64) -> BeaconBlock {
+            let block = self
+                .block_at(Slot::new(slot))
+                .expect("slot should not be skipped");
+            assert_eq!(
+                self.lookups
+                    .add_orphan(self.peer_id.clone(), block.clone(), &mut self.network),
+                None
+            );
+            block
+        }
+
+        fn respond(&mut self, request_id: RequestId, blocks: Vec<BeaconBlock>) {
+            assert!(self.lookups.is_lookup_request(&self.peer_id, request_id));
+            self.lookups.on_blocks_response(
+                self.peer_id.clone(),
+                request_id,
+                blocks,
+                &mut self.network,
+            );
+        }
+
+        /// Returns the requests sent and the peers reported since the last call.
+        fn sent(&self) -> (Vec<(RequestId, RPCRequest)>, Vec<PeerAction>) {
+            let mut requests = vec![];
+            let mut reports = vec![];
+
+            for message in self.network_recv.try_iter() {
+                match message {
+                    NetworkMessage::Send(
+                        peer_id,
+                        OutgoingMessage::RPC(RPCEvent::Request { id, body, .. }),
+                    ) => {
+                        assert_eq!(peer_id, self.peer_id);
+                        requests.push((id, body));
+                    }
+                    NetworkMessage::ReportPeer { peer_id, action } => {
+                        assert_eq!(peer_id, self.peer_id);
+                        reports.push(action);
+                    }
+                    _ => {}
+                }
+            }
+
+            (requests, reports)
+        }
+
+        fn assert_nothing_sent(&self) {
+            let (requests, reports) = self.sent();
+            assert!(requests.is_empty(), "unexpected requests {:?}", requests);
+            assert!(reports.is_empty(), "unexpected reports {:?}", reports);
+        }
+
+        /// Returns the id of the single request sent since the last call, which must be for the
+        /// block with `block_root`.
+        fn requested_root(&self, block_root: Hash256) -> RequestId {
+            let (requests, reports) = self.sent();
+            assert!(reports.is_empty(), "unexpected reports {:?}", reports);
+            assert_eq!(requests.len(), 1);
+
+            match &requests[0] {
+                (id, RPCRequest::BeaconBlocksByRoot(req)) => {
+                    assert_eq!(req.roots, vec![block_root]);
+                    *id
+                }
+                other => panic!("unexpected request {:?}", other),
+            }
+        }
+    }
+
+    #[test]
+    fn ancestors_are_requested_by_root_across_skipped_slots() {
+        // Slot 5 is skipped, so the parent of the block at slot 6 is at slot 4.
+        let mut tester = Tester::new(8, Slot::new(5), Slot::new(3));
+
+        let orphan = tester.add_orphan(6);
+        let parent = tester.block_at(Slot::new(4)).unwrap();
+        let request_id = tester.requested_root(parent.canonical_root());
+        assert_eq!(orphan.previous_block_root, parent.canonical_root());
+
+        tester.respond(request_id, vec![parent.clone()]);
+
+        tester.assert_nothing_sent();
+        assert!(tester.is_known(&parent));
+        assert!(tester.is_known(&orphan));
+        assert!(!tester
+            .lookups
+            .is_lookup_request(&tester.peer_id, request_id));
+    }
+
+    #[test]
+    fn lookup_continues_until_parent_is_known() {
+        let mut tester = Tester::new(8, Slot::new(8), Slot::new(2));
+
+        let orphan = tester.add_orphan(5);
+        for slot in (3..5).rev() {
+            let parent = tester.block_at(Slot::new(slot)).unwrap();
+            let request_id = tester.requested_root(parent.canonical_root());
+            assert!(!tester.is_known(&orphan));
+
+            tester.respond(request_id, vec![parent]);
+        }
+
+        assert!(tester.is_known(&orphan));
+    }
+
+    #[test]
+    fn unlinked_parent_is_penalised() {
+        let mut tester = Tester::new(8, Slot::new(8), Slot::new(2));
+
+        let orphan = tester.add_orphan(5);
+        let request_id = tester.requested_root(orphan.previous_block_root);
+
+        let unrelated = tester.block_at(Slot::new(3)).unwrap();
+        tester.respond(request_id, vec![unrelated]);
+
+        let (requests, reports) = tester.sent();
+        assert!(requests.is_empty());
+        assert_eq!(reports, vec![PeerAction::InvalidBlock]);
+        assert!(!tester
+            .lookups
+            .is_lookup_request(&tester.peer_id, request_id));
+        assert!(!tester.is_known(&orphan));
+    }
+
+    #[test]
+    fn future_segment_is_not_penalised() {
+        let mut tester = Tester::new(8, Slot::new(8), Slot::new(2));
+
+        let orphan = tester.add_orphan(5);
+        // The orphan is valid, but our clock has not yet reached its slot.
+        tester.chain.slot_clock.set_slot(4);
+
+        let parent = tester.block_at(Slot::new(4)).unwrap();
+        let request_id = tester.requested_root(parent.canonical_root());
+        tester.respond(request_id, vec![parent.clone()]);
+
+        let grandparent = tester.block_at(Slot::new(3)).unwrap();
+        let request_id = tester.requested_root(grandparent.canonical_root());
+        tester.respond(request_id, vec![grandparent]);
+
+        tester.assert_nothing_sent();
+        assert!(tester.is_known(&parent));
+        assert!(!tester.is_known(&orphan));
+        assert_eq!(tester.lookups.lookups.len(), 0);
+    }
+
+    #[test]
+    fn missing_parent_is_not_penalised() {
+        let mut tester = Tester::new(8, Slot::new(8), Slot::new(2));
+
+        let orphan = tester.add_orphan(5);
+        let request_id = tester.requested_root(orphan.previous_block_root);
+        tester.respond(request_id, vec![]);
+
+        tester.assert_nothing_sent();
+        assert!(!tester
+            .lookups
+            .is_lookup_request(&tester.peer_id, request_id));
+    }
+
+    #[test]
+    fn duplicate_orphans_share_a_lookup() {
+        let mut tester = Tester::new(8, Slot::new(8), Slot::new(2));
+
+        let orphan = tester.add_orphan(5);
+        tester.requested_root(orphan.previous_block_root);
+
+        // The orphan itself, and its parent, are already being looked up.
+        tester.add_orphan(5);
+        tester.add_orphan(4);
+        tester.assert_nothing_sent();
+    }
+
+    #[test]
+    fn descendant_orphan_joins_lookup() {
+        let mut tester = Tester::new(8, Slot::new(8), Slot::new(2));
+
+        let orphan = tester.add_orphan(4);
+        let request_id = tester.requested_root(orphan.previous_block_root);
+
+        // The children of the orphan are imported with it, without further requests.
+        let child = tester.add_orphan(5);
+        let grandchild = tester.add_orphan(6);
+        tester.assert_nothing_sent();
+
+        let parent = tester.block_at(Slot::new(3)).unwrap();
+        tester.respond(request_id, vec![parent]);
+
+        tester.assert_nothing_sent();
+        assert!(tester.is_known(&orphan));
+        assert!(tester.is_known(&child));
+        assert!(tester.is_known(&grandchild));
+        assert_eq!(tester.lookups.lookups.len(), 0);
+    }
+
+    #[test]
+    fn orphan_is_returned_when_lookups_are_full() {
+        let mut tester = Tester::new(8, Slot::new(8), Slot::new(2));
+
+        for i in 0..MAX_PARENT_LOOKUPS {
+            let mut block = BeaconBlock::empty(&tester.chain.spec);
+            block.previous_block_root = Hash256::from(i as u64 + 1);
+            tester.lookups.lookups.push(ParentLookup {
+                peer_id: tester.peer_id.clone(),
+                request_id: RequestId::from(i as u64),
+                requested: Instant::now(),
+                blocks: vec![block],
+                children: vec![],
+            });
+        }
+
+        let orphan = tester.block_at(Slot::new(5)).unwrap();
+        assert_eq!(
+            tester
+                .lookups
+                .add_orphan(tester.peer_id.clone(), orphan.clone(), &mut tester.network),
+            Some(orphan)
+        );
+        tester.assert_nothing_sent();
+        assert_eq!(tester.lookups.lookups.len(), MAX_PARENT_LOOKUPS);
+    }
+
+    #[test]
+    fn timed_out_lookup_is_abandoned() {
+        let mut tester = Tester::new(8, Slot::new(8), Slot::new(2));
+
+        let orphan = tester.add_orphan(5);
+        let request_id = tester.requested_root(orphan.previous_block_root);
+
+        // Nothing has timed out yet.
+        tester
+            .lookups
+            .expire_lookups(Instant::now(), &mut tester.network);
+        assert!(tester
+            .lookups
+            .is_lookup_request(&tester.peer_id, request_id));
+
+        tester.lookups.expire_lookups(
+            Instant::now() + PARENT_REQUEST_TIMEOUT * 2,
+            &mut tester.network,
+        );
+        let (requests, reports) = tester.sent();
+        assert!(requests.is_empty());
+        assert_eq!(reports, vec![PeerAction::RPCTimeout]);
+        assert!(!tester
+            .lookups
+            .is_lookup_request(&tester.peer_id, request_id));
+    }
+
+    #[test]
+    fn deep_lookup_requests_hello() {
+        let num_blocks = MAX_PARENT_DEPTH + 4;
+        let mut tester = Tester::new(num_blocks, Slot::new(num_blocks as u64), Slot::new(1));
+
+        // The orphan is at slot `MAX_PARENT_DEPTH + 3`, so its ancestors after slot 1 are one more
+        // than the limit.
+        let mut slot = num_blocks as u64 - 1;
+        tester.add_orphan(slot);
+
+        loop {
+            let (mut requests, reports) = tester.sent();
+            assert!(reports.is_empty());
+            assert_eq!(requests.len(), 1);
+
+            match requests.remove(0) {
+                (request_id, RPCRequest::BeaconBlocksByRoot(_)) => {
+                    slot -= 1;
+                    let parent = tester.block_at(Slot::new(slot)).unwrap();
+                    tester.respond(request_id, vec![parent]);
+                }
+                (_, RPCRequest::Hello(_)) => break,
+                other => panic!("unexpected request {:?}", other),
+            }
+        }
+
+        assert!(
+            slot > 1,
+            "the lookup should stop before reaching a known block"
+        );
+        assert_eq!(tester.lookups.lookups.len(), 0);
+    }
+}
@@ -0,0 +1,739 @@
+use crate::message_handler::NetworkContext;
+use beacon_chain::{BeaconChain, BeaconChainTypes, BlockProcessingOutcome};
+use eth2_libp2p::rpc::methods::*;
+use eth2_libp2p::rpc::{RPCRequest, RequestId};
+use eth2_libp2p::{PeerAction, PeerId};
+use slog::{debug, error, info, o, warn};
+use std::collections::{BTreeMap, HashMap, HashSet};
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+use types::{BeaconBlock, Epoch, EthSpec, Hash256, Slot};
+
+/// The maximum number of batches which may be held at once (i.e., downloading or awaiting
+/// import). Bounds the memory used when an early batch is slow to download.
+const MAX_BATCHES: usize = 8;
+
+/// The number of distinct peers which may fail to provide a batch before the sync is abandoned.
+const MAX_BATCH_ATTEMPTS: usize = 5;
+
+/// A batch which has been requested for longer than this is requested from another peer.
+const BATCH_TIMEOUT: Duration = Duration::from_secs(30);
+
+/// The number of distinct peers which must return an empty batch before the batch is accepted.
+///
+/// A peer is only asked for a batch if it claims a head at or beyond the batch, so the batch may
+/// only be empty if every slot in it was skipped.
+const EMPTY_BATCH_CONFIRMATIONS: usize = 2;
+
+/// The progress of a `RangeSync`.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum SyncState {
+    /// No peer has blocks that we are missing. New blocks are received via gossip.
+    Idle,
+    /// Blocks are being downloaded from peers and imported.
+    Syncing {
+        /// The slot from which the sync began.
+        start_slot: Slot,
+        /// All blocks up to and including this slot have been imported.
+        imported_slot: Slot,
+        /// The highest best slot of the peers being synced from.
+        target_slot: Slot,
+    },
+}
+
+/// The download progress of a `Batch`.
+enum BatchState {
+    /// Waiting for an idle peer to download from.
+    Pending,
+    /// The blocks in the batch have been requested from `peer_id`.
+    Awaiting {
+        peer_id: PeerId,
+        request_id: RequestId,
+        requested: Instant,
+    },
+    /// All blocks in the batch have been downloaded from `peer_id` and are awaiting import.
+    Downloaded {
+        peer_id: PeerId,
+        blocks: Vec<BeaconBlock>,
+    },
+}
+
+/// The blocks of a single epoch, downloaded from a single peer.
+struct Batch {
+    epoch: Epoch,
+    state: BatchState,
+    /// Peers which failed to provide a valid batch and will not be asked again.
+    failed_peers: HashSet<PeerId>,
+    /// The number of peers which have returned no blocks for the batch.
+    empty_responses: usize,
+}
+
+impl Batch {
+    fn new(epoch: Epoch) -> Self {
+        Self {
+            epoch,
+            state: BatchState::Pending,
+            failed_peers: HashSet::new(),
+            empty_responses: 0,
+        }
+    }
+
+    /// The peer and request the batch is waiting upon, if any.
+    fn request(&self) -> Option<(&PeerId, RequestId, Instant)> {
+        match &self.state {
+            BatchState::Awaiting {
+                peer_id,
+                request_id,
+                requested,
+            } => Some((peer_id, *request_id, *requested)),
+            _ => None,
+        }
+    }
+}
+
+/// Syncs a node which is behind its peers by downloading the missing range of slots in
+/// epoch-sized batches.
+///
+/// Batches are downloaded in parallel, one per peer, from any peer whose best slot reaches the
+/// batch. A batch that cannot be downloaded, or whose blocks fail to import, is requested again
+/// from a different peer. Batches are imported in order, as soon as all prior batches have been
+/// imported, and the first block of each batch must be the child of the last imported block.
+///
+/// Requests which are not answered within `BATCH_TIMEOUT` are expired by `Self::on_tick`.
+pub struct RangeSync<T: BeaconChainTypes> {
+    chain: Arc<BeaconChain<T>>,
+    /// The best slot of each peer which has blocks we are missing.
+    peers: HashMap<PeerId, Slot>,
+    /// Batches which are yet to be imported, keyed by epoch.
+    batches: BTreeMap<Epoch, Batch>,
+    /// The epoch of the next batch to be created.
+    next_epoch: Epoch,
+    /// The root of the last block imported by the sync, if any.
+    last_imported_root: Option<Hash256>,
+    state: SyncState,
+    log: slog::Logger,
+}
+
+impl<T: BeaconChainTypes> RangeSync<T> {
+    pub fn new(chain: Arc<BeaconChain<T>>, log: &slog::Logger) -> Self {
+        Self {
+            chain,
+            peers: HashMap::new(),
+            batches: BTreeMap::new(),
+            next_epoch: Epoch::new(0),
+            last_imported_root: None,
+            state: SyncState::Idle,
+            log: log.new(o!("Service" => "RangeSync")),
+        }
+    }
+
+    pub fn state(&self) -> SyncState {
+        self.state
+    }
+
+    /// Adds a peer whose head is at `best_slot` and which has blocks we do not, starting a sync if
+    /// one is not already running.
+    pub fn add_peer(&mut self, peer_id: PeerId, best_slot: Slot, network: &mut NetworkContext) {
+        match &mut self.state {
+            SyncState::Idle => {
+                // Start from our finalized epoch, as all blocks after it may be re-orged.
+                let start_epoch = self.chain.head().beacon_state.finalized_epoch;
+                let start_slot = start_epoch.start_slot(T::EthSpec::slots_per_epoch());
+
+                info!(
+                    self.log, "SyncStarted";
+                    "start_slot" => start_slot,
+                    "target_slot" => best_slot,
+                );
+
+                self.next_epoch = start_epoch;
+                self.state = SyncState::Syncing {
+                    start_slot,
+                    imported_slot: start_slot,
+                    target_slot: best_slot,
+                };
+            }
+            SyncState::Syncing { target_slot, .. } => {
+                if best_slot > *target_slot {
+                    *target_slot = best_slot;
+                }
+            }
+        }
+
+        debug!(
+            self.log, "SyncPeerAdded";
+            "peer" => format!("{:?}", peer_id),
+            "best_slot" => best_slot,
+        );
+
+        self.peers.insert(peer_id, best_slot);
+        self.request_batches(network);
+    }
+
+    /// Removes a peer (e.g., because it disconnected), requesting its batches from other peers.
+    pub fn remove_peer(&mut self, peer_id: &PeerId, network: &mut NetworkContext) {
+        if self.peers.remove(peer_id).is_none() {
+            return;
+        }
+
+        for batch in self.batches.values_mut() {
+            if batch.request().map(|(peer, _, _)| peer) == Some(peer_id) {
+                batch.state = BatchState::Pending;
+            }
+        }
+
+        self.request_batches(network);
+    }
+
+    /// Requests batches which have timed out from other peers.
+    pub fn on_tick(&mut self, network: &mut NetworkContext) {
+        self.expire_requests(Instant::now(), network);
+    }
+
+    /// Fails each batch which was requested more than `BATCH_TIMEOUT` before `now`.
+    fn expire_requests(&mut self, now: Instant, network: &mut NetworkContext) {
+        let expired: Vec<Epoch> = self
+            .batches
+            .values()
+            .filter(|batch| {
+                batch.request().map_or(false, |(_, _, requested)| {
+                    now.duration_since(requested) > BATCH_TIMEOUT
+                })
+            })
+            .map(|batch| batch.epoch)
+            .collect();
+
+        for epoch in expired {
+            self.fail_batch(
+                epoch,
+                "request timed out",
+                Some(PeerAction::RPCTimeout),
+                network,
+            );
+        }
+    }
+
+    /// Returns `true` if the response to `request_id` from `peer_id` belongs to a batch.
+    pub fn is_batch_request(&self, peer_id: &PeerId, request_id: RequestId) -> bool {
+        self.batch_for_request(peer_id, request_id).is_some()
+    }
+
+    /// Handles the blocks for a batch, importing the batch if all prior batches are imported.
+    pub fn on_blocks_response(
+        &mut self,
+        peer_id: PeerId,
+        request_id: RequestId,
+        mut blocks: Vec<BeaconBlock>,
+        network: &mut NetworkContext,
+    ) {
+        let epoch = match self.batch_for_request(&peer_id, request_id) {
+            Some(epoch) => epoch,
+            None => return,
+        };
+        let slots_per_epoch = T::EthSpec::slots_per_epoch();
+
+        blocks.retain(|block| block.slot.epoch(slots_per_epoch) == epoch);
+        blocks.sort_by_key(|block| block.slot);
+        blocks.dedup_by_key(|block| block.slot);
+
+        // Each block must be the parent of the next.
+        let links = blocks
+            .windows(2)
+            .all(|pair| pair[1].previous_block_root == pair[0].canonical_root());
+        if !links {
+            return self.fail_batch(
+                epoch,
+                "blocks do not form a chain",
+                Some(PeerAction::InvalidBlock),
+                network,
+            );
+        }
+
+        let batch = self
+            .batches
+            .get_mut(&epoch)
+            .expect("batch exists for request");
+
+        if blocks.is_empty() {
+            batch.empty_responses += 1;
+
+            // Another peer must confirm that every slot was skipped, but the peer may be honest so
+            // it is not penalised.
+            if batch.empty_responses < EMPTY_BATCH_CONFIRMATIONS {
+                return self.fail_batch(epoch, "empty batch", None, network);
+            }
+        }
+
+        batch.state = BatchState::Downloaded { peer_id, blocks };
+        self.import_batches(network);
+    }
+
+    /// Returns the epoch of the batch awaiting `request_id` from `peer_id`, if any.
+    fn batch_for_request(&self, peer_id: &PeerId, request_id: RequestId) -> Option<Epoch> {
+        self.batches
+            .values()
+            .find(|batch| {
+                batch.request().map(|(peer, id, _)| (peer, id)) == Some((peer_id, request_id))
+            })
+            .map(|batch| batch.epoch)
+    }
+
+    /// Marks the peer which provided the batch as faulty, reporting it for `action` (if any), and
+    /// requests the batch from another peer.
+    ///
+    /// Abandons the sync if too many peers have failed to provide the batch.
+    fn fail_batch(
+        &mut self,
+        epoch: Epoch,
+        reason: &str,
+        action: Option<PeerAction>,
+        network: &mut NetworkContext,
+    ) {
+        let batch = match self.batches.get_mut(&epoch) {
+            Some(batch) => batch,
+            None => return,
+        };
+
+        let peer_id = match &batch.state {
+            BatchState::Downloaded { peer_id, .. } => Some(peer_id.clone()),
+            _ => batch.request().map(|(peer_id, _, _)| peer_id.clone()),
+        };
+
+        warn!(
+            self.log, "BatchFailed";
+            "epoch" => epoch,
+            "reason" => reason,
+            "peer" => format!("{:?}", peer_id),
+        );
+
+        if let Some(peer_id) = peer_id {
+            if let Some(action) = action {
+                network.report_peer(peer_id.clone(), action);
+            }
+            batch.failed_peers.insert(peer_id);
+        }
+        batch.state = BatchState::Pending;
+
+        if batch.failed_peers.len() >= MAX_BATCH_ATTEMPTS {
+            error!(
+                self.log, "SyncFailed";
+                "msg" => "too many peers failed to provide a batch",
+                "epoch" => epoch,
+            );
+            self.reset();
+        } else {
+            self.request_batches(network);
+        }
+    }
+
+    /// Creates batches up to the target slot and assigns pending batches to idle peers.
+    fn request_batches(&mut self, network: &mut NetworkContext) {
+        let target_slot = match self.state {
+            SyncState::Syncing { target_slot, .. } => target_slot,
+            SyncState::Idle => return,
+        };
+        let slots_per_epoch = T::EthSpec::slots_per_epoch();
+
+        while self.batches.len() < MAX_BATCHES
+            && self.next_epoch <= target_slot.epoch(slots_per_epoch)
+        {
+            self.batches
+                .insert(self.next_epoch, Batch::new(self.next_epoch));
+            self.next_epoch += 1;
+        }
+
+        let mut busy_peers: HashSet<PeerId> = self
+            .batches
+            .values()
+            .filter_map(|batch| batch.request().map(|(peer_id, _, _)| peer_id.clone()))
+            .collect();
+
+        for batch in self.batches.values_mut() {
+            match batch.state {
+                BatchState::Pending => {}
+                _ => continue,
+            }
+
+            let start_slot = batch.epoch.start_slot(slots_per_epoch);
+            let peer_id = match self
+                .peers
+                .iter()
+                .find(|(peer_id, best_slot)| {
+                    **best_slot >= start_slot
+                        && !busy_peers.contains(peer_id)
+                        && !batch.failed_peers.contains(peer_id)
+                })
+                .map(|(peer_id, _)| peer_id.clone())
+            {
+                Some(peer_id) => peer_id,
+                None => continue,
+            };
+
+            debug!(
+                self.log, "BatchRequested";
+                "epoch" => batch.epoch,
+                "peer" => format!("{:?}", peer_id),
+            );
+
+            let request_id = network.send_rpc_request(
+                peer_id.clone(),
+                RPCRequest::BeaconBlocksByRange(BeaconBlocksByRangeRequest {
+                    start_slot,
+                    count: slots_per_epoch,
+                    step: 1,
+                }),
+            );
+
+            busy_peers.insert(peer_id.clone());
+            batch.state = BatchState::Awaiting {
+                peer_id,
+                request_id,
+                requested: Instant::now(),
+            };
+        }
+    }
+
+    /// Imports each downloaded batch which directly follows the last imported batch.
+    fn import_batches(&mut self, network: &mut NetworkContext) {
+        let slots_per_epoch = T::EthSpec::slots_per_epoch();
+
+        while let Some(epoch) = self.batches.keys().next().cloned() {
+            let blocks = match &self.batches[&epoch].state {
+                BatchState::Downloaded { blocks, .. } => blocks,
+                // The next batch to import is still downloading.
+                _ => break,
+            };
+
+            // The batch must continue the chain of the previous batch.
+            let links = match (self.last_imported_root, blocks.first()) {
+                (Some(root), Some(first)) => first.previous_block_root == root,
+                _ => true,
+            };
+            if !links {
+                return self.fail_batch(
+                    epoch,
+                    "batch does not extend the previous batch",
+                    Some(PeerAction::InvalidBlock),
+                    network,
+                );
+            }
+
+            let invalid = blocks
+                .iter()
+                .filter_map(|block| match self.chain.process_block(block.clone()) {
+                    Ok(BlockProcessingOutcome::Processed { .. })
+                    | Ok(BlockProcessingOutcome::BlockIsAlreadyKnown)
+                    | Ok(BlockProcessingOutcome::FinalizedSlot) => None,
+                    outcome => Some(format!("{:?}", outcome)),
+                })
+                .next();
+
+            if let Some(outcome) = invalid {
+                debug!(self.log, "InvalidBatch"; "epoch" => epoch, "outcome" => outcome);
+                return self.fail_batch(
+                    epoch,
+                    "invalid block",
+                    Some(PeerAction::InvalidBlock),
+                    network,
+                );
+            }
+
+            if let Some(last) = blocks.last() {
+                self.last_imported_root = Some(last.canonical_root());
+            }
+            self.batches.remove(&epoch);
+
+            if let SyncState::Syncing {
+                imported_slot,
+                target_slot,
+                ..
+            } = &mut self.state
+            {
+                *imported_slot = (epoch + 1).start_slot(slots_per_epoch) - 1;
+
+                info!(
+                    self.log, "SyncProgress";
+                    "imported_slot" => *imported_slot,
+                    "target_slot" => *target_slot,
+                );
+
+                if *imported_slot >= *target_slot {
+                    info!(self.log, "SyncComplete"; "head_slot" => self.chain.head().beacon_block.slot);
+                    return self.reset();
+                }
+            }
+        }
+
+        self.request_batches(network);
+    }
+
+    /// Ends the sync, forgetting all peers and batches.
+    fn reset(&mut self) {
+        self.peers.clear();
+        self.batches.clear();
+        self.last_imported_root = None;
+        self.state = SyncState::Idle;
+    }
+}
+
+#[cfg(all(test, not(debug_assertions)))]
+mod tests {
+    use super::*;
+    use crate::service::{NetworkMessage, OutgoingMessage};
+    use beacon_chain::test_utils::{
+        AttestationStrategy, BeaconChainHarness, BlockStrategy, CommonTypes,
+    };
+    use crossbeam_channel::Receiver;
+    use eth2_libp2p::RPCEvent;
+    use lmd_ghost::ThreadSafeReducedTree;
+    use store::MemoryStore;
+    use types::MinimalEthSpec;
+
+    type TestForkChoice = ThreadSafeReducedTree<MemoryStore, MinimalEthSpec>;
+    type TestHarness = BeaconChainHarness<TestForkChoice, MinimalEthSpec>;
+
+    const VALIDATOR_COUNT: usize = 16;
+
+    /// A `RangeSync` on a fresh chain, syncing from peers which serve the `source` chain.
+    struct Tester {
+        source: TestHarness,
+        chain: Arc<BeaconChain<CommonTypes<TestForkChoice, MinimalEthSpec>>>,
+        sync: RangeSync<CommonTypes<TestForkChoice, MinimalEthSpec>>,
+        network: NetworkContext,
+        network_recv: Receiver<NetworkMessage>,
+    }
+
+    impl Tester {
+        /// Builds a `source` chain of `num_epochs` full epochs.
+        fn new(num_epochs: u64) -> Self {
+            let slots_per_epoch = MinimalEthSpec::slots_per_epoch();
+
+            let source = TestHarness::new(VALIDATOR_COUNT);
+            source.advance_slot();
+            source.extend_chain(
+                (num_epochs * slots_per_epoch) as usize,
+                BlockStrategy::OnCanonicalHead,
+                AttestationStrategy::AllValidators,
+            );
+
+            let chain = TestHarness::new(VALIDATOR_COUNT).chain;
+            chain
+                .slot_clock
+                .set_slot(source.chain.head().beacon_block.slot.as_u64());
+
+            let log = slog::Logger::root(slog::Discard, o!());
+            let (network_send, network_recv) = crossbeam_channel::unbounded();
+
+            Self {
+                source,
+                sync: RangeSync::new(chain.clone(), &log),
+                chain,
+                network: NetworkContext::new(network_send, log),
+                network_recv,
+            }
+        }
+
+        /// Adds a peer whose head is the head of the `source` chain.
+        fn add_peer(&mut self, peer_id: &PeerId) {
+            let best_slot = self.source.chain.head().beacon_block.slot;
+            self.sync
+                .add_peer(peer_id.clone(), best_slot, &mut self.network);
+        }
+
+        /// The blocks of `epoch` in the `source` chain.
+        fn blocks(&self, epoch: u64) -> Vec<BeaconBlock> {
+            let slots_per_epoch = MinimalEthSpec::slots_per_epoch();
+
+            (epoch * slots_per_epoch..(epoch + 1) * slots_per_epoch)
+                .map(Slot::new)
+                .filter_map(|slot| {
+                    self.source
+                        .chain
+                        .block_at_slot(slot)
+                        .unwrap()
+                        .filter(|block| block.slot == slot)
+                })
+                .collect()
+        }
+
+        fn respond(&mut self, peer_id: &PeerId, request_id: RequestId, blocks: Vec<BeaconBlock>) {
+            assert!(self.sync.is_batch_request(peer_id, request_id));
+            self.sync
+                .on_blocks_response(peer_id.clone(), request_id, blocks, &mut self.network);
+        }
+
+        /// Returns the batches requested and the peers reported since the last call.
+        fn sent(&self) -> (Vec<(PeerId, RequestId, Epoch)>, Vec<(PeerId, PeerAction)>) {
+            let mut requests = vec![];
+            let mut reports = vec![];
+
+            for message in self.network_recv.try_iter() {
+                match message {
+                    NetworkMessage::Send(
+                        peer_id,
+                        OutgoingMessage::RPC(RPCEvent::Request {
+                            id,
+                            body: RPCRequest::BeaconBlocksByRange(req),
+                            ..
+                        }),
+                    ) => {
+                        let epoch = req.start_slot.epoch(MinimalEthSpec::slots_per_epoch());
+                        requests.push((peer_id, id, epoch));
+                    }
+                    NetworkMessage::ReportPeer { peer_id, action } => {
+                        reports.push((peer_id, action))
+                    }
+                    _ => {}
+                }
+            }
+
+            (requests, reports)
+        }
+
+        fn imported_slot(&self) -> Slot {
+            match self.sync.state() {
+                SyncState::Syncing { imported_slot, .. } => imported_slot,
+                SyncState::Idle => panic!("sync should be running"),
+            }
+        }
+    }
+
+    #[test]
+    fn batches_are_imported_in_order() {
+        let mut tester = Tester::new(4);
+        let (a, b) = (PeerId::random(), PeerId::random());
+
+        tester.add_peer(&a);
+        tester.add_peer(&b);
+        let (requests, _) = tester.sent();
+        assert_eq!(requests.len(), 2);
+        assert_eq!(requests[0].2, Epoch::new(0));
+        assert_eq!(requests[1].2, Epoch::new(1));
+
+        // The second batch must wait for the first.
+        let second = tester.blocks(1);
+        tester.respond(&requests[1].0, requests[1].1, second.clone());
+        assert_eq!(tester.imported_slot(), Slot::new(0));
+        assert_eq!(
+            tester.chain.get_block(&second[0].canonical_root()),
+            Ok(None)
+        );
+
+        tester.respond(&requests[0].0, requests[0].1, tester.blocks(0));
+        assert_eq!(tester.imported_slot(), Slot::new(15));
+        assert!(tester
+            .chain
+            .get_block(&second[0].canonical_root())
+            .unwrap()
+            .is_some());
+
+        // Both peers are free to download the next batches.
+        let (requests, _) = tester.sent();
+        let mut epochs: Vec<_> = requests.iter().map(|(_, _, epoch)| *epoch).collect();
+        epochs.sort();
+        assert_eq!(epochs, vec![Epoch::new(2), Epoch::new(3)]);
+    }
+
+    #[test]
+    fn timed_out_batch_is_requested_from_another_peer() {
+        let mut tester = Tester::new(2);
+        let (a, b) = (PeerId::random(), PeerId::random());
+
+        tester.add_peer(&a);
+        let (requests, _) = tester.sent();
+        assert_eq!(requests.len(), 1);
+        let timed_out = requests[0].1;
+
+        // Nothing has timed out yet.
+        tester
+            .sync
+            .expire_requests(Instant::now(), &mut tester.network);
+        assert_eq!(tester.sent(), (vec![], vec![]));
+
+        tester
+            .sync
+            .expire_requests(Instant::now() + BATCH_TIMEOUT * 2, &mut tester.network);
+        let (requests, reports) = tester.sent();
+        assert_eq!(reports, vec![(a.clone(), PeerAction::RPCTimeout)]);
+        assert!(!tester.sync.is_batch_request(&a, timed_out));
+
+        // The peer is not asked for the same batch again, but it may download another.
+        assert_eq!(requests.len(), 1);
+        assert_eq!((&requests[0].0, requests[0].2), (&a, Epoch::new(1)));
+
+        tester.add_peer(&b);
+        let (requests, _) = tester.sent();
+        assert_eq!(requests.len(), 1);
+        assert_eq!((&requests[0].0, requests[0].2), (&b, Epoch::new(0)));
+    }
+
+    #[test]
+    fn removed_peer_batches_are_requested_from_another_peer() {
+        let mut tester = Tester::new(2);
+        let (a, b) = (PeerId::random(), PeerId::random());
+
+        tester.add_peer(&a);
+        let (requests, _) = tester.sent();
+        let removed = requests[0].1;
+
+        tester.sync.remove_peer(&a, &mut tester.network);
+        assert!(!tester.sync.is_batch_request(&a, removed));
+        let (requests, reports) = tester.sent();
+        assert!(requests.is_empty());
+        assert!(reports.is_empty(), "disconnecting should not be penalised");
+
+        tester.add_peer(&b);
+        let (requests, _) = tester.sent();
+        assert_eq!(requests.len(), 1);
+        assert_eq!((&requests[0].0, requests[0].2), (&b, Epoch::new(0)));
+    }
+
+    #[test]
+    fn batch_must_extend_previous_batch() {
+        let mut tester = Tester::new(4);
+        let (a, b) = (PeerId::random(), PeerId::random());
+
+        tester.add_peer(&a);
+        tester.add_peer(&b);
+        let (requests, _) = tester.sent();
+
+        // Omit the first block of the second batch, so that it does not extend the first.
+        let second = tester.blocks(1)[1..].to_vec();
+        tester.respond(&requests[1].0, requests[1].1, second.clone());
+        tester.respond(&requests[0].0, requests[0].1, tester.blocks(0));
+
+        let (_, reports) = tester.sent();
+        assert_eq!(reports, vec![(b.clone(), PeerAction::InvalidBlock)]);
+        assert_eq!(tester.imported_slot(), Slot::new(7));
+        assert_eq!(
+            tester.chain.get_block(&second[0].canonical_root()),
+            Ok(None)
+        );
+    }
+
+    #[test]
+    fn empty_batch_must_be_confirmed() {
+        let mut tester = Tester::new(2);
+        let (a, b) = (PeerId::random(), PeerId::random());
+
+        tester.add_peer(&a);
+        let (requests, _) = tester.sent();
+        tester.respond(&a, requests[0].1, vec![]);
+
+        let (requests, reports) = tester.sent();
+        assert!(reports.is_empty(), "an empty batch should not be penalised");
+        assert_eq!(tester.imported_slot(), Slot::new(0));
+
+        // The peer is not asked for the same batch again, but it may download another.
+        assert_eq!(requests.len(), 1);
+        assert_eq!((&requests[0].0, requests[0].2), (&a, Epoch::new(1)));
+
+        tester.add_peer(&b);
+        let (requests, _) = tester.sent();
+        assert_eq!((&requests[0].0, requests[0].2), (&b, Epoch::new(0)));
+
+        // A second peer confirms that the batch is empty.
+        tester.respond(&b, requests[0].1, vec![]);
+        assert_eq!(tester.imported_slot(), Slot::new(7));
+    }
+}
@@ -1,21 +1,24 @@
 use super::import_queue::ImportQueue;
+use super::parent_lookup::ParentLookups;
+use super::range_sync::{RangeSync, SyncState};
 use crate::message_handler::NetworkContext;
-use beacon_chain::{BeaconChain, BeaconChainTypes, BlockProcessingOutcome};
+use beacon_chain::{
+    AttestationInvalid, AttestationValidationError, BeaconChain, BeaconChainTypes,
+    BlockProcessingOutcome,
+};
 use eth2_libp2p::rpc::methods::*;
 use eth2_libp2p::rpc::{RPCRequest, RPCResponse, RequestId};
-use eth2_libp2p::PeerId;
-use slog::{debug, error, info, o, trace, warn};
+use eth2_libp2p::{PeerAction, PeerId};
+use slog::{debug, error, info, o, warn};
 use std::collections::HashMap;
 use std::sync::Arc;
 use std::time::Duration;
 use store::Store;
 use types::{
-    Attestation, BeaconBlock, BeaconBlockBody, BeaconBlockHeader, Epoch, EthSpec, Hash256, Slot,
+    Attestation, AttesterSlashing, BeaconBlock, BeaconBlockBody, BeaconBlockHeader, Epoch, EthSpec,
+    Hash256, ProposerSlashing, Slot, Transfer, VoluntaryExit,
 };
 
-/// The number of slots that we can import blocks ahead of us, before going into full Sync mode.
-const SLOT_IMPORT_TOLERANCE: u64 = 100;
-
 /// The amount of seconds a block (or partial block) may exist in the import queue.
 const QUEUE_STALE_SECS: u64 = 6;
 
@@ -54,14 +57,6 @@ impl<T: BeaconChainTypes> From<&Arc<BeaconChain<T>>> for PeerSyncInfo {
     }
 }
 
-/// The current syncing state.
-#[derive(PartialEq)]
-pub enum SyncState {
-    Idle,
-    Downloading,
-    _Stopped,
-}
-
 /// Simple Syncing protocol.
 pub struct SimpleSync<T: BeaconChainTypes> {
     /// A reference to the underlying beacon chain.
@@ -70,8 +65,10 @@ pub struct SimpleSync<T: BeaconChainTypes> {
     known_peers: HashMap<PeerId, PeerSyncInfo>,
     /// A queue to allow importing of blocks
     import_queue: ImportQueue<T>,
-    /// The current state of the syncing protocol.
-    state: SyncState,
+    /// Downloads blocks in batches when we are behind our peers.
+    range_sync: RangeSync<T>,
+    /// Downloads the ancestors of gossiped blocks whose parent is unknown.
+    parent_lookups: ParentLookups<T>,
     log: slog::Logger,
 }
 
@@ -88,15 +85,26 @@ impl<T: BeaconChainTypes> SimpleSync<T> {
             chain: beacon_chain.clone(),
             known_peers: HashMap::new(),
             import_queue,
-            state: SyncState::Idle,
+            range_sync: RangeSync::new(beacon_chain.clone(), log),
+            parent_lookups: ParentLookups::new(beacon_chain.clone(), log),
             log: sync_logger,
         }
     }
 
+    /// Returns the progress of syncing from our peers.
+    pub fn sync_state(&self) -> SyncState {
+        self.range_sync.state()
+    }
+
     /// Handle a `Goodbye` message from a peer.
     ///
-    /// Removes the peer from `known_peers`.
-    pub fn on_goodbye(&mut self, peer_id: PeerId, reason: GoodbyeReason) {
+    /// Removes the peer from `known_peers` and the range sync.
+    pub fn on_goodbye(
+        &mut self,
+        peer_id: PeerId,
+        reason: GoodbyeReason,
+        network: &mut NetworkContext,
+    ) {
         info!(
             self.log, "PeerGoodbye";
             "peer" => format!("{:?}", peer_id),
@@ -104,6 +112,23 @@ impl<T: BeaconChainTypes> SimpleSync<T> {
         );
 
         self.known_peers.remove(&peer_id);
+        self.range_sync.remove_peer(&peer_id, network);
+    }
+
+    /// Handle the disconnection of a peer.
+    ///
+    /// Removes the peer from `known_peers` and the range sync.
+    pub fn on_disconnect(&mut self, peer_id: PeerId, network: &mut NetworkContext) {
+        info!(self.log, "PeerDisconnected"; "peer" => format!("{:?}", peer_id));
+
+        self.known_peers.remove(&peer_id);
+        self.range_sync.remove_peer(&peer_id, network);
+    }
+
+    /// Called every few seconds, to expire requests which have not been answered.
+    pub fn on_tick(&mut self, network: &mut NetworkContext) {
+        self.range_sync.on_tick(network);
+        self.parent_lookups.on_tick(network);
     }
 
     /// Handle the connection of a new peer.
@@ -164,6 +189,22 @@ impl<T: BeaconChainTypes> SimpleSync<T> {
 
         let start_slot = |epoch: Epoch| epoch.start_slot(T::EthSpec::slots_per_epoch());
 
+        // Our block root at the remote's finalized slot. A database error is not the fault of the
+        // peer, so the peer is not judged.
+        let remote_finalized_slot = start_slot(remote.latest_finalized_epoch);
+        let local_root_at_remote_finalized_slot =
+            match self.chain.block_root_at_slot(remote_finalized_slot) {
+                Ok(root) => root,
+                Err(e) => {
+                    error!(
+                        self.log, "Unable to read canonical block root";
+                        "slot" => remote_finalized_slot,
+                        "error" => format!("{:?}", e)
+                    );
+                    return;
+                }
+            };
+
         if local.network_id != remote.network_id {
             // The node is on a different network, disconnect them.
             info!(
@@ -172,12 +213,12 @@ impl<T: BeaconChainTypes> SimpleSync<T> {
                 "reason" => "network_id"
             );
 
+            network.report_peer(peer_id.clone(), PeerAction::WrongChain);
             network.disconnect(peer_id.clone(), GoodbyeReason::IrreleventNetwork);
         } else if remote.latest_finalized_epoch <= local.latest_finalized_epoch
             && remote.latest_finalized_root != self.chain.spec.zero_hash
             && local.latest_finalized_root != self.chain.spec.zero_hash
-            && (self.root_at_slot(start_slot(remote.latest_finalized_epoch))
-                != Some(remote.latest_finalized_root))
+            && local_root_at_remote_finalized_slot != Some(remote.latest_finalized_root)
         {
             // The remotes finalized epoch is less than or greater than ours, but the block root is
             // different to the one in our chain.
@@ -188,6 +229,7 @@ impl<T: BeaconChainTypes> SimpleSync<T> {
                 "peer" => format!("{:?}", peer_id),
                 "reason" => "different finalized chain"
             );
+            network.report_peer(peer_id.clone(), PeerAction::WrongChain);
             network.disconnect(peer_id.clone(), GoodbyeReason::IrreleventNetwork);
         } else if remote.latest_finalized_epoch < local.latest_finalized_epoch {
             // The node has a lower finalized epoch, their chain is not useful to us. There are two
@@ -229,30 +271,10 @@ impl<T: BeaconChainTypes> SimpleSync<T> {
             // head that are worth downloading.
             debug!(self.log, "UsefulPeer"; "peer" => format!("{:?}", peer_id));
 
-            let start_slot = local
-                .latest_finalized_epoch
-                .start_slot(T::EthSpec::slots_per_epoch());
-            let required_slots = remote.best_slot - start_slot;
-
-            self.request_block_roots(
-                peer_id,
-                BeaconBlockRootsRequest {
-                    start_slot,
-                    count: required_slots.into(),
-                },
-                network,
-            );
+            self.range_sync.add_peer(peer_id, remote.best_slot, network);
         }
     }
 
-    fn root_at_slot(&self, target_slot: Slot) -> Option<Hash256> {
-        self.chain
-            .rev_iter_block_roots(target_slot)
-            .take(1)
-            .find(|(_root, slot)| *slot == target_slot)
-            .map(|(root, _slot)| root)
-    }
-
     /// Handle a `BeaconBlockRoots` request from the peer.
     pub fn on_beacon_block_roots_request(
         &mut self,
@@ -509,6 +531,148 @@ impl<T: BeaconChainTypes> SimpleSync<T> {
         self.process_import_queue(network);
     }
 
+    /// Handle a `BeaconBlocksByRange` request from the peer.
+    pub fn on_beacon_blocks_by_range_request(
+        &mut self,
+        peer_id: PeerId,
+        request_id: RequestId,
+        req: BeaconBlocksByRangeRequest,
+        network: &mut NetworkContext,
+    ) {
+        // Copy the head so its lock is not held while walking the chain.
+        let (head_root, head_slot) = {
+            let head = self.chain.head();
+            (head.beacon_block_root, head.beacon_block.slot)
+        };
+        let end_slot = req.start_slot + req.count.saturating_mul(req.step);
+        let is_stepped_slot = |slot: Slot| (slot - req.start_slot).as_u64() % req.step == 0;
+
+        // Collect the roots at each step of the range. The iterator does not include the head
+        // block, so it is added separately.
+        let mut roots: Vec<(Hash256, Slot)> = self
+            .chain
+            .rev_iter_block_roots(std::cmp::min(end_slot, head_slot))
+            .take_while(|(_root, slot)| *slot >= req.start_slot)
+            .filter(|(_root, slot)| is_stepped_slot(*slot))
+            .collect();
+
+        roots.reverse();
+        if head_slot >= req.start_slot && head_slot < end_slot && is_stepped_slot(head_slot) {
+            roots.push((head_root, head_slot));
+        }
+
+        // Skipped slots repeat the root of the prior block, which is omitted.
+        let blocks: Vec<BeaconBlock> = roots
+            .iter()
+            .filter_map(|(root, slot)| {
+                self.chain
+                    .store
+                    .get::<BeaconBlock>(root)
+                    .ok()?
+                    .filter(|block| block.slot == *slot)
+            })
+            .collect();
+
+        debug!(
+            self.log,
+            "BlocksByRangeRequest";
+            "peer" => format!("{:?}", peer_id),
+            "start_slot" => req.start_slot,
+            "count" => req.count,
+            "step" => req.step,
+            "returned" => blocks.len(),
+        );
+
+        network.send_rpc_response(
+            peer_id,
+            request_id,
+            RPCResponse::BeaconBlocksByRange(BeaconBlocksResponse { blocks }),
+        )
+    }
+
+    /// Handle a `BeaconBlocksByRange` response from the peer.
+    pub fn on_beacon_blocks_by_range_response(
+        &mut self,
+        peer_id: PeerId,
+        request_id: RequestId,
+        res: BeaconBlocksResponse,
+        network: &mut NetworkContext,
+    ) {
+        if self.range_sync.is_batch_request(&peer_id, request_id) {
+            return self
+                .range_sync
+                .on_blocks_response(peer_id, request_id, res.blocks, network);
+        }
+
+        self.on_unrequested_blocks(peer_id, res.blocks, network);
+    }
+
+    /// Handle a `BeaconBlocksByRoot` request from the peer.
+    pub fn on_beacon_blocks_by_root_request(
+        &mut self,
+        peer_id: PeerId,
+        request_id: RequestId,
+        req: BeaconBlocksByRootRequest,
+        network: &mut NetworkContext,
+    ) {
+        let blocks: Vec<BeaconBlock> = req
+            .roots
+            .iter()
+            .filter_map(|root| self.chain.store.get::<BeaconBlock>(root).ok()?)
+            .collect();
+
+        debug!(
+            self.log,
+            "BlocksByRootRequest";
+            "peer" => format!("{:?}", peer_id),
+            "requested" => req.roots.len(),
+            "returned" => blocks.len(),
+        );
+
+        network.send_rpc_response(
+            peer_id,
+            request_id,
+            RPCResponse::BeaconBlocksByRoot(BeaconBlocksResponse { blocks }),
+        )
+    }
+
+    /// Handle a `BeaconBlocksByRoot` response from the peer.
+    pub fn on_beacon_blocks_by_root_response(
+        &mut self,
+        peer_id: PeerId,
+        request_id: RequestId,
+        res: BeaconBlocksResponse,
+        network: &mut NetworkContext,
+    ) {
+        if self.parent_lookups.is_lookup_request(&peer_id, request_id) {
+            return self
+                .parent_lookups
+                .on_blocks_response(peer_id, request_id, res.blocks, network);
+        }
+
+        self.on_unrequested_blocks(peer_id, res.blocks, network);
+    }
+
+    /// Queues blocks which arrived in response to a request that is no longer tracked (e.g., it
+    /// timed out) and imports them, if possible.
+    fn on_unrequested_blocks(
+        &mut self,
+        peer_id: PeerId,
+        blocks: Vec<BeaconBlock>,
+        network: &mut NetworkContext,
+    ) {
+        debug!(
+            self.log,
+            "BlocksResponse";
+            "peer" => format!("{:?}", peer_id),
+            "count" => blocks.len(),
+        );
+
+        self.import_queue.enqueue_full_blocks(blocks, peer_id);
+        self.import_queue.remove_stale();
+        self.process_import_queue(network);
+    }
+
     /// Process a gossip message declaring a new block.
     ///
     /// Attempts to apply to block to the beacon chain. May queue the block for later processing.
@@ -525,29 +689,19 @@ impl<T: BeaconChainTypes> SimpleSync<T> {
         {
             match outcome {
                 BlockProcessingOutcome::Processed { .. } => SHOULD_FORWARD_GOSSIP_BLOCK,
-                BlockProcessingOutcome::ParentUnknown { parent } => {
-                    // Clean the stale entries from the queue.
-                    self.import_queue.remove_stale();
-
-                    // Add this block to the queue
-                    self.import_queue
-                        .enqueue_full_blocks(vec![block], peer_id.clone());
-                    trace!(
-                        self.log,
-                        "NewGossipBlock";
-                        "peer" => format!("{:?}", peer_id),
-                    );
-
-                    // Unless the parent is in the queue, request the parent block from the peer.
-                    //
-                    // It is likely that this is duplicate work, given we already send a hello
-                    // request. However, I believe there are some edge-cases where the hello
-                    // message doesn't suffice, so we perform this request as well.
-                    if !self.import_queue.contains_block_root(parent) {
-                        // Send a hello to learn of the clients best slot so we can then sync the required
-                        // parent(s).
+                BlockProcessingOutcome::ParentUnknown { .. } => {
+                    // Download the missing ancestors from the peer that sent the block.
+                    if let Some(block) =
+                        self.parent_lookups
+                            .add_orphan(peer_id.clone(), block, network)
+                    {
+                        // Too many lookups are in progress. Queue the block until its ancestors
+                        // arrive, and sync from the peer in case we are behind it.
+                        self.import_queue.remove_stale();
+                        self.import_queue
+                            .enqueue_full_blocks(vec![block], peer_id.clone());
                         network.send_rpc_request(
-                            peer_id.clone(),
+                            peer_id,
                             RPCRequest::Hello(hello_message(&self.chain)),
                         );
                     }
@@ -580,14 +734,82 @@ impl<T: BeaconChainTypes> SimpleSync<T> {
     /// Not currently implemented.
     pub fn on_attestation_gossip(
         &mut self,
-        _peer_id: PeerId,
+        peer_id: PeerId,
         msg: Attestation,
-        _network: &mut NetworkContext,
+        network: &mut NetworkContext,
     ) {
         match self.chain.process_attestation(msg) {
             Ok(()) => info!(self.log, "ImportedAttestation"; "source" => "gossip"),
             Err(e) => {
-                warn!(self.log, "InvalidAttestation"; "source" => "gossip", "error" => format!("{:?}", e))
+                warn!(self.log, "InvalidAttestation"; "source" => "gossip", "error" => format!("{:?}", e));
+
+                // Only penalise attestations which are invalid on any fork. The others may only be
+                // invalid against our head, or we may have failed to process them.
+                if let AttestationValidationError::Invalid(invalid) = e {
+                    if is_invalid_on_any_fork(&invalid) {
+                        network.report_peer(peer_id, PeerAction::InvalidAttestation);
+                    }
+                }
+            }
+        }
+    }
+
+    /// Process a gossip message declaring a new voluntary exit.
+    pub fn on_voluntary_exit_gossip(
+        &mut self,
+        _peer_id: PeerId,
+        msg: VoluntaryExit,
+        _network: &mut NetworkContext,
+    ) {
+        match self.chain.process_voluntary_exit(msg) {
+            Ok(()) => info!(self.log, "ImportedVoluntaryExit"; "source" => "gossip"),
+            Err(e) => {
+                warn!(self.log, "InvalidVoluntaryExit"; "source" => "gossip", "error" => format!("{:?}", e))
+            }
+        }
+    }
+
+    /// Process a gossip message declaring a new proposer slashing.
+    pub fn on_proposer_slashing_gossip(
+        &mut self,
+        _peer_id: PeerId,
+        msg: ProposerSlashing,
+        _network: &mut NetworkContext,
+    ) {
+        match self.chain.process_proposer_slashing(msg) {
+            Ok(()) => info!(self.log, "ImportedProposerSlashing"; "source" => "gossip"),
+            Err(e) => {
+                warn!(self.log, "InvalidProposerSlashing"; "source" => "gossip", "error" => format!("{:?}", e))
+            }
+        }
+    }
+
+    /// Process a gossip message declaring a new attester slashing.
+    pub fn on_attester_slashing_gossip(
+        &mut self,
+        _peer_id: PeerId,
+        msg: AttesterSlashing,
+        _network: &mut NetworkContext,
+    ) {
+        match self.chain.process_attester_slashing(msg) {
+            Ok(()) => info!(self.log, "ImportedAttesterSlashing"; "source" => "gossip"),
+            Err(e) => {
+                warn!(self.log, "InvalidAttesterSlashing"; "source" => "gossip", "error" => format!("{:?}", e))
+            }
+        }
+    }
+
+    /// Process a gossip message declaring a new transfer.
+    pub fn on_transfer_gossip(
+        &mut self,
+        _peer_id: PeerId,
+        msg: Transfer,
+        _network: &mut NetworkContext,
+    ) {
+        match self.chain.process_transfer(msg) {
+            Ok(()) => info!(self.log, "ImportedTransfer"; "source" => "gossip"),
+            Err(e) => {
+                warn!(self.log, "InvalidTransfer"; "source" => "gossip", "error" => format!("{:?}", e))
             }
         }
     }
@@ -626,30 +848,6 @@ impl<T: BeaconChainTypes> SimpleSync<T> {
         }
     }
 
-    /// Request some `BeaconBlockRoots` from the remote peer.
-    fn request_block_roots(
-        &mut self,
-        peer_id: PeerId,
-        req: BeaconBlockRootsRequest,
-        network: &mut NetworkContext,
-    ) {
-        // Potentially set state to sync.
-        if self.state == SyncState::Idle && req.count > SLOT_IMPORT_TOLERANCE {
-            debug!(self.log, "Entering downloading sync state.");
-            self.state = SyncState::Downloading;
-        }
-
-        debug!(
-            self.log,
-            "RPCRequest(BeaconBlockRoots)";
-            "count" => req.count,
-            "peer" => format!("{:?}", peer_id)
-        );
-
-        // TODO: handle count > max count.
-        network.send_rpc_request(peer_id.clone(), RPCRequest::BeaconBlockRoots(req));
-    }
-
     /// Request some `BeaconBlockHeaders` from the remote peer.
     fn request_block_headers(
         &mut self,
@@ -710,7 +908,6 @@ impl<T: BeaconChainTypes> SimpleSync<T> {
     ///
     ///  - Attempting to import the block into the beacon chain.
     ///  - Logging
-    ///  - Requesting unavailable blocks (e.g., if parent is unknown).
     ///  - Disconnecting faulty nodes.
     ///
     /// This function does not remove processed blocks from the import queue.
@@ -733,6 +930,7 @@ impl<T: BeaconChainTypes> SimpleSync<T> {
                         "block_root" => format!("{}", block_root),
                         "peer" => format!("{:?}", peer_id),
                     );
+                    network.report_peer(peer_id, PeerAction::ValidBlock);
                 }
                 BlockProcessingOutcome::ParentUnknown { parent } => {
                     // The block was valid and we processed it successfully.
@@ -742,20 +940,6 @@ impl<T: BeaconChainTypes> SimpleSync<T> {
                         "parent_root" => format!("{}", parent),
                         "peer" => format!("{:?}", peer_id),
                     );
-
-                    // Unless the parent is in the queue, request the parent block from the peer.
-                    //
-                    // It is likely that this is duplicate work, given we already send a hello
-                    // request. However, I believe there are some edge-cases where the hello
-                    // message doesn't suffice, so we perform this request as well.
-                    if !self.import_queue.contains_block_root(parent) {
-                        // Send a hello to learn of the clients best slot so we can then sync the require
-                        // parent(s).
-                        network.send_rpc_request(
-                            peer_id.clone(),
-                            RPCRequest::Hello(hello_message(&self.chain)),
-                        );
-                    }
                 }
                 BlockProcessingOutcome::FutureSlot {
                     present_slot,
@@ -786,6 +970,17 @@ impl<T: BeaconChainTypes> SimpleSync<T> {
                         );
                     }
                 }
+                BlockProcessingOutcome::StateRootMismatch
+                | BlockProcessingOutcome::PerBlockProcessingError(_) => {
+                    debug!(
+                        self.log, "InvalidBlock";
+                        "source" => source,
+                        "msg" => "peer sent invalid block",
+                        "outcome" => format!("{:?}", outcome),
+                        "peer" => format!("{:?}", peer_id),
+                    );
+                    network.report_peer(peer_id, PeerAction::InvalidBlock);
+                }
                 _ => {
                     debug!(
                         self.log, "InvalidBlock";
@@ -812,7 +1007,7 @@ impl<T: BeaconChainTypes> SimpleSync<T> {
 }
 
 /// Build a `HelloMessage` representing the state of the given `beacon_chain`.
-fn hello_message<T: BeaconChainTypes>(beacon_chain: &BeaconChain<T>) -> HelloMessage {
+pub(super) fn hello_message<T: BeaconChainTypes>(beacon_chain: &BeaconChain<T>) -> HelloMessage {
     let spec = &beacon_chain.spec;
     let state = &beacon_chain.head().beacon_state;
 
@@ -824,3 +1019,15 @@ fn hello_message<T: BeaconChainTypes>(beacon_chain: &BeaconChain<T>) -> HelloMes
         best_slot: state.slot,
     }
 }
+
+/// Returns `true` if an attestation which failed validation with `invalid` would fail against any
+/// state, rather than only against the state of our head.
+fn is_invalid_on_any_fork(invalid: &AttestationInvalid) -> bool {
+    match invalid {
+        AttestationInvalid::PreGenesis { .. }
+        | AttestationInvalid::CustodyBitfieldHasSetBits
+        | AttestationInvalid::AggregationBitfieldIsEmpty
+        | AttestationInvalid::ShardBlockRootNotZero => true,
+        _ => false,
+    }
+}
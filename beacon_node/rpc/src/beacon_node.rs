@@ -25,6 +25,7 @@ impl<T: BeaconChainTypes> BeaconNodeService for BeaconNodeServiceInstance<T> {
         let state = &self.chain.head().beacon_state;
         let state_fork = state.fork.clone();
         let genesis_time = state.genesis_time;
+        let finalized_epoch = state.finalized_epoch;
 
         // build the rpc fork struct
         let mut fork = Fork::new();
@@ -37,6 +38,7 @@ impl<T: BeaconChainTypes> BeaconNodeService for BeaconNodeServiceInstance<T> {
         node_info.set_fork(fork);
         node_info.set_genesis_time(genesis_time);
         node_info.set_genesis_slot(spec.genesis_slot.as_u64());
+        node_info.set_finalized_epoch(finalized_epoch.as_u64());
         node_info.set_chain_id(u32::from(spec.chain_id));
 
         // send the node_info the requester
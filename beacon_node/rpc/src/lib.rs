@@ -2,11 +2,13 @@ mod attestation;
 mod beacon_block;
 mod beacon_node;
 pub mod config;
+mod operation;
 mod validator;
 
 use self::attestation::AttestationServiceInstance;
 use self::beacon_block::BeaconBlockServiceInstance;
 use self::beacon_node::BeaconNodeServiceInstance;
+use self::operation::OperationServiceInstance;
 use self::validator::ValidatorServiceInstance;
 use beacon_chain::{BeaconChain, BeaconChainTypes};
 pub use config::Config as RPCConfig;
@@ -15,7 +17,7 @@ use grpcio::{Environment, ServerBuilder};
 use network::NetworkMessage;
 use protos::services_grpc::{
     create_attestation_service, create_beacon_block_service, create_beacon_node_service,
-    create_validator_service,
+    create_operation_service, create_validator_service,
 };
 use slog::{info, o, warn};
 use std::sync::Arc;
@@ -60,18 +62,27 @@ pub fn start_server<T: BeaconChainTypes + Clone + 'static>(
     };
     let attestation_service = {
         let instance = AttestationServiceInstance {
-            network_chan,
+            network_chan: network_chan.clone(),
             chain: beacon_chain.clone(),
             log: log.clone(),
         };
         create_attestation_service(instance)
     };
+    let operation_service = {
+        let instance = OperationServiceInstance {
+            network_chan,
+            chain: beacon_chain.clone(),
+            log: log.clone(),
+        };
+        create_operation_service(instance)
+    };
 
     let mut server = ServerBuilder::new(env)
         .register_service(beacon_block_service)
         .register_service(validator_service)
         .register_service(beacon_node_service)
         .register_service(attestation_service)
+        .register_service(operation_service)
         .bind(config.listen_address.to_string(), config.port)
         .build()
         .unwrap();
@@ -0,0 +1,304 @@
+use beacon_chain::{BeaconChain, BeaconChainTypes};
+use eth2_libp2p::{
+    PubsubMessage, TopicBuilder, ATTESTER_SLASHING_TOPIC, PROPOSER_SLASHING_TOPIC, TRANSFER_TOPIC,
+    VOLUNTARY_EXIT_TOPIC,
+};
+use futures::Future;
+use grpcio::{RpcContext, RpcStatus, RpcStatusCode, UnarySink};
+use network::NetworkMessage;
+use operation_pool::DepositInsertStatus;
+use protobuf::RepeatedField;
+use protos::services::{
+    Attestation as AttestationProto, AttestationGroup, Empty, OperationPoolCounts,
+    PendingAttestation, PendingAttestationsResponse, PendingOperationsResponse,
+    PublishOperationRequest, PublishOperationResponse,
+};
+use protos::services_grpc::OperationService;
+use slog::{error, info, trace, warn};
+use ssz::{ssz_encode, Decode, Encode};
+use std::fmt::Debug;
+use std::sync::Arc;
+use types::{AttesterSlashing, Deposit, ProposerSlashing, Transfer, VoluntaryExit};
+
+#[derive(Clone)]
+pub struct OperationServiceInstance<T: BeaconChainTypes> {
+    pub chain: Arc<BeaconChain<T>>,
+    pub network_chan: crossbeam_channel::Sender<NetworkMessage>,
+    pub log: slog::Logger,
+}
+
+impl<T: BeaconChainTypes> OperationService for OperationServiceInstance<T> {
+    /// Add a `VoluntaryExit` to the operation pool and, if it is valid, publish it.
+    fn publish_voluntary_exit(
+        &mut self,
+        ctx: RpcContext,
+        req: PublishOperationRequest,
+        sink: UnarySink<PublishOperationResponse>,
+    ) {
+        trace!(self.log, "Publishing voluntary exit");
+
+        let result = decode::<VoluntaryExit>(&req, "voluntary exit").map(|exit| {
+            match self.chain.process_voluntary_exit(exit.clone()) {
+                Ok(()) => self.publish(VOLUNTARY_EXIT_TOPIC, PubsubMessage::VoluntaryExit(exit)),
+                Err(e) => self.rejected("InvalidVoluntaryExit", e),
+            }
+        });
+
+        self.reply(ctx, req, sink, result)
+    }
+
+    /// Add a `ProposerSlashing` to the operation pool and, if it is valid, publish it.
+    fn publish_proposer_slashing(
+        &mut self,
+        ctx: RpcContext,
+        req: PublishOperationRequest,
+        sink: UnarySink<PublishOperationResponse>,
+    ) {
+        trace!(self.log, "Publishing proposer slashing");
+
+        let result =
+            decode::<ProposerSlashing>(&req, "proposer slashing").map(|slashing| {
+                match self.chain.process_proposer_slashing(slashing.clone()) {
+                    Ok(()) => self.publish(
+                        PROPOSER_SLASHING_TOPIC,
+                        PubsubMessage::ProposerSlashing(slashing),
+                    ),
+                    Err(e) => self.rejected("InvalidProposerSlashing", e),
+                }
+            });
+
+        self.reply(ctx, req, sink, result)
+    }
+
+    /// Add an `AttesterSlashing` to the operation pool and, if it is valid, publish it.
+    fn publish_attester_slashing(
+        &mut self,
+        ctx: RpcContext,
+        req: PublishOperationRequest,
+        sink: UnarySink<PublishOperationResponse>,
+    ) {
+        trace!(self.log, "Publishing attester slashing");
+
+        let result =
+            decode::<AttesterSlashing>(&req, "attester slashing").map(|slashing| {
+                match self.chain.process_attester_slashing(slashing.clone()) {
+                    Ok(()) => self.publish(
+                        ATTESTER_SLASHING_TOPIC,
+                        PubsubMessage::AttesterSlashing(slashing),
+                    ),
+                    Err(e) => self.rejected("InvalidAttesterSlashing", e),
+                }
+            });
+
+        self.reply(ctx, req, sink, result)
+    }
+
+    /// Add a `Transfer` to the operation pool and, if it is valid, publish it.
+    fn publish_transfer(
+        &mut self,
+        ctx: RpcContext,
+        req: PublishOperationRequest,
+        sink: UnarySink<PublishOperationResponse>,
+    ) {
+        trace!(self.log, "Publishing transfer");
+
+        let result = decode::<Transfer>(&req, "transfer").map(|transfer| {
+            match self.chain.process_transfer(transfer.clone()) {
+                Ok(()) => self.publish(TRANSFER_TOPIC, PubsubMessage::Transfer(transfer)),
+                Err(e) => self.rejected("InvalidTransfer", e),
+            }
+        });
+
+        self.reply(ctx, req, sink, result)
+    }
+
+    /// Add a `Deposit` to the operation pool.
+    ///
+    /// Deposits are not published, as every node reads them from the Eth1 chain.
+    fn publish_deposit(
+        &mut self,
+        ctx: RpcContext,
+        req: PublishOperationRequest,
+        sink: UnarySink<PublishOperationResponse>,
+    ) {
+        trace!(self.log, "Publishing deposit");
+
+        let result = decode::<Deposit>(&req, "deposit").map(|deposit| {
+            match self.chain.process_deposit(deposit) {
+                Ok(status) => {
+                    let status = match status {
+                        DepositInsertStatus::Fresh => "Fresh",
+                        DepositInsertStatus::Duplicate => "Duplicate",
+                        DepositInsertStatus::Replaced(_) => "Replaced",
+                    };
+                    info!(self.log, "PublishDeposit"; "status" => status);
+
+                    let mut resp = PublishOperationResponse::new();
+                    resp.set_success(true);
+                    resp.set_msg(status.as_bytes().to_vec());
+                    resp
+                }
+                Err(e) => self.rejected("InvalidDeposit", e),
+            }
+        });
+
+        self.reply(ctx, req, sink, result)
+    }
+
+    /// Return the number of each type of operation in the pool.
+    fn get_operation_pool_counts(
+        &mut self,
+        ctx: RpcContext,
+        _req: Empty,
+        sink: UnarySink<OperationPoolCounts>,
+    ) {
+        trace!(self.log, "Getting operation pool counts");
+
+        let op_pool = &self.chain.op_pool;
+        let mut counts = OperationPoolCounts::new();
+        counts.set_attestations(op_pool.num_attestations() as u64);
+        counts.set_deposits(op_pool.num_deposits() as u64);
+        counts.set_proposer_slashings(op_pool.num_proposer_slashings() as u64);
+        counts.set_attester_slashings(op_pool.num_attester_slashings() as u64);
+        counts.set_voluntary_exits(op_pool.num_voluntary_exits() as u64);
+        counts.set_transfers(op_pool.num_transfers() as u64);
+
+        let error_log = self.log.clone();
+        let f = sink
+            .success(counts)
+            .map_err(move |e| error!(error_log, "failed to reply with counts: {:?}", e));
+        ctx.spawn(f)
+    }
+
+    /// Return the attestations in the pool, grouped by `AttestationId`.
+    fn get_pending_attestations(
+        &mut self,
+        ctx: RpcContext,
+        _req: Empty,
+        sink: UnarySink<PendingAttestationsResponse>,
+    ) {
+        trace!(self.log, "Getting pending attestations");
+
+        let mut resp = PendingAttestationsResponse::new();
+        for (id, attestations) in self.chain.op_pool.get_all_attestations() {
+            let mut group = AttestationGroup::new();
+            group.set_id(id.as_bytes().to_vec());
+
+            for attestation in attestations {
+                let mut pending = PendingAttestation::new();
+                pending
+                    .set_aggregation_bits(attestation.aggregation_bitfield.num_set_bits() as u64);
+
+                let mut attestation_proto = AttestationProto::new();
+                attestation_proto.set_ssz(ssz_encode(&attestation));
+                pending.set_attestation(attestation_proto);
+
+                group.mut_attestations().push(pending);
+            }
+
+            resp.mut_groups().push(group);
+        }
+
+        let error_log = self.log.clone();
+        let f = sink
+            .success(resp)
+            .map_err(move |e| error!(error_log, "failed to reply with attestations: {:?}", e));
+        ctx.spawn(f)
+    }
+
+    /// Return the SSZ encoding of every non-attestation operation in the pool.
+    fn get_pending_operations(
+        &mut self,
+        ctx: RpcContext,
+        _req: Empty,
+        sink: UnarySink<PendingOperationsResponse>,
+    ) {
+        trace!(self.log, "Getting pending operations");
+
+        let pool = &self.chain.op_pool;
+
+        let mut resp = PendingOperationsResponse::new();
+        resp.set_deposits(encode_all(&pool.get_all_deposits()));
+        resp.set_proposer_slashings(encode_all(&pool.get_all_proposer_slashings()));
+        resp.set_attester_slashings(encode_all(&pool.get_all_attester_slashings()));
+        resp.set_voluntary_exits(encode_all(&pool.get_all_voluntary_exits()));
+        resp.set_transfers(encode_all(&pool.get_all_transfers()));
+
+        let error_log = self.log.clone();
+        let f = sink
+            .success(resp)
+            .map_err(move |e| error!(error_log, "failed to reply with operations: {:?}", e));
+        ctx.spawn(f)
+    }
+}
+
+impl<T: BeaconChainTypes> OperationServiceInstance<T> {
+    /// Publish an accepted operation to the network, returning a successful response.
+    fn publish(&self, topic: &str, message: PubsubMessage) -> PublishOperationResponse {
+        info!(self.log, "PublishOperation"; "topic" => topic);
+
+        self.network_chan
+            .send(NetworkMessage::Publish {
+                topics: vec![TopicBuilder::new(topic).build()],
+                message: Box::new(message),
+            })
+            .unwrap_or_else(|e| {
+                error!(
+                    self.log,
+                    "PublishOperation";
+                    "type" => "failed to publish operation to gossipsub",
+                    "error" => format!("{:?}", e)
+                );
+            });
+
+        let mut resp = PublishOperationResponse::new();
+        resp.set_success(true);
+        resp
+    }
+
+    /// Returns an unsuccessful response describing why the operation pool rejected an operation.
+    fn rejected<E: Debug>(&self, kind: &str, error: E) -> PublishOperationResponse {
+        warn!(
+            self.log,
+            "PublishOperation";
+            "type" => kind,
+            "error" => format!("{:?}", error),
+        );
+
+        let mut resp = PublishOperationResponse::new();
+        resp.set_success(false);
+        resp.set_msg(format!("{}: {:?}", kind, error).as_bytes().to_vec());
+        resp
+    }
+
+    fn reply(
+        &self,
+        ctx: RpcContext,
+        req: PublishOperationRequest,
+        sink: UnarySink<PublishOperationResponse>,
+        result: Result<PublishOperationResponse, RpcStatus>,
+    ) {
+        let error_log = self.log.clone();
+        let f = match result {
+            Ok(resp) => sink.success(resp),
+            Err(status) => sink.fail(status),
+        }
+        .map_err(move |e| error!(error_log, "failed to reply {:?}: {:?}", req, e));
+        ctx.spawn(f)
+    }
+}
+
+/// Encodes each operation as SSZ.
+fn encode_all<O: Encode>(operations: &[O]) -> RepeatedField<Vec<u8>> {
+    RepeatedField::from_vec(operations.iter().map(ssz_encode).collect())
+}
+
+/// Decodes the SSZ operation in `req`, failing the request if it is malformed.
+fn decode<O: Decode>(req: &PublishOperationRequest, name: &str) -> Result<O, RpcStatus> {
+    O::from_ssz_bytes(req.get_ssz()).map_err(|_| {
+        RpcStatus::new(
+            RpcStatusCode::InvalidArgument,
+            Some(format!("Invalid {}", name)),
+        )
+    })
+}
@@ -119,9 +119,23 @@ fn main() {
                 .value_name("DB")
                 .help("Type of database to use.")
                 .takes_value(true)
-                .possible_values(&["disk", "memory"])
+                .possible_values(&["disk", "hot_cold", "rocksdb", "memory"])
                 .default_value("memory"),
         )
+        .arg(
+            Arg::with_name("slots-per-restore-point")
+                .long("slots-per-restore-point")
+                .value_name("SLOT_COUNT")
+                .help("The number of slots between full states stored in the freezer database (default 2048). Cannot be changed once the database exists.")
+                .takes_value(true),
+        )
+        .arg(
+            Arg::with_name("dump-fork-choice")
+                .long("dump-fork-choice")
+                .value_name("DIR")
+                .help("Write the fork choice tree to fork_choice.json and fork_choice.dot in DIR every slot, replacing those of the previous slot.")
+                .takes_value(true),
+        )
         .arg(
             Arg::with_name("spec-constants")
                 .long("spec-constants")
@@ -6,14 +6,12 @@ use futures::sync::oneshot;
 use futures::Future;
 use slog::{error, info, warn};
 use std::cell::RefCell;
-use std::path::Path;
-use std::path::PathBuf;
-use store::{DiskStore, MemoryStore};
+use store::{DiskStore, HotColdDB, MemoryStore, RocksDB};
 use tokio::runtime::Builder;
 use tokio::runtime::Runtime;
 use tokio::runtime::TaskExecutor;
 use tokio_timer::clock::Clock;
-use types::{MainnetEthSpec, MinimalEthSpec};
+use types::{ChainSpec, MainnetEthSpec, MinimalEthSpec};
 
 pub fn run_beacon_node(
     client_config: ClientConfig,
@@ -28,9 +26,6 @@ pub fn run_beacon_node(
 
     let executor = runtime.executor();
 
-    let db_path: PathBuf = client_config
-        .db_path()
-        .ok_or_else::<error::Error, _>(|| "Unable to access database path".into())?;
     let db_type = &client_config.db_type;
     let spec_constants = eth2_config.spec_constants.clone();
 
@@ -52,7 +47,20 @@ pub fn run_beacon_node(
 
     let result = match (db_type.as_str(), spec_constants.as_str()) {
         ("disk", "minimal") => run::<ClientType<DiskStore, MinimalEthSpec>>(
-            &db_path,
+            client_config,
+            eth2_config,
+            executor,
+            runtime,
+            log,
+        ),
+        ("hot_cold", "minimal") => run::<ClientType<HotColdDB, MinimalEthSpec>>(
+            client_config,
+            eth2_config,
+            executor,
+            runtime,
+            log,
+        ),
+        ("rocksdb", "minimal") => run::<ClientType<RocksDB, MinimalEthSpec>>(
             client_config,
             eth2_config,
             executor,
@@ -60,7 +68,6 @@ pub fn run_beacon_node(
             log,
         ),
         ("memory", "minimal") => run::<ClientType<MemoryStore, MinimalEthSpec>>(
-            &db_path,
             client_config,
             eth2_config,
             executor,
@@ -68,7 +75,20 @@ pub fn run_beacon_node(
             log,
         ),
         ("disk", "mainnet") => run::<ClientType<DiskStore, MainnetEthSpec>>(
-            &db_path,
+            client_config,
+            eth2_config,
+            executor,
+            runtime,
+            log,
+        ),
+        ("hot_cold", "mainnet") => run::<ClientType<HotColdDB, MainnetEthSpec>>(
+            client_config,
+            eth2_config,
+            executor,
+            runtime,
+            log,
+        ),
+        ("rocksdb", "mainnet") => run::<ClientType<RocksDB, MainnetEthSpec>>(
             client_config,
             eth2_config,
             executor,
@@ -76,7 +96,6 @@ pub fn run_beacon_node(
             log,
         ),
         ("memory", "mainnet") => run::<ClientType<MemoryStore, MainnetEthSpec>>(
-            &db_path,
             client_config,
             eth2_config,
             executor,
@@ -93,7 +112,6 @@ pub fn run_beacon_node(
 }
 
 pub fn run<T>(
-    db_path: &Path,
     client_config: ClientConfig,
     eth2_config: Eth2Config,
     executor: TaskExecutor,
@@ -104,7 +122,7 @@ where
     T: BeaconChainTypes + InitialiseBeaconChain<T> + Clone + Send + Sync + 'static,
     T::Store: OpenDatabase,
 {
-    let store = T::Store::open_database(&db_path)?;
+    let store = T::Store::open_database(&client_config, &eth2_config.spec)?;
 
     let client: Client<T> = Client::new(client_config, eth2_config, store, log.clone(), &executor)?;
 
@@ -140,17 +158,50 @@ where
 ///
 /// Panics if unable to open the database.
 pub trait OpenDatabase: Sized {
-    fn open_database(path: &Path) -> error::Result<Self>;
+    fn open_database(client_config: &ClientConfig, spec: &ChainSpec) -> error::Result<Self>;
 }
 
 impl OpenDatabase for MemoryStore {
-    fn open_database(_path: &Path) -> error::Result<Self> {
+    fn open_database(_client_config: &ClientConfig, _spec: &ChainSpec) -> error::Result<Self> {
         Ok(MemoryStore::open())
     }
 }
 
 impl OpenDatabase for DiskStore {
-    fn open_database(path: &Path) -> error::Result<Self> {
-        DiskStore::open(path).map_err(|e| format!("Unable to open database: {:?}", e).into())
+    fn open_database(client_config: &ClientConfig, _spec: &ChainSpec) -> error::Result<Self> {
+        let path = client_config
+            .db_path()
+            .ok_or_else::<error::Error, _>(|| "Unable to access database path".into())?;
+
+        DiskStore::open(&path).map_err(|e| format!("Unable to open database: {:?}", e).into())
+    }
+}
+
+impl OpenDatabase for HotColdDB {
+    fn open_database(client_config: &ClientConfig, spec: &ChainSpec) -> error::Result<Self> {
+        let hot_path = client_config
+            .hot_db_path()
+            .ok_or_else::<error::Error, _>(|| "Unable to access database path".into())?;
+        let cold_path = client_config
+            .freezer_db_path()
+            .ok_or_else::<error::Error, _>(|| "Unable to access freezer database path".into())?;
+
+        HotColdDB::open(
+            &hot_path,
+            &cold_path,
+            client_config.slots_per_restore_point,
+            spec.clone(),
+        )
+        .map_err(|e| format!("Unable to open database: {:?}", e).into())
+    }
+}
+
+impl OpenDatabase for RocksDB {
+    fn open_database(client_config: &ClientConfig, _spec: &ChainSpec) -> error::Result<Self> {
+        let path = client_config
+            .rocks_db_path()
+            .ok_or_else::<error::Error, _>(|| "Unable to access database path".into())?;
+
+        RocksDB::open(&path).map_err(|e| format!("Unable to open database: {:?}", e).into())
     }
 }
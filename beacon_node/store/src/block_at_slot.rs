@@ -5,7 +5,7 @@ fn get_block_bytes<T: Store>(store: &T, root: Hash256) -> Result<Option<Vec<u8>>
     store.get_bytes(BeaconBlock::db_column().into(), &root[..])
 }
 
-fn read_slot_from_block_bytes(bytes: &[u8]) -> Result<Slot, DecodeError> {
+pub(crate) fn read_slot_from_block_bytes(bytes: &[u8]) -> Result<Slot, DecodeError> {
     let end = std::cmp::min(Slot::ssz_fixed_len(), bytes.len());
 
     Slot::from_ssz_bytes(&bytes[0..end])
@@ -0,0 +1,90 @@
+//! An index of the canonical chain, mapping each slot to the block root and state root of the
+//! canonical chain at that slot.
+//!
+//! The index has the same semantics as `BeaconState::block_roots` and `BeaconState::state_roots`:
+//! the block root at a skipped slot is the root of the most recent prior block. Unlike those
+//! fields, it is not limited to `SlotsPerHistoricalRoot` slots.
+//!
+//! The index is maintained by the `BeaconChain` as the head changes.
+use super::*;
+
+/// Returns the key for `slot` in the canonical index columns.
+fn slot_key(slot: Slot) -> [u8; 8] {
+    slot.as_u64().to_be_bytes()
+}
+
+fn get_root<T: Store>(store: &T, column: DBColumn, slot: Slot) -> Result<Option<Hash256>, Error> {
+    match store.get_bytes(column.into(), &slot_key(slot))? {
+        Some(bytes) if bytes.len() == 32 => Ok(Some(Hash256::from_slice(&bytes))),
+        Some(bytes) => Err(Error::DBError {
+            message: format!("Invalid canonical index root length: {}", bytes.len()),
+        }),
+        None => Ok(None),
+    }
+}
+
+pub fn get_canonical_block_root<T: Store>(store: &T, slot: Slot) -> Result<Option<Hash256>, Error> {
+    get_root(store, DBColumn::CanonicalBlockRoots, slot)
+}
+
+pub fn get_canonical_state_root<T: Store>(store: &T, slot: Slot) -> Result<Option<Hash256>, Error> {
+    get_root(store, DBColumn::CanonicalStateRoots, slot)
+}
+
+/// Add writes to `batch` which set the canonical block and state roots at `slot`.
+pub fn put_canonical_roots(
+    batch: &mut WriteBatch,
+    slot: Slot,
+    block_root: Hash256,
+    state_root: Hash256,
+) {
+    batch.put_bytes(
+        DBColumn::CanonicalBlockRoots.into(),
+        &slot_key(slot),
+        block_root.as_bytes(),
+    );
+    batch.put_bytes(
+        DBColumn::CanonicalStateRoots.into(),
+        &slot_key(slot),
+        state_root.as_bytes(),
+    );
+}
+
+/// Add deletions to `batch` which remove `slot` from the index, e.g., when a reorg has shortened
+/// the canonical chain.
+pub fn delete_canonical_roots(batch: &mut WriteBatch, slot: Slot) {
+    batch.key_delete(DBColumn::CanonicalBlockRoots.into(), &slot_key(slot));
+    batch.key_delete(DBColumn::CanonicalStateRoots.into(), &slot_key(slot));
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn put_and_delete() {
+        let store = MemoryStore::open();
+        let slot = Slot::new(42);
+
+        let mut batch = WriteBatch::new();
+        put_canonical_roots(&mut batch, slot, Hash256::from(1), Hash256::from(2));
+        store.write_batch(&batch).unwrap();
+
+        assert_eq!(
+            store.get_canonical_block_root(slot),
+            Ok(Some(Hash256::from(1)))
+        );
+        assert_eq!(
+            store.get_canonical_state_root(slot),
+            Ok(Some(Hash256::from(2)))
+        );
+        assert_eq!(store.get_canonical_block_root(slot + 1), Ok(None));
+
+        let mut batch = WriteBatch::new();
+        delete_canonical_roots(&mut batch, slot);
+        store.write_batch(&batch).unwrap();
+
+        assert_eq!(store.get_canonical_block_root(slot), Ok(None));
+        assert_eq!(store.get_canonical_state_root(slot), Ok(None));
+    }
+}
@@ -1,9 +1,25 @@
 use ssz::DecodeError;
+use types::Slot;
 
 #[derive(Debug, PartialEq)]
 pub enum Error {
     SszDecodeError(DecodeError),
-    DBError { message: String },
+    DBError {
+        message: String,
+    },
+    SlotsPerRestorePointChanged {
+        stored: u64,
+        configured: u64,
+    },
+    InvalidSlotsPerRestorePoint(u64),
+    MissingRestorePoint(Slot),
+    /// The roots of the canonical chain could not be read back to the split, which may be because
+    /// a state is missing from the database.
+    IncompleteFreeze {
+        split_slot: Slot,
+        lowest_slot: Option<Slot>,
+    },
+    StateReconstructionError(String),
 }
 
 impl From<DecodeError> for Error {
@@ -0,0 +1,778 @@
+use crate::block_at_slot::read_slot_from_block_bytes;
+use crate::iter::{BlockRootsIterator, StateRootsIterator};
+use crate::*;
+use parking_lot::RwLock;
+use ssz::{Decode, Encode};
+use ssz_derive::{Decode, Encode};
+use state_processing::{
+    per_block_processing_without_verifying_block_signature, per_slot_processing,
+};
+use std::collections::HashSet;
+use std::path::Path;
+use std::sync::Arc;
+
+/// 32-byte key for accessing the `Split` in the hot database.
+pub const SPLIT_DB_KEY: &str = "FREEZERDBSPLITFREEZERDBSPLITFREE";
+/// 32-byte key for accessing the `FreezerConfig` in the cold database.
+pub const FREEZER_CONFIG_DB_KEY: &str = "FREEZERDBCONFIGFREEZERDBCONFIGFR";
+
+/// The default number of slots between each full state stored in the freezer.
+pub const DEFAULT_SLOTS_PER_RESTORE_POINT: u64 = 2048;
+
+/// A store split into two databases:
+///
+/// - A "hot" database, containing all blocks and states that are not yet finalized. Reads and
+///   writes are identical to a `DiskStore`.
+/// - A "cold" freezer database, containing finalized blocks and states from the canonical chain.
+///   The freezer is append-only and keyed by slot, with an index from each root to its slot.
+///
+/// Blocks and states are migrated from the hot database to the freezer by `freeze_to_state`,
+/// which is called by the `BeaconChain` each time finalization advances.
+///
+/// The freezer only stores one full state per `slots_per_restore_point` slots (a "restore
+/// point"). Other finalized states are reconstructed on demand by loading the prior restore point
+/// and replaying frozen blocks upon it, so a smaller `slots_per_restore_point` trades disk space
+/// for faster reads of frozen states.
+pub struct HotColdDB {
+    /// All blocks and states prior to this slot are stored in the freezer, whilst all blocks and
+    /// states at or after this slot are stored in the hot database.
+    split: RwLock<Split>,
+    /// The maximum number of slots between full states in the freezer.
+    slots_per_restore_point: u64,
+    /// Unfinalized blocks and states, and all other items.
+    hot_db: LevelDB,
+    /// Finalized blocks and states from the canonical chain.
+    cold_db: LevelDB,
+    /// Used when replaying blocks to reconstruct frozen states.
+    spec: ChainSpec,
+}
+
+/// The slot at which the hot database ends and the freezer begins.
+#[derive(Debug, Clone, Copy, PartialEq, Default, Encode, Decode)]
+pub struct Split {
+    pub slot: Slot,
+}
+
+impl StoreItem for Split {
+    fn db_column() -> DBColumn {
+        DBColumn::BeaconChain
+    }
+
+    fn as_store_bytes(&self) -> Vec<u8> {
+        self.as_ssz_bytes()
+    }
+
+    fn from_store_bytes(bytes: &mut [u8]) -> Result<Self, Error> {
+        Self::from_ssz_bytes(bytes).map_err(Into::into)
+    }
+}
+
+/// The parameters with which the freezer was created, which may not be changed afterwards.
+#[derive(Debug, Clone, Copy, PartialEq, Encode, Decode)]
+struct FreezerConfig {
+    slots_per_restore_point: u64,
+}
+
+impl StoreItem for FreezerConfig {
+    fn db_column() -> DBColumn {
+        DBColumn::BeaconChain
+    }
+
+    fn as_store_bytes(&self) -> Vec<u8> {
+        self.as_ssz_bytes()
+    }
+
+    fn from_store_bytes(bytes: &mut [u8]) -> Result<Self, Error> {
+        Self::from_ssz_bytes(bytes).map_err(Into::into)
+    }
+}
+
+impl HotColdDB {
+    /// Open the hot database at `hot_path` and the freezer at `cold_path`, creating new databases
+    /// if they do not already exist.
+    ///
+    /// Returns an error if `slots_per_restore_point` differs from that of an existing freezer.
+    pub fn open(
+        hot_path: &Path,
+        cold_path: &Path,
+        slots_per_restore_point: u64,
+        spec: ChainSpec,
+    ) -> Result<Self, Error> {
+        if slots_per_restore_point == 0 {
+            return Err(Error::InvalidSlotsPerRestorePoint(slots_per_restore_point));
+        }
+
+        let hot_db = LevelDB::open(hot_path)?;
+        let cold_db = LevelDB::open(cold_path)?;
+
+        let config_key = Hash256::from_slice(FREEZER_CONFIG_DB_KEY.as_bytes());
+        match cold_db.get::<FreezerConfig>(&config_key)? {
+            Some(config) if config.slots_per_restore_point != slots_per_restore_point => {
+                return Err(Error::SlotsPerRestorePointChanged {
+                    stored: config.slots_per_restore_point,
+                    configured: slots_per_restore_point,
+                });
+            }
+            Some(_) => (),
+            None => cold_db.put(
+                &config_key,
+                &FreezerConfig {
+                    slots_per_restore_point,
+                },
+            )?,
+        }
+
+        let split: Split = hot_db.get(&split_key())?.unwrap_or_default();
+
+        Ok(Self {
+            split: RwLock::new(split),
+            slots_per_restore_point,
+            hot_db,
+            cold_db,
+            spec,
+        })
+    }
+
+    /// Returns the slot before which all blocks and states are stored in the freezer.
+    pub fn split_slot(&self) -> Slot {
+        self.split.read().slot
+    }
+
+    /// Returns the freezer key for an item at `slot`.
+    fn slot_key(slot: Slot) -> [u8; 8] {
+        slot.as_u64().to_be_bytes()
+    }
+
+    /// Returns the key in `DBColumn::FreezerRestorePoints` for the restore point covering `slot`.
+    fn restore_point_key(&self, slot: Slot) -> [u8; 8] {
+        (slot.as_u64() / self.slots_per_restore_point).to_be_bytes()
+    }
+
+    /// If `column` is stored in the freezer, returns the column of the freezer index that maps
+    /// roots of that column to slots.
+    fn freezer_index_column(column: &str) -> Option<DBColumn> {
+        let block_column: &str = DBColumn::BeaconBlock.into();
+        let state_column: &str = DBColumn::BeaconState.into();
+
+        if column == block_column {
+            Some(DBColumn::FreezerBlockSlots)
+        } else if column == state_column {
+            Some(DBColumn::FreezerStateSlots)
+        } else {
+            None
+        }
+    }
+
+    /// Returns the slot at which the item with the given `root` is stored in the freezer, if any.
+    fn get_freezer_slot(&self, index_column: DBColumn, root: &[u8]) -> Result<Option<Slot>, Error> {
+        match self.cold_db.get_bytes(index_column.into(), root)? {
+            Some(bytes) => Ok(Some(Slot::from_ssz_bytes(&bytes)?)),
+            None => Ok(None),
+        }
+    }
+
+    /// Add a copy of the block with `block_root` from the hot database to the freezer `batch`.
+    fn freeze_block(&self, block_root: &Hash256, batch: &mut WriteBatch) -> Result<(), Error> {
+        let block_column: &str = DBColumn::BeaconBlock.into();
+
+        if let Some(bytes) = self.hot_db.get_bytes(block_column, block_root.as_bytes())? {
+            let slot = read_slot_from_block_bytes(&bytes)?;
+
+            batch.put_bytes(block_column, &Self::slot_key(slot), &bytes);
+            batch.put_bytes(
+                DBColumn::FreezerBlockSlots.into(),
+                block_root.as_bytes(),
+                &slot.as_ssz_bytes(),
+            );
+        }
+
+        Ok(())
+    }
+
+    /// Add the state with `state_root` at `slot` to the freezer `batch`.
+    ///
+    /// The full state is only stored if it is the first state frozen within its restore point
+    /// interval, either in the freezer or in `restore_points`, the intervals already started in
+    /// `batch`. States must therefore be frozen in ascending slot order.
+    fn freeze_state(
+        &self,
+        state_root: &Hash256,
+        slot: Slot,
+        batch: &mut WriteBatch,
+        restore_points: &mut HashSet<[u8; 8]>,
+    ) -> Result<(), Error> {
+        let state_column: &str = DBColumn::BeaconState.into();
+
+        // States for skipped slots are never stored.
+        let bytes = match self.hot_db.get_bytes(state_column, state_root.as_bytes())? {
+            Some(bytes) => bytes,
+            None => return Ok(()),
+        };
+
+        let restore_point_key = self.restore_point_key(slot);
+
+        if !restore_points.contains(&restore_point_key)
+            && !self
+                .cold_db
+                .key_exists(DBColumn::FreezerRestorePoints.into(), &restore_point_key)?
+        {
+            batch.put_bytes(state_column, &Self::slot_key(slot), &bytes);
+            batch.put_bytes(
+                DBColumn::FreezerRestorePoints.into(),
+                &restore_point_key,
+                &slot.as_ssz_bytes(),
+            );
+            restore_points.insert(restore_point_key);
+        }
+
+        batch.put_bytes(
+            DBColumn::FreezerStateSlots.into(),
+            state_root.as_bytes(),
+            &slot.as_ssz_bytes(),
+        );
+
+        Ok(())
+    }
+
+    /// Reconstruct the frozen state with `state_root` at `slot` by replaying frozen blocks upon
+    /// the prior restore point.
+    fn load_frozen_state<E: EthSpec>(
+        &self,
+        state_root: &Hash256,
+        slot: Slot,
+    ) -> Result<BeaconState<E>, Error> {
+        let state_column: &str = DBColumn::BeaconState.into();
+        let block_column: &str = DBColumn::BeaconBlock.into();
+
+        let restore_point_slot = match self.cold_db.get_bytes(
+            DBColumn::FreezerRestorePoints.into(),
+            &self.restore_point_key(slot),
+        )? {
+            Some(bytes) => Slot::from_ssz_bytes(&bytes)?,
+            None => return Err(Error::MissingRestorePoint(slot)),
+        };
+
+        let mut state: BeaconState<E> = match self
+            .cold_db
+            .get_bytes(state_column, &Self::slot_key(restore_point_slot))?
+        {
+            Some(mut bytes) => BeaconState::from_store_bytes(&mut bytes)?,
+            None => return Err(Error::MissingRestorePoint(slot)),
+        };
+
+        while state.slot < slot {
+            per_slot_processing(&mut state, &self.spec)
+                .map_err(|e| Error::StateReconstructionError(format!("{:?}", e)))?;
+
+            if let Some(bytes) = self
+                .cold_db
+                .get_bytes(block_column, &Self::slot_key(state.slot))?
+            {
+                let block = BeaconBlock::from_ssz_bytes(&bytes)?;
+
+                state
+                    .build_committee_cache(RelativeEpoch::Current, &self.spec)
+                    .map_err(|e| Error::StateReconstructionError(format!("{:?}", e)))?;
+                per_block_processing_without_verifying_block_signature(
+                    &mut state, &block, &self.spec,
+                )
+                .map_err(|e| Error::StateReconstructionError(format!("{:?}", e)))?;
+            }
+        }
+
+        if state.canonical_root() != *state_root {
+            return Err(Error::StateReconstructionError(format!(
+                "Replayed state does not match root {}",
+                state_root
+            )));
+        }
+
+        Ok(state)
+    }
+
+    /// Returns the key in `DBColumn::HotBlockSlots` for the block with `block_root` at `slot`.
+    ///
+    /// Keys are ordered by slot, so the blocks in a range of slots may be read without scanning
+    /// the whole database.
+    fn hot_block_slot_key(slot: Slot, block_root: &[u8]) -> Vec<u8> {
+        let mut key = Self::slot_key(slot).to_vec();
+        key.extend_from_slice(block_root);
+        key
+    }
+
+    /// Returns the operations which maintain `DBColumn::HotBlockSlots` when `op` is applied to the
+    /// hot database.
+    fn hot_block_index_op(&self, op: &WriteOp) -> Result<Option<WriteOp>, Error> {
+        let block_column: &str = DBColumn::BeaconBlock.into();
+        let index_column: &str = DBColumn::HotBlockSlots.into();
+
+        Ok(match op {
+            WriteOp::Put { column, key, value } if column == block_column => Some(WriteOp::Put {
+                column: index_column.to_string(),
+                key: Self::hot_block_slot_key(read_slot_from_block_bytes(value)?, key),
+                value: vec![],
+            }),
+            WriteOp::Delete { column, key } if column == block_column => {
+                match self.hot_db.get_bytes(block_column, key)? {
+                    Some(bytes) => Some(WriteOp::Delete {
+                        column: index_column.to_string(),
+                        key: Self::hot_block_slot_key(read_slot_from_block_bytes(&bytes)?, key),
+                    }),
+                    None => None,
+                }
+            }
+            _ => None,
+        })
+    }
+
+    /// Add the deletion of all blocks in the hot database from `from_slot` up to (but excluding)
+    /// `to_slot`, along with their states, to `batch`.
+    ///
+    /// After blocks from the canonical chain have been migrated, the remaining blocks in the range
+    /// are those of forks that were abandoned by finalization.
+    fn prune_hot_db(
+        &self,
+        from_slot: Slot,
+        to_slot: Slot,
+        batch: &mut WriteBatch,
+    ) -> Result<(), Error> {
+        let block_column: &str = DBColumn::BeaconBlock.into();
+        let index_column: &str = DBColumn::HotBlockSlots.into();
+
+        for index_key in self.hot_db.keys_in_range(
+            index_column,
+            &Self::slot_key(from_slot),
+            &Self::slot_key(to_slot),
+        ) {
+            let block_root = &index_key[8..];
+
+            // The genesis block is aliased by the zero hash; the alias is always retained.
+            if block_root == Hash256::zero().as_bytes() {
+                continue;
+            }
+
+            if let Some(bytes) = self.hot_db.get_bytes(block_column, block_root)? {
+                let block = BeaconBlock::from_ssz_bytes(&bytes)?;
+
+                batch.key_delete(DBColumn::BeaconState.into(), block.state_root.as_bytes());
+                batch.key_delete(block_column, block_root);
+            }
+            batch.key_delete(index_column, &index_key);
+        }
+
+        Ok(())
+    }
+}
+
+impl Store for HotColdDB {
+    /// Retrieve some bytes in `column` with `key`, first from the hot database and then from the
+    /// freezer.
+    ///
+    /// Only frozen states at restore points may be read as bytes, use `get_state` to read any
+    /// state.
+    fn get_bytes(&self, column: &str, key: &[u8]) -> Result<Option<Vec<u8>>, Error> {
+        if let Some(bytes) = self.hot_db.get_bytes(column, key)? {
+            return Ok(Some(bytes));
+        }
+
+        match Self::freezer_index_column(column) {
+            Some(index_column) => match self.get_freezer_slot(index_column, key)? {
+                Some(slot) => self.cold_db.get_bytes(column, &Self::slot_key(slot)),
+                None => Ok(None),
+            },
+            None => Ok(None),
+        }
+    }
+
+    /// Store some `value` in `column`, indexed with `key`.
+    ///
+    /// Values are always written to the hot database.
+    fn put_bytes(&self, column: &str, key: &[u8], value: &[u8]) -> Result<(), Error> {
+        let mut batch = WriteBatch::new();
+        batch.put_bytes(column, key, value);
+        self.write_batch(&batch)
+    }
+
+    /// Return `true` if `key` exists in `column`, in either the hot database or the freezer.
+    ///
+    /// Agrees with `get_bytes`, so frozen states which are not restore points do not exist as
+    /// bytes. Use `get_state` to determine if any state is available.
+    fn key_exists(&self, column: &str, key: &[u8]) -> Result<bool, Error> {
+        if self.hot_db.key_exists(column, key)? {
+            return Ok(true);
+        }
+
+        match Self::freezer_index_column(column) {
+            Some(index_column) => match self.get_freezer_slot(index_column, key)? {
+                Some(slot) => self.cold_db.key_exists(column, &Self::slot_key(slot)),
+                None => Ok(false),
+            },
+            None => Ok(false),
+        }
+    }
+
+    /// Removes `key` from `column` in the hot database.
+    ///
+    /// The freezer is append-only, so frozen items are never removed.
+    fn key_delete(&self, column: &str, key: &[u8]) -> Result<(), Error> {
+        let mut batch = WriteBatch::new();
+        batch.key_delete(column, key);
+        self.write_batch(&batch)
+    }
+
+    /// Atomically apply all operations in `batch` to the hot database, along with the updates to
+    /// the index of hot blocks by slot.
+    fn write_batch(&self, batch: &WriteBatch) -> Result<(), Error> {
+        let mut indexed = WriteBatch::new();
+
+        for op in batch.ops() {
+            if let Some(index_op) = self.hot_block_index_op(op)? {
+                indexed.push(index_op);
+            }
+            indexed.push(op.clone());
+        }
+
+        self.hot_db.write_batch(&indexed)
+    }
+
+    /// Calls `f` with every key and value in `column` of the hot database, then of the freezer.
+    ///
+    /// Blocks and states in the freezer are keyed by slot, rather than by root.
+    fn for_each_in_column(
+        &self,
+        column: &str,
+        f: &mut dyn FnMut(&[u8], &[u8]),
+    ) -> Result<(), Error> {
+        self.hot_db.for_each_in_column(column, f)?;
+        self.cold_db.for_each_in_column(column, f)
+    }
+
+    /// Retrieve the state with `state_root` from the hot database or, failing that, reconstruct
+    /// it from the freezer.
+    ///
+    /// Reconstructed states are not cached: each frozen state which is not a restore point costs
+    /// a load of the prior restore point and the replay of up to `slots_per_restore_point - 1`
+    /// slots (and their blocks). Callers reading many frozen states, such as iterators over old
+    /// slots, should prefer the roots stored in a single state or the canonical index.
+    fn get_state<E: EthSpec>(&self, state_root: &Hash256) -> Result<Option<BeaconState<E>>, Error> {
+        if let Some(mut bytes) = self
+            .hot_db
+            .get_bytes(DBColumn::BeaconState.into(), state_root.as_bytes())?
+        {
+            return Ok(Some(BeaconState::from_store_bytes(&mut bytes)?));
+        }
+
+        match self.get_freezer_slot(DBColumn::FreezerStateSlots, state_root.as_bytes())? {
+            Some(slot) => self.load_frozen_state(state_root, slot).map(Some),
+            None => Ok(None),
+        }
+    }
+
+    /// Move all canonical blocks and states prior to `finalized_state` into the freezer, then
+    /// delete all blocks and states prior to `finalized_state` from the hot database.
+    ///
+    /// The finalized block and state themselves remain in the hot database.
+    fn freeze_to_state<E: EthSpec>(
+        store: Arc<Self>,
+        _finalized_state_root: Hash256,
+        finalized_state: &BeaconState<E>,
+    ) -> Result<(), Error> {
+        let current_split_slot = store.split_slot();
+
+        if finalized_state.slot <= current_split_slot {
+            return Ok(());
+        }
+
+        // Collect all roots before modifying the database, since the iterators may need to load
+        // older states.
+        let mut state_roots: Vec<(Hash256, Slot)> =
+            StateRootsIterator::new(store.clone(), finalized_state, finalized_state.slot)
+                .take_while(|(_, slot)| *slot >= current_split_slot)
+                .collect();
+        check_walk_reaches_split(state_roots.last(), current_split_slot)?;
+        state_roots.reverse();
+
+        let block_roots_by_slot: Vec<(Hash256, Slot)> =
+            BlockRootsIterator::new(store.clone(), finalized_state, finalized_state.slot)
+                .take_while(|(_, slot)| *slot >= current_split_slot)
+                .collect();
+        check_walk_reaches_split(block_roots_by_slot.last(), current_split_slot)?;
+
+        let mut seen_block_roots = HashSet::new();
+        let block_roots: Vec<Hash256> = block_roots_by_slot
+            .into_iter()
+            .map(|(root, _)| root)
+            .filter(|root| seen_block_roots.insert(*root))
+            .collect();
+
+        // Blocks are frozen before states, so that every frozen state may be reconstructed.
+        let mut cold_batch = WriteBatch::new();
+        for block_root in &block_roots {
+            store.freeze_block(block_root, &mut cold_batch)?;
+        }
+
+        let mut restore_points = HashSet::new();
+        for (state_root, slot) in &state_roots {
+            store.freeze_state(state_root, *slot, &mut cold_batch, &mut restore_points)?;
+        }
+
+        // The freezer is written atomically before the hot database is modified. If the node
+        // crashes before the split advances, the items remain in the hot database and are frozen
+        // again (overwriting identical values) at the next finalization.
+        store.cold_db.write_batch(&cold_batch)?;
+
+        // The split advances in the same batch that prunes the hot database, so an item is never
+        // in neither database.
+        let split = Split {
+            slot: finalized_state.slot,
+        };
+        let mut hot_batch = WriteBatch::new();
+        hot_batch.put(&split_key(), &split);
+        store.prune_hot_db(current_split_slot, split.slot, &mut hot_batch)?;
+        store.hot_db.write_batch(&hot_batch)?;
+
+        *store.split.write() = split;
+
+        Ok(())
+    }
+}
+
+/// Returns an error unless `lowest`, the last root yielded by a reverse iterator, is at the
+/// `split_slot`.
+///
+/// The root iterators end early, rather than returning an error, if a state cannot be read. A
+/// truncated walk must not be followed by pruning, since the roots it missed would be deleted from
+/// the hot database without having been frozen.
+fn check_walk_reaches_split(
+    lowest: Option<&(Hash256, Slot)>,
+    split_slot: Slot,
+) -> Result<(), Error> {
+    match lowest {
+        Some((_, slot)) if *slot == split_slot => Ok(()),
+        _ => Err(Error::IncompleteFreeze {
+            split_slot,
+            lowest_slot: lowest.map(|(_, slot)| *slot),
+        }),
+    }
+}
+
+/// Returns the key of the `Split` in the hot database.
+fn split_key() -> Hash256 {
+    Hash256::from_slice(SPLIT_DB_KEY.as_bytes())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::tempdir;
+    use tree_hash::TreeHash;
+    use types::test_utils::TestingBeaconStateBuilder;
+
+    fn open(hot_path: &Path, cold_path: &Path, slots_per_restore_point: u64) -> HotColdDB {
+        HotColdDB::open(
+            hot_path,
+            cold_path,
+            slots_per_restore_point,
+            MinimalEthSpec::default_spec(),
+        )
+        .unwrap()
+    }
+
+    #[test]
+    fn reads_fall_back_to_freezer() {
+        let hot_dir = tempdir().unwrap();
+        let cold_dir = tempdir().unwrap();
+        let store = open(hot_dir.path(), cold_dir.path(), 4);
+        let spec = MinimalEthSpec::default_spec();
+
+        let mut block = BeaconBlock::empty(&spec);
+        block.slot = Slot::new(3);
+        let root = Hash256::from_slice(&block.tree_hash_root());
+
+        store.put(&root, &block).unwrap();
+        let mut batch = WriteBatch::new();
+        store.freeze_block(&root, &mut batch).unwrap();
+        store.cold_db.write_batch(&batch).unwrap();
+        store.hot_db.delete::<BeaconBlock>(&root).unwrap();
+
+        assert!(!store.hot_db.exists::<BeaconBlock>(&root).unwrap());
+        assert!(store.exists::<BeaconBlock>(&root).unwrap());
+        assert_eq!(store.get::<BeaconBlock>(&root).unwrap(), Some(block));
+    }
+
+    #[test]
+    fn replays_states_from_restore_point() {
+        let hot_dir = tempdir().unwrap();
+        let cold_dir = tempdir().unwrap();
+        let store = open(hot_dir.path(), cold_dir.path(), 4);
+        let spec = MinimalEthSpec::default_spec();
+
+        let (mut state, _keypairs): (BeaconState<MinimalEthSpec>, _) =
+            TestingBeaconStateBuilder::from_single_keypair(0, &Keypair::random(), &spec).build();
+
+        // Store and freeze a state at each of the first slots, without any blocks.
+        let mut roots = vec![];
+        for _ in 0..6 {
+            let root = state.canonical_root();
+            store.put(&root, &state).unwrap();
+            let mut batch = WriteBatch::new();
+            store
+                .freeze_state(&root, state.slot, &mut batch, &mut HashSet::new())
+                .unwrap();
+            store.cold_db.write_batch(&batch).unwrap();
+            store
+                .hot_db
+                .delete::<BeaconState<MinimalEthSpec>>(&root)
+                .unwrap();
+            roots.push((root, state.clone()));
+
+            per_slot_processing(&mut state, &spec).unwrap();
+        }
+
+        // Only the first state in each restore point interval is stored in full.
+        let state_column: &str = DBColumn::BeaconState.into();
+        for (root, state) in &roots {
+            let is_restore_point = state.slot.as_u64() % 4 == 0;
+            assert_eq!(
+                store
+                    .cold_db
+                    .key_exists(state_column, &HotColdDB::slot_key(state.slot))
+                    .unwrap(),
+                is_restore_point
+            );
+
+            let loaded: BeaconState<MinimalEthSpec> = store.get(root).unwrap().unwrap();
+            assert_eq!(loaded.slot, state.slot);
+            assert_eq!(loaded.canonical_root(), *root);
+        }
+    }
+
+    #[test]
+    fn restore_point_interval_cannot_change() {
+        let hot_dir = tempdir().unwrap();
+        let cold_dir = tempdir().unwrap();
+
+        drop(open(hot_dir.path(), cold_dir.path(), 4));
+
+        assert_eq!(
+            HotColdDB::open(
+                hot_dir.path(),
+                cold_dir.path(),
+                8,
+                MinimalEthSpec::default_spec()
+            )
+            .err(),
+            Some(Error::SlotsPerRestorePointChanged {
+                stored: 4,
+                configured: 8
+            })
+        );
+    }
+
+    #[test]
+    fn split_survives_restart() {
+        let hot_dir = tempdir().unwrap();
+        let cold_dir = tempdir().unwrap();
+
+        {
+            let store = open(hot_dir.path(), cold_dir.path(), 4);
+            assert_eq!(store.split_slot(), Slot::new(0));
+
+            store
+                .hot_db
+                .put(
+                    &split_key(),
+                    &Split {
+                        slot: Slot::new(64),
+                    },
+                )
+                .unwrap();
+        }
+
+        let store = open(hot_dir.path(), cold_dir.path(), 4);
+        assert_eq!(store.split_slot(), Slot::new(64));
+    }
+
+    #[test]
+    fn incomplete_walk_is_not_pruned() {
+        let hot_dir = tempdir().unwrap();
+        let cold_dir = tempdir().unwrap();
+        let store = Arc::new(open(hot_dir.path(), cold_dir.path(), 4));
+        let spec = MinimalEthSpec::default_spec();
+
+        let (mut state, _keypairs): (BeaconState<MinimalEthSpec>, _) =
+            TestingBeaconStateBuilder::from_single_keypair(0, &Keypair::random(), &spec).build();
+
+        let genesis_root = state.canonical_root();
+        store.put(&genesis_root, &state).unwrap();
+
+        // Advance beyond the roots held in a single state, without storing the states between, so
+        // the walk back to the split cannot be completed.
+        while state.slot < MinimalEthSpec::slots_per_historical_root() as u64 + 8 {
+            per_slot_processing(&mut state, &spec).unwrap();
+        }
+
+        assert_eq!(
+            HotColdDB::freeze_to_state(store.clone(), state.canonical_root(), &state),
+            Err(Error::IncompleteFreeze {
+                split_slot: Slot::new(0),
+                lowest_slot: Some(state.slot - MinimalEthSpec::slots_per_historical_root() as u64),
+            })
+        );
+        assert_eq!(store.split_slot(), Slot::new(0));
+        assert!(store
+            .hot_db
+            .exists::<BeaconState<MinimalEthSpec>>(&genesis_root)
+            .unwrap());
+    }
+
+    #[test]
+    fn prunes_hot_blocks_by_slot() {
+        let hot_dir = tempdir().unwrap();
+        let cold_dir = tempdir().unwrap();
+        let store = open(hot_dir.path(), cold_dir.path(), 4);
+        let spec = MinimalEthSpec::default_spec();
+
+        let genesis = BeaconBlock::empty(&spec);
+        store.put(&Hash256::zero(), &genesis).unwrap();
+
+        // A block at each slot, plus a fork at slot 2.
+        let mut roots = vec![];
+        for (slot, state_root) in &[(1, 1), (2, 2), (2, 3), (3, 4), (4, 5)] {
+            let mut block = BeaconBlock::empty(&spec);
+            block.slot = Slot::new(*slot);
+            block.state_root = Hash256::from(*state_root as u64);
+            let root = Hash256::from_slice(&block.tree_hash_root());
+
+            store.put(&root, &block).unwrap();
+            roots.push((root, block.slot));
+        }
+
+        let mut batch = WriteBatch::new();
+        store
+            .prune_hot_db(Slot::new(0), Slot::new(3), &mut batch)
+            .unwrap();
+        store.hot_db.write_batch(&batch).unwrap();
+
+        for (root, slot) in &roots {
+            assert_eq!(
+                store.hot_db.exists::<BeaconBlock>(root).unwrap(),
+                *slot >= 3,
+                "block at slot {}",
+                slot
+            );
+        }
+        assert!(store
+            .hot_db
+            .exists::<BeaconBlock>(&Hash256::zero())
+            .unwrap());
+
+        // Only the index entries of the remaining blocks are retained.
+        let index_column: &str = DBColumn::HotBlockSlots.into();
+        assert_eq!(store.hot_db.keys_in_column(index_column).len(), 3);
+
+        // Deleting a block also removes it from the index.
+        store.delete::<BeaconBlock>(&roots[4].0).unwrap();
+        assert_eq!(store.hot_db.keys_in_column(index_column).len(), 2);
+    }
+}
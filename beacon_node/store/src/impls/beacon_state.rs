@@ -61,4 +61,9 @@ impl<T: EthSpec> StoreItem for BeaconState<T> {
         let container = StorageContainer::from_ssz_bytes(bytes)?;
         container.try_into()
     }
+
+    /// Retrieve the state via `Store::get_state`, allowing the store to reconstruct it.
+    fn db_get(store: &impl Store, key: &Hash256) -> Result<Option<Self>, Error> {
+        store.get_state(key)
+    }
 }
@@ -1,8 +1,10 @@
 use super::*;
 use db_key::Key;
+use leveldb::batch::{Batch, Writebatch};
 use leveldb::database::kv::KV;
 use leveldb::database::Database;
 use leveldb::error::Error as LevelDBError;
+use leveldb::iterator::{Iterable, LevelDBIterator};
 use leveldb::options::{Options, ReadOptions, WriteOptions};
 use std::path::Path;
 use std::sync::Arc;
@@ -35,6 +37,36 @@ impl LevelDB {
         WriteOptions::new()
     }
 
+    /// Returns all keys in `column`, with the column prefix removed.
+    ///
+    /// Reads every key in the database, so should only be used for maintenance tasks.
+    pub fn keys_in_column(&self, column: &str) -> Vec<Vec<u8>> {
+        let prefix = column.as_bytes();
+
+        self.db
+            .keys_iter(self.read_options())
+            .filter(|column_key| column_key.key.starts_with(prefix))
+            .map(|column_key| column_key.key[prefix.len()..].to_vec())
+            .collect()
+    }
+
+    /// Returns the keys in `column` which are at least `from` and less than `to`, with the column
+    /// prefix removed.
+    ///
+    /// Unlike `keys_in_column`, only keys within the range are read.
+    pub fn keys_in_range(&self, column: &str, from: &[u8], to: &[u8]) -> Vec<Vec<u8>> {
+        let prefix_len = column.len();
+        let from = Self::get_key_for_col(column, from);
+        let to = Self::get_key_for_col(column, to);
+
+        self.db
+            .keys_iter(self.read_options())
+            .from(&from)
+            .take_while(|column_key| column_key.key < to.key)
+            .map(|column_key| column_key.key[prefix_len..].to_vec())
+            .collect()
+    }
+
     fn get_key_for_col(col: &str, key: &[u8]) -> BytesKey {
         let mut col = col.as_bytes().to_vec();
         col.append(&mut key.to_vec());
@@ -93,6 +125,50 @@ impl Store for LevelDB {
             .delete(self.write_options(), column_key)
             .map_err(Into::into)
     }
+
+    /// Atomically apply all operations in `batch` using a leveldb write batch.
+    fn write_batch(&self, batch: &WriteBatch) -> Result<(), Error> {
+        let mut leveldb_batch = Writebatch::new();
+
+        for op in batch.ops() {
+            match op {
+                WriteOp::Put { column, key, value } => {
+                    leveldb_batch.put(Self::get_key_for_col(column, key), value);
+                }
+                WriteOp::Delete { column, key } => {
+                    leveldb_batch.delete(Self::get_key_for_col(column, key));
+                }
+            }
+        }
+
+        self.db
+            .write(self.write_options(), &leveldb_batch)
+            .map_err(Into::into)
+    }
+
+    /// Calls `f` with every key and value in `column`, with the column prefix removed from each
+    /// key.
+    ///
+    /// Seeks to the start of the column, so keys in other columns are not read.
+    fn for_each_in_column(
+        &self,
+        column: &str,
+        f: &mut dyn FnMut(&[u8], &[u8]),
+    ) -> Result<(), Error> {
+        let prefix = column.as_bytes();
+        let start = Self::get_key_for_col(column, &[]);
+
+        for (column_key, value) in self
+            .db
+            .iter(self.read_options())
+            .from(&start)
+            .take_while(|(column_key, _)| column_key.key.starts_with(prefix))
+        {
+            f(&column_key.key[prefix.len()..], &value);
+        }
+
+        Ok(())
+    }
 }
 
 impl From<LevelDBError> for Error {
@@ -3,6 +3,9 @@
 //! Provides the following stores:
 //!
 //! - `DiskStore`: an on-disk store backed by leveldb. Used in production.
+//! - `HotColdDB`: a pair of leveldb stores, one for unfinalized data and an append-only freezer
+//! for finalized data which stores only periodic full states.
+//! - `RocksDB`: an on-disk store backed by rocksdb, with a column family per `DBColumn`.
 //! - `MemoryStore`: an in-memory store backed by a hash-map. Used for testing.
 //!
 //! Provides a simple API for storing/retrieving all types that sometimes needs type-hints. See
@@ -10,15 +13,23 @@
 
 mod block_at_slot;
 mod errors;
+mod hot_cold_store;
 mod impls;
 mod leveldb_store;
 mod memory_store;
+mod rocksdb_store;
+mod write_batch;
 
+pub mod canonical_index;
 pub mod iter;
 
+pub use self::hot_cold_store::{HotColdDB, DEFAULT_SLOTS_PER_RESTORE_POINT};
 pub use self::leveldb_store::LevelDB as DiskStore;
 pub use self::memory_store::MemoryStore;
+pub use self::rocksdb_store::RocksDB;
+pub use self::write_batch::{WriteBatch, WriteOp};
 pub use errors::Error;
+use std::sync::Arc;
 pub use types::*;
 
 /// An object capable of storing and retrieving objects implementing `StoreItem`.
@@ -60,6 +71,44 @@ pub trait Store: Sync + Send + Sized {
         block_at_slot::get_block_at_preceeding_slot(self, slot, start_block_root)
     }
 
+    /// Returns the root of the canonical block at `slot`, from the canonical index.
+    ///
+    /// If `slot` was skipped, returns the root of the most recent prior block. Returns `None` if
+    /// `slot` is later than the canonical head.
+    fn get_canonical_block_root(&self, slot: Slot) -> Result<Option<Hash256>, Error> {
+        canonical_index::get_canonical_block_root(self, slot)
+    }
+
+    /// Returns the root of the canonical state at `slot`, from the canonical index.
+    ///
+    /// Returns `None` if `slot` is later than the canonical head.
+    fn get_canonical_state_root(&self, slot: Slot) -> Result<Option<Hash256>, Error> {
+        canonical_index::get_canonical_state_root(self, slot)
+    }
+
+    /// Retrieve the state with `state_root` from `Self`.
+    ///
+    /// Stores that do not keep every state in full (e.g., `HotColdDB`) may override this to
+    /// reconstruct the state on demand. `get::<BeaconState<E>>` uses this method.
+    fn get_state<E: EthSpec>(&self, state_root: &Hash256) -> Result<Option<BeaconState<E>>, Error> {
+        match self.get_bytes(DBColumn::BeaconState.into(), state_root.as_bytes())? {
+            Some(mut bytes) => Ok(Some(BeaconState::from_store_bytes(&mut bytes)?)),
+            None => Ok(None),
+        }
+    }
+
+    /// Inform the store that `finalized_state` (with root `finalized_state_root`) has been
+    /// finalized, allowing it to migrate older blocks and states into long-term storage.
+    ///
+    /// The default implementation does nothing.
+    fn freeze_to_state<E: EthSpec>(
+        _store: Arc<Self>,
+        _finalized_state_root: Hash256,
+        _finalized_state: &BeaconState<E>,
+    ) -> Result<(), Error> {
+        Ok(())
+    }
+
     /// Retrieve some bytes in `column` with `key`.
     fn get_bytes(&self, column: &str, key: &[u8]) -> Result<Option<Vec<u8>>, Error>;
 
@@ -71,6 +120,20 @@ pub trait Store: Sync + Send + Sized {
 
     /// Removes `key` from `column`.
     fn key_delete(&self, column: &str, key: &[u8]) -> Result<(), Error>;
+
+    /// Atomically apply all operations in `batch`.
+    ///
+    /// Either every operation is applied or, if an error is returned, none are.
+    fn write_batch(&self, batch: &WriteBatch) -> Result<(), Error>;
+
+    /// Calls `f` with every key and value in `column`, in an unspecified order.
+    ///
+    /// Reads the entire column, so should only be used for maintenance tasks.
+    fn for_each_in_column(
+        &self,
+        column: &str,
+        f: &mut dyn FnMut(&[u8], &[u8]),
+    ) -> Result<(), Error>;
 }
 
 /// A unique column identifier.
@@ -78,6 +141,35 @@ pub enum DBColumn {
     BeaconBlock,
     BeaconState,
     BeaconChain,
+    /// Maps the root of each block in the freezer to its slot.
+    FreezerBlockSlots,
+    /// Maps the root of each state in the freezer to its slot.
+    FreezerStateSlots,
+    /// Maps each restore point interval in the freezer to the slot of its full state.
+    FreezerRestorePoints,
+    /// Maps each slot to the root of the canonical block at that slot.
+    CanonicalBlockRoots,
+    /// Maps each slot to the root of the canonical state at that slot.
+    CanonicalStateRoots,
+    /// Indexes each block in the hot database by slot, so that blocks may be pruned by slot.
+    HotBlockSlots,
+}
+
+impl DBColumn {
+    /// Returns every column, e.g., for creating one column family per column.
+    pub fn all() -> Vec<DBColumn> {
+        vec![
+            DBColumn::BeaconBlock,
+            DBColumn::BeaconState,
+            DBColumn::BeaconChain,
+            DBColumn::FreezerBlockSlots,
+            DBColumn::FreezerStateSlots,
+            DBColumn::FreezerRestorePoints,
+            DBColumn::CanonicalBlockRoots,
+            DBColumn::CanonicalStateRoots,
+            DBColumn::HotBlockSlots,
+        ]
+    }
 }
 
 impl<'a> Into<&'a str> for DBColumn {
@@ -87,6 +179,12 @@ impl<'a> Into<&'a str> for DBColumn {
             DBColumn::BeaconBlock => &"blk",
             DBColumn::BeaconState => &"ste",
             DBColumn::BeaconChain => &"bch",
+            DBColumn::FreezerBlockSlots => &"fbs",
+            DBColumn::FreezerStateSlots => &"fss",
+            DBColumn::FreezerRestorePoints => &"frp",
+            DBColumn::CanonicalBlockRoots => &"cbr",
+            DBColumn::CanonicalStateRoots => &"csr",
+            DBColumn::HotBlockSlots => &"hbs",
         }
     }
 }
@@ -190,6 +288,57 @@ mod tests {
         assert_eq!(store.get::<StorableThing>(&key), Ok(None));
     }
 
+    fn test_batch_impl(store: impl Store) {
+        let stale_key = Hash256::random();
+        let key = Hash256::random();
+        let other_key = Hash256::random();
+
+        store
+            .put(&stale_key, &StorableThing { a: 0, b: 0 })
+            .unwrap();
+
+        let mut batch = WriteBatch::new();
+        batch.put(&key, &StorableThing { a: 1, b: 1 });
+        batch.put(&other_key, &StorableThing { a: 2, b: 2 });
+        batch.delete::<StorableThing>(&stale_key);
+        // Later operations take precedence over earlier ones.
+        batch.put(&key, &StorableThing { a: 3, b: 3 });
+
+        // Nothing is written until the batch is applied.
+        assert_eq!(store.exists::<StorableThing>(&key), Ok(false));
+
+        store.write_batch(&batch).unwrap();
+
+        assert_eq!(
+            store.get::<StorableThing>(&key),
+            Ok(Some(StorableThing { a: 3, b: 3 }))
+        );
+        assert_eq!(
+            store.get::<StorableThing>(&other_key),
+            Ok(Some(StorableThing { a: 2, b: 2 }))
+        );
+        assert_eq!(store.exists::<StorableThing>(&stale_key), Ok(false));
+    }
+
+    fn test_column_impl(store: impl Store) {
+        let block_column: &str = DBColumn::BeaconBlock.into();
+        let state_column: &str = DBColumn::BeaconState.into();
+
+        store.put_bytes(block_column, &[2], &[20]).unwrap();
+        store.put_bytes(block_column, &[1], &[10]).unwrap();
+        store.put_bytes(state_column, &[3], &[30]).unwrap();
+
+        let mut items = vec![];
+        store
+            .for_each_in_column(block_column, &mut |key, value| {
+                items.push((key.to_vec(), value.to_vec()))
+            })
+            .unwrap();
+        items.sort();
+
+        assert_eq!(items, vec![(vec![1], vec![10]), (vec![2], vec![20])]);
+    }
+
     #[test]
     fn diskdb() {
         let dir = tempdir().unwrap();
@@ -199,6 +348,46 @@ mod tests {
         test_impl(store);
     }
 
+    #[test]
+    fn diskdb_write_batch() {
+        let dir = tempdir().unwrap();
+        let store = DiskStore::open(dir.path()).unwrap();
+
+        test_batch_impl(store);
+    }
+
+    #[test]
+    fn diskdb_columns() {
+        let dir = tempdir().unwrap();
+        let store = DiskStore::open(dir.path()).unwrap();
+
+        test_column_impl(store);
+    }
+
+    #[test]
+    fn rocksdb() {
+        let dir = tempdir().unwrap();
+        let store = RocksDB::open(dir.path()).unwrap();
+
+        test_impl(store);
+    }
+
+    #[test]
+    fn rocksdb_write_batch() {
+        let dir = tempdir().unwrap();
+        let store = RocksDB::open(dir.path()).unwrap();
+
+        test_batch_impl(store);
+    }
+
+    #[test]
+    fn rocksdb_columns() {
+        let dir = tempdir().unwrap();
+        let store = RocksDB::open(dir.path()).unwrap();
+
+        test_column_impl(store);
+    }
+
     #[test]
     fn memorydb() {
         let store = MemoryStore::open();
@@ -206,6 +395,16 @@ mod tests {
         test_impl(store);
     }
 
+    #[test]
+    fn memorydb_write_batch() {
+        test_batch_impl(MemoryStore::open());
+    }
+
+    #[test]
+    fn memorydb_columns() {
+        test_column_impl(MemoryStore::open());
+    }
+
     #[test]
     fn exists() {
         let store = MemoryStore::open();
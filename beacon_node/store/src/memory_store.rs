@@ -1,4 +1,4 @@
-use super::{Error, Store};
+use super::{Error, Store, WriteBatch, WriteOp};
 use parking_lot::RwLock;
 use std::collections::HashMap;
 use std::sync::Arc;
@@ -64,4 +64,35 @@ impl Store for MemoryStore {
 
         Ok(())
     }
+
+    /// Apply all operations in `batch` whilst holding the write lock.
+    fn write_batch(&self, batch: &WriteBatch) -> Result<(), Error> {
+        let mut db = self.db.write();
+
+        for op in batch.ops() {
+            match op {
+                WriteOp::Put { column, key, value } => {
+                    db.insert(MemoryStore::get_key_for_col(column, key), value.clone());
+                }
+                WriteOp::Delete { column, key } => {
+                    db.remove(&MemoryStore::get_key_for_col(column, key));
+                }
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Calls `f` with every key and value in `column`, whilst holding the read lock.
+    fn for_each_in_column(&self, col: &str, f: &mut dyn FnMut(&[u8], &[u8])) -> Result<(), Error> {
+        let prefix = col.as_bytes();
+
+        for (column_key, value) in self.db.read().iter() {
+            if column_key.starts_with(prefix) {
+                f(&column_key[prefix.len()..], value);
+            }
+        }
+
+        Ok(())
+    }
 }
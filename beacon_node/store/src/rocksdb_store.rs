@@ -0,0 +1,173 @@
+use super::*;
+use rocksdb::Error as RocksError;
+use rocksdb::{ColumnFamily, IteratorMode, Options, WriteBatch as RocksWriteBatch, DB};
+use std::path::Path;
+use std::sync::Arc;
+
+/// A wrapped RocksDB database, with one column family per `DBColumn`.
+#[derive(Clone)]
+pub struct RocksDB {
+    /// Shared so that clones of the store use the same database handle.
+    db: Arc<DB>,
+}
+
+impl RocksDB {
+    /// Open a database at `path`, creating a new database if one does not already exist.
+    ///
+    /// Any missing column families are created.
+    pub fn open(path: &Path) -> Result<Self, Error> {
+        let mut options = Options::default();
+
+        options.create_if_missing(true);
+        options.create_missing_column_families(true);
+
+        let columns: Vec<&str> = DBColumn::all().into_iter().map(Into::into).collect();
+
+        let db = Arc::new(DB::open_cf(&options, path, &columns)?);
+
+        Ok(Self { db })
+    }
+
+    /// Returns the column family for `col`, or an error if `col` is not a `DBColumn`.
+    fn cf_handle(&self, col: &str) -> Result<ColumnFamily, Error> {
+        self.db.cf_handle(col).ok_or_else(|| Error::DBError {
+            message: format!("Unknown column: {}", col),
+        })
+    }
+}
+
+impl Store for RocksDB {
+    /// Retrieve some bytes in `column` with `key`.
+    fn get_bytes(&self, col: &str, key: &[u8]) -> Result<Option<Vec<u8>>, Error> {
+        let handle = self.cf_handle(col)?;
+
+        Ok(self.db.get_cf(handle, key)?.map(|value| value.to_vec()))
+    }
+
+    /// Store some `value` in `column`, indexed with `key`.
+    fn put_bytes(&self, col: &str, key: &[u8], val: &[u8]) -> Result<(), Error> {
+        let handle = self.cf_handle(col)?;
+
+        self.db.put_cf(handle, key, val).map_err(Into::into)
+    }
+
+    /// Return `true` if `key` exists in `column`.
+    fn key_exists(&self, col: &str, key: &[u8]) -> Result<bool, Error> {
+        let handle = self.cf_handle(col)?;
+
+        Ok(self.db.get_cf(handle, key)?.is_some())
+    }
+
+    /// Removes `key` from `column`.
+    fn key_delete(&self, col: &str, key: &[u8]) -> Result<(), Error> {
+        let handle = self.cf_handle(col)?;
+
+        self.db.delete_cf(handle, key).map_err(Into::into)
+    }
+
+    /// Atomically apply all operations in `batch` using a RocksDB write batch.
+    fn write_batch(&self, batch: &WriteBatch) -> Result<(), Error> {
+        let mut rocks_batch = RocksWriteBatch::default();
+
+        for op in batch.ops() {
+            match op {
+                WriteOp::Put { column, key, value } => {
+                    rocks_batch.put_cf(self.cf_handle(column)?, key, value)?;
+                }
+                WriteOp::Delete { column, key } => {
+                    rocks_batch.delete_cf(self.cf_handle(column)?, key)?;
+                }
+            }
+        }
+
+        self.db.write(rocks_batch).map_err(Into::into)
+    }
+
+    /// Calls `f` with every key and value in the column family of `column`.
+    fn for_each_in_column(&self, col: &str, f: &mut dyn FnMut(&[u8], &[u8])) -> Result<(), Error> {
+        let handle = self.cf_handle(col)?;
+
+        for (key, value) in self.db.iterator_cf(handle, IteratorMode::Start)? {
+            f(&key, &value);
+        }
+
+        Ok(())
+    }
+}
+
+impl From<RocksError> for Error {
+    fn from(e: RocksError) -> Error {
+        Error::DBError {
+            message: e.to_string(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::thread;
+    use tempfile::tempdir;
+
+    #[test]
+    fn columns_are_separate() {
+        let dir = tempdir().unwrap();
+        let db = RocksDB::open(dir.path()).unwrap();
+
+        db.put_bytes(DBColumn::BeaconBlock.into(), b"key", b"block")
+            .unwrap();
+        db.put_bytes(DBColumn::BeaconState.into(), b"key", b"state")
+            .unwrap();
+
+        assert_eq!(
+            db.get_bytes(DBColumn::BeaconBlock.into(), b"key").unwrap(),
+            Some(b"block".to_vec())
+        );
+        assert_eq!(
+            db.get_bytes(DBColumn::BeaconState.into(), b"key").unwrap(),
+            Some(b"state".to_vec())
+        );
+        assert!(db.get_bytes("unknown", b"key").is_err());
+    }
+
+    #[test]
+    fn concurrent_writes_survive_restart() {
+        let dir = tempdir().unwrap();
+        let col: &str = DBColumn::BeaconChain.into();
+
+        let thread_count = 10;
+        let write_count = 10;
+
+        // We're expecting the product of these numbers to fit in one byte.
+        assert!(thread_count * write_count <= 255);
+
+        {
+            let db = RocksDB::open(dir.path()).unwrap();
+
+            let handles: Vec<_> = (0..thread_count)
+                .map(|t| {
+                    let db = db.clone();
+                    thread::spawn(move || {
+                        for w in 0..write_count {
+                            let key = (t * w) as u8;
+                            db.put_bytes(col, &[key], &[42]).unwrap();
+                        }
+                    })
+                })
+                .collect();
+
+            for handle in handles {
+                handle.join().unwrap();
+            }
+        }
+
+        let db = RocksDB::open(dir.path()).unwrap();
+
+        for t in 0..thread_count {
+            for w in 0..write_count {
+                let key = (t * w) as u8;
+                assert_eq!(db.get_bytes(col, &[key]).unwrap(), Some(vec![42]));
+            }
+        }
+    }
+}
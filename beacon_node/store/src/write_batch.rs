@@ -0,0 +1,81 @@
+use crate::*;
+
+/// A single operation in a `WriteBatch`.
+#[derive(Debug, Clone, PartialEq)]
+pub enum WriteOp {
+    /// Store `value` in `column`, indexed with `key`.
+    Put {
+        column: String,
+        key: Vec<u8>,
+        value: Vec<u8>,
+    },
+    /// Remove `key` from `column`.
+    Delete { column: String, key: Vec<u8> },
+}
+
+/// A list of writes that are applied to a `Store` atomically, via `Store::write_batch`.
+///
+/// Either all operations in the batch are applied, or none are. Operations are applied in the
+/// order in which they were added.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct WriteBatch {
+    ops: Vec<WriteOp>,
+}
+
+impl WriteBatch {
+    /// Create a new, empty batch.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Add a write of `item` with `key` to the batch.
+    pub fn put<I: StoreItem>(&mut self, key: &Hash256, item: &I) {
+        self.put_bytes(
+            I::db_column().into(),
+            key.as_bytes(),
+            &item.as_store_bytes(),
+        );
+    }
+
+    /// Add a deletion of the item of type `I` with `key` to the batch.
+    pub fn delete<I: StoreItem>(&mut self, key: &Hash256) {
+        self.key_delete(I::db_column().into(), key.as_bytes());
+    }
+
+    /// Add a write of some `value` in `column`, indexed with `key`, to the batch.
+    pub fn put_bytes(&mut self, column: &str, key: &[u8], value: &[u8]) {
+        self.ops.push(WriteOp::Put {
+            column: column.to_string(),
+            key: key.to_vec(),
+            value: value.to_vec(),
+        });
+    }
+
+    /// Add a deletion of `key` from `column` to the batch.
+    pub fn key_delete(&mut self, column: &str, key: &[u8]) {
+        self.ops.push(WriteOp::Delete {
+            column: column.to_string(),
+            key: key.to_vec(),
+        });
+    }
+
+    /// Add an existing operation to the batch.
+    pub fn push(&mut self, op: WriteOp) {
+        self.ops.push(op);
+    }
+
+    /// Returns the operations in the batch, in the order they were added.
+    pub fn ops(&self) -> &[WriteOp] {
+        &self.ops
+    }
+
+    /// Returns the number of operations in the batch.
+    pub fn len(&self) -> usize {
+        self.ops.len()
+    }
+
+    /// Returns `true` if the batch contains no operations.
+    pub fn is_empty(&self) -> bool {
+        self.ops.is_empty()
+    }
+}
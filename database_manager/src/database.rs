@@ -0,0 +1,493 @@
+use beacon_chain::lmd_ghost::LmdGhost;
+use beacon_chain::{
+    BeaconChainTypes, PersistedBeaconChain, PersistedForkChoice, BEACON_CHAIN_DB_KEY,
+    FORK_CHOICE_DB_KEY,
+};
+use client::{ClientConfig, ClientType};
+use ssz::Decode;
+use std::collections::{HashMap, HashSet};
+use std::marker::PhantomData;
+use std::path::PathBuf;
+use std::sync::Arc;
+use store::{DBColumn, DiskStore, Error as StoreError, HotColdDB, RocksDB, Store, WriteBatch};
+use types::{BeaconBlock, ChainSpec, EthSpec, Hash256};
+
+/// The `BeaconChainTypes` of a beacon node using the store `S`.
+pub type NodeTypes<S, E> = ClientType<S, E>;
+
+/// A store which may be written by the beacon node, and opened from its `ClientConfig`.
+pub trait OpenStore: Store {
+    /// Open the existing database described by `client_config`.
+    ///
+    /// Returns an error if there is no database, instead of creating one.
+    fn open_existing(client_config: &ClientConfig, spec: &ChainSpec) -> Result<Self, String>;
+}
+
+/// Returns `path` if it exists.
+fn existing_path(path: Option<PathBuf>) -> Result<PathBuf, String> {
+    let path = path.ok_or_else(|| "Failed to access the data directory".to_string())?;
+
+    if path.exists() {
+        Ok(path)
+    } else {
+        Err(format!("No database found at {:?}", path))
+    }
+}
+
+impl OpenStore for DiskStore {
+    fn open_existing(client_config: &ClientConfig, _spec: &ChainSpec) -> Result<Self, String> {
+        let path = existing_path(client_config.db_path())?;
+
+        DiskStore::open(&path).map_err(|e| format!("Unable to open database: {:?}", e))
+    }
+}
+
+impl OpenStore for HotColdDB {
+    fn open_existing(client_config: &ClientConfig, spec: &ChainSpec) -> Result<Self, String> {
+        let hot_path = existing_path(client_config.hot_db_path())?;
+        let cold_path = existing_path(client_config.freezer_db_path())?;
+
+        HotColdDB::open(
+            &hot_path,
+            &cold_path,
+            client_config.slots_per_restore_point,
+            spec.clone(),
+        )
+        .map_err(|e| format!("Unable to open database: {:?}", e))
+    }
+}
+
+impl OpenStore for RocksDB {
+    fn open_existing(client_config: &ClientConfig, _spec: &ChainSpec) -> Result<Self, String> {
+        let path = existing_path(client_config.rocks_db_path())?;
+
+        RocksDB::open(&path).map_err(|e| format!("Unable to open database: {:?}", e))
+    }
+}
+
+/// The number of entries in some column, and the number of bytes they occupy.
+pub struct ColumnStats {
+    pub column: &'static str,
+    pub count: usize,
+    pub key_bytes: usize,
+    pub value_bytes: usize,
+}
+
+/// The number of items deleted (or that would be deleted) by `Database::prune`.
+#[derive(Debug, Default, PartialEq)]
+pub struct PruneSummary {
+    pub blocks: usize,
+    pub states: usize,
+    /// Blocks which would otherwise have been deleted, but are referenced by the persisted fork
+    /// choice or operation pool.
+    pub referenced: usize,
+}
+
+/// An existing beacon node database, opened for inspection and maintenance.
+///
+/// The beacon node must not be running, as the database may only be opened by one process.
+pub struct Database<S, E> {
+    store: Arc<S>,
+    _phantom: PhantomData<E>,
+}
+
+impl<S: Store, E: EthSpec + Clone> Database<S, E> {
+    /// Wrap an open `store`.
+    pub fn new(store: Arc<S>) -> Self {
+        Self {
+            store,
+            _phantom: PhantomData,
+        }
+    }
+
+    /// Returns the number of entries and their sizes for each of `columns`.
+    ///
+    /// Reads every entry in each column, so is proportional to the size of those columns.
+    pub fn column_stats(&self, columns: Vec<DBColumn>) -> Result<Vec<ColumnStats>, StoreError> {
+        columns
+            .into_iter()
+            .map(|column| {
+                let column: &'static str = column.into();
+                let mut stats = ColumnStats {
+                    column,
+                    count: 0,
+                    key_bytes: 0,
+                    value_bytes: 0,
+                };
+
+                self.store.for_each_in_column(column, &mut |key, value| {
+                    stats.count += 1;
+                    stats.key_bytes += key.len();
+                    stats.value_bytes += value.len();
+                })?;
+
+                Ok(stats)
+            })
+            .collect()
+    }
+
+    /// Returns the `PersistedBeaconChain`, if any.
+    pub fn persisted_chain(
+        &self,
+    ) -> Result<Option<PersistedBeaconChain<NodeTypes<S, E>>>, StoreError> {
+        let key = Hash256::from_slice(BEACON_CHAIN_DB_KEY.as_bytes());
+        self.store.get(&key)
+    }
+
+    /// Returns every block in the database with its root, ordered by slot.
+    ///
+    /// The genesis block is only returned once, although it is also stored under the zero hash.
+    /// Blocks in the freezer of a `HotColdDB` are keyed by slot instead of root, and are not
+    /// returned.
+    pub fn blocks(&self) -> Result<Vec<(Hash256, BeaconBlock)>, StoreError> {
+        let mut blocks = vec![];
+        let mut error = None;
+
+        self.store
+            .for_each_in_column(DBColumn::BeaconBlock.into(), &mut |key, value| {
+                if key.len() != 32 || key == Hash256::zero().as_bytes() || error.is_some() {
+                    return;
+                }
+
+                match BeaconBlock::from_ssz_bytes(value) {
+                    Ok(block) => blocks.push((Hash256::from_slice(key), block)),
+                    Err(e) => error = Some(e),
+                }
+            })?;
+
+        if let Some(e) = error {
+            return Err(e.into());
+        }
+
+        blocks.sort_by_key(|(root, block)| (block.slot, *root));
+
+        Ok(blocks)
+    }
+
+    /// Returns every block whose state is not in the database.
+    pub fn blocks_without_states(&self) -> Result<Vec<(Hash256, BeaconBlock)>, StoreError> {
+        let state_column: &str = DBColumn::BeaconState.into();
+        let mut missing = vec![];
+
+        for (root, block) in self.blocks()? {
+            if !self
+                .store
+                .key_exists(state_column, block.state_root.as_bytes())?
+            {
+                missing.push((root, block));
+            }
+        }
+
+        Ok(missing)
+    }
+
+    /// Returns the roots of all blocks referenced by the persisted fork choice and the
+    /// attestations in the persisted operation pool.
+    ///
+    /// Deleting any of these blocks would prevent the beacon node from restarting.
+    fn referenced_roots(
+        &self,
+        persisted_chain: &PersistedBeaconChain<NodeTypes<S, E>>,
+    ) -> Result<HashSet<Hash256>, String> {
+        let mut roots = HashSet::new();
+
+        for (_, attestations) in &persisted_chain.op_pool.attestations {
+            for attestation in attestations {
+                roots.insert(attestation.data.beacon_block_root);
+                roots.insert(attestation.data.source_root);
+                roots.insert(attestation.data.target_root);
+            }
+        }
+
+        let fork_choice_key = Hash256::from_slice(FORK_CHOICE_DB_KEY.as_bytes());
+        let persisted_fork_choice: Option<PersistedForkChoice> =
+            self.store
+                .get(&fork_choice_key)
+                .map_err(|e| format!("Unable to read persisted fork choice: {:?}", e))?;
+
+        if let Some(persisted_fork_choice) = persisted_fork_choice {
+            let backend = <NodeTypes<S, E> as BeaconChainTypes>::LmdGhost::from_bytes(
+                &persisted_fork_choice.backend_bytes,
+                self.store.clone(),
+            )
+            .map_err(|e| format!("Unable to decode persisted fork choice: {}", e))?;
+            let dump = backend
+                .dump()
+                .map_err(|e| format!("Unable to read persisted fork choice: {}", e))?;
+
+            roots.insert(dump.root);
+            roots.extend(dump.nodes.iter().map(|node| node.block_root));
+        }
+
+        Ok(roots)
+    }
+
+    /// Delete all blocks that are neither ancestors nor descendants of the finalized block of the
+    /// persisted head, along with their states.
+    ///
+    /// Blocks referenced by the persisted fork choice or operation pool are kept, so that the
+    /// beacon node may always restart.
+    ///
+    /// If `dry_run` is `true`, nothing is deleted.
+    pub fn prune(&self, dry_run: bool) -> Result<PruneSummary, String> {
+        let persisted_chain = self
+            .persisted_chain()
+            .map_err(|e| format!("Unable to read persisted chain: {:?}", e))?
+            .ok_or_else(|| "Database does not contain a persisted chain".to_string())?;
+
+        // The finalized root is the zero hash until the first epoch is finalized.
+        let finalized_root = match persisted_chain.canonical_head.beacon_state.finalized_root {
+            root if root == Hash256::zero() => persisted_chain.genesis_block_root,
+            root => root,
+        };
+
+        let blocks: HashMap<Hash256, BeaconBlock> = self
+            .blocks()
+            .map_err(|e| format!("Unable to read blocks: {:?}", e))?
+            .into_iter()
+            .collect();
+
+        if !blocks.contains_key(&finalized_root) {
+            return Err(format!(
+                "Finalized block {} is not in the database",
+                finalized_root
+            ));
+        }
+
+        let mut children: HashMap<Hash256, Vec<Hash256>> = HashMap::new();
+        for (root, block) in &blocks {
+            children
+                .entry(block.previous_block_root)
+                .or_default()
+                .push(*root);
+        }
+
+        let mut reachable = HashSet::new();
+
+        // Ancestors of the finalized block (including itself).
+        let mut root = finalized_root;
+        while let Some(block) = blocks.get(&root) {
+            if !reachable.insert(root) {
+                break;
+            }
+            root = block.previous_block_root;
+        }
+
+        // Descendants of the finalized block.
+        let mut queue = vec![finalized_root];
+        while let Some(root) = queue.pop() {
+            for child in children.get(&root).into_iter().flatten() {
+                if reachable.insert(*child) {
+                    queue.push(*child);
+                }
+            }
+        }
+
+        let mut summary = PruneSummary::default();
+
+        let referenced = self.referenced_roots(&persisted_chain)?;
+        for root in blocks.keys() {
+            if !reachable.contains(root) && referenced.contains(root) {
+                reachable.insert(*root);
+                summary.referenced += 1;
+            }
+        }
+
+        let reachable_states: HashSet<Hash256> = reachable
+            .iter()
+            .filter_map(|root| blocks.get(root))
+            .map(|block| block.state_root)
+            .collect();
+
+        let mut batch = WriteBatch::new();
+
+        for (root, block) in blocks.iter().filter(|(root, _)| !reachable.contains(root)) {
+            batch.delete::<BeaconBlock>(root);
+            summary.blocks += 1;
+
+            let state_column: &str = DBColumn::BeaconState.into();
+            let state_key = block.state_root.as_bytes();
+
+            if !reachable_states.contains(&block.state_root)
+                && self
+                    .store
+                    .key_exists(state_column, state_key)
+                    .map_err(|e| format!("Unable to read state: {:?}", e))?
+            {
+                batch.key_delete(state_column, state_key);
+                summary.states += 1;
+            }
+        }
+
+        if !dry_run {
+            self.store
+                .write_batch(&batch)
+                .map_err(|e| format!("Unable to delete items: {:?}", e))?;
+        }
+
+        Ok(summary)
+    }
+}
+
+#[cfg(test)]
+#[cfg(not(debug_assertions))]
+mod tests {
+    use super::*;
+    use beacon_chain::lmd_ghost::ThreadSafeReducedTree;
+    use beacon_chain::test_utils::{AttestationStrategy, BeaconChainHarness, BlockStrategy};
+    use beacon_chain::BeaconChain;
+    use operation_pool::AttestationId;
+    use store::MemoryStore;
+    use types::test_utils::{SeedableRng, TestRandom, XorShiftRng};
+    use types::{Attestation, MinimalEthSpec, Slot};
+
+    type TestForkChoice = ThreadSafeReducedTree<MemoryStore, MinimalEthSpec>;
+    type Harness = BeaconChainHarness<TestForkChoice, MinimalEthSpec>;
+
+    const VALIDATOR_COUNT: usize = 24;
+
+    /// Builds a finalized chain with a single fork block which branches before the finalized
+    /// block, and persists it. Returns the harness and the root of the fork block.
+    fn get_forked_harness() -> (Harness, Hash256) {
+        let harness = Harness::new(VALIDATOR_COUNT);
+        harness.advance_slot();
+
+        harness.extend_chain(
+            2,
+            BlockStrategy::OnCanonicalHead,
+            AttestationStrategy::AllValidators,
+        );
+        harness.advance_slot();
+        harness.extend_chain(
+            1,
+            BlockStrategy::OnCanonicalHead,
+            AttestationStrategy::AllValidators,
+        );
+
+        // A block at slot 4 upon the block at slot 2, which no validator attests to.
+        let fork_root = harness.extend_chain(
+            1,
+            BlockStrategy::ForkCanonicalChainAt {
+                previous_slot: Slot::new(2),
+                first_slot: Slot::new(4),
+            },
+            AttestationStrategy::SomeValidators(vec![]),
+        );
+
+        harness.extend_chain(
+            MinimalEthSpec::slots_per_epoch() as usize * 5,
+            BlockStrategy::OnCanonicalHead,
+            AttestationStrategy::AllValidators,
+        );
+        assert!(
+            harness.chain.head().beacon_state.finalized_epoch > 0,
+            "chain should finalize"
+        );
+        assert_ne!(
+            harness.chain.head().beacon_block_root,
+            fork_root,
+            "fork should not be the head"
+        );
+
+        harness.chain.persist().unwrap();
+
+        (harness, fork_root)
+    }
+
+    fn get_database(harness: &Harness) -> Database<MemoryStore, MinimalEthSpec> {
+        Database::new(harness.chain.store.clone())
+    }
+
+    #[test]
+    fn dry_run_prune_deletes_nothing() {
+        let (harness, fork_root) = get_forked_harness();
+        let db = get_database(&harness);
+        let num_blocks = db.blocks().unwrap().len();
+
+        assert_eq!(
+            db.prune(true),
+            Ok(PruneSummary {
+                blocks: 1,
+                states: 1,
+                referenced: 0,
+            })
+        );
+
+        assert_eq!(db.blocks().unwrap().len(), num_blocks);
+        assert!(harness
+            .chain
+            .store
+            .exists::<BeaconBlock>(&fork_root)
+            .unwrap());
+    }
+
+    #[test]
+    fn prune_deletes_fork() {
+        let (harness, fork_root) = get_forked_harness();
+        let db = get_database(&harness);
+        let num_blocks = db.blocks().unwrap().len();
+
+        assert_eq!(
+            db.prune(false),
+            Ok(PruneSummary {
+                blocks: 1,
+                states: 1,
+                referenced: 0,
+            })
+        );
+
+        assert_eq!(db.blocks().unwrap().len(), num_blocks - 1);
+        assert!(!harness
+            .chain
+            .store
+            .exists::<BeaconBlock>(&fork_root)
+            .unwrap());
+        assert!(db.blocks_without_states().unwrap().is_empty());
+
+        let restored = BeaconChain::<NodeTypes<MemoryStore, MinimalEthSpec>>::from_store(
+            harness.chain.store.clone(),
+            harness.spec.clone(),
+        )
+        .unwrap()
+        .expect("should load persisted chain");
+
+        assert_eq!(
+            restored.head().beacon_block_root,
+            harness.chain.head().beacon_block_root
+        );
+        assert_eq!(db.prune(true), Ok(PruneSummary::default()));
+    }
+
+    #[test]
+    fn prune_keeps_blocks_referenced_by_op_pool() {
+        let (harness, fork_root) = get_forked_harness();
+        let db = get_database(&harness);
+
+        let mut persisted_chain = db.persisted_chain().unwrap().unwrap();
+
+        let mut attestation = Attestation::random_for_test(&mut XorShiftRng::from_seed([42; 16]));
+        attestation.data.beacon_block_root = fork_root;
+        let id = AttestationId::from_data(&attestation.data, &persisted_chain.state, &harness.spec);
+        persisted_chain
+            .op_pool
+            .attestations
+            .push((id, vec![attestation]));
+
+        let key = Hash256::from_slice(BEACON_CHAIN_DB_KEY.as_bytes());
+        harness.chain.store.put(&key, &persisted_chain).unwrap();
+
+        assert_eq!(
+            db.prune(false),
+            Ok(PruneSummary {
+                blocks: 0,
+                states: 0,
+                referenced: 1,
+            })
+        );
+        assert!(harness
+            .chain
+            .store
+            .exists::<BeaconBlock>(&fork_root)
+            .unwrap());
+    }
+}
@@ -0,0 +1,375 @@
+mod database;
+
+use clap::{App, Arg, ArgMatches, SubCommand};
+use client::ClientConfig;
+use database::{Database, OpenStore};
+use slog::{crit, info, o, warn, Drain};
+use std::path::PathBuf;
+use std::sync::Arc;
+use store::{DBColumn, DiskStore, HotColdDB, RocksDB, Store};
+use types::{EthSpec, MainnetEthSpec, MinimalEthSpec, Slot};
+
+pub const DEFAULT_DATA_DIR: &str = ".lighthouse";
+
+/// The names of the columns accepted by `stats --column`, as returned by `DBColumn::into`.
+const COLUMN_NAMES: [&str; 9] = [
+    "blk", "ste", "bch", "fbs", "fss", "frp", "cbr", "csr", "hbs",
+];
+
+fn main() {
+    // Logging
+    let decorator = slog_term::TermDecorator::new().build();
+    let drain = slog_term::CompactFormat::new(decorator).build().fuse();
+    let drain = slog_async::Async::new(drain).build().fuse();
+    let log = slog::Logger::root(drain, o!());
+
+    // CLI
+    let matches = App::new("Lighthouse Database Manager")
+        .version("0.0.1")
+        .author("Sigma Prime <contact@sigmaprime.io>")
+        .about("Inspects and maintains the database of a stopped Lighthouse beacon node")
+        .arg(
+            Arg::with_name("datadir")
+                .long("datadir")
+                .short("d")
+                .value_name("DIR")
+                .help("Data directory of the beacon node.")
+                .takes_value(true),
+        )
+        .arg(
+            Arg::with_name("spec-constants")
+                .long("spec-constants")
+                .value_name("TITLE")
+                .short("s")
+                .help("The title of the spec constants used by the beacon node.")
+                .takes_value(true)
+                .possible_values(&["mainnet", "minimal"])
+                .default_value("minimal"),
+        )
+        .arg(
+            Arg::with_name("db")
+                .long("db")
+                .value_name("DB")
+                .help("Type of database used by the beacon node.")
+                .takes_value(true)
+                .possible_values(&["disk", "hot_cold", "rocksdb"])
+                .default_value("disk"),
+        )
+        .arg(
+            Arg::with_name("slots-per-restore-point")
+                .long("slots-per-restore-point")
+                .value_name("SLOT_COUNT")
+                .help("The number of slots between full states in the freezer database, as used by the beacon node.")
+                .takes_value(true),
+        )
+        .subcommand(
+            SubCommand::with_name("stats")
+                .about("Reports the number of entries and bytes used by each database column")
+                .version("0.0.1")
+                .author("Sigma Prime <contact@sigmaprime.io>")
+                .arg(
+                    Arg::with_name("column")
+                        .long("column")
+                        .value_name("COLUMN")
+                        .help("Only reads the given column, instead of every column.")
+                        .takes_value(true)
+                        .possible_values(&COLUMN_NAMES),
+                ),
+        )
+        .subcommand(
+            SubCommand::with_name("head")
+                .about("Prints the persisted head of the beacon chain")
+                .version("0.0.1")
+                .author("Sigma Prime <contact@sigmaprime.io>"),
+        )
+        .subcommand(
+            SubCommand::with_name("blocks")
+                .about("Lists all stored blocks, ordered by slot")
+                .version("0.0.1")
+                .author("Sigma Prime <contact@sigmaprime.io>")
+                .arg(
+                    Arg::with_name("from")
+                        .long("from")
+                        .value_name("SLOT")
+                        .help("The lowest slot to list.")
+                        .takes_value(true),
+                )
+                .arg(
+                    Arg::with_name("to")
+                        .long("to")
+                        .value_name("SLOT")
+                        .help("The highest slot to list.")
+                        .takes_value(true),
+                ),
+        )
+        .subcommand(
+            SubCommand::with_name("verify")
+                .about("Checks that the state of every stored block is also stored")
+                .version("0.0.1")
+                .author("Sigma Prime <contact@sigmaprime.io>"),
+        )
+        .subcommand(
+            SubCommand::with_name("prune")
+                .about("Deletes blocks and states that conflict with the finalized block")
+                .version("0.0.1")
+                .author("Sigma Prime <contact@sigmaprime.io>")
+                .arg(
+                    Arg::with_name("dry-run")
+                        .long("dry-run")
+                        .help("Reports what would be deleted, without deleting it."),
+                ),
+        )
+        .get_matches();
+
+    let data_dir = match matches
+        .value_of("datadir")
+        .and_then(|v| Some(PathBuf::from(v)))
+    {
+        Some(v) => v,
+        None => {
+            // use the default
+            let mut default_dir = match dirs::home_dir() {
+                Some(v) => v,
+                None => {
+                    crit!(log, "Failed to find a home directory");
+                    return;
+                }
+            };
+            default_dir.push(DEFAULT_DATA_DIR);
+            default_dir
+        }
+    };
+
+    let mut client_config = ClientConfig::default();
+    client_config.data_dir = data_dir;
+
+    if let Some(slots) = matches.value_of("slots-per-restore-point") {
+        client_config.slots_per_restore_point = match slots.parse() {
+            Ok(slots) => slots,
+            Err(_) => {
+                crit!(log, "Invalid slots per restore point"; "value" => slots);
+                return;
+            }
+        };
+    }
+
+    let db_type = matches.value_of("db").expect("Has a default value");
+
+    info!(
+        log,
+        "Opening database";
+        "db" => db_type,
+        "data_dir" => format!("{:?}", client_config.data_dir)
+    );
+
+    let result = match (db_type, matches.value_of("spec-constants")) {
+        ("disk", Some("mainnet")) => {
+            run::<DiskStore, MainnetEthSpec>(&matches, &client_config, &log)
+        }
+        ("disk", Some("minimal")) => {
+            run::<DiskStore, MinimalEthSpec>(&matches, &client_config, &log)
+        }
+        ("hot_cold", Some("mainnet")) => {
+            run::<HotColdDB, MainnetEthSpec>(&matches, &client_config, &log)
+        }
+        ("hot_cold", Some("minimal")) => {
+            run::<HotColdDB, MinimalEthSpec>(&matches, &client_config, &log)
+        }
+        ("rocksdb", Some("mainnet")) => {
+            run::<RocksDB, MainnetEthSpec>(&matches, &client_config, &log)
+        }
+        ("rocksdb", Some("minimal")) => {
+            run::<RocksDB, MinimalEthSpec>(&matches, &client_config, &log)
+        }
+        _ => unreachable!(), // Guarded by clap.
+    };
+
+    if let Err(e) = result {
+        crit!(log, "Database command failed"; "error" => e);
+        // Allow the async logger to flush before exiting.
+        drop(log);
+        std::process::exit(1);
+    }
+}
+
+fn run<S: OpenStore, E: EthSpec + Clone>(
+    matches: &ArgMatches,
+    client_config: &ClientConfig,
+    log: &slog::Logger,
+) -> Result<(), String> {
+    let store = S::open_existing(client_config, &E::default_spec())?;
+    let db: Database<S, E> = Database::new(Arc::new(store));
+
+    match matches.subcommand() {
+        ("stats", Some(m)) => {
+            let columns = match m.value_of("column") {
+                Some(name) => DBColumn::all()
+                    .into_iter()
+                    .filter(|column| {
+                        let column_name: &str = column.into();
+                        column_name == name
+                    })
+                    .collect(),
+                None => DBColumn::all(),
+            };
+            print_stats(&db, columns)
+        }
+        ("head", Some(_)) => print_head(&db),
+        ("blocks", Some(m)) => {
+            let from = parse_slot(m.value_of("from"))?.unwrap_or_else(|| Slot::new(0));
+            let to = parse_slot(m.value_of("to"))?.unwrap_or_else(Slot::max_value);
+            print_blocks(&db, from, to)
+        }
+        ("verify", Some(_)) => verify(&db, log),
+        ("prune", Some(m)) => prune(&db, m.is_present("dry-run"), log),
+        _ => Err(
+            "The database manager must be run with a subcommand. See help for more information."
+                .into(),
+        ),
+    }
+}
+
+fn parse_slot(value: Option<&str>) -> Result<Option<Slot>, String> {
+    value
+        .map(|v| {
+            v.parse::<u64>()
+                .map(Slot::new)
+                .map_err(|_| format!("Invalid slot: {}", v))
+        })
+        .transpose()
+}
+
+fn print_stats<S: Store, E: EthSpec + Clone>(
+    db: &Database<S, E>,
+    columns: Vec<DBColumn>,
+) -> Result<(), String> {
+    let column_stats = db
+        .column_stats(columns)
+        .map_err(|e| format!("Unable to read database: {:?}", e))?;
+
+    println!(
+        "{:<8} {:>12} {:>16} {:>16}",
+        "column", "entries", "key bytes", "value bytes"
+    );
+
+    for stats in column_stats {
+        println!(
+            "{:<8} {:>12} {:>16} {:>16}",
+            stats.column, stats.count, stats.key_bytes, stats.value_bytes
+        );
+    }
+
+    Ok(())
+}
+
+fn print_head<S: Store, E: EthSpec + Clone>(db: &Database<S, E>) -> Result<(), String> {
+    let persisted_chain = db
+        .persisted_chain()
+        .map_err(|e| format!("Unable to read persisted chain: {:?}", e))?
+        .ok_or_else(|| "Database does not contain a persisted chain".to_string())?;
+
+    let head = &persisted_chain.canonical_head;
+    let state = &head.beacon_state;
+
+    println!("{:<24} {:?}", "head_block_root:", head.beacon_block_root);
+    println!("{:<24} {}", "head_slot:", head.beacon_block.slot);
+    println!("{:<24} {:?}", "head_state_root:", head.beacon_state_root);
+    println!(
+        "{:<24} {}",
+        "current_justified_epoch:", state.current_justified_epoch
+    );
+    println!(
+        "{:<24} {:?}",
+        "current_justified_root:", state.current_justified_root
+    );
+    println!("{:<24} {}", "finalized_epoch:", state.finalized_epoch);
+    println!("{:<24} {:?}", "finalized_root:", state.finalized_root);
+    println!(
+        "{:<24} {:?}",
+        "genesis_block_root:", persisted_chain.genesis_block_root
+    );
+    println!("{:<24} {}", "present_slot:", persisted_chain.state.slot);
+
+    Ok(())
+}
+
+fn print_blocks<S: Store, E: EthSpec + Clone>(
+    db: &Database<S, E>,
+    from: Slot,
+    to: Slot,
+) -> Result<(), String> {
+    let blocks = db
+        .blocks()
+        .map_err(|e| format!("Unable to read blocks: {:?}", e))?;
+
+    println!(
+        "{:>10} {:<66} {:<66} {:<66}",
+        "slot", "block_root", "parent_root", "state_root"
+    );
+
+    for (root, block) in blocks
+        .iter()
+        .filter(|(_, block)| block.slot >= from && block.slot <= to)
+    {
+        println!(
+            "{:>10} {:<66?} {:<66?} {:<66?}",
+            block.slot, root, block.previous_block_root, block.state_root
+        );
+    }
+
+    Ok(())
+}
+
+fn verify<S: Store, E: EthSpec + Clone>(
+    db: &Database<S, E>,
+    log: &slog::Logger,
+) -> Result<(), String> {
+    let missing = db
+        .blocks_without_states()
+        .map_err(|e| format!("Unable to read database: {:?}", e))?;
+
+    for (root, block) in &missing {
+        warn!(
+            log,
+            "Block state is missing";
+            "block_root" => format!("{:?}", root),
+            "slot" => block.slot,
+            "state_root" => format!("{:?}", block.state_root),
+        );
+    }
+
+    if missing.is_empty() {
+        info!(log, "Database is consistent");
+        Ok(())
+    } else {
+        Err(format!("{} blocks are missing states", missing.len()))
+    }
+}
+
+fn prune<S: Store, E: EthSpec + Clone>(
+    db: &Database<S, E>,
+    dry_run: bool,
+    log: &slog::Logger,
+) -> Result<(), String> {
+    let summary = db.prune(dry_run)?;
+
+    if dry_run {
+        info!(
+            log,
+            "Found prunable items";
+            "blocks" => summary.blocks,
+            "states" => summary.states,
+            "kept_referenced_blocks" => summary.referenced,
+        );
+    } else {
+        info!(
+            log,
+            "Pruned database";
+            "blocks" => summary.blocks,
+            "states" => summary.states,
+            "kept_referenced_blocks" => summary.referenced,
+        );
+    }
+
+    Ok(())
+}
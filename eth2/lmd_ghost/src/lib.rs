@@ -1,10 +1,14 @@
+mod proto_array;
 mod reduced_tree;
+mod tree_dump;
 
 use std::sync::Arc;
 use store::Store;
 use types::{BeaconBlock, EthSpec, Hash256, Slot};
 
+pub use proto_array::ThreadSafeProtoArray;
 pub use reduced_tree::ThreadSafeReducedTree;
+pub use tree_dump::{TreeDump, TreeDumpNode};
 
 pub type Result<T> = std::result::Result<T, String>;
 
@@ -43,4 +47,18 @@ pub trait LmdGhost<S: Store, E: EthSpec>: Send + Sync {
         finalized_block: &BeaconBlock,
         finalized_block_root: Hash256,
     ) -> Result<()>;
+
+    /// Returns the state of the fork choice (e.g., the latest vote of each validator) as bytes, so
+    /// that it may be persisted across restarts.
+    fn as_bytes(&self) -> Vec<u8>;
+
+    /// Restore an instance from bytes produced by `as_bytes`.
+    fn from_bytes(bytes: &[u8], store: Arc<S>) -> Result<Self>
+    where
+        Self: Sized;
+
+    /// Returns a snapshot of the tree, for debugging.
+    ///
+    /// Weights are those computed by the most recent call to `find_head`.
+    fn dump(&self) -> Result<TreeDump>;
 }
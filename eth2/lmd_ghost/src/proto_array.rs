@@ -0,0 +1,515 @@
+//! An implementation of "proto-array" LMD GHOST fork choice.
+//!
+//! Every block which descends from the finalized block is stored in a flat `Vec`, where each block
+//! is at a higher index than its parent. The latest vote of each validator is tracked and, when
+//! the head is requested, the change in weight caused by each moved vote (or changed balance) is
+//! applied to the tree in a single pass, from the highest index to the lowest. The same pass
+//! determines the best child and best descendant of each block, so the head is simply the best
+//! descendant of the start block.
+//!
+//! The store is only read when a block's parent is not in the tree (e.g., after a restart), all
+//! other operations are performed in memory.
+use super::{LmdGhost, Result as SuperResult, TreeDump, TreeDumpNode};
+use parking_lot::RwLock;
+use ssz::{Decode, Encode};
+use ssz_derive::{Decode, Encode};
+use std::collections::HashMap;
+use std::marker::PhantomData;
+use std::sync::Arc;
+use store::{Error as StoreError, Store};
+use types::{BeaconBlock, EthSpec, Hash256, Slot};
+
+type Result<T> = std::result::Result<T, Error>;
+
+#[derive(Debug, PartialEq)]
+pub enum Error {
+    MissingNode(Hash256),
+    MissingBlock(Hash256),
+    NotDescendantOfRoot(Hash256),
+    InvalidNodeDelta(usize),
+    StoreError(StoreError),
+    ValidatorWeightUnknown(usize),
+}
+
+impl From<StoreError> for Error {
+    fn from(e: StoreError) -> Error {
+        Error::StoreError(e)
+    }
+}
+
+pub struct ThreadSafeProtoArray<T, E> {
+    core: RwLock<ProtoArray<T, E>>,
+}
+
+impl<T, E> LmdGhost<T, E> for ThreadSafeProtoArray<T, E>
+where
+    T: Store,
+    E: EthSpec,
+{
+    fn new(store: Arc<T>, genesis_block: &BeaconBlock, genesis_root: Hash256) -> Self {
+        ThreadSafeProtoArray {
+            core: RwLock::new(ProtoArray::new(store, genesis_block, genesis_root)),
+        }
+    }
+
+    fn process_attestation(
+        &self,
+        validator_index: usize,
+        block_hash: Hash256,
+        block_slot: Slot,
+    ) -> SuperResult<()> {
+        self.core
+            .write()
+            .process_message(validator_index, block_hash, block_slot);
+
+        Ok(())
+    }
+
+    /// Process a block that was seen on the network.
+    fn process_block(&self, block: &BeaconBlock, block_hash: Hash256) -> SuperResult<()> {
+        self.core
+            .write()
+            .add_block(block.slot, block_hash, block.previous_block_root)
+            .map_err(|e| format!("process_block failed: {:?}", e))
+    }
+
+    fn find_head<F>(
+        &self,
+        start_block_slot: Slot,
+        start_block_root: Hash256,
+        weight_fn: F,
+    ) -> SuperResult<Hash256>
+    where
+        F: Fn(usize) -> Option<u64> + Copy,
+    {
+        self.core
+            .write()
+            .update_weights_and_find_head(start_block_slot, start_block_root, weight_fn)
+            .map_err(|e| format!("find_head failed: {:?}", e))
+    }
+
+    fn update_finalized_root(&self, new_block: &BeaconBlock, new_root: Hash256) -> SuperResult<()> {
+        self.core
+            .write()
+            .update_root(new_block, new_root)
+            .map_err(|e| format!("update_finalized_root failed: {:?}", e))
+    }
+
+    fn as_bytes(&self) -> Vec<u8> {
+        self.core.read().as_ssz_container().as_ssz_bytes()
+    }
+
+    fn from_bytes(bytes: &[u8], store: Arc<T>) -> SuperResult<Self> {
+        let container = SszProtoArray::from_ssz_bytes(bytes)
+            .map_err(|e| format!("Invalid ProtoArray bytes: {:?}", e))?;
+
+        Ok(ThreadSafeProtoArray {
+            core: RwLock::new(ProtoArray::from_ssz_container(container, store)),
+        })
+    }
+
+    fn dump(&self) -> SuperResult<TreeDump> {
+        Ok(self.core.read().dump())
+    }
+}
+
+/// A persistable representation of a `ProtoArray`.
+///
+/// The `indices` map is not stored, since it can be rebuilt from `nodes`.
+#[derive(Encode, Decode)]
+struct SszProtoArray {
+    nodes: Vec<ProtoNode>,
+    votes: Vec<Option<Vote>>,
+    root_slot: Slot,
+}
+
+struct ProtoArray<T, E> {
+    store: Arc<T>,
+    /// All blocks in the tree. A node is always at a higher index than its parent.
+    nodes: Vec<ProtoNode>,
+    /// Maps block roots to their index in `nodes`.
+    indices: HashMap<Hash256, usize>,
+    /// Maps validator indices to their latest votes.
+    votes: Vec<Option<Vote>>,
+    /// The slot of the root of the tree, votes and blocks prior to this slot are ignored.
+    root_slot: Slot,
+    /// The head found by the most recent call to `update_weights_and_find_head`.
+    head: Option<Hash256>,
+    _phantom: PhantomData<E>,
+}
+
+impl<T, E> ProtoArray<T, E>
+where
+    T: Store,
+    E: EthSpec,
+{
+    pub fn new(store: Arc<T>, genesis_block: &BeaconBlock, genesis_root: Hash256) -> Self {
+        let mut indices = HashMap::new();
+        indices.insert(genesis_root, 0);
+
+        Self {
+            store,
+            nodes: vec![ProtoNode::new(genesis_root, None, genesis_block.slot)],
+            indices,
+            votes: vec![],
+            root_slot: genesis_block.slot,
+            head: None,
+            _phantom: PhantomData,
+        }
+    }
+
+    fn as_ssz_container(&self) -> SszProtoArray {
+        SszProtoArray {
+            nodes: self.nodes.clone(),
+            votes: self.votes.clone(),
+            root_slot: self.root_slot,
+        }
+    }
+
+    fn from_ssz_container(container: SszProtoArray, store: Arc<T>) -> Self {
+        let indices = container
+            .nodes
+            .iter()
+            .enumerate()
+            .map(|(index, node)| (node.root, index))
+            .collect();
+
+        Self {
+            store,
+            nodes: container.nodes,
+            indices,
+            votes: container.votes,
+            root_slot: container.root_slot,
+            head: None,
+            _phantom: PhantomData,
+        }
+    }
+
+    /// Record a vote from `validator_index`, replacing their previous vote if it was for an
+    /// earlier slot.
+    ///
+    /// The vote is not applied to the tree until the next call to `update_weights_and_find_head`.
+    pub fn process_message(&mut self, validator_index: usize, block_hash: Hash256, slot: Slot) {
+        if slot < self.root_slot {
+            return;
+        }
+
+        if self.votes.len() <= validator_index {
+            self.votes
+                .resize_with(validator_index + 1, Default::default);
+        }
+
+        match &mut self.votes[validator_index] {
+            Some(vote) => {
+                if vote.next_slot < slot {
+                    vote.next_root = block_hash;
+                    vote.next_slot = slot;
+                }
+            }
+            None => {
+                self.votes[validator_index] = Some(Vote {
+                    current_root: None,
+                    next_root: block_hash,
+                    next_slot: slot,
+                    balance: 0,
+                })
+            }
+        }
+    }
+
+    pub fn update_weights_and_find_head<F>(
+        &mut self,
+        start_block_slot: Slot,
+        start_block_root: Hash256,
+        weight_fn: F,
+    ) -> Result<Hash256>
+    where
+        F: Fn(usize) -> Option<u64> + Copy,
+    {
+        // It is possible that the given `start_block_root` is not in the tree, in which case it
+        // (and any unknown ancestors) are loaded from the store.
+        if !self.indices.contains_key(&start_block_root) {
+            if let Some(block) = self.store.get::<BeaconBlock>(&start_block_root)? {
+                self.add_block(
+                    start_block_slot,
+                    start_block_root,
+                    block.previous_block_root,
+                )?;
+            }
+        }
+
+        let start_index = self.get_index(start_block_root)?;
+
+        let deltas = self.compute_deltas(weight_fn)?;
+        self.apply_deltas(deltas)?;
+
+        let head_index = self.nodes[start_index]
+            .best_descendant
+            .unwrap_or(start_index);
+        let head = self.nodes[head_index].root;
+
+        self.head = Some(head);
+
+        Ok(head)
+    }
+
+    /// Returns a snapshot of the tree.
+    ///
+    /// Voters are those whose votes have been applied to the tree by `find_head`, so they agree
+    /// with the weights.
+    fn dump(&self) -> TreeDump {
+        let mut voters: HashMap<Hash256, Vec<usize>> = HashMap::new();
+        for (validator_index, vote) in self.votes.iter().enumerate() {
+            if let Some(root) = vote.and_then(|vote| vote.current_root) {
+                voters.entry(root).or_default().push(validator_index);
+            }
+        }
+
+        let nodes = self
+            .nodes
+            .iter()
+            .map(|node| TreeDumpNode {
+                block_root: node.root,
+                slot: node.slot,
+                parent_root: node.parent.map(|index| self.nodes[index].root),
+                children: vec![],
+                weight: node.weight,
+                voters: voters.remove(&node.root).unwrap_or_default(),
+            })
+            .collect();
+
+        TreeDump::new(self.nodes[0].root, self.head, nodes)
+    }
+
+    /// Returns the change in weight of each node caused by votes that have moved (or whose
+    /// validators' balances have changed) since the last call, marking those votes as applied.
+    ///
+    /// Votes for blocks that are not yet in the tree are held back until the block is added.
+    fn compute_deltas<F>(&mut self, weight_fn: F) -> Result<Vec<i64>>
+    where
+        F: Fn(usize) -> Option<u64> + Copy,
+    {
+        let indices = &self.indices;
+        let mut deltas = vec![0_i64; self.nodes.len()];
+
+        for (validator_index, vote) in self.votes.iter_mut().enumerate() {
+            let vote = match vote {
+                Some(vote) => vote,
+                None => continue,
+            };
+
+            let next_index = match indices.get(&vote.next_root) {
+                Some(&index) => index,
+                None => continue,
+            };
+
+            let balance = weight_fn(validator_index)
+                .ok_or_else(|| Error::ValidatorWeightUnknown(validator_index))?;
+
+            if vote.current_root != Some(vote.next_root) || vote.balance != balance {
+                // The previous block may have been pruned, in which case there is nothing to
+                // subtract from.
+                if let Some(&current_index) = vote.current_root.and_then(|root| indices.get(&root))
+                {
+                    deltas[current_index] -= vote.balance as i64;
+                }
+
+                deltas[next_index] += balance as i64;
+
+                vote.current_root = Some(vote.next_root);
+                vote.balance = balance;
+            }
+        }
+
+        Ok(deltas)
+    }
+
+    /// Apply `deltas` to the weight of each node, passing each delta up to the node's parent, then
+    /// update the best child and best descendant of the parent.
+    ///
+    /// Iterating backwards ensures all children of a node are visited before the node itself, so a
+    /// node's weight and best descendant are final by the time it is compared with its siblings.
+    fn apply_deltas(&mut self, mut deltas: Vec<i64>) -> Result<()> {
+        for node in &mut self.nodes {
+            node.best_child = None;
+            node.best_descendant = None;
+        }
+
+        for index in (0..self.nodes.len()).rev() {
+            let delta = deltas[index];
+            let node = &mut self.nodes[index];
+
+            node.weight = if delta < 0 {
+                node.weight.checked_sub(delta.abs() as u64)
+            } else {
+                node.weight.checked_add(delta as u64)
+            }
+            .ok_or_else(|| Error::InvalidNodeDelta(index))?;
+
+            if let Some(parent_index) = node.parent {
+                deltas[parent_index] += delta;
+
+                self.maybe_update_best_child(parent_index, index);
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Make `child_index` the best child of `parent_index` if it is heavier than the existing best
+    /// child. Ties are broken by preferring the highest block root.
+    fn maybe_update_best_child(&mut self, parent_index: usize, child_index: usize) {
+        let child = &self.nodes[child_index];
+
+        let is_best = match self.nodes[parent_index].best_child {
+            Some(best_index) => {
+                let best = &self.nodes[best_index];
+                (child.weight, child.root) > (best.weight, best.root)
+            }
+            None => true,
+        };
+
+        if is_best {
+            let best_descendant = child.best_descendant.unwrap_or(child_index);
+
+            let parent = &mut self.nodes[parent_index];
+            parent.best_child = Some(child_index);
+            parent.best_descendant = Some(best_descendant);
+        }
+    }
+
+    /// Add the block with `root` to the tree.
+    ///
+    /// If the parent is not in the tree, the unknown ancestors are loaded from the store. Blocks
+    /// prior to the root of the tree are ignored, whilst an error is returned for later blocks that
+    /// do not descend from the root.
+    fn add_block(&mut self, slot: Slot, root: Hash256, parent_root: Hash256) -> Result<()> {
+        if slot < self.root_slot || self.indices.contains_key(&root) {
+            return Ok(());
+        }
+
+        let mut unknown = vec![(root, slot)];
+        let mut parent_root = parent_root;
+
+        while !self.indices.contains_key(&parent_root) {
+            let parent = self
+                .store
+                .get::<BeaconBlock>(&parent_root)?
+                .ok_or_else(|| Error::MissingBlock(parent_root))?;
+
+            // Every block in the tree other than the root is later than the root, so an unknown
+            // block at or prior to the root slot cannot descend from the root.
+            if parent.slot <= self.root_slot {
+                return Err(Error::NotDescendantOfRoot(root));
+            }
+
+            unknown.push((parent_root, parent.slot));
+            parent_root = parent.previous_block_root;
+        }
+
+        let mut parent_index = self.get_index(parent_root)?;
+
+        for (root, slot) in unknown.into_iter().rev() {
+            let index = self.nodes.len();
+
+            self.nodes
+                .push(ProtoNode::new(root, Some(parent_index), slot));
+            self.indices.insert(root, index);
+
+            parent_index = index;
+        }
+
+        Ok(())
+    }
+
+    /// Make `new_root` the root of the tree, removing all blocks which do not descend from it.
+    pub fn update_root(&mut self, new_block: &BeaconBlock, new_root: Hash256) -> Result<()> {
+        if !self.indices.contains_key(&new_root) {
+            self.add_block(new_block.slot, new_root, new_block.previous_block_root)?;
+        }
+
+        let root_index = self.get_index(new_root)?;
+
+        // Since parents precede their children, a single forward pass can determine whether each
+        // node descends from the new root.
+        let old_nodes = std::mem::replace(&mut self.nodes, vec![]);
+        let mut new_indices: Vec<Option<usize>> = vec![None; old_nodes.len()];
+
+        for (index, mut node) in old_nodes.into_iter().enumerate().skip(root_index) {
+            if index == root_index {
+                node.parent = None;
+            } else {
+                match node.parent.and_then(|parent| new_indices[parent]) {
+                    Some(parent) => node.parent = Some(parent),
+                    None => continue,
+                }
+            }
+
+            // Best children are recomputed during the next call to `find_head`.
+            node.best_child = None;
+            node.best_descendant = None;
+
+            new_indices[index] = Some(self.nodes.len());
+            self.nodes.push(node);
+        }
+
+        self.indices = self
+            .nodes
+            .iter()
+            .enumerate()
+            .map(|(index, node)| (node.root, index))
+            .collect();
+        self.root_slot = new_block.slot;
+
+        Ok(())
+    }
+
+    fn get_index(&self, root: Hash256) -> Result<usize> {
+        self.indices
+            .get(&root)
+            .cloned()
+            .ok_or_else(|| Error::MissingNode(root))
+    }
+}
+
+#[derive(Clone, Debug, Encode, Decode)]
+pub struct ProtoNode {
+    pub root: Hash256,
+    pub parent: Option<usize>,
+    pub slot: Slot,
+    /// The sum of the balances of all validators voting for this block or its descendants.
+    pub weight: u64,
+    pub best_child: Option<usize>,
+    pub best_descendant: Option<usize>,
+}
+
+impl ProtoNode {
+    fn new(root: Hash256, parent: Option<usize>, slot: Slot) -> Self {
+        Self {
+            root,
+            parent,
+            slot,
+            weight: 0,
+            best_child: None,
+            best_descendant: None,
+        }
+    }
+}
+
+/// The latest vote of a validator.
+///
+/// `current_root` and `balance` describe the vote as it is currently applied to the tree, whilst
+/// `next_root` is the latest vote that has been received.
+#[derive(Debug, Clone, Copy, Encode, Decode)]
+pub struct Vote {
+    current_root: Option<Hash256>,
+    next_root: Hash256,
+    next_slot: Slot,
+    balance: u64,
+}
+
+impl From<Error> for String {
+    fn from(e: Error) -> String {
+        format!("{:?}", e)
+    }
+}
@@ -3,8 +3,10 @@
 //! This algorithm was concieved at IC3 Cornell, 2019.
 //!
 //! This implementation is incomplete and has known bugs. Do not use in production.
-use super::{LmdGhost, Result as SuperResult};
+use super::{LmdGhost, Result as SuperResult, TreeDump, TreeDumpNode};
 use parking_lot::RwLock;
+use ssz::{Decode, Encode};
+use ssz_derive::{Decode, Encode};
 use std::collections::HashMap;
 use std::marker::PhantomData;
 use std::sync::Arc;
@@ -87,6 +89,35 @@ where
             .update_root(new_block.slot, new_root)
             .map_err(|e| format!("update_finalized_root failed: {:?}", e))
     }
+
+    fn as_bytes(&self) -> Vec<u8> {
+        self.core.read().as_ssz_container().as_ssz_bytes()
+    }
+
+    fn from_bytes(bytes: &[u8], store: Arc<T>) -> SuperResult<Self> {
+        let container = SszReducedTree::from_ssz_bytes(bytes)
+            .map_err(|e| format!("Invalid ReducedTree bytes: {:?}", e))?;
+
+        Ok(ThreadSafeReducedTree {
+            core: RwLock::new(ReducedTree::from_ssz_container(container, store)),
+        })
+    }
+
+    fn dump(&self) -> SuperResult<TreeDump> {
+        self.core
+            .read()
+            .dump()
+            .map_err(|e| format!("dump failed: {:?}", e))
+    }
+}
+
+/// A persistable representation of a `ReducedTree`.
+#[derive(Encode, Decode)]
+struct SszReducedTree {
+    nodes: Vec<Node>,
+    latest_votes: Vec<Option<Vote>>,
+    root_hash: Hash256,
+    root_slot: Slot,
 }
 
 struct ReducedTree<T, E> {
@@ -97,6 +128,8 @@ struct ReducedTree<T, E> {
     latest_votes: ElasticList<Option<Vote>>,
     /// Stores the root of the tree, used for pruning.
     root: (Hash256, Slot),
+    /// The head found by the most recent call to `update_weights_and_find_head`.
+    head: Option<Hash256>,
     _phantom: PhantomData<E>,
 }
 
@@ -122,6 +155,35 @@ where
             nodes,
             latest_votes: ElasticList::default(),
             root: (genesis_root, genesis_block.slot),
+            head: None,
+            _phantom: PhantomData,
+        }
+    }
+
+    fn as_ssz_container(&self) -> SszReducedTree {
+        let mut nodes: Vec<Node> = self.nodes.values().cloned().collect();
+        // Sorted so the encoding does not depend upon the iteration order of the `HashMap`.
+        nodes.sort_by_key(|node| node.block_hash);
+
+        SszReducedTree {
+            nodes,
+            latest_votes: self.latest_votes.0.clone(),
+            root_hash: self.root.0,
+            root_slot: self.root.1,
+        }
+    }
+
+    fn from_ssz_container(container: SszReducedTree, store: Arc<T>) -> Self {
+        Self {
+            store,
+            nodes: container
+                .nodes
+                .into_iter()
+                .map(|node| (node.block_hash, node))
+                .collect(),
+            latest_votes: ElasticList(container.latest_votes),
+            root: (container.root_hash, container.root_slot),
+            head: None,
             _phantom: PhantomData,
         }
     }
@@ -217,9 +279,37 @@ where
         let _root_weight = self.update_weight(start_block_root, weight_fn)?;
 
         let start_node = self.get_node(start_block_root)?;
-        let head_node = self.find_head_from(start_node)?;
+        let head = self.find_head_from(start_node)?.block_hash;
+
+        self.head = Some(head);
 
-        Ok(head_node.block_hash)
+        Ok(head)
+    }
+
+    /// Returns a snapshot of the tree, reading the slot of each node from the store.
+    fn dump(&self) -> Result<TreeDump> {
+        let nodes = self
+            .nodes
+            .values()
+            .map(|node| {
+                let slot = if node.block_hash == self.root.0 {
+                    self.root.1
+                } else {
+                    self.get_block(node.block_hash)?.slot
+                };
+
+                Ok(TreeDumpNode {
+                    block_root: node.block_hash,
+                    slot,
+                    parent_root: node.parent_hash,
+                    children: vec![],
+                    weight: node.weight,
+                    voters: node.voters.clone(),
+                })
+            })
+            .collect::<Result<Vec<_>>>()?;
+
+        Ok(TreeDump::new(self.root.0, self.head, nodes))
     }
 
     fn find_head_from<'a>(&'a self, start_node: &'a Node) -> Result<&'a Node> {
@@ -232,24 +322,60 @@ where
                 .map(|hash| self.get_node(*hash))
                 .collect::<Result<Vec<&Node>>>()?;
 
-            // TODO: check if `max_by` is `O(n^2)`.
-            let best_child = children
+            // There can only be no maximum if there are no children. This code path is guarded
+            // against that condition.
+            let max_weight = children
                 .iter()
-                .max_by(|a, b| {
-                    if a.weight != b.weight {
-                        a.weight.cmp(&b.weight)
-                    } else {
-                        a.block_hash.cmp(&b.block_hash)
-                    }
-                })
-                // There can only be no maximum if there are no children. This code path is guarded
-                // against that condition.
+                .map(|child| child.weight)
+                .max()
                 .expect("There must be a maximally weighted node.");
 
+            let heaviest: Vec<&Node> = children
+                .into_iter()
+                .filter(|child| child.weight == max_weight)
+                .collect();
+
+            if heaviest.len() == 1 {
+                return self.find_head_from(heaviest[0]);
+            }
+
+            // A child in the reduced tree may be a later block than the child of `start_node` in
+            // the block tree. Ties are broken by the root of the latter, as in the spec.
+            let mut best_child = None;
+            for child in heaviest {
+                let branch_root = self.find_branch_root(start_node.block_hash, child.block_hash)?;
+
+                match best_child {
+                    Some((best_root, _)) if best_root >= branch_root => {}
+                    _ => best_child = Some((branch_root, child)),
+                }
+            }
+
+            let (_, best_child) = best_child.expect("There must be a maximally weighted node.");
+
             self.find_head_from(best_child)
         }
     }
 
+    /// Returns the root of the block which is both a child of `ancestor` and an ancestor of (or
+    /// equal to) `descendant`.
+    fn find_branch_root(&self, ancestor: Hash256, descendant: Hash256) -> Result<Hash256> {
+        let root_slot = self.root_slot();
+        let mut root = descendant;
+
+        loop {
+            let block = self.get_block(root)?;
+
+            if block.previous_block_root == ancestor {
+                return Ok(root);
+            } else if block.slot <= root_slot {
+                return Err(Error::NoCommonAncestor((ancestor, descendant)));
+            }
+
+            root = block.previous_block_root;
+        }
+    }
+
     fn update_weight<F>(&mut self, start_block_root: Hash256, weight_fn: F) -> Result<u64>
     where
         F: Fn(usize) -> Option<u64> + Copy,
@@ -281,6 +407,13 @@ where
             // Unwrap is safe as prior `if` statements ensures the result is `Some`.
             let vote = self.latest_votes.get(validator_index).unwrap();
 
+            // Votes are retained when `update_root` prunes the block they are for, so that earlier
+            // attestations from the validator are still ignored. There is no node to remove the
+            // vote from.
+            if !self.nodes.contains_key(&vote.hash) {
+                return Ok(());
+            }
+
             let should_delete = {
                 self.get_mut_node(vote.hash)?.remove_voter(validator_index);
                 let node = self.get_node(vote.hash)?.clone();
@@ -504,30 +637,39 @@ where
     /// For the two given block roots (`a_root` and `b_root`), find the first block they share in
     /// the tree. Viz, find the block that these two distinct blocks forked from.
     fn find_least_common_ancestor(&self, a_root: Hash256, b_root: Hash256) -> Result<Hash256> {
+        let a = self.get_block(a_root)?;
+        let b = self.get_block(b_root)?;
+        let slot = std::cmp::min(a.slot, b.slot);
+
         // If the blocks behind `a_root` and `b_root` are not at the same slot, take the highest
         // block (by slot) down to be equal with the lower slot.
         //
-        // The result is two roots which identify two blocks at the same height.
-        let (a_root, b_root) = {
-            let a = self.get_block(a_root)?;
-            let b = self.get_block(b_root)?;
-
-            if a.slot > b.slot {
-                (self.find_ancestor_at_slot(a_root, b.slot)?, b_root)
-            } else if b.slot > a.slot {
-                (a_root, self.find_ancestor_at_slot(b_root, a.slot)?)
-            } else {
-                (a_root, b_root)
-            }
+        // The result is two roots which identify the ancestors of both blocks at the same height.
+        // If the highest block's chain skipped that slot, its ancestor is from an earlier slot.
+        let (a_ancestor, b_ancestor) = if a.slot > b.slot {
+            (self.find_ancestor_at_slot(a_root, slot)?, b_root)
+        } else if b.slot > a.slot {
+            (a_root, self.find_ancestor_at_slot(b_root, slot)?)
+        } else {
+            (a_root, b_root)
         };
 
-        let ((a_root, _a_slot), (_b_root, _b_slot)) = self
-            .iter_ancestors(a_root)?
-            .zip(self.iter_ancestors(b_root)?)
+        if a_ancestor == b_ancestor {
+            return Ok(a_ancestor);
+        }
+
+        // Compare the ancestors of both blocks slot-by-slot, below `slot`.
+        //
+        // Note: the iterators start from the original blocks, not the ancestors found above. An
+        // ancestor from an earlier (skipped) slot would yield its ancestors from a lower slot than
+        // the other block, so the two would never be compared at the same slot.
+        let ((ancestor, _a_slot), (_b_root, _b_slot)) = self
+            .iter_ancestors_from(a_root, slot)?
+            .zip(self.iter_ancestors_from(b_root, slot)?)
             .find(|((a_root, _), (b_root, _))| a_root == b_root)
             .ok_or_else(|| Error::NoCommonAncestor((a_root, b_root)))?;
 
-        Ok(a_root)
+        Ok(ancestor)
     }
 
     fn iter_ancestors(&self, child: Hash256) -> Result<BlockRootsIterator<E, T>> {
@@ -541,6 +683,15 @@ where
         ))
     }
 
+    /// Iterate the ancestors of `child` prior to `slot`, which must not be later than the slot of
+    /// `child`.
+    fn iter_ancestors_from(&self, child: Hash256, slot: Slot) -> Result<BlockRootsIterator<E, T>> {
+        let block = self.get_block(child)?;
+        let state = self.get_state(block.state_root)?;
+
+        Ok(BlockRootsIterator::owned(self.store.clone(), state, slot))
+    }
+
     fn get_node(&self, hash: Hash256) -> Result<&Node> {
         self.nodes
             .get(&hash)
@@ -570,7 +721,7 @@ where
     }
 }
 
-#[derive(Default, Clone, Debug)]
+#[derive(Default, Clone, Debug, Encode, Decode)]
 pub struct Node {
     pub parent_hash: Option<Hash256>,
     pub children: Vec<Hash256>,
@@ -609,7 +760,7 @@ impl Node {
     }
 }
 
-#[derive(Debug, Clone, Copy)]
+#[derive(Debug, Clone, Copy, Encode, Decode)]
 pub struct Vote {
     hash: Hash256,
     slot: Slot,
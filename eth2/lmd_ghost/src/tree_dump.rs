@@ -0,0 +1,190 @@
+//! A backend-agnostic snapshot of a fork choice tree, for debugging.
+use super::Result;
+use serde_derive::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::fmt::Write;
+use types::{Hash256, Slot};
+
+/// A snapshot of the blocks, votes and weights known to some `LmdGhost` implementation.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct TreeDump {
+    /// The root of the tree (i.e., the finalized block).
+    pub root: Hash256,
+    /// The head returned by the most recent call to `find_head`, if any.
+    pub head: Option<Hash256>,
+    /// All nodes in the tree, ordered by slot then root.
+    pub nodes: Vec<TreeDumpNode>,
+}
+
+/// A single block in a `TreeDump`.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct TreeDumpNode {
+    pub block_root: Hash256,
+    pub slot: Slot,
+    /// The closest ancestor which is also in the tree.
+    ///
+    /// This is not necessarily the parent block, since some implementations (e.g., the reduced
+    /// tree) do not store every block.
+    pub parent_root: Option<Hash256>,
+    pub children: Vec<Hash256>,
+    /// The weight of the node as of the most recent call to `find_head`.
+    pub weight: u64,
+    /// The indices of the validators whose latest vote is for this block.
+    pub voters: Vec<usize>,
+}
+
+impl TreeDump {
+    /// Builds a dump from `nodes`, filling in the `children` of each node from the `parent_root`
+    /// of the others.
+    pub fn new(root: Hash256, head: Option<Hash256>, mut nodes: Vec<TreeDumpNode>) -> Self {
+        nodes.sort_by_key(|node| (node.slot, node.block_root));
+
+        let mut children: HashMap<Hash256, Vec<Hash256>> = HashMap::new();
+        for node in &nodes {
+            if let Some(parent_root) = node.parent_root {
+                children
+                    .entry(parent_root)
+                    .or_default()
+                    .push(node.block_root);
+            }
+        }
+
+        for node in &mut nodes {
+            node.children = children.remove(&node.block_root).unwrap_or_default();
+            node.voters.sort();
+        }
+
+        Self { root, head, nodes }
+    }
+
+    /// Returns `self` as pretty-printed JSON.
+    pub fn to_json(&self) -> Result<String> {
+        serde_json::to_string_pretty(self).map_err(|e| format!("Unable to encode tree: {:?}", e))
+    }
+
+    /// Returns `self` as a Graphviz DOT digraph, with an edge from each node to its children.
+    ///
+    /// The root is drawn in bold and the head is filled. Voters are only counted, since there may
+    /// be many thousands of them; use `to_json` for the full list.
+    pub fn to_dot(&self) -> String {
+        let mut dot = String::new();
+
+        // Writing to a `String` cannot fail.
+        writeln!(dot, "digraph ForkChoice {{").unwrap();
+        writeln!(dot, "    rankdir=LR;").unwrap();
+        writeln!(dot, "    node [shape=box, fontname=monospace];").unwrap();
+
+        for node in &self.nodes {
+            let mut style = vec![];
+            if node.block_root == self.root {
+                style.push("bold");
+            }
+            if Some(node.block_root) == self.head {
+                style.push("filled");
+            }
+
+            writeln!(
+                dot,
+                "    \"{:?}\" [label=\"{}\\nslot {}\\nweight {}\\nvoters {}\", style=\"{}\"];",
+                node.block_root,
+                node.block_root,
+                node.slot,
+                node.weight,
+                node.voters.len(),
+                style.join(",")
+            )
+            .unwrap();
+        }
+
+        for node in &self.nodes {
+            for child in &node.children {
+                writeln!(dot, "    \"{:?}\" -> \"{:?}\";", node.block_root, child).unwrap();
+            }
+        }
+
+        writeln!(dot, "}}").unwrap();
+
+        dot
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn node(root: u64, slot: u64, parent: Option<u64>, voters: Vec<usize>) -> TreeDumpNode {
+        TreeDumpNode {
+            block_root: Hash256::from(root),
+            slot: Slot::new(slot),
+            parent_root: parent.map(Hash256::from),
+            children: vec![],
+            weight: voters.len() as u64,
+            voters,
+        }
+    }
+
+    /// A root with two children, where the head is the child with the most voters.
+    fn dump() -> TreeDump {
+        TreeDump::new(
+            Hash256::from(1),
+            Some(Hash256::from(3)),
+            vec![
+                node(3, 2, Some(1), vec![2, 0]),
+                node(2, 1, Some(1), vec![1]),
+                node(1, 0, None, vec![]),
+            ],
+        )
+    }
+
+    #[test]
+    fn new_sorts_nodes_and_fills_children() {
+        let dump = dump();
+
+        let roots: Vec<Hash256> = dump.nodes.iter().map(|node| node.block_root).collect();
+        assert_eq!(
+            roots,
+            vec![Hash256::from(1), Hash256::from(2), Hash256::from(3)]
+        );
+
+        assert_eq!(
+            dump.nodes[0].children,
+            vec![Hash256::from(2), Hash256::from(3)]
+        );
+        assert!(dump.nodes[1].children.is_empty());
+        assert_eq!(dump.nodes[2].voters, vec![0, 2]);
+    }
+
+    #[test]
+    fn json_roundtrip() {
+        let dump = dump();
+        let json = dump.to_json().unwrap();
+
+        assert_eq!(serde_json::from_str::<TreeDump>(&json).unwrap(), dump);
+    }
+
+    #[test]
+    fn dot_has_every_node_and_edge() {
+        let dump = dump();
+        let dot = dump.to_dot();
+        let id = |root: u64| format!("\"{:?}\"", Hash256::from(root));
+
+        assert!(dot.starts_with("digraph ForkChoice {\n"));
+        assert!(dot.ends_with("}\n"));
+
+        let node_line = |root: u64| {
+            dot.lines()
+                .find(|line| line.trim_start().starts_with(&format!("{} [", id(root))))
+                .unwrap_or_else(|| panic!("should have a line for node {}", root))
+                .to_string()
+        };
+
+        assert!(node_line(1).contains("style=\"bold\""));
+        assert!(node_line(2).contains("style=\"\""));
+        assert!(node_line(3).contains("style=\"filled\""));
+        assert!(node_line(3).contains("slot 2\\nweight 2\\nvoters 2"));
+
+        assert!(dot.contains(&format!("{} -> {};", id(1), id(2))));
+        assert!(dot.contains(&format!("{} -> {};", id(1), id(3))));
+        assert_eq!(dot.matches("->").count(), 2);
+    }
+}
@@ -0,0 +1,411 @@
+#![cfg(not(debug_assertions))]
+
+//! Runs `ThreadSafeReducedTree` and `ThreadSafeProtoArray` over identical sequences of blocks and
+//! votes, asserting that they always agree upon the head.
+//!
+//! All votes are for leaves of the tree. In most rounds each validator has a distinct power-of-two
+//! balance, so sibling subtrees never have equal, non-zero weights. In tied rounds every validator
+//! has the same balance and the votes are split evenly between two leaves, so the head is decided
+//! by the tie-break.
+
+use beacon_chain::test_utils::{AttestationStrategy, BeaconChainHarness, BlockStrategy};
+use lmd_ghost::{LmdGhost, ThreadSafeProtoArray, ThreadSafeReducedTree};
+use rand::Rng;
+use std::collections::{HashMap, HashSet};
+use std::sync::Arc;
+use store::{MemoryStore, Store};
+use types::test_utils::{SeedableRng, XorShiftRng};
+use types::{BeaconBlock, Hash256, MinimalEthSpec, Slot};
+
+type TestEthSpec = MinimalEthSpec;
+type ReducedTree = ThreadSafeReducedTree<MemoryStore, TestEthSpec>;
+type ProtoArray = ThreadSafeProtoArray<MemoryStore, TestEthSpec>;
+
+// Small enough that every validator may have a distinct power-of-two balance.
+const VALIDATOR_COUNT: usize = 16;
+
+/// Builds a chain with several forks, returning the store and every block in it (including
+/// genesis), ordered by slot.
+fn build_block_tree() -> (Arc<MemoryStore>, Vec<(Hash256, BeaconBlock)>) {
+    let harness: BeaconChainHarness<ProtoArray, TestEthSpec> =
+        BeaconChainHarness::new(VALIDATOR_COUNT);
+
+    // Move past the zero slot.
+    harness.advance_slot();
+
+    let mut heads = vec![];
+
+    heads.push(harness.extend_chain(
+        8,
+        BlockStrategy::OnCanonicalHead,
+        AttestationStrategy::AllValidators,
+    ));
+    heads.push(harness.extend_chain(
+        4,
+        BlockStrategy::ForkCanonicalChainAt {
+            previous_slot: Slot::new(2),
+            first_slot: Slot::new(4),
+        },
+        AttestationStrategy::SomeValidators(vec![]),
+    ));
+    heads.push(harness.extend_chain(
+        6,
+        BlockStrategy::ForkCanonicalChainAt {
+            previous_slot: Slot::new(5),
+            first_slot: Slot::new(7),
+        },
+        AttestationStrategy::SomeValidators(vec![]),
+    ));
+
+    harness.advance_slot();
+
+    heads.push(harness.extend_chain(
+        6,
+        BlockStrategy::OnCanonicalHead,
+        AttestationStrategy::AllValidators,
+    ));
+
+    let store = harness.chain.store.clone();
+    let mut blocks = HashMap::new();
+
+    for head in heads {
+        let mut root = head;
+
+        while !blocks.contains_key(&root) {
+            let block: BeaconBlock = store
+                .get(&root)
+                .expect("should read db")
+                .expect("should find block");
+            let parent_root = block.previous_block_root;
+
+            blocks.insert(root, block);
+
+            if parent_root == Hash256::zero() {
+                break;
+            }
+            root = parent_root;
+        }
+    }
+
+    let mut blocks: Vec<_> = blocks.into_iter().collect();
+    blocks.sort_by_key(|(root, block)| (block.slot, *root));
+
+    (store, blocks)
+}
+
+/// Both backends, fed with the same blocks and votes.
+struct Differential {
+    store: Arc<MemoryStore>,
+    reduced_tree: ReducedTree,
+    proto_array: ProtoArray,
+    /// Blocks which have not yet been given to the backends.
+    pending: Vec<(Hash256, BeaconBlock)>,
+    /// Maps every block given to the backends to its parent.
+    parents: HashMap<Hash256, Hash256>,
+    /// The root and slot of the finalized block.
+    root: (Hash256, Slot),
+    /// The balance of each validator, used by the next call to `find_head`.
+    balances: Vec<u64>,
+    rng: XorShiftRng,
+    round: u64,
+}
+
+impl Differential {
+    fn new(seed: u8) -> Self {
+        let (store, mut blocks) = build_block_tree();
+        let (genesis_root, genesis_block) = blocks.remove(0);
+
+        assert_eq!(
+            genesis_block.previous_block_root,
+            Hash256::zero(),
+            "first block should be genesis"
+        );
+
+        let mut parents = HashMap::new();
+        parents.insert(genesis_root, genesis_block.previous_block_root);
+
+        Self {
+            reduced_tree: ReducedTree::new(store.clone(), &genesis_block, genesis_root),
+            proto_array: ProtoArray::new(store.clone(), &genesis_block, genesis_root),
+            store,
+            pending: blocks,
+            parents,
+            root: (genesis_root, genesis_block.slot),
+            balances: vec![1; VALIDATOR_COUNT],
+            rng: XorShiftRng::from_seed([seed; 16]),
+            round: 0,
+        }
+    }
+
+    fn is_descendant(&self, mut root: Hash256, ancestor: Hash256) -> bool {
+        loop {
+            if root == ancestor {
+                return true;
+            }
+
+            match self.parents.get(&root) {
+                Some(parent) => root = *parent,
+                None => return false,
+            }
+        }
+    }
+
+    /// Give the next `count` blocks to both backends, skipping any that conflict with the
+    /// finalized block.
+    fn process_blocks(&mut self, count: usize) {
+        let count = std::cmp::min(count, self.pending.len());
+
+        for (root, block) in self.pending.drain(0..count).collect::<Vec<_>>() {
+            self.parents.insert(root, block.previous_block_root);
+
+            if !self.is_descendant(root, self.root.0) {
+                continue;
+            }
+
+            self.reduced_tree
+                .process_block(&block, root)
+                .expect("reduced tree should process block");
+            self.proto_array
+                .process_block(&block, root)
+                .expect("proto array should process block");
+        }
+    }
+
+    /// Returns all blocks given to the backends that descend from the finalized block and have no
+    /// children.
+    fn leaves(&self) -> Vec<Hash256> {
+        let parents: HashSet<Hash256> = self.parents.values().cloned().collect();
+
+        let mut leaves: Vec<Hash256> = self
+            .parents
+            .keys()
+            .filter(|root| !parents.contains(*root))
+            .filter(|root| self.is_descendant(**root, self.root.0))
+            .cloned()
+            .collect();
+        leaves.sort();
+
+        leaves
+    }
+
+    /// Every validator votes for `choose(validator_index)`.
+    fn vote<F: FnMut(usize) -> Hash256>(&mut self, mut choose: F) {
+        self.round += 1;
+
+        // Votes are only accepted if they are for a later slot than the previous vote.
+        let slot = self.root.1 + self.round;
+
+        for validator_index in 0..VALIDATOR_COUNT {
+            let block_root = choose(validator_index);
+
+            self.reduced_tree
+                .process_attestation(validator_index, block_root, slot)
+                .expect("reduced tree should process attestation");
+            self.proto_array
+                .process_attestation(validator_index, block_root, slot)
+                .expect("proto array should process attestation");
+        }
+    }
+
+    /// Every validator votes for a random leaf of the tree, with distinct balances.
+    fn vote_for_random_leaves(&mut self) {
+        let leaves = self.leaves();
+        let mut rng = self.rng.clone();
+
+        self.vote(|_| leaves[rng.gen_range(0, leaves.len())]);
+
+        // Varying the balances between rounds exercises balance changes without introducing ties.
+        let multiplier = 1 + self.round % 3;
+        self.balances = (0..VALIDATOR_COUNT)
+            .map(|validator_index| (1 << validator_index) * multiplier)
+            .collect();
+
+        self.rng = rng;
+    }
+
+    /// Half of the validators vote for one random leaf and half for another, with equal balances.
+    ///
+    /// Returns the two leaves, or `None` if there are not two leaves to choose from.
+    fn vote_for_tied_leaves(&mut self) -> Option<(Hash256, Hash256)> {
+        let mut leaves = self.leaves();
+
+        if leaves.len() < 2 {
+            return None;
+        }
+
+        let a = leaves.remove(self.rng.gen_range(0, leaves.len()));
+        let b = leaves.remove(self.rng.gen_range(0, leaves.len()));
+
+        self.vote(|validator_index| if validator_index % 2 == 0 { a } else { b });
+        self.balances = vec![1; VALIDATOR_COUNT];
+
+        Some((a, b))
+    }
+
+    /// Returns the head, asserting that both backends agree upon it.
+    fn find_head(&self) -> Hash256 {
+        let balances = &self.balances;
+        let weight = |validator_index: usize| balances.get(validator_index).cloned();
+
+        let (root, slot) = self.root;
+
+        let reduced_tree_head = self
+            .reduced_tree
+            .find_head(slot, root, weight)
+            .expect("reduced tree should find head");
+        let proto_array_head = self
+            .proto_array
+            .find_head(slot, root, weight)
+            .expect("proto array should find head");
+
+        assert_eq!(
+            reduced_tree_head, proto_array_head,
+            "backends should agree on the head in round {}",
+            self.round
+        );
+
+        proto_array_head
+    }
+
+    /// Finalize the ancestor of the head at (or prior to) `slot`, after moving all votes to the
+    /// head.
+    fn finalize(&mut self, slot: Slot) {
+        let head = self.find_head();
+        self.vote(|_| head);
+        assert_eq!(self.find_head(), head);
+
+        let mut root = head;
+        let block = loop {
+            let block = self.block(root);
+
+            if block.slot <= slot {
+                break block;
+            }
+            root = block.previous_block_root;
+        };
+
+        self.reduced_tree
+            .update_finalized_root(&block, root)
+            .expect("reduced tree should update finalized root");
+        self.proto_array
+            .update_finalized_root(&block, root)
+            .expect("proto array should update finalized root");
+
+        self.root = (root, block.slot);
+
+        assert_eq!(
+            self.find_head(),
+            head,
+            "finalization should not change head"
+        );
+    }
+
+    /// Replace both backends with instances restored from their own bytes.
+    fn restore(&mut self) {
+        self.reduced_tree =
+            ReducedTree::from_bytes(&self.reduced_tree.as_bytes(), self.store.clone())
+                .expect("reduced tree should restore from bytes");
+        self.proto_array = ProtoArray::from_bytes(&self.proto_array.as_bytes(), self.store.clone())
+            .expect("proto array should restore from bytes");
+    }
+
+    fn block(&self, root: Hash256) -> BeaconBlock {
+        self.store
+            .get(&root)
+            .expect("should read db")
+            .expect("should find block")
+    }
+}
+
+#[test]
+fn heads_agree_on_forked_chain() {
+    for seed in 1..4 {
+        let mut differential = Differential::new(seed);
+
+        while !differential.pending.is_empty() {
+            differential.process_blocks(3);
+
+            for _ in 0..2 {
+                differential.vote_for_random_leaves();
+                differential.find_head();
+            }
+        }
+    }
+}
+
+#[test]
+fn heads_agree_on_tied_weights() {
+    let mut tied_rounds = 0;
+
+    for seed in 1..4 {
+        let mut differential = Differential::new(seed);
+
+        while !differential.pending.is_empty() {
+            differential.process_blocks(3);
+
+            for _ in 0..2 {
+                if let Some((a, b)) = differential.vote_for_tied_leaves() {
+                    let head = differential.find_head();
+
+                    assert!(
+                        head == a || head == b,
+                        "head should be one of the tied leaves"
+                    );
+                    tied_rounds += 1;
+                }
+
+                differential.vote_for_random_leaves();
+                differential.find_head();
+            }
+        }
+
+        differential.finalize(Slot::new(5));
+
+        if differential.vote_for_tied_leaves().is_some() {
+            differential.find_head();
+            tied_rounds += 1;
+        }
+    }
+
+    assert!(tied_rounds > 0, "should have tested tied weights");
+}
+
+#[test]
+fn heads_agree_after_finalization() {
+    let mut differential = Differential::new(42);
+
+    differential.process_blocks(10);
+    differential.vote_for_random_leaves();
+    differential.find_head();
+
+    differential.finalize(Slot::new(3));
+
+    while !differential.pending.is_empty() {
+        differential.process_blocks(3);
+        differential.vote_for_random_leaves();
+        differential.find_head();
+    }
+
+    differential.finalize(Slot::new(8));
+
+    differential.vote_for_random_leaves();
+    differential.find_head();
+}
+
+#[test]
+fn heads_agree_after_restore() {
+    let mut differential = Differential::new(7);
+
+    while !differential.pending.is_empty() {
+        differential.process_blocks(4);
+        differential.vote_for_random_leaves();
+        let head = differential.find_head();
+
+        differential.restore();
+        assert_eq!(
+            differential.find_head(),
+            head,
+            "restoring should not change head"
+        );
+    }
+}
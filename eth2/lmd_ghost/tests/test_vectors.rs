@@ -0,0 +1,314 @@
+//! Runs the fork choice scenarios in `tests/test_vectors` against each `LmdGhost` implementation.
+//!
+//! Each YAML file describes a single scenario:
+//!
+//! ```yaml
+//! title: A description of the scenario
+//! genesis_root: 1          # The root of the genesis (initially finalized) block, at slot 0.
+//! balances: [1, 1]         # The weight of each validator.
+//! steps:
+//!   - block: {root: 2, parent: 1, slot: 1}
+//!   - attestation: {validator: 0, root: 2, slot: 1}
+//!   - balances: [2, 1]
+//!   - find_head: {expected: 2}            # `start` defaults to the finalized root.
+//!   - find_head: {start: 1, expected: 2}
+//!   - finalize: 2
+//! ```
+//!
+//! Roots are given as integers and converted with `Hash256::from`, so ties are broken in favour of
+//! the largest integer. A block must have a higher slot than its parent, and scenarios must fit
+//! within `SlotsPerHistoricalRoot` slots.
+
+use lmd_ghost::{LmdGhost, ThreadSafeProtoArray, ThreadSafeReducedTree};
+use std::collections::HashMap;
+use std::fs::{self, File};
+use std::io::prelude::*;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use store::{MemoryStore, Store};
+use types::{BeaconBlock, BeaconState, Eth1Data, EthSpec, Hash256, MinimalEthSpec, Slot};
+use yaml_rust::{Yaml, YamlLoader};
+
+type TestEthSpec = MinimalEthSpec;
+
+struct Scenario {
+    title: String,
+    genesis_root: u64,
+    balances: Vec<u64>,
+    steps: Vec<Step>,
+}
+
+enum Step {
+    Block {
+        root: u64,
+        parent: u64,
+        slot: u64,
+    },
+    Attestation {
+        validator: usize,
+        root: u64,
+        slot: u64,
+    },
+    Balances(Vec<u64>),
+    FindHead {
+        start: Option<u64>,
+        expected: u64,
+    },
+    Finalize(u64),
+}
+
+fn get_u64(yaml: &Yaml, field: &str) -> u64 {
+    yaml[field]
+        .as_i64()
+        .unwrap_or_else(|| panic!("Missing integer field: {}", field)) as u64
+}
+
+fn get_balances(yaml: &Yaml) -> Vec<u64> {
+    yaml.as_vec()
+        .expect("Balances should be a list")
+        .iter()
+        .map(|balance| balance.as_i64().expect("Balance should be an integer") as u64)
+        .collect()
+}
+
+impl Scenario {
+    fn from_file(path: &Path) -> Self {
+        let mut yaml_str = String::new();
+        File::open(path)
+            .and_then(|mut file| file.read_to_string(&mut yaml_str))
+            .unwrap_or_else(|e| panic!("Unable to read {:?}: {:?}", path, e));
+
+        let docs = YamlLoader::load_from_str(&yaml_str)
+            .unwrap_or_else(|e| panic!("Invalid YAML in {:?}: {:?}", path, e));
+        let doc = &docs[0];
+
+        let steps = doc["steps"]
+            .as_vec()
+            .expect("Scenario should have steps")
+            .iter()
+            .map(|step| {
+                if !step["block"].is_badvalue() {
+                    let block = &step["block"];
+                    Step::Block {
+                        root: get_u64(block, "root"),
+                        parent: get_u64(block, "parent"),
+                        slot: get_u64(block, "slot"),
+                    }
+                } else if !step["attestation"].is_badvalue() {
+                    let attestation = &step["attestation"];
+                    Step::Attestation {
+                        validator: get_u64(attestation, "validator") as usize,
+                        root: get_u64(attestation, "root"),
+                        slot: get_u64(attestation, "slot"),
+                    }
+                } else if !step["balances"].is_badvalue() {
+                    Step::Balances(get_balances(&step["balances"]))
+                } else if !step["find_head"].is_badvalue() {
+                    let find_head = &step["find_head"];
+                    Step::FindHead {
+                        start: find_head["start"].as_i64().map(|start| start as u64),
+                        expected: get_u64(find_head, "expected"),
+                    }
+                } else if !step["finalize"].is_badvalue() {
+                    Step::Finalize(
+                        step["finalize"]
+                            .as_i64()
+                            .expect("Root should be an integer") as u64,
+                    )
+                } else {
+                    panic!("Unknown step in {:?}: {:?}", path, step)
+                }
+            })
+            .collect();
+
+        Self {
+            title: doc["title"]
+                .as_str()
+                .expect("Scenario should have a title")
+                .to_string(),
+            genesis_root: get_u64(doc, "genesis_root"),
+            balances: get_balances(&doc["balances"]),
+            steps,
+        }
+    }
+
+    /// Run the scenario against a new instance of `T`, panicking if any head is not as expected.
+    fn run<T: LmdGhost<MemoryStore, TestEthSpec>>(&self) {
+        let mut chain = TestChain::new(Hash256::from(self.genesis_root));
+        let fork_choice = T::new(
+            chain.store.clone(),
+            &chain.block(chain.finalized_root),
+            chain.finalized_root,
+        );
+        let mut balances = self.balances.clone();
+
+        for (i, step) in self.steps.iter().enumerate() {
+            let context = format!("{}: step {}", self.title, i);
+
+            match step {
+                Step::Block { root, parent, slot } => {
+                    let root = Hash256::from(*root);
+                    let block = chain.add_block(root, Hash256::from(*parent), Slot::new(*slot));
+
+                    fork_choice
+                        .process_block(&block, root)
+                        .unwrap_or_else(|e| panic!("{}: {}", context, e));
+                }
+                Step::Attestation {
+                    validator,
+                    root,
+                    slot,
+                } => fork_choice
+                    .process_attestation(*validator, Hash256::from(*root), Slot::new(*slot))
+                    .unwrap_or_else(|e| panic!("{}: {}", context, e)),
+                Step::Balances(new_balances) => balances = new_balances.clone(),
+                Step::FindHead { start, expected } => {
+                    let start = start.map(Hash256::from).unwrap_or(chain.finalized_root);
+                    let weight = |validator_index: usize| balances.get(validator_index).cloned();
+
+                    let head = fork_choice
+                        .find_head(chain.block(start).slot, start, weight)
+                        .unwrap_or_else(|e| panic!("{}: {}", context, e));
+
+                    assert_eq!(head, Hash256::from(*expected), "{}", context);
+
+                    let dump = fork_choice
+                        .dump()
+                        .unwrap_or_else(|e| panic!("{}: {}", context, e));
+
+                    assert_eq!(dump.root, chain.finalized_root, "{}", context);
+                    assert_eq!(dump.head, Some(head), "{}", context);
+                    assert!(
+                        dump.nodes.iter().any(|node| node.block_root == head),
+                        "{}: head should be in dump",
+                        context
+                    );
+                }
+                Step::Finalize(root) => {
+                    let root = Hash256::from(*root);
+
+                    fork_choice
+                        .update_finalized_root(&chain.block(root), root)
+                        .unwrap_or_else(|e| panic!("{}: {}", context, e));
+
+                    chain.finalized_root = root;
+                }
+            }
+        }
+    }
+}
+
+/// A tree of blocks in a `MemoryStore`, each with a state that contains the roots of its
+/// ancestors.
+struct TestChain {
+    store: Arc<MemoryStore>,
+    states: HashMap<Hash256, BeaconState<TestEthSpec>>,
+    finalized_root: Hash256,
+}
+
+impl TestChain {
+    fn new(genesis_root: Hash256) -> Self {
+        let spec = TestEthSpec::default_spec();
+        let eth1_data = Eth1Data {
+            deposit_root: Hash256::zero(),
+            block_hash: Hash256::zero(),
+            deposit_count: 0,
+        };
+
+        let mut chain = Self {
+            store: Arc::new(MemoryStore::open()),
+            states: HashMap::new(),
+            finalized_root: genesis_root,
+        };
+
+        chain.put(
+            genesis_root,
+            BeaconBlock::empty(&spec),
+            BeaconState::genesis(0, eth1_data, &spec),
+        );
+
+        chain
+    }
+
+    /// Add a block at `slot` to the store, returning it.
+    fn add_block(&mut self, root: Hash256, parent_root: Hash256, slot: Slot) -> BeaconBlock {
+        let spec = TestEthSpec::default_spec();
+
+        let mut state = self
+            .states
+            .get(&parent_root)
+            .unwrap_or_else(|| panic!("Unknown parent: {:?}", parent_root))
+            .clone();
+
+        assert!(
+            state.slot < slot,
+            "Block must have a higher slot than its parent"
+        );
+
+        let parent_slot = state.slot;
+        state.slot = slot;
+
+        for ancestor_slot in parent_slot.as_u64()..slot.as_u64() {
+            state
+                .set_block_root(Slot::new(ancestor_slot), parent_root)
+                .expect("Scenario should fit within SlotsPerHistoricalRoot");
+        }
+
+        let mut block = BeaconBlock::empty(&spec);
+        block.slot = slot;
+        block.previous_block_root = parent_root;
+
+        self.put(root, block, state)
+    }
+
+    fn put(
+        &mut self,
+        root: Hash256,
+        mut block: BeaconBlock,
+        state: BeaconState<TestEthSpec>,
+    ) -> BeaconBlock {
+        block.state_root = state.canonical_root();
+
+        self.store.put(&block.state_root, &state).unwrap();
+        self.store.put(&root, &block).unwrap();
+        self.states.insert(root, state);
+
+        block
+    }
+
+    fn block(&self, root: Hash256) -> BeaconBlock {
+        self.store
+            .get(&root)
+            .unwrap()
+            .unwrap_or_else(|| panic!("Unknown block: {:?}", root))
+    }
+}
+
+fn scenarios() -> Vec<Scenario> {
+    let dir = PathBuf::from(env!("CARGO_MANIFEST_DIR")).join("tests/test_vectors");
+
+    let mut paths: Vec<PathBuf> = fs::read_dir(&dir)
+        .expect("Should read test vector directory")
+        .map(|entry| entry.expect("Should read directory entry").path())
+        .filter(|path| path.extension().map_or(false, |ext| ext == "yaml"))
+        .collect();
+    paths.sort();
+
+    assert!(!paths.is_empty(), "No test vectors in {:?}", dir);
+
+    paths.iter().map(|path| Scenario::from_file(path)).collect()
+}
+
+#[test]
+fn reduced_tree() {
+    for scenario in scenarios() {
+        scenario.run::<ThreadSafeReducedTree<MemoryStore, TestEthSpec>>();
+    }
+}
+
+#[test]
+fn proto_array() {
+    for scenario in scenarios() {
+        scenario.run::<ThreadSafeProtoArray<MemoryStore, TestEthSpec>>();
+    }
+}
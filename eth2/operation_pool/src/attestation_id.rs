@@ -32,6 +32,11 @@ impl AttestationId {
         int_to_bytes8(spec.get_domain(epoch, Domain::Attestation, &state.fork))
     }
 
+    /// The SSZ encoding of the `AttestationData`, followed by the domain bytes.
+    pub fn as_bytes(&self) -> &[u8] {
+        &self.v
+    }
+
     pub fn domain_bytes_match(&self, domain_bytes: &[u8]) -> bool {
         &self.v[self.v.len() - DOMAIN_BYTES_LEN..] == domain_bytes
     }
@@ -3,10 +3,10 @@ mod attestation_id;
 mod max_cover;
 mod persistence;
 
+pub use attestation_id::AttestationId;
 pub use persistence::PersistedOperationPool;
 
 use attestation::{earliest_attestation_validators, AttMaxCover};
-use attestation_id::AttestationId;
 use itertools::Itertools;
 use max_cover::maximum_cover;
 use parking_lot::RwLock;
@@ -109,6 +109,16 @@ impl<T: EthSpec> OperationPool<T> {
         self.attestations.read().values().map(Vec::len).sum()
     }
 
+    /// Every attestation in the pool, grouped by `AttestationId` and ordered by id.
+    pub fn get_all_attestations(&self) -> Vec<(AttestationId, Vec<Attestation>)> {
+        self.attestations
+            .read()
+            .iter()
+            .map(|(id, attestations)| (id.clone(), attestations.clone()))
+            .sorted_by(|(a, _), (b, _)| a.cmp(b))
+            .collect()
+    }
+
     /// Get a list of attestations for inclusion in a block.
     pub fn get_attestations(&self, state: &BeaconState<T>, spec: &ChainSpec) -> Vec<Attestation> {
         // Attestations for the current fork, which may be from the current or previous epoch.
@@ -148,7 +158,9 @@ impl<T: EthSpec> OperationPool<T> {
 
     /// Add a deposit to the pool.
     ///
-    /// No two distinct deposits should be added with the same index.
+    /// No two distinct deposits should be added with the same index. If one is, the pooled deposit
+    /// is replaced, so callers must first verify the incoming deposit against the current deposit
+    /// root (see `BeaconChain::process_deposit`).
     pub fn insert_deposit(
         &self,
         deposit: Deposit,
@@ -196,6 +208,11 @@ impl<T: EthSpec> OperationPool<T> {
         self.deposits.read().len()
     }
 
+    /// Every deposit in the pool, ordered by index.
+    pub fn get_all_deposits(&self) -> Vec<Deposit> {
+        self.deposits.read().values().cloned().collect()
+    }
+
     /// Insert a proposer slashing into the pool.
     pub fn insert_proposer_slashing(
         &self,
@@ -212,6 +229,21 @@ impl<T: EthSpec> OperationPool<T> {
         Ok(())
     }
 
+    /// Total number of proposer slashings in the pool.
+    pub fn num_proposer_slashings(&self) -> usize {
+        self.proposer_slashings.read().len()
+    }
+
+    /// Every proposer slashing in the pool, ordered by proposer index.
+    pub fn get_all_proposer_slashings(&self) -> Vec<ProposerSlashing> {
+        self.proposer_slashings
+            .read()
+            .values()
+            .cloned()
+            .sorted_by_key(|slashing| slashing.proposer_index)
+            .collect()
+    }
+
     /// Compute the tuple ID that is used to identify an attester slashing.
     ///
     /// Depends on the fork field of the state, but not on the state's epoch.
@@ -239,6 +271,16 @@ impl<T: EthSpec> OperationPool<T> {
         Ok(())
     }
 
+    /// Total number of attester slashings in the pool.
+    pub fn num_attester_slashings(&self) -> usize {
+        self.attester_slashings.read().len()
+    }
+
+    /// Every attester slashing in the pool, in arbitrary order.
+    pub fn get_all_attester_slashings(&self) -> Vec<AttesterSlashing> {
+        self.attester_slashings.read().values().cloned().collect()
+    }
+
     /// Get proposer and attester slashings for inclusion in a block.
     ///
     /// This function computes both types of slashings together, because
@@ -338,6 +380,21 @@ impl<T: EthSpec> OperationPool<T> {
         Ok(())
     }
 
+    /// Total number of voluntary exits in the pool.
+    pub fn num_voluntary_exits(&self) -> usize {
+        self.voluntary_exits.read().len()
+    }
+
+    /// Every voluntary exit in the pool, ordered by validator index.
+    pub fn get_all_voluntary_exits(&self) -> Vec<VoluntaryExit> {
+        self.voluntary_exits
+            .read()
+            .values()
+            .cloned()
+            .sorted_by_key(|exit| exit.validator_index)
+            .collect()
+    }
+
     /// Get a list of voluntary exits for inclusion in a block.
     pub fn get_voluntary_exits(
         &self,
@@ -375,6 +432,16 @@ impl<T: EthSpec> OperationPool<T> {
         Ok(())
     }
 
+    /// Total number of transfers in the pool.
+    pub fn num_transfers(&self) -> usize {
+        self.transfers.read().len()
+    }
+
+    /// Every transfer in the pool, in arbitrary order.
+    pub fn get_all_transfers(&self) -> Vec<Transfer> {
+        self.transfers.read().iter().cloned().collect()
+    }
+
     /// Get a list of transfers for inclusion in a block.
     // TODO: improve the economic optimality of this function by accounting for
     // dependencies between transfers in the same block e.g. A pays B, B pays C
@@ -554,6 +621,26 @@ mod tests {
         assert_eq!(op_pool.num_deposits(), 0);
     }
 
+    #[test]
+    fn deposits_ordered_by_index() {
+        let rng = &mut XorShiftRng::from_seed([42; 16]);
+        let op_pool = OperationPool::<MinimalEthSpec>::new();
+        let deposits = dummy_deposits(rng, 10, 5);
+
+        for &i in &[3, 0, 4, 1, 2] {
+            assert_eq!(op_pool.insert_deposit(deposits[i].clone()), Ok(Fresh));
+        }
+
+        assert_eq!(op_pool.get_all_deposits(), deposits);
+
+        let persisted = PersistedOperationPool::from_operation_pool(&op_pool);
+        assert_eq!(persisted.deposits, deposits);
+        assert_eq!(op_pool.num_proposer_slashings(), 0);
+        assert_eq!(op_pool.num_attester_slashings(), 0);
+        assert_eq!(op_pool.num_voluntary_exits(), 0);
+        assert_eq!(op_pool.num_transfers(), 0);
+    }
+
     // Create a random deposit
     fn make_deposit(rng: &mut XorShiftRng) -> Deposit {
         Deposit::random_for_test(rng)
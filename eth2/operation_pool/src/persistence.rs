@@ -8,62 +8,36 @@ use types::*;
 ///
 /// Operations are stored in arbitrary order, so it's not a good idea to compare instances
 /// of this type (or its encoded form) for equality. Convert back to an `OperationPool` first.
+///
+/// The fields are public so that a persisted pool may be inspected without restoring it (e.g., by
+/// the database manager).
 #[derive(Encode, Decode)]
 pub struct PersistedOperationPool {
     /// Mapping from attestation ID to attestation mappings.
     // We could save space by not storing the attestation ID, but it might
     // be difficult to make that roundtrip due to eager aggregation.
-    attestations: Vec<(AttestationId, Vec<Attestation>)>,
-    deposits: Vec<Deposit>,
+    pub attestations: Vec<(AttestationId, Vec<Attestation>)>,
+    /// Deposits, ordered by index.
+    pub deposits: Vec<Deposit>,
     /// Attester slashings.
-    attester_slashings: Vec<AttesterSlashing>,
+    pub attester_slashings: Vec<AttesterSlashing>,
     /// Proposer slashings.
-    proposer_slashings: Vec<ProposerSlashing>,
+    pub proposer_slashings: Vec<ProposerSlashing>,
     /// Voluntary exits.
-    voluntary_exits: Vec<VoluntaryExit>,
+    pub voluntary_exits: Vec<VoluntaryExit>,
     /// Transfers.
-    transfers: Vec<Transfer>,
+    pub transfers: Vec<Transfer>,
 }
 
 impl PersistedOperationPool {
     /// Convert an `OperationPool` into serializable form.
     pub fn from_operation_pool<T: EthSpec>(operation_pool: &OperationPool<T>) -> Self {
-        let attestations = operation_pool
-            .attestations
-            .read()
-            .iter()
-            .map(|(att_id, att)| (att_id.clone(), att.clone()))
-            .collect();
-
-        let deposits = operation_pool
-            .deposits
-            .read()
-            .iter()
-            .map(|(_, d)| d.clone())
-            .collect();
-
-        let attester_slashings = operation_pool
-            .attester_slashings
-            .read()
-            .iter()
-            .map(|(_, slashing)| slashing.clone())
-            .collect();
-
-        let proposer_slashings = operation_pool
-            .proposer_slashings
-            .read()
-            .iter()
-            .map(|(_, slashing)| slashing.clone())
-            .collect();
-
-        let voluntary_exits = operation_pool
-            .voluntary_exits
-            .read()
-            .iter()
-            .map(|(_, exit)| exit.clone())
-            .collect();
-
-        let transfers = operation_pool.transfers.read().iter().cloned().collect();
+        let attestations = operation_pool.get_all_attestations();
+        let deposits = operation_pool.get_all_deposits();
+        let attester_slashings = operation_pool.get_all_attester_slashings();
+        let proposer_slashings = operation_pool.get_all_proposer_slashings();
+        let voluntary_exits = operation_pool.get_all_voluntary_exits();
+        let transfers = operation_pool.get_all_transfers();
 
         Self {
             attestations,
@@ -1,5 +1,5 @@
 use crate::{
-    test_utils::{fork_from_hex_str, TestRandom},
+    test_utils::{fork_from_hex_str, fork_to_hex_str, TestRandom},
     Epoch,
 };
 
@@ -25,9 +25,15 @@ use tree_hash_derive::{CachedTreeHash, TreeHash};
     TestRandom,
 )]
 pub struct Fork {
-    #[serde(deserialize_with = "fork_from_hex_str")]
+    #[serde(
+        serialize_with = "fork_to_hex_str",
+        deserialize_with = "fork_from_hex_str"
+    )]
     pub previous_version: [u8; 4],
-    #[serde(deserialize_with = "fork_from_hex_str")]
+    #[serde(
+        serialize_with = "fork_to_hex_str",
+        deserialize_with = "fork_from_hex_str"
+    )]
     pub current_version: [u8; 4],
     pub epoch: Epoch,
 }
@@ -96,4 +102,17 @@ mod tests {
         assert_eq!(fork.get_fork_version(epoch), current_version);
         assert_eq!(fork.get_fork_version(epoch + 1), current_version);
     }
+
+    #[test]
+    fn serde_round_trip() {
+        let fork = Fork {
+            previous_version: [0, 1, 2, 3],
+            current_version: [0xaa, 0xbb, 0xcc, 0xdd],
+            epoch: Epoch::new(7),
+        };
+
+        let json = serde_json::to_string(&fork).unwrap();
+        assert!(json.contains("\"0xaabbccdd\""), "versions should be hex");
+        assert_eq!(serde_json::from_str::<Fork>(&json).unwrap(), fork);
+    }
 }
@@ -14,5 +14,7 @@ pub use rand::{
     RngCore,
     {prng::XorShiftRng, SeedableRng},
 };
-pub use serde_utils::{fork_from_hex_str, graffiti_from_hex_str, u8_from_hex_str, u8_to_hex_str};
+pub use serde_utils::{
+    fork_from_hex_str, fork_to_hex_str, graffiti_from_hex_str, u8_from_hex_str, u8_to_hex_str,
+};
 pub use test_random::TestRandom;
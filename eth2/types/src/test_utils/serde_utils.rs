@@ -45,6 +45,17 @@ where
     Ok(array)
 }
 
+#[allow(clippy::trivially_copy_pass_by_ref)] // Serde requires the `bytes` to be a ref.
+pub fn fork_to_hex_str<S>(bytes: &[u8; FORK_BYTES_LEN], serializer: S) -> Result<S::Ok, S::Error>
+where
+    S: Serializer,
+{
+    let mut hex: String = "0x".to_string();
+    hex.push_str(&hex::encode(bytes));
+
+    serializer.serialize_str(&hex)
+}
+
 pub fn graffiti_from_hex_str<'de, D>(deserializer: D) -> Result<[u8; GRAFFITI_BYTES_LEN], D::Error>
 where
     D: Deserializer<'de>,
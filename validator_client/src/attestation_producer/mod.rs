@@ -1,23 +1,26 @@
 mod beacon_node_attestation;
 mod grpc;
+mod rest;
 
 use std::sync::Arc;
 use types::{ChainSpec, Domain, Fork};
 //TODO: Move these higher up in the crate
 use super::block_producer::{BeaconNodeError, PublishOutcome, ValidatorEvent};
 use crate::signer::Signer;
-use beacon_node_attestation::BeaconNodeAttestation;
+use crate::slashing_protection::{NotSafe, SlashingProtection};
+pub use beacon_node_attestation::BeaconNodeAttestation;
 use slog::{error, info, warn};
 use tree_hash::TreeHash;
 use types::{
     AggregateSignature, Attestation, AttestationData, AttestationDataAndCustodyBit,
-    AttestationDuty, Bitfield,
+    AttestationDuty, Bitfield, Hash256,
 };
 
 //TODO: Group these errors at a crate level
 #[derive(Debug, PartialEq)]
 pub enum Error {
     BeaconNodeError(BeaconNodeError),
+    SlashingProtection(NotSafe),
 }
 
 impl From<BeaconNodeError> for Error {
@@ -39,6 +42,8 @@ pub struct AttestationProducer<'a, B: BeaconNodeAttestation, S: Signer> {
     pub beacon_node: Arc<B>,
     /// The signer to sign the block.
     pub signer: &'a S,
+    /// The signing history of the validator, used to prevent slashable attestations.
+    pub slashing_protection: &'a SlashingProtection,
     /// Used for caclulating epoch.
     pub slots_per_epoch: u64,
 }
@@ -74,11 +79,6 @@ impl<'a, B: BeaconNodeAttestation, S: Signer> AttestationProducer<'a, B, S> {
     /// Assumes that an attestation is required at this slot (does not check the duties).
     ///
     /// Ensures the message is not slashable.
-    ///
-    /// !!! UNSAFE !!!
-    ///
-    /// The slash-protection code is not yet implemented. There is zero protection against
-    /// slashing.
     pub fn produce_attestation(&mut self) -> Result<ValidatorEvent, Error> {
         let epoch = self.duty.slot.epoch(self.slots_per_epoch);
 
@@ -87,7 +87,7 @@ impl<'a, B: BeaconNodeAttestation, S: Signer> AttestationProducer<'a, B, S> {
             .produce_attestation_data(self.duty.slot, self.duty.shard)?;
         if self.safe_to_produce(&attestation) {
             let domain = self.spec.get_domain(epoch, Domain::Attestation, &self.fork);
-            if let Some(attestation) = self.sign_attestation(attestation, self.duty, domain) {
+            if let Some(attestation) = self.sign_attestation(attestation, self.duty, domain)? {
                 match self.beacon_node.publish_attestation(attestation) {
                     Ok(PublishOutcome::InvalidAttestation(_string)) => {
                         Ok(ValidatorEvent::InvalidAttestation)
@@ -109,15 +109,15 @@ impl<'a, B: BeaconNodeAttestation, S: Signer> AttestationProducer<'a, B, S> {
 
     /// Consumes an attestation, returning the attestation signed by the validators private key.
     ///
-    /// Important: this function will not check to ensure the attestation is not slashable. This must be
-    /// done upstream.
+    /// The attestation is recorded in the slashing protection database before it is signed.
+    /// Returns an error if the attestation is slashable or it could not be recorded.
     fn sign_attestation(
         &mut self,
         attestation: AttestationData,
         duties: AttestationDuty,
         domain: u64,
-    ) -> Option<Attestation> {
-        self.store_produce(&attestation);
+    ) -> Result<Option<Attestation>, Error> {
+        self.store_produce(&attestation)?;
 
         // build the aggregate signature
         let aggregate_signature = {
@@ -127,7 +127,10 @@ impl<'a, B: BeaconNodeAttestation, S: Signer> AttestationProducer<'a, B, S> {
             }
             .tree_hash_root();
 
-            let sig = self.signer.sign_message(&message, domain)?;
+            let sig = match self.signer.sign_message(&message, domain) {
+                Some(sig) => sig,
+                None => return Ok(None),
+            };
 
             let mut agg_sig = AggregateSignature::new();
             agg_sig.add(&sig);
@@ -138,30 +141,36 @@ impl<'a, B: BeaconNodeAttestation, S: Signer> AttestationProducer<'a, B, S> {
         let custody_bitfield = Bitfield::with_capacity(duties.committee_len);
         aggregation_bitfield.set(duties.committee_index, true);
 
-        Some(Attestation {
+        Ok(Some(Attestation {
             aggregation_bitfield,
             data: attestation,
             custody_bitfield,
             signature: aggregate_signature,
-        })
+        }))
     }
 
     /// Returns `true` if signing an attestation is safe (non-slashable).
-    ///
-    /// !!! UNSAFE !!!
-    ///
-    /// Important: this function is presently stubbed-out. It provides ZERO SAFETY.
-    fn safe_to_produce(&self, _attestation: &AttestationData) -> bool {
-        //TODO: Implement slash protection
-        true
+    fn safe_to_produce(&self, attestation: &AttestationData) -> bool {
+        self.slashing_protection
+            .check_attestation(
+                attestation.source_epoch,
+                attestation.target_epoch,
+                Hash256::from_slice(&attestation.tree_hash_root()),
+            )
+            .is_ok()
     }
 
     /// Record that an attestation was produced so that slashable votes may not be made in the future.
     ///
-    /// !!! UNSAFE !!!
-    ///
-    /// Important: this function is presently stubbed-out. It provides ZERO SAFETY.
-    fn store_produce(&mut self, _attestation: &AttestationData) {
-        // TODO: Implement slash protection
+    /// The slashing conditions are checked again whilst recording, so it is impossible for two
+    /// conflicting attestations to be recorded concurrently.
+    fn store_produce(&mut self, attestation: &AttestationData) -> Result<(), Error> {
+        self.slashing_protection
+            .record_attestation(
+                attestation.source_epoch,
+                attestation.target_epoch,
+                Hash256::from_slice(&attestation.tree_hash_root()),
+            )
+            .map_err(Error::SlashingProtection)
     }
 }
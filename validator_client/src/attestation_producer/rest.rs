@@ -0,0 +1,30 @@
+use super::beacon_node_attestation::BeaconNodeAttestation;
+use crate::block_producer::{BeaconNodeError, PublishOutcome};
+use crate::rest_client::{BeaconNodeRestClient, Error};
+use serde::de::IgnoredAny;
+use types::{Attestation, AttestationData, Slot};
+
+impl BeaconNodeAttestation for BeaconNodeRestClient {
+    fn produce_attestation_data(
+        &self,
+        slot: Slot,
+        shard: u64,
+    ) -> Result<AttestationData, BeaconNodeError> {
+        self.get(
+            "validator/attestation_data",
+            &[("slot", slot.to_string()), ("shard", shard.to_string())],
+        )
+        .map_err(|e| BeaconNodeError::RemoteFailure(format!("{:?}", e)))
+    }
+
+    fn publish_attestation(
+        &self,
+        attestation: Attestation,
+    ) -> Result<PublishOutcome, BeaconNodeError> {
+        match self.post::<_, IgnoredAny>("validator/attestation", &attestation) {
+            Ok(_) => Ok(PublishOutcome::Valid),
+            Err(Error::BadRequest(message)) => Ok(PublishOutcome::InvalidAttestation(message)),
+            Err(e) => Err(BeaconNodeError::RemoteFailure(format!("{:?}", e))),
+        }
+    }
+}
@@ -1,18 +1,20 @@
 mod beacon_node_block;
 mod grpc;
+mod rest;
 
-use self::beacon_node_block::BeaconNodeBlock;
-pub use self::beacon_node_block::{BeaconNodeError, PublishOutcome};
+pub use self::beacon_node_block::{BeaconNodeBlock, BeaconNodeError, PublishOutcome};
 pub use self::grpc::BeaconBlockGrpcClient;
 use crate::signer::Signer;
+use crate::slashing_protection::{NotSafe, SlashingProtection};
 use slog::{error, info, warn};
 use std::sync::Arc;
 use tree_hash::{SignedRoot, TreeHash};
-use types::{BeaconBlock, ChainSpec, Domain, Fork, Slot};
+use types::{BeaconBlock, ChainSpec, Domain, Fork, Hash256, Slot};
 
 #[derive(Debug, PartialEq)]
 pub enum Error {
     BeaconNodeError(BeaconNodeError),
+    SlashingProtection(NotSafe),
 }
 
 #[derive(Debug, PartialEq)]
@@ -48,6 +50,8 @@ pub struct BlockProducer<'a, B: BeaconNodeBlock, S: Signer> {
     pub beacon_node: Arc<B>,
     /// The signer to sign the block.
     pub signer: &'a S,
+    /// The signing history of the validator, used to prevent slashable blocks.
+    pub slashing_protection: &'a SlashingProtection,
     /// Used for caclulating epoch.
     pub slots_per_epoch: u64,
 }
@@ -80,11 +84,6 @@ impl<'a, B: BeaconNodeBlock, S: Signer> BlockProducer<'a, B, S> {
     /// Assumes that a block is required at this slot (does not check the duties).
     ///
     /// Ensures the message is not slashable.
-    ///
-    /// !!! UNSAFE !!!
-    ///
-    /// The slash-protection code is not yet implemented. There is zero protection against
-    /// slashing.
     pub fn produce_block(&mut self) -> Result<ValidatorEvent, Error> {
         let epoch = self.slot.epoch(self.slots_per_epoch);
 
@@ -105,7 +104,7 @@ impl<'a, B: BeaconNodeBlock, S: Signer> BlockProducer<'a, B, S> {
                 let domain = self
                     .spec
                     .get_domain(epoch, Domain::BeaconProposer, &self.fork);
-                if let Some(block) = self.sign_block(block, domain) {
+                if let Some(block) = self.sign_block(block, domain)? {
                     self.beacon_node.publish_beacon_block(block)?;
                     Ok(ValidatorEvent::BlockProduced(self.slot))
                 } else {
@@ -121,39 +120,39 @@ impl<'a, B: BeaconNodeBlock, S: Signer> BlockProducer<'a, B, S> {
 
     /// Consumes a block, returning that block signed by the validators private key.
     ///
-    /// Important: this function will not check to ensure the block is not slashable. This must be
-    /// done upstream.
-    fn sign_block(&mut self, mut block: BeaconBlock, domain: u64) -> Option<BeaconBlock> {
-        self.store_produce(&block);
+    /// The block is recorded in the slashing protection database before it is signed. Returns an
+    /// error if the block is slashable or it could not be recorded.
+    fn sign_block(
+        &mut self,
+        mut block: BeaconBlock,
+        domain: u64,
+    ) -> Result<Option<BeaconBlock>, Error> {
+        self.store_produce(&block)?;
 
         match self.signer.sign_message(&block.signed_root()[..], domain) {
-            None => None,
+            None => Ok(None),
             Some(signature) => {
                 block.signature = signature;
-                Some(block)
+                Ok(Some(block))
             }
         }
     }
 
     /// Returns `true` if signing a block is safe (non-slashable).
-    ///
-    /// !!! UNSAFE !!!
-    ///
-    /// Important: this function is presently stubbed-out. It provides ZERO SAFETY.
-    fn safe_to_produce(&self, _block: &BeaconBlock) -> bool {
-        // TODO: ensure the producer doesn't produce slashable blocks.
-        // https://github.com/sigp/lighthouse/issues/160
-        true
+    fn safe_to_produce(&self, block: &BeaconBlock) -> bool {
+        self.slashing_protection
+            .check_block(block.slot, Hash256::from_slice(&block.signed_root()))
+            .is_ok()
     }
 
-    /// Record that a block was produced so that slashable votes may not be made in the future.
-    ///
-    /// !!! UNSAFE !!!
+    /// Record that a block was produced so that slashable blocks may not be made in the future.
     ///
-    /// Important: this function is presently stubbed-out. It provides ZERO SAFETY.
-    fn store_produce(&mut self, _block: &BeaconBlock) {
-        // TODO: record this block production to prevent future slashings.
-        // https://github.com/sigp/lighthouse/issues/160
+    /// The slashing conditions are checked again whilst recording, so it is impossible for two
+    /// conflicting blocks to be recorded concurrently.
+    fn store_produce(&mut self, block: &BeaconBlock) -> Result<(), Error> {
+        self.slashing_protection
+            .record_block(block.slot, Hash256::from_slice(&block.signed_root()))
+            .map_err(Error::SlashingProtection)
     }
 }
 
@@ -0,0 +1,40 @@
+use super::beacon_node_block::*;
+use crate::rest_client::{BeaconNodeRestClient, Error};
+use serde::de::IgnoredAny;
+use ssz::Encode;
+use types::{BeaconBlock, Signature, Slot};
+
+impl BeaconNodeBlock for BeaconNodeRestClient {
+    /// Request a Beacon Node (BN) to produce a new block at the supplied slot.
+    ///
+    /// The BN responds with an error if it is unable to produce at the supplied slot, so this never
+    /// returns `None`.
+    fn produce_beacon_block(
+        &self,
+        slot: Slot,
+        randao_reveal: &Signature,
+    ) -> Result<Option<BeaconBlock>, BeaconNodeError> {
+        let randao_reveal = format!("0x{}", hex::encode(randao_reveal.as_ssz_bytes()));
+
+        let block = self
+            .get(
+                "validator/block",
+                &[("slot", slot.to_string()), ("randao_reveal", randao_reveal)],
+            )
+            .map_err(|e| BeaconNodeError::RemoteFailure(format!("{:?}", e)))?;
+
+        Ok(Some(block))
+    }
+
+    /// Request a Beacon Node (BN) to publish a block.
+    ///
+    /// Generally, this will be called after a `produce_beacon_block` call with a block that has
+    /// been completed (signed) by the validator client.
+    fn publish_beacon_block(&self, block: BeaconBlock) -> Result<PublishOutcome, BeaconNodeError> {
+        match self.post::<_, IgnoredAny>("validator/block", &block) {
+            Ok(_) => Ok(PublishOutcome::Valid),
+            Err(Error::BadRequest(message)) => Ok(PublishOutcome::InvalidBlock(message)),
+            Err(e) => Err(BeaconNodeError::RemoteFailure(format!("{:?}", e))),
+        }
+    }
+}
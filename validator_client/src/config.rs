@@ -16,6 +16,9 @@ pub struct Config {
     pub data_dir: PathBuf,
     /// The server at which the Beacon Node can be contacted
     pub server: String,
+    /// If set, the URL of the Beacon Node HTTP API, which is used instead of gRPC at `server`.
+    #[serde(default)]
+    pub rest_server: Option<String>,
     /// The number of slots per epoch.
     pub slots_per_epoch: u64,
 }
@@ -28,6 +31,7 @@ impl Default for Config {
         Self {
             data_dir: PathBuf::from(".lighthouse-validator"),
             server: "localhost:5051".to_string(),
+            rest_server: None,
             slots_per_epoch: MainnetEthSpec::slots_per_epoch(),
         }
     }
@@ -47,6 +51,10 @@ impl Config {
             self.server = srv.to_string();
         };
 
+        if let Some(rest_server) = args.value_of("rest-server") {
+            self.rest_server = Some(rest_server.to_string());
+        };
+
         Ok(())
     }
 
@@ -114,10 +122,16 @@ impl Config {
         }
     }
 
+    /// Returns the directory which stores the private key and signing history of the validator
+    /// with the given keypair.
+    pub fn validator_dir(&self, key: &Keypair) -> PathBuf {
+        self.data_dir.join(key.identifier())
+    }
+
     /// Saves a keypair to a file inside the appropriate validator directory. Returns the saved path filename.
     #[allow(dead_code)]
     pub fn save_key(&self, key: &Keypair) -> Result<PathBuf, Error> {
-        let validator_config_path = self.data_dir.join(key.identifier());
+        let validator_config_path = self.validator_dir(key);
         let key_path = validator_config_path.join(DEFAULT_PRIVATE_KEY_FILENAME);
 
         fs::create_dir_all(&validator_config_path)?;
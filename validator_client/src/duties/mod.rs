@@ -1,6 +1,7 @@
 mod beacon_node_duties;
 mod epoch_duties;
 mod grpc;
+mod rest;
 // TODO: reintroduce tests
 //#[cfg(test)]
 //mod test_node;
@@ -0,0 +1,50 @@
+use super::beacon_node_duties::{BeaconNodeDuties, BeaconNodeDutiesError};
+use super::epoch_duties::{EpochDuties, EpochDuty};
+use crate::rest_client::BeaconNodeRestClient;
+use serde_derive::{Deserialize, Serialize};
+use types::{AttestationDuty, Epoch, PublicKey, Slot};
+
+#[derive(Serialize)]
+struct DutiesRequest<'a> {
+    epoch: Epoch,
+    pubkeys: &'a [PublicKey],
+}
+
+#[derive(Deserialize)]
+struct ValidatorDuty {
+    pubkey: PublicKey,
+    block_production_slot: Option<Slot>,
+    attestation_duty: Option<AttestationDuty>,
+}
+
+impl BeaconNodeDuties for BeaconNodeRestClient {
+    /// Requests all duties (block signing and committee attesting) from the Beacon Node (BN).
+    fn request_duties(
+        &self,
+        epoch: Epoch,
+        pub_keys: &[PublicKey],
+    ) -> Result<EpochDuties, BeaconNodeDutiesError> {
+        let duties: Vec<ValidatorDuty> = self
+            .post(
+                "validator/duties",
+                &DutiesRequest {
+                    epoch,
+                    pubkeys: pub_keys,
+                },
+            )
+            .map_err(|e| BeaconNodeDutiesError::RemoteFailure(format!("{:?}", e)))?;
+
+        Ok(duties
+            .into_iter()
+            .map(|duty| {
+                // A validator without an attestation duty is not active in the epoch.
+                let epoch_duty = duty.attestation_duty.map(|attestation_duty| EpochDuty {
+                    block_production_slot: duty.block_production_slot,
+                    attestation_duty,
+                });
+
+                (duty.pubkey, epoch_duty)
+            })
+            .collect())
+    }
+}
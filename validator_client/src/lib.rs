@@ -1,3 +1,4 @@
 pub mod config;
+pub mod slashing_protection;
 
 pub use crate::config::Config;
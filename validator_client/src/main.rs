@@ -3,18 +3,18 @@ mod block_producer;
 mod config;
 mod duties;
 pub mod error;
+mod rest_client;
 mod service;
 mod signer;
 
 use crate::config::Config as ValidatorClientConfig;
-use crate::service::Service as ValidatorService;
 use clap::{App, Arg};
 use eth2_config::{read_from_file, write_to_file, Eth2Config};
-use protos::services_grpc::ValidatorServiceClient;
 use slog::{crit, error, info, o, Drain};
 use std::fs;
 use std::path::PathBuf;
-use types::{Keypair, MainnetEthSpec, MinimalEthSpec};
+use types::{MainnetEthSpec, MinimalEthSpec};
+use validator_client::slashing_protection;
 
 pub const DEFAULT_SPEC: &str = "minimal";
 pub const DEFAULT_DATA_DIR: &str = ".lighthouse-validator";
@@ -56,6 +56,13 @@ fn main() {
                 .help("Address to connect to BeaconNode.")
                 .takes_value(true),
         )
+        .arg(
+            Arg::with_name("rest-server")
+                .long("rest-server")
+                .value_name("URL")
+                .help("URL of the BeaconNode HTTP API (e.g., http://localhost:5052). If given, it is used instead of gRPC.")
+                .takes_value(true),
+        )
         .arg(
             Arg::with_name("spec-constants")
                 .long("spec-constants")
@@ -176,16 +183,8 @@ fn main() {
     );
 
     let result = match eth2_config.spec_constants.as_str() {
-        "mainnet" => ValidatorService::<ValidatorServiceClient, Keypair>::start::<MainnetEthSpec>(
-            client_config,
-            eth2_config,
-            log.clone(),
-        ),
-        "minimal" => ValidatorService::<ValidatorServiceClient, Keypair>::start::<MinimalEthSpec>(
-            client_config,
-            eth2_config,
-            log.clone(),
-        ),
+        "mainnet" => service::start::<MainnetEthSpec>(client_config, eth2_config, log.clone()),
+        "minimal" => service::start::<MinimalEthSpec>(client_config, eth2_config, log.clone()),
         other => {
             crit!(log, "Unknown spec constants"; "title" => other);
             return;
@@ -0,0 +1,108 @@
+//! A client for the HTTP/JSON API of the beacon node, as an alternative to gRPC.
+//!
+//! The `BeaconNodeDuties`, `BeaconNodeBlock` and `BeaconNodeAttestation` traits are implemented
+//! upon `BeaconNodeRestClient` in the `duties`, `block_producer` and `attestation_producer`
+//! modules.
+use crate::service::NodeInfo;
+use reqwest::{Client, StatusCode, Url};
+use serde::{de::DeserializeOwned, Serialize};
+use serde_derive::Deserialize;
+
+#[derive(Debug)]
+pub enum Error {
+    /// The server URL could not be joined with an API path.
+    InvalidUrl(String),
+    /// The request could not be sent, or the response could not be read.
+    Reqwest(reqwest::Error),
+    /// The beacon node rejected the request as invalid.
+    BadRequest(String),
+    /// The beacon node responded with some other error status.
+    ErrorStatus(StatusCode, String),
+}
+
+/// The body of an error response from the beacon node.
+#[derive(Deserialize)]
+struct ErrorResponse {
+    error: String,
+}
+
+/// Makes requests to the HTTP API of a single beacon node (or a load balancer in front of many).
+pub struct BeaconNodeRestClient {
+    client: Client,
+    server: Url,
+}
+
+impl BeaconNodeRestClient {
+    /// Create a client for the API at `server` (e.g., `http://localhost:5052`).
+    pub fn new(server: &str) -> Result<Self, String> {
+        let server = Url::parse(server)
+            .map_err(|e| format!("Invalid beacon node URL {}: {:?}", server, e))?;
+
+        Ok(Self {
+            client: Client::new(),
+            server,
+        })
+    }
+
+    /// Returns the properties of the beacon node, via `GET /node/info`.
+    pub fn node_info(&self) -> Result<NodeInfo, Error> {
+        self.get("node/info", &[])
+    }
+
+    /// Sends a `GET` request to `path` with the given query, reading the response as JSON.
+    pub fn get<T: DeserializeOwned>(
+        &self,
+        path: &str,
+        query: &[(&str, String)],
+    ) -> Result<T, Error> {
+        let response = self
+            .client
+            .get(self.url(path)?)
+            .query(query)
+            .send()
+            .map_err(Error::Reqwest)?;
+
+        Self::read_response(response)
+    }
+
+    /// Sends a `POST` request to `path` with `body` as JSON, reading the response as JSON.
+    pub fn post<B: Serialize, T: DeserializeOwned>(
+        &self,
+        path: &str,
+        body: &B,
+    ) -> Result<T, Error> {
+        let response = self
+            .client
+            .post(self.url(path)?)
+            .json(body)
+            .send()
+            .map_err(Error::Reqwest)?;
+
+        Self::read_response(response)
+    }
+
+    fn url(&self, path: &str) -> Result<Url, Error> {
+        self.server
+            .join(path)
+            .map_err(|e| Error::InvalidUrl(format!("{:?}", e)))
+    }
+
+    fn read_response<T: DeserializeOwned>(mut response: reqwest::Response) -> Result<T, Error> {
+        let status = response.status();
+
+        if status.is_success() {
+            response.json().map_err(Error::Reqwest)
+        } else {
+            let message = response
+                .json::<ErrorResponse>()
+                .map(|body| body.error)
+                .unwrap_or_else(|_| status.to_string());
+
+            if status == StatusCode::BAD_REQUEST {
+                Err(Error::BadRequest(message))
+            } else {
+                Err(Error::ErrorStatus(status, message))
+            }
+        }
+    }
+}
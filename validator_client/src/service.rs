@@ -8,13 +8,17 @@
 /// When a validator needs to either produce a block or sign an attestation, it requests the
 /// data from the beacon node and performs the signing before publishing the block to the beacon
 /// node.
-use crate::attestation_producer::AttestationProducer;
-use crate::block_producer::{BeaconBlockGrpcClient, BlockProducer};
+///
+/// The beacon node is contacted via gRPC, or via its HTTP API if a `rest_server` is configured.
+use crate::attestation_producer::{AttestationProducer, BeaconNodeAttestation};
+use crate::block_producer::{BeaconBlockGrpcClient, BeaconNodeBlock, BlockProducer};
 use crate::config::Config as ValidatorConfig;
 use crate::duties::{BeaconNodeDuties, DutiesManager, EpochDutiesMap};
 use crate::error as error_chain;
 use crate::error::ErrorKind;
+use crate::rest_client::BeaconNodeRestClient;
 use crate::signer::Signer;
+use crate::slashing_protection::SlashingProtection;
 use bls::Keypair;
 use eth2_config::Eth2Config;
 use grpcio::{ChannelBuilder, EnvBuilder};
@@ -23,6 +27,7 @@ use protos::services_grpc::{
     AttestationServiceClient, BeaconBlockServiceClient, BeaconNodeServiceClient,
     ValidatorServiceClient,
 };
+use serde_derive::Deserialize;
 use slog::{error, info, warn};
 use slot_clock::{SlotClock, SystemTimeSlotClock};
 use std::sync::Arc;
@@ -38,10 +43,139 @@ use types::{ChainSpec, Epoch, EthSpec, Fork, Slot};
 /// per-slot processes.
 const TIME_DELAY_FROM_SLOT: Duration = Duration::from_millis(200);
 
+/// The properties of the beacon node which are checked before starting the service.
+#[derive(Debug, Deserialize)]
+pub struct NodeInfo {
+    pub version: String,
+    pub fork: Fork,
+    pub chain_id: u8,
+    pub genesis_time: u64,
+    pub genesis_slot: Slot,
+    pub finalized_epoch: Epoch,
+}
+
+/// Fetches the `NodeInfo` of the beacon node.
+type GetNodeInfo = Box<dyn Fn() -> Result<NodeInfo, String> + Send>;
+
+/// Connects to the beacon node and runs the validator service until it exits.
+///
+/// The node is contacted via its HTTP API if `client_config.rest_server` is set, otherwise via
+/// gRPC at `client_config.server`.
+pub fn start<T: EthSpec>(
+    client_config: ValidatorConfig,
+    eth2_config: Eth2Config,
+    log: slog::Logger,
+) -> error_chain::Result<()> {
+    match client_config.rest_server.clone() {
+        Some(server) => {
+            let client = Arc::new(BeaconNodeRestClient::new(&server)?);
+
+            let get_node_info = {
+                let client = client.clone();
+                move || client.node_info().map_err(|e| format!("{:?}", e))
+            };
+            let node_info = wait_for_node_info(&log, &get_node_info);
+
+            Service::initialize_service::<T>(
+                client_config,
+                eth2_config,
+                node_info,
+                Box::new(get_node_info),
+                client.clone(),
+                client.clone(),
+                client,
+                log,
+            )?
+            .run()
+        }
+        None => {
+            let env = Arc::new(EnvBuilder::new().build());
+            let channel = || ChannelBuilder::new(env.clone()).connect(&client_config.server);
+
+            // Beacon node gRPC beacon node endpoints.
+            let beacon_node_client = BeaconNodeServiceClient::new(channel());
+
+            let get_node_info = move || {
+                let info = beacon_node_client
+                    .info(&Empty::new())
+                    .map_err(|e| format!("{}", e))?;
+
+                let proto_fork = info.get_fork();
+                let mut previous_version: [u8; 4] = [0; 4];
+                let mut current_version: [u8; 4] = [0; 4];
+                previous_version.copy_from_slice(&proto_fork.get_previous_version()[..4]);
+                current_version.copy_from_slice(&proto_fork.get_current_version()[..4]);
+
+                Ok(NodeInfo {
+                    version: info.get_version().to_string(),
+                    fork: Fork {
+                        previous_version,
+                        current_version,
+                        epoch: Epoch::from(proto_fork.get_epoch()),
+                    },
+                    chain_id: info.get_chain_id() as u8,
+                    genesis_time: info.get_genesis_time(),
+                    genesis_slot: Slot::from(info.get_genesis_slot()),
+                    finalized_epoch: Epoch::from(info.get_finalized_epoch()),
+                })
+            };
+            let node_info = wait_for_node_info(&log, &get_node_info);
+
+            // Beacon node gRPC beacon block endpoints.
+            let beacon_block_client = {
+                let beacon_block_service_client =
+                    Arc::new(BeaconBlockServiceClient::new(channel()));
+                // a wrapper around the service client to implement the beacon block node trait
+                Arc::new(BeaconBlockGrpcClient::new(beacon_block_service_client))
+            };
+
+            // Beacon node gRPC validator endpoints.
+            let validator_client = Arc::new(ValidatorServiceClient::new(channel()));
+
+            //Beacon node gRPC attester endpoints.
+            let attestation_client = Arc::new(AttestationServiceClient::new(channel()));
+
+            Service::initialize_service::<T>(
+                client_config,
+                eth2_config,
+                node_info,
+                Box::new(get_node_info),
+                validator_client,
+                beacon_block_client,
+                attestation_client,
+                log,
+            )?
+            .run()
+        }
+    }
+}
+
+/// Calls `get_node_info` until the beacon node responds.
+fn wait_for_node_info<F>(log: &slog::Logger, get_node_info: F) -> NodeInfo
+where
+    F: Fn() -> Result<NodeInfo, String>,
+{
+    loop {
+        match get_node_info() {
+            Ok(info) => return info,
+            Err(e) => {
+                warn!(log, "Could not connect to node. Error: {}", e);
+                info!(log, "Retrying in 5 seconds...");
+                std::thread::sleep(Duration::from_secs(5));
+            }
+        }
+    }
+}
+
 /// The validator service. This is the main thread that executes and maintains validator
 /// duties.
-//TODO: Generalize the BeaconNode types to use testing
-pub struct Service<B: BeaconNodeDuties + 'static, S: Signer + 'static> {
+pub struct Service<D, B, A, S>
+where
+    D: BeaconNodeDuties + 'static,
+    B: BeaconNodeBlock + 'static,
+    A: BeaconNodeAttestation + 'static,
+    S: Signer + 'static,
+{
     /// The node's current fork version we are processing on.
     fork: Fork,
     /// The slot clock for this service.
@@ -52,109 +186,70 @@ pub struct Service<B: BeaconNodeDuties + 'static, S: Signer + 'static> {
     /// The chain specification for this clients instance.
     spec: Arc<ChainSpec>,
     /// The duties manager which maintains the state of when to perform actions.
-    duties_manager: Arc<DutiesManager<B, S>>,
-    // GRPC Clients
-    /// The beacon block GRPC client.
-    beacon_block_client: Arc<BeaconBlockGrpcClient>,
-    /// The attester GRPC client.
-    attestation_client: Arc<AttestationServiceClient>,
+    duties_manager: Arc<DutiesManager<D, S>>,
+    /// The signing history of each validator, indexed identically to the duties manager signers.
+    slashing_protection: Arc<Vec<SlashingProtection>>,
+    /// Fetches the beacon node's finalized epoch, below which signing histories are pruned.
+    get_node_info: GetNodeInfo,
+    /// The beacon node block client.
+    beacon_block_client: Arc<B>,
+    /// The beacon node attestation client.
+    attestation_client: Arc<A>,
     /// The validator client logger.
     log: slog::Logger,
 }
 
-impl<B: BeaconNodeDuties + 'static, S: Signer + 'static> Service<B, S> {
-    ///  Initial connection to the beacon node to determine its properties.
-    ///
-    ///  This tries to connect to a beacon node. Once connected, it initialised the gRPC clients
-    ///  and returns an instance of the service.
+impl<D, B, A> Service<D, B, A, Keypair>
+where
+    D: BeaconNodeDuties + 'static,
+    B: BeaconNodeBlock + 'static,
+    A: BeaconNodeAttestation + 'static,
+{
+    ///  Validates the beacon node's properties, loads the validator keys and returns an instance
+    ///  of the service which uses the given beacon node clients.
     fn initialize_service<T: EthSpec>(
         client_config: ValidatorConfig,
         eth2_config: Eth2Config,
+        node_info: NodeInfo,
+        get_node_info: GetNodeInfo,
+        validator_client: Arc<D>,
+        beacon_block_client: Arc<B>,
+        attestation_client: Arc<A>,
         log: slog::Logger,
-    ) -> error_chain::Result<Service<ValidatorServiceClient, Keypair>> {
-        // initialise the beacon node client to check for a connection
-
-        let env = Arc::new(EnvBuilder::new().build());
-        // Beacon node gRPC beacon node endpoints.
-        let beacon_node_client = {
-            let ch = ChannelBuilder::new(env.clone()).connect(&client_config.server);
-            BeaconNodeServiceClient::new(ch)
-        };
-
-        // retrieve node information and validate the beacon node
-        let node_info = loop {
-            match beacon_node_client.info(&Empty::new()) {
-                Err(e) => {
-                    warn!(log, "Could not connect to node. Error: {}", e);
-                    info!(log, "Retrying in 5 seconds...");
-                    std::thread::sleep(Duration::from_secs(5));
-                    continue;
-                }
-                Ok(info) => {
-                    // verify the node's genesis time
-                    if SystemTime::now()
-                        .duration_since(SystemTime::UNIX_EPOCH)
-                        .unwrap()
-                        .as_secs()
-                        < info.genesis_time
-                    {
-                        error!(
-                            log,
-                            "Beacon Node's genesis time is in the future. No work to do.\n Exiting"
-                        );
-                        return Err("Genesis time in the future".into());
-                    }
-                    // verify the node's chain id
-                    if eth2_config.spec.chain_id != info.chain_id as u8 {
-                        error!(
-                            log,
-                            "Beacon Node's genesis time is in the future. No work to do.\n Exiting"
-                        );
-                        return Err(format!("Beacon node has the wrong chain id. Expected chain id: {}, node's chain id: {}", eth2_config.spec.chain_id, info.chain_id).into());
-                    }
-                    break info;
-                }
-            };
-        };
+    ) -> error_chain::Result<Self> {
+        // verify the node's genesis time
+        if SystemTime::now()
+            .duration_since(SystemTime::UNIX_EPOCH)
+            .unwrap()
+            .as_secs()
+            < node_info.genesis_time
+        {
+            error!(
+                log,
+                "Beacon Node's genesis time is in the future. No work to do.\n Exiting"
+            );
+            return Err("Genesis time in the future".into());
+        }
+        // verify the node's chain id
+        if eth2_config.spec.chain_id != node_info.chain_id {
+            error!(
+                log,
+                "Beacon Node's genesis time is in the future. No work to do.\n Exiting"
+            );
+            return Err(format!(
+                "Beacon node has the wrong chain id. Expected chain id: {}, node's chain id: {}",
+                eth2_config.spec.chain_id, node_info.chain_id
+            )
+            .into());
+        }
 
         // build requisite objects to form Self
-        let genesis_time = node_info.get_genesis_time();
-        let genesis_slot = Slot::from(node_info.get_genesis_slot());
+        let genesis_time = node_info.genesis_time;
+        let genesis_slot = node_info.genesis_slot;
 
         info!(log,"Beacon node connected"; "Node Version" => node_info.version.clone(), "Chain ID" => node_info.chain_id, "Genesis time" => genesis_time);
 
-        let proto_fork = node_info.get_fork();
-        let mut previous_version: [u8; 4] = [0; 4];
-        let mut current_version: [u8; 4] = [0; 4];
-        previous_version.copy_from_slice(&proto_fork.get_previous_version()[..4]);
-        current_version.copy_from_slice(&proto_fork.get_current_version()[..4]);
-        let fork = Fork {
-            previous_version,
-            current_version,
-            epoch: Epoch::from(proto_fork.get_epoch()),
-        };
-
-        // initialize the RPC clients
-
-        // Beacon node gRPC beacon block endpoints.
-        let beacon_block_client = {
-            let ch = ChannelBuilder::new(env.clone()).connect(&client_config.server);
-            let beacon_block_service_client = Arc::new(BeaconBlockServiceClient::new(ch));
-            // a wrapper around the service client to implement the beacon block node trait
-            Arc::new(BeaconBlockGrpcClient::new(beacon_block_service_client))
-        };
-
-        // Beacon node gRPC validator endpoints.
-        let validator_client = {
-            let ch = ChannelBuilder::new(env.clone()).connect(&client_config.server);
-            Arc::new(ValidatorServiceClient::new(ch))
-        };
-
-        //Beacon node gRPC attester endpoints.
-        let attestation_client = {
-            let ch = ChannelBuilder::new(env.clone()).connect(&client_config.server);
-            Arc::new(AttestationServiceClient::new(ch))
-        };
+        let fork = node_info.fork;
 
         // build the validator slot clock
         let slot_clock = SystemTimeSlotClock::new(
@@ -180,8 +275,29 @@ impl<B: BeaconNodeDuties + 'static, S: Signer + 'static> Service<B, S> {
             }
         };
 
+        // Load the signing history of each validator, refusing to start if any cannot be read.
+        let slashing_protection = keypairs
+            .iter()
+            .map(|keypair| {
+                SlashingProtection::open(&client_config.validator_dir(keypair)).map_err(|e| {
+                    error_chain::Error::from(format!(
+                        "Unable to load slashing protection for {}: {:?}",
+                        keypair.identifier(),
+                        e
+                    ))
+                })
+            })
+            .collect::<error_chain::Result<Vec<_>>>()?;
+        let slashing_protection = Arc::new(slashing_protection);
+
         let slots_per_epoch = T::slots_per_epoch();
 
+        for protection in slashing_protection.iter() {
+            protection
+                .prune(node_info.finalized_epoch, slots_per_epoch)
+                .map_err(|e| format!("Unable to prune slashing protection: {:?}", e))?;
+        }
+
         // TODO: keypairs are randomly generated; they should be loaded from a file or generated.
         // https://github.com/sigp/lighthouse/issues/160
         //let keypairs = Arc::new(generate_deterministic_keypairs(8));
@@ -209,25 +325,25 @@ impl<B: BeaconNodeDuties + 'static, S: Signer + 'static> Service<B, S> {
             slots_per_epoch,
             spec,
             duties_manager,
+            slashing_protection,
+            get_node_info,
             beacon_block_client,
             attestation_client,
             log,
         })
     }
+}
 
-    /// Initialise the service then run the core thread.
-    // TODO: Improve handling of generic BeaconNode types, to stub grpcClient
-    pub fn start<T: EthSpec>(
-        client_config: ValidatorConfig,
-        eth2_config: Eth2Config,
-        log: slog::Logger,
-    ) -> error_chain::Result<()> {
-        // connect to the node and retrieve its properties and initialize the gRPC clients
-        let mut service = Service::<ValidatorServiceClient, Keypair>::initialize_service::<T>(
-            client_config,
-            eth2_config,
-            log,
-        )?;
+impl<D, B, A, S> Service<D, B, A, S>
+where
+    D: BeaconNodeDuties + 'static,
+    B: BeaconNodeBlock + 'static,
+    A: BeaconNodeAttestation + 'static,
+    S: Signer + 'static,
+{
+    /// Run the core thread, which performs the duties of every validator each slot.
+    fn run(self) -> error_chain::Result<()> {
+        let mut service = self;
 
         // we have connected to a node and established its parameters. Spin up the core service
 
@@ -277,6 +393,11 @@ impl<B: BeaconNodeDuties + 'static, S: Signer + 'static> Service<B, S> {
         /* get the new current slot and epoch */
         self.update_current_slot()?;
 
+        /* prune signing histories below the finalized epoch, once per epoch */
+        if self.current_slot.as_u64() % self.slots_per_epoch == 0 {
+            self.prune_slashing_protection();
+        }
+
         /* check for new duties */
         self.check_for_duties();
 
@@ -311,6 +432,27 @@ impl<B: BeaconNodeDuties + 'static, S: Signer + 'static> Service<B, S> {
         Ok(())
     }
 
+    /// Prunes the signing history of each validator below the beacon node's finalized epoch.
+    fn prune_slashing_protection(&self) {
+        let finalized_epoch = match (self.get_node_info)() {
+            Ok(info) => info.finalized_epoch,
+            Err(e) => {
+                warn!(self.log, "Unable to fetch finalized epoch"; "error" => e);
+                return;
+            }
+        };
+
+        for protection in self.slashing_protection.iter() {
+            if let Err(e) = protection.prune(finalized_epoch, self.slots_per_epoch) {
+                warn!(
+                    self.log,
+                    "Unable to prune slashing protection";
+                    "error" => format!("{:?}", e)
+                );
+            }
+        }
+    }
+
     /// For all known validator keypairs, update any known duties from the beacon node.
     fn check_for_duties(&mut self) {
         let cloned_manager = self.duties_manager.clone();
@@ -335,6 +477,7 @@ impl<B: BeaconNodeDuties + 'static, S: Signer + 'static> Service<B, S> {
                     // we need to produce a block
                     // spawns a thread to produce a beacon block
                     let signers = self.duties_manager.signers.clone(); // this is an arc
+                    let slashing_protection = self.slashing_protection.clone(); // this is an arc
                     let fork = self.fork.clone();
                     let slot = self.current_slot;
                     let spec = self.spec.clone();
@@ -350,6 +493,7 @@ impl<B: BeaconNodeDuties + 'static, S: Signer + 'static> Service<B, S> {
                             spec,
                             beacon_node,
                             signer,
+                            slashing_protection: &slashing_protection[signer_index],
                             slots_per_epoch,
                         };
                         block_producer.handle_produce_block(log);
@@ -359,6 +503,7 @@ impl<B: BeaconNodeDuties + 'static, S: Signer + 'static> Service<B, S> {
                     // we need to produce an attestation
                     // spawns a thread to produce and sign an attestation
                     let signers = self.duties_manager.signers.clone(); // this is an arc
+                    let slashing_protection = self.slashing_protection.clone(); // this is an arc
                     let fork = self.fork.clone();
                     let spec = self.spec.clone();
                     let beacon_node = self.attestation_client.clone();
@@ -373,6 +518,7 @@ impl<B: BeaconNodeDuties + 'static, S: Signer + 'static> Service<B, S> {
                             spec,
                             beacon_node,
                             signer,
+                            slashing_protection: &slashing_protection[signer_index],
                             slots_per_epoch,
                         };
                         attestation_producer.handle_produce_attestation(log);
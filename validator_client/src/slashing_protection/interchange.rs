@@ -0,0 +1,132 @@
+use super::{LowerBound, NotSafe, SignedAttestation, SignedBlock, SigningHistory};
+use serde_derive::{Deserialize, Serialize};
+use std::fs::File;
+use std::path::Path;
+use types::PublicKey;
+
+/// The version of the interchange format produced by this client.
+pub const INTERCHANGE_VERSION: u64 = 1;
+
+/// A portable, JSON-encoded record of the signing history of many validators.
+///
+/// Example:
+///
+/// ```json
+/// {
+///   "version": 1,
+///   "validators": [
+///     {
+///       "pubkey": "0xa99a...",
+///       "blocks": [{ "slot": 81, "signing_root": "0x4ff6..." }],
+///       "attestations": [{ "source_epoch": 2, "target_epoch": 3, "signing_root": "0x587d..." }],
+///       "lower_bound": { "slot": 16, "epoch": 2 }
+///     }
+///   ]
+/// }
+/// ```
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Interchange {
+    pub version: u64,
+    pub validators: Vec<InterchangeValidator>,
+}
+
+/// The signing history of a single validator, as stored in an `Interchange`.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct InterchangeValidator {
+    pub pubkey: PublicKey,
+    pub blocks: Vec<SignedBlock>,
+    pub attestations: Vec<SignedAttestation>,
+    /// The bound beneath which the history was pruned. Absent if it was never pruned.
+    #[serde(default)]
+    pub lower_bound: LowerBound,
+}
+
+impl InterchangeValidator {
+    /// Create a new record for the validator with the given `pubkey`.
+    pub fn new(pubkey: PublicKey, history: SigningHistory) -> Self {
+        Self {
+            pubkey,
+            blocks: history.blocks(),
+            attestations: history.attestations(),
+            lower_bound: history.lower_bound(),
+        }
+    }
+
+    /// Returns the records of `self` as a `SigningHistory`.
+    pub fn history(&self) -> SigningHistory {
+        SigningHistory::new(&self.blocks, &self.attestations, self.lower_bound)
+    }
+}
+
+impl Interchange {
+    /// Create a new, current-version interchange containing `validators`.
+    pub fn new(validators: Vec<InterchangeValidator>) -> Self {
+        Self {
+            version: INTERCHANGE_VERSION,
+            validators,
+        }
+    }
+
+    /// Read an interchange from the JSON file at `path`.
+    ///
+    /// Returns an error if the file is not valid JSON or is of an unsupported version.
+    pub fn read(path: &Path) -> Result<Self, NotSafe> {
+        let file = File::open(path)?;
+
+        let interchange: Self = serde_json::from_reader(file)
+            .map_err(|e| NotSafe::InvalidInterchange(format!("{:?}", e)))?;
+
+        if interchange.version != INTERCHANGE_VERSION {
+            return Err(NotSafe::InvalidInterchange(format!(
+                "Unsupported version {}, expected {}",
+                interchange.version, INTERCHANGE_VERSION
+            )));
+        }
+
+        Ok(interchange)
+    }
+
+    /// Write `self` as JSON to `path`, replacing any existing file.
+    pub fn write(&self, path: &Path) -> Result<(), NotSafe> {
+        let file = File::create(path)?;
+
+        serde_json::to_writer_pretty(file, self)
+            .map_err(|e| NotSafe::InvalidInterchange(format!("{:?}", e)))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::tempdir;
+    use types::{Epoch, Hash256, Keypair, Slot};
+
+    #[test]
+    fn json_round_trip() {
+        let dir = tempdir().unwrap();
+        let path = dir.path().join("interchange.json");
+
+        let history = SigningHistory::new(
+            &[SignedBlock {
+                slot: Slot::new(3),
+                signing_root: Hash256::from(1),
+            }],
+            &[SignedAttestation {
+                source_epoch: Epoch::new(0),
+                target_epoch: Epoch::new(1),
+                signing_root: Hash256::from(2),
+            }],
+            LowerBound::default(),
+        );
+        let interchange = Interchange::new(vec![InterchangeValidator::new(
+            Keypair::random().pk,
+            history.clone(),
+        )]);
+
+        interchange.write(&path).unwrap();
+        let decoded = Interchange::read(&path).unwrap();
+
+        assert_eq!(decoded, interchange);
+        assert_eq!(decoded.validators[0].history(), history);
+    }
+}
@@ -0,0 +1,860 @@
+//! Persistent protection against signing slashable messages.
+//!
+//! Each validator directory contains a `slashing_protection.bin` file which records every block
+//! slot and attestation source/target epoch that the validator has signed. Before signing a new
+//! message it is checked against this history and refused if it would constitute a double
+//! proposal, a double vote or a surround vote.
+//!
+//! The file is an append-only log of records. Each record is appended and synced to disk _before_
+//! the message is signed, so a crash between recording and signing can only cause a message to be
+//! skipped, never to be signed twice. A record which was only partially appended when the process
+//! crashed is discarded when the log is next opened.
+//!
+//! Only one process may have the history open at a time. This is enforced by an exclusive lock on
+//! a `slashing_protection.lock` file, which is held for as long as the history is open. The log
+//! itself cannot be locked, since pruning and importing replace it with a new file.
+//!
+//! Once an epoch is finalized, records which can no longer conflict with an honest message are
+//! pruned and replaced by a `LowerBound`, beneath which all messages are refused. This keeps the
+//! history, and the cost of checking it, bounded.
+//!
+//! Histories may be moved between machines using the JSON format in the `interchange` module.
+mod interchange;
+
+pub use interchange::{Interchange, InterchangeValidator, INTERCHANGE_VERSION};
+
+use bincode;
+use fs2::FileExt;
+use parking_lot::Mutex;
+use serde_derive::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::fs::{self, File, OpenOptions};
+use std::io::{Cursor, Error as IoError, ErrorKind, Write};
+use std::path::{Path, PathBuf};
+use types::{Epoch, Hash256, Slot};
+
+/// The name of the file storing the signing history inside a validator directory.
+pub const SLASHING_PROTECTION_FILENAME: &str = "slashing_protection.bin";
+
+/// The name of the file locked by the process which has the signing history open.
+pub const SLASHING_PROTECTION_LOCK_FILENAME: &str = "slashing_protection.lock";
+
+/// The reasons a message may be refused by `SlashingProtection`.
+#[derive(Debug, PartialEq)]
+pub enum NotSafe {
+    /// A different block has already been signed at this slot.
+    DoubleBlockProposal { slot: Slot },
+    /// A different attestation has already been signed with this target epoch.
+    DoubleVote { target_epoch: Epoch },
+    /// The attestation surrounds a previously signed attestation.
+    SurroundingVote {
+        source_epoch: Epoch,
+        target_epoch: Epoch,
+    },
+    /// The attestation is surrounded by a previously signed attestation.
+    SurroundedVote {
+        source_epoch: Epoch,
+        target_epoch: Epoch,
+    },
+    /// The attestation source is later than its target.
+    InvalidAttestation,
+    /// The message is below the lower bound of the pruned history, so it cannot be checked.
+    BelowLowerBound(LowerBound),
+    /// The history is already open in another process, such as a running validator client.
+    Locked(PathBuf),
+    /// The history could not be read from or written to disk.
+    IoError(String),
+    /// An interchange file could not be parsed or has an unsupported version.
+    InvalidInterchange(String),
+}
+
+impl From<IoError> for NotSafe {
+    fn from(e: IoError) -> NotSafe {
+        NotSafe::IoError(format!("{:?}", e))
+    }
+}
+
+/// A block that has been signed by a validator.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct SignedBlock {
+    pub slot: Slot,
+    pub signing_root: Hash256,
+}
+
+/// An attestation that has been signed by a validator.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct SignedAttestation {
+    pub source_epoch: Epoch,
+    pub target_epoch: Epoch,
+    pub signing_root: Hash256,
+}
+
+/// The earliest block slot and attestation source epoch which may be signed.
+///
+/// Records beneath the bound have been pruned, so any message beneath it is refused. The default
+/// bound permits all messages.
+#[derive(Debug, Clone, Copy, PartialEq, Default, Serialize, Deserialize)]
+pub struct LowerBound {
+    pub slot: Slot,
+    pub epoch: Epoch,
+}
+
+impl LowerBound {
+    /// The bound below which nothing may be signed once `finalized_epoch` has been finalized.
+    ///
+    /// Every honest block is produced after the start of the finalized epoch, and every honest
+    /// attestation has a source at or after it.
+    pub fn finalized(finalized_epoch: Epoch, slots_per_epoch: u64) -> Self {
+        Self {
+            slot: finalized_epoch.start_slot(slots_per_epoch),
+            epoch: finalized_epoch,
+        }
+    }
+
+    /// Returns the greater of each component of `self` and `other`.
+    fn max(self, other: LowerBound) -> Self {
+        Self {
+            slot: std::cmp::max(self.slot, other.slot),
+            epoch: std::cmp::max(self.epoch, other.epoch),
+        }
+    }
+}
+
+/// A single entry in the on-disk log.
+#[derive(Serialize, Deserialize)]
+enum Record {
+    Block(SignedBlock),
+    Attestation(SignedAttestation),
+    LowerBound(LowerBound),
+}
+
+/// All messages signed by a single validator, indexed by block slot and attestation target epoch.
+#[derive(Debug, Clone, PartialEq, Default)]
+pub struct SigningHistory {
+    /// The signing roots of blocks at each slot.
+    blocks: BTreeMap<Slot, Vec<Hash256>>,
+    /// The source epoch and signing root of attestations with each target epoch.
+    attestations: BTreeMap<Epoch, Vec<(Epoch, Hash256)>>,
+    lower_bound: LowerBound,
+}
+
+impl SigningHistory {
+    /// Builds a history from a list of records, such as those in an interchange file.
+    pub fn new(
+        blocks: &[SignedBlock],
+        attestations: &[SignedAttestation],
+        lower_bound: LowerBound,
+    ) -> Self {
+        let mut history = Self::default();
+        for block in blocks {
+            history.insert_block(block);
+        }
+        for attestation in attestations {
+            history.insert_attestation(attestation);
+        }
+        history.prune(lower_bound);
+        history
+    }
+
+    /// Returns every signed block, in slot order.
+    pub fn blocks(&self) -> Vec<SignedBlock> {
+        self.blocks
+            .iter()
+            .flat_map(|(slot, roots)| {
+                roots.iter().map(move |signing_root| SignedBlock {
+                    slot: *slot,
+                    signing_root: *signing_root,
+                })
+            })
+            .collect()
+    }
+
+    /// Returns every signed attestation, in target epoch order.
+    pub fn attestations(&self) -> Vec<SignedAttestation> {
+        self.attestations
+            .iter()
+            .flat_map(|(target_epoch, votes)| {
+                votes
+                    .iter()
+                    .map(move |(source_epoch, signing_root)| SignedAttestation {
+                        source_epoch: *source_epoch,
+                        target_epoch: *target_epoch,
+                        signing_root: *signing_root,
+                    })
+            })
+            .collect()
+    }
+
+    /// Returns the bound beneath which records have been pruned.
+    pub fn lower_bound(&self) -> LowerBound {
+        self.lower_bound
+    }
+
+    /// Returns `Ok(())` if signing a block at `slot` with `signing_root` is not slashable.
+    ///
+    /// Re-signing an identical block is permitted.
+    pub fn check_block(&self, slot: Slot, signing_root: Hash256) -> Result<(), NotSafe> {
+        if slot < self.lower_bound.slot {
+            return Err(NotSafe::BelowLowerBound(self.lower_bound));
+        }
+
+        match self.blocks.get(&slot) {
+            Some(roots) if roots.iter().any(|root| *root != signing_root) => {
+                Err(NotSafe::DoubleBlockProposal { slot })
+            }
+            _ => Ok(()),
+        }
+    }
+
+    /// Returns `Ok(())` if signing an attestation with the given source, target and
+    /// `signing_root` is not slashable.
+    ///
+    /// Re-signing an identical attestation is permitted.
+    pub fn check_attestation(
+        &self,
+        source_epoch: Epoch,
+        target_epoch: Epoch,
+        signing_root: Hash256,
+    ) -> Result<(), NotSafe> {
+        if source_epoch > target_epoch {
+            return Err(NotSafe::InvalidAttestation);
+        }
+
+        if source_epoch < self.lower_bound.epoch {
+            return Err(NotSafe::BelowLowerBound(self.lower_bound));
+        }
+
+        if let Some(votes) = self.attestations.get(&target_epoch) {
+            if votes
+                .iter()
+                .any(|vote| *vote != (source_epoch, signing_root))
+            {
+                return Err(NotSafe::DoubleVote { target_epoch });
+            }
+        }
+
+        // A prior vote can only be surrounded if its target lies strictly between our source and
+        // target.
+        if source_epoch + 1 < target_epoch {
+            if let Some((prior_target, (prior_source, _))) = self
+                .attestations
+                .range(source_epoch + 1..target_epoch)
+                .flat_map(|(target, votes)| votes.iter().map(move |vote| (target, vote)))
+                .find(|(_, (prior_source, _))| source_epoch < *prior_source)
+            {
+                return Err(NotSafe::SurroundingVote {
+                    source_epoch: *prior_source,
+                    target_epoch: *prior_target,
+                });
+            }
+        }
+
+        // A prior vote can only surround us if its target is after ours.
+        if let Some((prior_target, (prior_source, _))) = self
+            .attestations
+            .range(target_epoch + 1..)
+            .flat_map(|(target, votes)| votes.iter().map(move |vote| (target, vote)))
+            .find(|(_, (prior_source, _))| *prior_source < source_epoch)
+        {
+            return Err(NotSafe::SurroundedVote {
+                source_epoch: *prior_source,
+                target_epoch: *prior_target,
+            });
+        }
+
+        Ok(())
+    }
+
+    /// Returns `true` if `block` has already been recorded.
+    fn contains_block(&self, block: &SignedBlock) -> bool {
+        self.blocks
+            .get(&block.slot)
+            .map_or(false, |roots| roots.contains(&block.signing_root))
+    }
+
+    /// Returns `true` if `attestation` has already been recorded.
+    fn contains_attestation(&self, attestation: &SignedAttestation) -> bool {
+        self.attestations
+            .get(&attestation.target_epoch)
+            .map_or(false, |votes| {
+                votes.contains(&(attestation.source_epoch, attestation.signing_root))
+            })
+    }
+
+    /// Records a block, without checking if it is safe.
+    ///
+    /// Returns `false` if the block was already recorded.
+    fn insert_block(&mut self, block: &SignedBlock) -> bool {
+        let roots = self.blocks.entry(block.slot).or_insert_with(Vec::new);
+        if roots.contains(&block.signing_root) {
+            false
+        } else {
+            roots.push(block.signing_root);
+            true
+        }
+    }
+
+    /// Records an attestation, without checking if it is safe.
+    ///
+    /// Returns `false` if the attestation was already recorded.
+    fn insert_attestation(&mut self, attestation: &SignedAttestation) -> bool {
+        let vote = (attestation.source_epoch, attestation.signing_root);
+        let votes = self
+            .attestations
+            .entry(attestation.target_epoch)
+            .or_insert_with(Vec::new);
+        if votes.contains(&vote) {
+            false
+        } else {
+            votes.push(vote);
+            true
+        }
+    }
+
+    /// Raises the lower bound to `lower_bound` and drops the records which can no longer conflict
+    /// with a message above it.
+    ///
+    /// An attestation with a source at or above the bound cannot double vote, surround or be
+    /// surrounded by an attestation whose target is below the bound, so those are dropped.
+    ///
+    /// Returns `false` if the bound was not raised.
+    fn prune(&mut self, lower_bound: LowerBound) -> bool {
+        let lower_bound = self.lower_bound.max(lower_bound);
+        if lower_bound == self.lower_bound {
+            return false;
+        }
+
+        self.lower_bound = lower_bound;
+        self.blocks = self.blocks.split_off(&lower_bound.slot);
+        self.attestations = self.attestations.split_off(&lower_bound.epoch);
+
+        true
+    }
+
+    /// Adds all the records of `other` to `self`, without checking if they are safe.
+    ///
+    /// Conflicting records are all retained and the higher lower bound is kept, which is
+    /// conservative: any message that conflicts with either history will be refused.
+    pub fn merge(&mut self, other: &SigningHistory) {
+        for block in other.blocks() {
+            self.insert_block(&block);
+        }
+        for attestation in other.attestations() {
+            self.insert_attestation(&attestation);
+        }
+        self.prune(other.lower_bound);
+    }
+
+    /// Returns the records from which `self` may be rebuilt.
+    fn records(&self) -> Vec<Record> {
+        std::iter::once(Record::LowerBound(self.lower_bound))
+            .chain(self.blocks().into_iter().map(Record::Block))
+            .chain(self.attestations().into_iter().map(Record::Attestation))
+            .collect()
+    }
+
+    /// Applies a record read from disk.
+    fn apply(&mut self, record: Record) {
+        match record {
+            Record::Block(block) => {
+                self.insert_block(&block);
+            }
+            Record::Attestation(attestation) => {
+                self.insert_attestation(&attestation);
+            }
+            Record::LowerBound(lower_bound) => {
+                self.prune(lower_bound);
+            }
+        }
+    }
+}
+
+/// The open log file and the history it contains.
+struct Log {
+    file: File,
+    /// The length of the file after the last complete record.
+    len: u64,
+    history: SigningHistory,
+}
+
+impl Log {
+    /// Appends `record` to the log and syncs it to disk.
+    ///
+    /// If the append fails, the file is truncated to remove any partially written record.
+    fn append(&mut self, record: &Record) -> Result<(), NotSafe> {
+        let bytes =
+            bincode::serialize(record).map_err(|e| IoError::new(ErrorKind::InvalidData, e))?;
+
+        let result = self
+            .file
+            .write_all(&bytes)
+            .and_then(|_| self.file.sync_data());
+
+        match result {
+            Ok(()) => {
+                self.len += bytes.len() as u64;
+                Ok(())
+            }
+            Err(e) => {
+                let _ = self.file.set_len(self.len);
+                Err(e.into())
+            }
+        }
+    }
+}
+
+/// The on-disk signing history of a single validator.
+///
+/// All checks and records are serialized through an internal lock, so a single instance may be
+/// shared between the block and attestation producers of a validator.
+pub struct SlashingProtection {
+    path: PathBuf,
+    log: Mutex<Log>,
+    /// Exclusively locked until this value is dropped.
+    _lock: File,
+}
+
+impl SlashingProtection {
+    /// Open the history stored in the validator directory `validator_dir`, creating an empty one
+    /// if it does not already exist.
+    ///
+    /// Returns `NotSafe::Locked` if the history is already open, in this or another process.
+    pub fn open(validator_dir: &Path) -> Result<Self, NotSafe> {
+        let path = validator_dir.join(SLASHING_PROTECTION_FILENAME);
+
+        let lock_path = validator_dir.join(SLASHING_PROTECTION_LOCK_FILENAME);
+        let lock = OpenOptions::new()
+            .create(true)
+            .write(true)
+            .open(&lock_path)?;
+        lock.try_lock_exclusive().map_err(|e| {
+            if e.raw_os_error() == fs2::lock_contended_error().raw_os_error() {
+                NotSafe::Locked(lock_path)
+            } else {
+                e.into()
+            }
+        })?;
+
+        let bytes = if path.exists() {
+            fs::read(&path)?
+        } else {
+            vec![]
+        };
+
+        let mut history = SigningHistory::default();
+        let mut cursor = Cursor::new(&bytes[..]);
+        while cursor.position() < bytes.len() as u64 {
+            match bincode::deserialize_from(&mut cursor) {
+                Ok(record) => history.apply(record),
+                // The final record was only partially written. It was never acknowledged, so the
+                // message it describes was never signed.
+                Err(ref e) if is_eof(e) => break,
+                Err(e) => {
+                    return Err(NotSafe::IoError(format!(
+                        "Corrupt signing history: {:?}",
+                        e
+                    )))
+                }
+            }
+        }
+
+        let file = OpenOptions::new().create(true).append(true).open(&path)?;
+        let len = cursor.position();
+        if len < bytes.len() as u64 {
+            file.set_len(len)?;
+        }
+
+        Ok(Self {
+            path,
+            log: Mutex::new(Log { file, len, history }),
+            _lock: lock,
+        })
+    }
+
+    /// Returns a copy of the signing history.
+    pub fn history(&self) -> SigningHistory {
+        self.log.lock().history.clone()
+    }
+
+    /// Returns `Ok(())` if signing a block at `slot` with `signing_root` is not slashable.
+    pub fn check_block(&self, slot: Slot, signing_root: Hash256) -> Result<(), NotSafe> {
+        self.log.lock().history.check_block(slot, signing_root)
+    }
+
+    /// Returns `Ok(())` if signing the given attestation is not slashable.
+    pub fn check_attestation(
+        &self,
+        source_epoch: Epoch,
+        target_epoch: Epoch,
+        signing_root: Hash256,
+    ) -> Result<(), NotSafe> {
+        self.log
+            .lock()
+            .history
+            .check_attestation(source_epoch, target_epoch, signing_root)
+    }
+
+    /// Checks the block is safe to sign and, if so, persists it to disk.
+    ///
+    /// The block must not be signed unless this function returns `Ok(())`.
+    pub fn record_block(&self, slot: Slot, signing_root: Hash256) -> Result<(), NotSafe> {
+        let mut log = self.log.lock();
+
+        log.history.check_block(slot, signing_root)?;
+
+        let block = SignedBlock { slot, signing_root };
+        if log.history.contains_block(&block) {
+            return Ok(());
+        }
+        log.append(&Record::Block(block.clone()))?;
+        log.history.insert_block(&block);
+
+        Ok(())
+    }
+
+    /// Checks the attestation is safe to sign and, if so, persists it to disk.
+    ///
+    /// The attestation must not be signed unless this function returns `Ok(())`.
+    pub fn record_attestation(
+        &self,
+        source_epoch: Epoch,
+        target_epoch: Epoch,
+        signing_root: Hash256,
+    ) -> Result<(), NotSafe> {
+        let mut log = self.log.lock();
+
+        log.history
+            .check_attestation(source_epoch, target_epoch, signing_root)?;
+
+        let attestation = SignedAttestation {
+            source_epoch,
+            target_epoch,
+            signing_root,
+        };
+        if log.history.contains_attestation(&attestation) {
+            return Ok(());
+        }
+        log.append(&Record::Attestation(attestation.clone()))?;
+        log.history.insert_attestation(&attestation);
+
+        Ok(())
+    }
+
+    /// Merges `other` into the stored history and persists it to disk.
+    ///
+    /// See `SigningHistory::merge`.
+    pub fn import(&self, other: &SigningHistory) -> Result<(), NotSafe> {
+        let mut log = self.log.lock();
+
+        let mut updated = log.history.clone();
+        updated.merge(other);
+        self.rewrite(&mut log, updated)
+    }
+
+    /// Prunes the records made redundant by the finalization of `finalized_epoch`.
+    ///
+    /// See `SigningHistory::prune`.
+    pub fn prune(&self, finalized_epoch: Epoch, slots_per_epoch: u64) -> Result<(), NotSafe> {
+        let mut log = self.log.lock();
+
+        let mut updated = log.history.clone();
+        if updated.prune(LowerBound::finalized(finalized_epoch, slots_per_epoch)) {
+            self.rewrite(&mut log, updated)
+        } else {
+            Ok(())
+        }
+    }
+
+    /// Writes the records of `history` to a temporary file and then renames it over the existing
+    /// log, so the log on disk is never partially rewritten.
+    ///
+    /// Renaming is only safe because no other process may have the log open while it is locked.
+    fn rewrite(&self, log: &mut Log, history: SigningHistory) -> Result<(), NotSafe> {
+        let tmp_path = self.path.with_extension("tmp");
+
+        let mut len = 0;
+        {
+            let mut file = File::create(&tmp_path)?;
+            for record in history.records() {
+                let bytes = bincode::serialize(&record)
+                    .map_err(|e| IoError::new(ErrorKind::InvalidData, e))?;
+                file.write_all(&bytes)?;
+                len += bytes.len() as u64;
+            }
+            file.sync_all()?;
+        }
+
+        fs::rename(&tmp_path, &self.path)?;
+        // Persist the rename itself, not just the contents of the new file.
+        if let Some(dir) = self.path.parent() {
+            File::open(dir)?.sync_all()?;
+        }
+
+        *log = Log {
+            file: OpenOptions::new().append(true).open(&self.path)?,
+            len,
+            history,
+        };
+
+        Ok(())
+    }
+}
+
+/// Returns `true` if `e` was caused by reaching the end of the input.
+fn is_eof(e: &bincode::Error) -> bool {
+    match **e {
+        bincode::ErrorKind::Io(ref e) => e.kind() == ErrorKind::UnexpectedEof,
+        _ => false,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::tempdir;
+
+    fn root(i: u64) -> Hash256 {
+        Hash256::from(i)
+    }
+
+    #[test]
+    fn double_block_proposal() {
+        let dir = tempdir().unwrap();
+        let protection = SlashingProtection::open(dir.path()).unwrap();
+
+        protection.record_block(Slot::new(1), root(1)).unwrap();
+
+        // Identical block may be re-signed.
+        assert_eq!(protection.record_block(Slot::new(1), root(1)), Ok(()));
+        assert_eq!(
+            protection.record_block(Slot::new(1), root(2)),
+            Err(NotSafe::DoubleBlockProposal { slot: Slot::new(1) })
+        );
+        assert_eq!(protection.record_block(Slot::new(2), root(2)), Ok(()));
+    }
+
+    #[test]
+    fn double_vote() {
+        let dir = tempdir().unwrap();
+        let protection = SlashingProtection::open(dir.path()).unwrap();
+
+        protection
+            .record_attestation(Epoch::new(0), Epoch::new(1), root(1))
+            .unwrap();
+
+        assert_eq!(
+            protection.record_attestation(Epoch::new(0), Epoch::new(1), root(1)),
+            Ok(())
+        );
+        assert_eq!(
+            protection.record_attestation(Epoch::new(0), Epoch::new(1), root(2)),
+            Err(NotSafe::DoubleVote {
+                target_epoch: Epoch::new(1)
+            })
+        );
+    }
+
+    #[test]
+    fn surround_votes() {
+        let dir = tempdir().unwrap();
+        let protection = SlashingProtection::open(dir.path()).unwrap();
+
+        protection
+            .record_attestation(Epoch::new(2), Epoch::new(3), root(1))
+            .unwrap();
+
+        assert_eq!(
+            protection.check_attestation(Epoch::new(1), Epoch::new(4), root(2)),
+            Err(NotSafe::SurroundingVote {
+                source_epoch: Epoch::new(2),
+                target_epoch: Epoch::new(3)
+            })
+        );
+
+        protection
+            .record_attestation(Epoch::new(3), Epoch::new(6), root(3))
+            .unwrap();
+
+        assert_eq!(
+            protection.check_attestation(Epoch::new(4), Epoch::new(5), root(4)),
+            Err(NotSafe::SurroundedVote {
+                source_epoch: Epoch::new(3),
+                target_epoch: Epoch::new(6)
+            })
+        );
+    }
+
+    #[test]
+    fn import_is_conservative() {
+        let dir = tempdir().unwrap();
+        let protection = SlashingProtection::open(dir.path()).unwrap();
+
+        protection.record_block(Slot::new(1), root(1)).unwrap();
+
+        let mut other = SigningHistory::default();
+        other.insert_block(&SignedBlock {
+            slot: Slot::new(1),
+            signing_root: root(2),
+        });
+        other.insert_attestation(&SignedAttestation {
+            source_epoch: Epoch::new(1),
+            target_epoch: Epoch::new(4),
+            signing_root: root(3),
+        });
+
+        protection.import(&other).unwrap();
+
+        // Neither of the conflicting blocks may be re-signed.
+        assert!(protection.check_block(Slot::new(1), root(1)).is_err());
+        assert!(protection.check_block(Slot::new(1), root(2)).is_err());
+        assert!(protection
+            .check_attestation(Epoch::new(2), Epoch::new(3), root(4))
+            .is_err());
+    }
+
+    #[test]
+    fn survives_restart() {
+        let dir = tempdir().unwrap();
+
+        {
+            let protection = SlashingProtection::open(dir.path()).unwrap();
+            protection.record_block(Slot::new(5), root(1)).unwrap();
+            protection
+                .record_attestation(Epoch::new(0), Epoch::new(1), root(2))
+                .unwrap();
+        }
+
+        let protection = SlashingProtection::open(dir.path()).unwrap();
+
+        assert_eq!(
+            protection.check_block(Slot::new(5), root(3)),
+            Err(NotSafe::DoubleBlockProposal { slot: Slot::new(5) })
+        );
+        assert_eq!(
+            protection.check_attestation(Epoch::new(0), Epoch::new(1), root(3)),
+            Err(NotSafe::DoubleVote {
+                target_epoch: Epoch::new(1)
+            })
+        );
+    }
+
+    #[test]
+    fn records_are_appended() {
+        let dir = tempdir().unwrap();
+        let path = dir.path().join(SLASHING_PROTECTION_FILENAME);
+        let protection = SlashingProtection::open(dir.path()).unwrap();
+
+        protection.record_block(Slot::new(1), root(1)).unwrap();
+        let len = fs::metadata(&path).unwrap().len();
+
+        protection.record_block(Slot::new(2), root(2)).unwrap();
+        assert_eq!(fs::metadata(&path).unwrap().len(), 2 * len);
+
+        // Re-signing an identical block does not add a record.
+        protection.record_block(Slot::new(2), root(2)).unwrap();
+        assert_eq!(fs::metadata(&path).unwrap().len(), 2 * len);
+    }
+
+    #[test]
+    fn partial_record_is_discarded() {
+        let dir = tempdir().unwrap();
+        let path = dir.path().join(SLASHING_PROTECTION_FILENAME);
+
+        {
+            let protection = SlashingProtection::open(dir.path()).unwrap();
+            protection.record_block(Slot::new(1), root(1)).unwrap();
+            protection.record_block(Slot::new(2), root(2)).unwrap();
+        }
+
+        // Simulate a crash part way through appending the second record.
+        let len = fs::metadata(&path).unwrap().len();
+        OpenOptions::new()
+            .write(true)
+            .open(&path)
+            .unwrap()
+            .set_len(len - 3)
+            .unwrap();
+
+        let protection = SlashingProtection::open(dir.path()).unwrap();
+        assert_eq!(
+            protection.check_block(Slot::new(1), root(3)),
+            Err(NotSafe::DoubleBlockProposal { slot: Slot::new(1) })
+        );
+        assert_eq!(protection.check_block(Slot::new(2), root(3)), Ok(()));
+
+        // New records are appended after the last complete record.
+        protection.record_block(Slot::new(3), root(3)).unwrap();
+        drop(protection);
+        let protection = SlashingProtection::open(dir.path()).unwrap();
+        assert_eq!(protection.history().blocks().len(), 2);
+    }
+
+    #[test]
+    fn open_history_is_locked() {
+        let dir = tempdir().unwrap();
+        let protection = SlashingProtection::open(dir.path()).unwrap();
+
+        assert_eq!(
+            SlashingProtection::open(dir.path()).err(),
+            Some(NotSafe::Locked(
+                dir.path().join(SLASHING_PROTECTION_LOCK_FILENAME)
+            ))
+        );
+
+        // The lock is released once the history is closed.
+        drop(protection);
+        assert!(SlashingProtection::open(dir.path()).is_ok());
+    }
+
+    #[test]
+    fn prune_below_finalized_epoch() {
+        let slots_per_epoch = 8;
+        let dir = tempdir().unwrap();
+
+        {
+            let protection = SlashingProtection::open(dir.path()).unwrap();
+            for epoch in 1..6 {
+                protection
+                    .record_block(Epoch::new(epoch).start_slot(slots_per_epoch), root(epoch))
+                    .unwrap();
+                protection
+                    .record_attestation(Epoch::new(epoch - 1), Epoch::new(epoch), root(epoch))
+                    .unwrap();
+            }
+
+            protection.prune(Epoch::new(3), slots_per_epoch).unwrap();
+        }
+
+        let protection = SlashingProtection::open(dir.path()).unwrap();
+        let history = protection.history();
+        assert_eq!(
+            history.lower_bound(),
+            LowerBound::finalized(Epoch::new(3), slots_per_epoch)
+        );
+        assert_eq!(history.blocks().len(), 3);
+        // The attestation 2 -> 3 is retained, since its target is not below the bound.
+        assert_eq!(history.attestations().len(), 3);
+
+        // Messages beneath the bound are refused, even though their records were pruned.
+        assert!(protection.check_block(Slot::new(9), root(9)).is_err());
+        assert!(protection
+            .check_attestation(Epoch::new(2), Epoch::new(6), root(9))
+            .is_err());
+
+        // Messages above the bound are still checked against the remaining records.
+        assert_eq!(
+            protection.check_block(Epoch::new(4).start_slot(slots_per_epoch), root(9)),
+            Err(NotSafe::DoubleBlockProposal {
+                slot: Epoch::new(4).start_slot(slots_per_epoch)
+            })
+        );
+        assert_eq!(
+            protection.check_attestation(Epoch::new(4), Epoch::new(6), root(6)),
+            Ok(())
+        );
+
+        // Pruning at an earlier epoch does not lower the bound.
+        protection.prune(Epoch::new(1), slots_per_epoch).unwrap();
+        assert_eq!(protection.history(), history);
+    }
+}